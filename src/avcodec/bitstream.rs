@@ -10,6 +10,7 @@ use std::{
 };
 
 wrap_ref!(AVBitStreamFilter: ffi::AVBitStreamFilter);
+wrap_static!(AVBitStreamFilter);
 
 impl AVBitStreamFilter {
     /// Find a bitstream filter instance with it's short name.
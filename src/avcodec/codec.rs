@@ -6,10 +6,16 @@ use std::{
 };
 
 use crate::{
-    avcodec::{AVCodecID, AVCodecParameters, AVPacket},
+    avcodec::{
+        padded_copy, profile_list, AVCodecID, AVCodecParameters, AVPacket, CodecId, CodecProps,
+        Level, Profile,
+    },
+    avformat::AVStream,
     avutil::{
-        AVChannelLayoutRef, AVDictionary, AVFrame, AVHWFramesContext, AVHWFramesContextMut,
-        AVHWFramesContextRef, AVPixelFormat, AVRational,
+        av_rescale_q, opt_named_consts, opt_names, AVChannelLayout, AVChannelLayoutRef,
+        AVDictionary, AVFrame, AVHWFramesContext, AVHWFramesContextMut, AVHWFramesContextRef,
+        AVMediaType, AVOptionConst, AVPixelFormat, AVRational, AVSampleFormat, PixelFormat,
+        SampleFormat,
     },
     error::{Result, RsmpegError},
     ffi,
@@ -17,6 +23,7 @@ use crate::{
 };
 
 wrap_ref!(AVCodec: ffi::AVCodec);
+wrap_static!(AVCodec);
 
 impl AVCodec {
     /// Find a static decoder instance with [`AVCodecID`]
@@ -57,6 +64,59 @@ impl AVCodec {
         unsafe { CStr::from_ptr(self.long_name) }
     }
 
+    /// Returns true if the codec is an encoder.
+    pub fn is_encoder(&self) -> bool {
+        unsafe { ffi::av_codec_is_encoder(self.as_ptr()) != 0 }
+    }
+
+    /// Returns true if the codec is a decoder.
+    pub fn is_decoder(&self) -> bool {
+        unsafe { ffi::av_codec_is_decoder(self.as_ptr()) != 0 }
+    }
+
+    /// Get the type of the data handled by the codec.
+    pub fn media_type(&self) -> AVMediaType {
+        AVMediaType(self.type_)
+    }
+
+    /// Get the `capabilities` field as [`CodecCapabilities`].
+    pub fn codec_capabilities(&self) -> CodecCapabilities {
+        CodecCapabilities::from_bits_retain(self.capabilities)
+    }
+
+    /// The encoder or decoder has delay and needs to be flushed at the end.
+    pub fn has_delay(&self) -> bool {
+        self.codec_capabilities().contains(CodecCapabilities::DELAY)
+    }
+
+    /// The audio encoder supports receiving a different number of samples in
+    /// each call.
+    pub fn supports_variable_frame_size(&self) -> bool {
+        self.codec_capabilities()
+            .contains(CodecCapabilities::VARIABLE_FRAME_SIZE)
+    }
+
+    /// The codec is experimental, `strict_std_compliance` must be set to
+    /// `FF_COMPLIANCE_EXPERIMENTAL` to use it.
+    pub fn is_experimental(&self) -> bool {
+        self.codec_capabilities()
+            .contains(CodecCapabilities::EXPERIMENTAL)
+    }
+
+    /// The codec is backed by a hardware implementation.
+    pub fn is_hardware(&self) -> bool {
+        self.codec_capabilities()
+            .contains(CodecCapabilities::HARDWARE)
+    }
+
+    /// The codec id of the codec uses only intra compression, every frame is
+    /// a keyframe.
+    pub fn is_intra_only(&self) -> bool {
+        CodecId(self.id)
+            .descriptor()
+            .is_some_and(|x| x.codec_props().contains(CodecProps::INTRA_ONLY))
+    }
+
     /// Iterate over all registered codecs.
     pub fn iterate() -> AVCodecIter {
         AVCodecIter {
@@ -118,6 +178,34 @@ impl<'codec> AVCodec {
         unsafe { Self::build_array(self.supported_framerates, AVRational { den: 0, num: 0 }) }
     }
 
+    /// Return the names of the private options of this [`AVCodec`], e.g.
+    /// `preset` and `crf` of libx264.
+    pub fn priv_option_names(&'codec self) -> Vec<&'codec CStr> {
+        unsafe { opt_names(self.priv_class) }
+    }
+
+    /// Return the named values accepted by the private option `name` of this
+    /// [`AVCodec`], e.g. the presets of the NVENC encoders. `None` if there is
+    /// no such option, empty if the option takes free form values.
+    pub fn priv_option_values(&'codec self, name: &CStr) -> Option<Vec<AVOptionConst<'codec>>> {
+        unsafe { opt_named_consts(self.priv_class, name) }
+    }
+
+    /// Return the `(profile, name)` pairs of the profiles this [`AVCodec`]
+    /// recognizes, empty if unknown.
+    pub fn profiles(&'codec self) -> Vec<(i32, &'codec CStr)> {
+        unsafe { profile_list(self.profiles) }
+    }
+
+    /// Return the name of `profile` if this [`AVCodec`] recognizes it.
+    pub fn profile_name(&'codec self, profile: impl Into<i32>) -> Option<&'codec CStr> {
+        unsafe {
+            ffi::av_get_profile_name(self.as_ptr(), profile.into())
+                .upgrade()
+                .map(|x| CStr::from_ptr(x.as_ptr()))
+        }
+    }
+
     /// Return supported pix_fmts of this [`AVCodec`].
     pub fn pix_fmts(&'codec self) -> Option<&'codec [AVPixelFormat]> {
         // terminates with -1
@@ -144,23 +232,167 @@ impl Drop for AVCodec {
     }
 }
 
-wrap_ref!(AVCodecContext: ffi::AVCodecContext);
+bitflags::bitflags! {
+    /// Capabilities of [`AVCodec`], `AV_CODEC_CAP_*`, see
+    /// [`AVCodec::codec_capabilities()`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CodecCapabilities: i32 {
+        /// Decoder can use draw_horiz_band callback.
+        const DRAW_HORIZ_BAND = ffi::AV_CODEC_CAP_DRAW_HORIZ_BAND as i32;
+        /// Codec uses get_buffer() or get_encode_buffer() for allocating
+        /// buffers and supports custom allocators.
+        const DR1 = ffi::AV_CODEC_CAP_DR1 as i32;
+        /// Encoder or decoder requires flushing with NULL input at the end.
+        const DELAY = ffi::AV_CODEC_CAP_DELAY as i32;
+        /// Codec can be fed a final frame with a smaller size.
+        const SMALL_LAST_FRAME = ffi::AV_CODEC_CAP_SMALL_LAST_FRAME as i32;
+        /// Codec is experimental.
+        const EXPERIMENTAL = ffi::AV_CODEC_CAP_EXPERIMENTAL as i32;
+        /// Codec should fill in channel configuration and samplerate.
+        const CHANNEL_CONF = ffi::AV_CODEC_CAP_CHANNEL_CONF as i32;
+        /// Codec supports frame-level multithreading.
+        const FRAME_THREADS = ffi::AV_CODEC_CAP_FRAME_THREADS as i32;
+        /// Codec supports slice-based (or partition-based) multithreading.
+        const SLICE_THREADS = ffi::AV_CODEC_CAP_SLICE_THREADS as i32;
+        /// Codec supports changed parameters at any point.
+        const PARAM_CHANGE = ffi::AV_CODEC_CAP_PARAM_CHANGE as i32;
+        /// Codec supports multithreading through a method other than slice
+        /// or frame level multithreading.
+        const OTHER_THREADS = ffi::AV_CODEC_CAP_OTHER_THREADS as i32;
+        /// Audio encoder supports receiving a different number of samples in
+        /// each call.
+        const VARIABLE_FRAME_SIZE = ffi::AV_CODEC_CAP_VARIABLE_FRAME_SIZE as i32;
+        /// Decoder is not a preferred choice for probing.
+        const AVOID_PROBING = ffi::AV_CODEC_CAP_AVOID_PROBING as i32;
+        /// Codec is backed by a hardware implementation.
+        const HARDWARE = ffi::AV_CODEC_CAP_HARDWARE as i32;
+        /// Codec is potentially backed by a hardware implementation, but not
+        /// necessarily.
+        const HYBRID = ffi::AV_CODEC_CAP_HYBRID as i32;
+        /// Encoder can copy `reordered_opaque` from frames to packets.
+        const ENCODER_REORDERED_OPAQUE = ffi::AV_CODEC_CAP_ENCODER_REORDERED_OPAQUE as i32;
+        /// Encoder can be flushed using `avcodec_flush_buffers()`.
+        const ENCODER_FLUSH = ffi::AV_CODEC_CAP_ENCODER_FLUSH as i32;
+        /// Encoder can output reconstructed frames.
+        const ENCODER_RECON_FRAME = ffi::AV_CODEC_CAP_ENCODER_RECON_FRAME as i32;
+
+        // Keep the capabilities unknown to this version.
+        const _ = !0;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags of [`AVCodecContext`], `AV_CODEC_FLAG_*`, see
+    /// [`AVCodecContext::codec_flags()`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CodecFlags: i32 {
+        /// Allow decoders to produce frames with data planes that are not
+        /// aligned to CPU requirements.
+        const UNALIGNED = ffi::AV_CODEC_FLAG_UNALIGNED as i32;
+        /// Use fixed qscale.
+        const QSCALE = ffi::AV_CODEC_FLAG_QSCALE as i32;
+        /// 4 MV per MB allowed / advanced prediction for H.263.
+        const FOUR_MV = ffi::AV_CODEC_FLAG_4MV as i32;
+        /// Output even those frames that might be corrupted.
+        const OUTPUT_CORRUPT = ffi::AV_CODEC_FLAG_OUTPUT_CORRUPT as i32;
+        /// Use qpel MC.
+        const QPEL = ffi::AV_CODEC_FLAG_QPEL as i32;
+        /// Request the encoder to output reconstructed frames.
+        const RECON_FRAME = ffi::AV_CODEC_FLAG_RECON_FRAME as i32;
+        /// Propagate the `opaque` fields of frames and packets.
+        const COPY_OPAQUE = ffi::AV_CODEC_FLAG_COPY_OPAQUE as i32;
+        /// Signal to the encoder that the frame durations are valid.
+        const FRAME_DURATION = ffi::AV_CODEC_FLAG_FRAME_DURATION as i32;
+        /// Use internal 2pass ratecontrol in first pass mode.
+        const PASS1 = ffi::AV_CODEC_FLAG_PASS1 as i32;
+        /// Use internal 2pass ratecontrol in second pass mode.
+        const PASS2 = ffi::AV_CODEC_FLAG_PASS2 as i32;
+        /// Loop filter.
+        const LOOP_FILTER = ffi::AV_CODEC_FLAG_LOOP_FILTER as i32;
+        /// Only decode/encode grayscale.
+        const GRAY = ffi::AV_CODEC_FLAG_GRAY as i32;
+        /// Compute the errors of the encoded frames, for PSNR.
+        const PSNR = ffi::AV_CODEC_FLAG_PSNR as i32;
+        /// Use interlaced DCT.
+        const INTERLACED_DCT = ffi::AV_CODEC_FLAG_INTERLACED_DCT as i32;
+        /// Force low delay.
+        const LOW_DELAY = ffi::AV_CODEC_FLAG_LOW_DELAY as i32;
+        /// Place global headers in extradata instead of every keyframe.
+        const GLOBAL_HEADER = ffi::AV_CODEC_FLAG_GLOBAL_HEADER as i32;
+        /// Use only bitexact stuff (except (I)DCT).
+        const BITEXACT = ffi::AV_CODEC_FLAG_BITEXACT as i32;
+        /// H.263 advanced intra coding / MPEG-4 AC prediction.
+        const AC_PRED = ffi::AV_CODEC_FLAG_AC_PRED as i32;
+        /// Interlaced motion estimation.
+        const INTERLACED_ME = ffi::AV_CODEC_FLAG_INTERLACED_ME as i32;
+        /// Closed GOP.
+        const CLOSED_GOP = ffi::AV_CODEC_FLAG_CLOSED_GOP as i32;
+
+        // Keep the flags unknown to this version.
+        const _ = !0;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags of [`AVCodecContext`], `AV_CODEC_FLAG2_*`, see
+    /// [`AVCodecContext::codec_flags2()`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CodecFlags2: i32 {
+        /// Allow non spec compliant speedup tricks.
+        const FAST = ffi::AV_CODEC_FLAG2_FAST as i32;
+        /// Skip bitstream encoding.
+        const NO_OUTPUT = ffi::AV_CODEC_FLAG2_NO_OUTPUT as i32;
+        /// Place global headers at every keyframe instead of in extradata.
+        const LOCAL_HEADER = ffi::AV_CODEC_FLAG2_LOCAL_HEADER as i32;
+        /// Input bitstream might be truncated at packet boundaries instead of
+        /// only at frame boundaries.
+        const CHUNKS = ffi::AV_CODEC_FLAG2_CHUNKS as i32;
+        /// Discard cropping information from SPS.
+        const IGNORE_CROP = ffi::AV_CODEC_FLAG2_IGNORE_CROP as i32;
+        /// Show all frames before the first keyframe.
+        const SHOW_ALL = ffi::AV_CODEC_FLAG2_SHOW_ALL as i32;
+        /// Export motion vectors through frame side data.
+        const EXPORT_MVS = ffi::AV_CODEC_FLAG2_EXPORT_MVS as i32;
+        /// Do not skip samples and export skip information as frame side data.
+        const SKIP_MANUAL = ffi::AV_CODEC_FLAG2_SKIP_MANUAL as i32;
+        /// Do not reset ASS ReadOrder field on flush (subtitles decoding).
+        const RO_FLUSH_NOOP = ffi::AV_CODEC_FLAG2_RO_FLUSH_NOOP as i32;
+        /// Generate/parse ICC profiles on encode/decode, as appropriate for
+        /// the type of file.
+        const ICC_PROFILES = ffi::AV_CODEC_FLAG2_ICC_PROFILES as i32;
+
+        // Keep the flags unknown to this version.
+        const _ = !0;
+    }
+}
+
+/// Callback negotiating the pixel format of a decoder, see
+/// [`AVCodecContext::set_get_format()`].
+pub type GetFormatFn = dyn FnMut(&[AVPixelFormat]) -> AVPixelFormat + Send;
+
+wrap_ref!(
+    AVCodecContext: ffi::AVCodecContext,
+    // Double boxed since a thin pointer is stored in `opaque`.
+    _get_format: Option<Box<Box<GetFormatFn>>> = None,
+    // Whether `stats_in` was allocated by `set_stats_in()`, so it's freed
+    // with the context.
+    stats_in_owned: bool = false,
+);
 settable!(AVCodecContext {
     framerate: AVRational,
     ch_layout: ffi::AVChannelLayout,
     height: i32,
     width: i32,
     sample_aspect_ratio: AVRational,
-    pix_fmt: i32,
     time_base: AVRational,
     pkt_timebase: AVRational,
     sample_rate: i32,
-    sample_fmt: i32,
     flags: i32,
     bit_rate: i64,
     strict_std_compliance: i32,
     gop_size: i32,
     max_b_frames: i32,
+    extra_hw_frames: i32,
 });
 
 impl AVCodecContext {
@@ -174,6 +406,21 @@ impl AVCodecContext {
         unsafe { Self::from_raw(codec_context) }
     }
 
+    /// Create a decoder context for `stream` with `codec`, filling its codec
+    /// parameters and `pkt_timebase` from the stream. [`Self::open()`] still
+    /// needs to be called.
+    ///
+    /// Returns `AVERROR(EINVAL)` if `codec` isn't a decoder.
+    pub fn from_stream(stream: &AVStream, codec: &AVCodec) -> Result<Self> {
+        if !codec.is_decoder() {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let mut context = Self::new(codec);
+        context.apply_codecpar(&stream.codecpar())?;
+        context.set_pkt_timebase(stream.time_base);
+        Ok(context)
+    }
+
     /// Initialize the [`AVCodecContext`].
     ///
     /// dict: A [`AVDictionary`] filled with [`AVCodecContext`] and [`AVCodec`]
@@ -183,14 +430,34 @@ impl AVCodecContext {
     ///
     /// Note: Always call this function before using decoding routines, such as [`Self::receive_frame()`].
     pub fn open(&mut self, dict: Option<AVDictionary>) -> Result<Option<AVDictionary>> {
+        self.open_codec(ptr::null(), dict)
+    }
+
+    /// Initialize the [`AVCodecContext`] with an explicit [`AVCodec`], like
+    /// [`Self::open()`].
+    ///
+    /// `codec` must be the codec the context was allocated for, otherwise
+    /// `AVERROR(EINVAL)` is returned. Use [`Self::reinit()`] to switch to
+    /// another codec.
+    pub fn open_with(
+        &mut self,
+        codec: &AVCodec,
+        dict: Option<AVDictionary>,
+    ) -> Result<Option<AVDictionary>> {
+        self.open_codec(codec.as_ptr(), dict)
+    }
+
+    fn open_codec(
+        &mut self,
+        codec: *const ffi::AVCodec,
+        dict: Option<AVDictionary>,
+    ) -> Result<Option<AVDictionary>> {
         if let Some(mut dict) = dict {
             let dict_ptr = {
                 // Doesn't use into_raw or we will drop the dict when error occurs.
                 let mut dict_ptr = dict.as_mut_ptr();
-                unsafe {
-                    ffi::avcodec_open2(self.as_mut_ptr(), ptr::null_mut(), &mut dict_ptr as *mut _)
-                }
-                .upgrade()?;
+                unsafe { ffi::avcodec_open2(self.as_mut_ptr(), codec, &mut dict_ptr as *mut _) }
+                    .upgrade()?;
                 dict_ptr
             };
             // If no error, dict's inner pointer is dangling, here we manually drop it by using into_raw().
@@ -199,12 +466,301 @@ impl AVCodecContext {
                 .upgrade()
                 .map(|dict_ptr| unsafe { AVDictionary::from_raw(dict_ptr) }))
         } else {
-            unsafe { ffi::avcodec_open2(self.as_mut_ptr(), ptr::null_mut(), ptr::null_mut()) }
-                .upgrade()?;
+            unsafe { ffi::avcodec_open2(self.as_mut_ptr(), codec, ptr::null_mut()) }.upgrade()?;
             Ok(None)
         }
     }
 
+    /// Free the current context and allocate a fresh one for `codec`, e.g.
+    /// when the codec of the input stream changes mid-stream.
+    ///
+    /// All the settings and the codec state are lost: parameters such as
+    /// [`Self::apply_codecpar()`] need to be set again before calling
+    /// [`Self::open()`].
+    pub fn reinit(&mut self, codec: &AVCodec) {
+        *self = Self::new(codec);
+    }
+
+    /// Set the callback choosing the pixel format of the decoded frames among
+    /// the formats supported for the stream, e.g. to select the hardware
+    /// pixel format for hardware decoding. Should be called before
+    /// [`Self::open()`].
+    ///
+    /// The formats are in order of preference of the decoder, the software
+    /// ones last. Return `AV_PIX_FMT_NONE` to fail the decoding. The callback
+    /// is called again when the stream parameters change, possibly from a
+    /// decoding thread.
+    ///
+    /// This uses the `opaque` field of the context, which shouldn't be changed
+    /// afterwards.
+    pub fn set_get_format<F>(&mut self, callback: F)
+    where
+        F: FnMut(&[AVPixelFormat]) -> AVPixelFormat + Send + 'static,
+    {
+        unsafe extern "C" fn get_format_c(
+            context: *mut ffi::AVCodecContext,
+            fmt: *const AVPixelFormat,
+        ) -> AVPixelFormat {
+            // `opaque` is set along with the callback.
+            let Some(callback) = (unsafe { ((*context).opaque as *mut Box<GetFormatFn>).as_mut() })
+            else {
+                return ffi::AV_PIX_FMT_NONE;
+            };
+            let formats = unsafe { AVCodec::build_array(fmt, ffi::AV_PIX_FMT_NONE) }.unwrap_or(&[]);
+            // Unwinding across FFI aborts, fail the decoding instead.
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback(formats)))
+                .unwrap_or(ffi::AV_PIX_FMT_NONE)
+        }
+
+        let mut callback: Box<Box<GetFormatFn>> = Box::new(Box::new(callback));
+        unsafe {
+            let context = self.deref_mut();
+            context.opaque = &mut *callback as *mut Box<GetFormatFn> as *mut c_void;
+            context.get_format = Some(get_format_c);
+        }
+        // Dropped after the context is freed.
+        self._get_format = Some(callback);
+    }
+
+    /// Set the pixel format, accepts both [`PixelFormat`] and the bare
+    /// [`AVPixelFormat`].
+    pub fn set_pix_fmt(&mut self, pix_fmt: impl Into<PixelFormat>) {
+        unsafe { self.deref_mut().pix_fmt = pix_fmt.into().0 }
+    }
+
+    /// Get the `pix_fmt` field as [`PixelFormat`].
+    pub fn pixel_format(&self) -> PixelFormat {
+        PixelFormat(self.pix_fmt)
+    }
+
+    /// Set the sample format, accepts both [`SampleFormat`] and the bare
+    /// `AVSampleFormat`.
+    pub fn set_sample_fmt(&mut self, sample_fmt: impl Into<SampleFormat>) {
+        unsafe { self.deref_mut().sample_fmt = sample_fmt.into().0 }
+    }
+
+    /// Get the `sample_fmt` field as [`SampleFormat`].
+    pub fn sample_format(&self) -> SampleFormat {
+        SampleFormat(self.sample_fmt)
+    }
+
+    /// Ask the decoder to output `sample_fmt`, accepts both [`SampleFormat`]
+    /// and the bare [`AVSampleFormat`]. Should be called before
+    /// [`Self::open()`].
+    ///
+    /// This is only a hint, most decoders ignore it, check
+    /// [`Self::output_request_status()`] once a frame is decoded and resample
+    /// if it wasn't honored.
+    pub fn set_request_sample_fmt(&mut self, sample_fmt: impl Into<SampleFormat>) {
+        unsafe { self.deref_mut().request_sample_fmt = sample_fmt.into().0 }
+    }
+
+    /// Whether the codec lists `sample_fmt` among its sample formats. For a
+    /// decoder, it's a prerequisite to honor
+    /// [`Self::set_request_sample_fmt()`].
+    pub fn supports_sample_fmt(&self, sample_fmt: impl Into<AVSampleFormat>) -> bool {
+        let sample_fmt = sample_fmt.into();
+        self.codec()
+            .sample_fmts()
+            .is_some_and(|x| x.contains(&sample_fmt))
+    }
+
+    /// Ask the decoder to downmix its output to `ch_layout`, through its
+    /// `downmix` private option, which replaced the `request_channel_layout`
+    /// field. Should be called before [`Self::open()`].
+    ///
+    /// Returns `Ok(false)` if the decoder can't downmix, e.g. only some
+    /// AC-3 and DTS decoders can, the channel layout is then left as is.
+    pub fn set_request_ch_layout(&mut self, ch_layout: &AVChannelLayout) -> Result<bool> {
+        let downmix = CStr::from_bytes_with_nul(b"downmix\0").unwrap();
+        if !self.codec().priv_option_names().contains(&downmix) {
+            return Ok(false);
+        }
+        unsafe {
+            ffi::av_opt_set_chlayout(
+                self.as_mut_ptr().cast(),
+                downmix.as_ptr(),
+                ch_layout.as_ptr(),
+                ffi::AV_OPT_SEARCH_CHILDREN as i32,
+            )
+        }
+        .upgrade()?;
+        Ok(true)
+    }
+
+    /// Check whether the decoder honored the output requested with
+    /// [`Self::set_request_sample_fmt()`] and
+    /// [`Self::set_request_ch_layout()`], should be called after the first
+    /// frame is decoded, as the output parameters may only be known then.
+    pub fn output_request_status(&self) -> OutputRequestStatus {
+        let sample_fmt = match self.request_sample_fmt {
+            ffi::AV_SAMPLE_FMT_NONE => None,
+            x => Some(x == self.sample_fmt),
+        };
+        let mut downmix = mem::MaybeUninit::<ffi::AVChannelLayout>::uninit();
+        let ret = unsafe {
+            ffi::av_opt_get_chlayout(
+                self.as_ptr() as *mut _,
+                b"downmix\0".as_ptr() as _,
+                ffi::AV_OPT_SEARCH_CHILDREN as i32,
+                downmix.as_mut_ptr(),
+            )
+        };
+        let ch_layout = (ret >= 0).then(|| {
+            let mut downmix = unsafe { downmix.assume_init() };
+            let honored = (downmix.nb_channels > 0)
+                .then(|| unsafe { ffi::av_channel_layout_compare(&downmix, &self.ch_layout) == 0 });
+            unsafe { ffi::av_channel_layout_uninit(&mut downmix) };
+            honored
+        });
+        OutputRequestStatus {
+            sample_fmt,
+            ch_layout: ch_layout.flatten(),
+        }
+    }
+
+    /// Set the profile, accepts both [`Profile`] and the bare `i32`, e.g.
+    /// [`Profile::H264_HIGH`] to get a stream decodable by devices
+    /// supporting only it, whichever H.264 encoder is used.
+    pub fn set_profile(&mut self, profile: impl Into<Profile>) {
+        unsafe { self.deref_mut().profile = profile.into().0 }
+    }
+
+    /// Get the `profile` field as [`Profile`].
+    pub fn profile(&self) -> Profile {
+        Profile(self.profile)
+    }
+
+    /// Set the level, accepts both [`Level`] and the bare `i32`, e.g.
+    /// `Level::h264(4, 1)`.
+    pub fn set_level(&mut self, level: impl Into<Level>) {
+        unsafe { self.deref_mut().level = level.into().0 }
+    }
+
+    /// Get the `level` field as [`Level`].
+    pub fn level(&self) -> Level {
+        Level(self.level)
+    }
+
+    /// Get the `flags` field as [`CodecFlags`].
+    pub fn codec_flags(&self) -> CodecFlags {
+        CodecFlags::from_bits_retain(self.flags)
+    }
+
+    /// Set the `flags` field from [`CodecFlags`].
+    pub fn set_codec_flags(&mut self, flags: CodecFlags) {
+        self.set_flags(flags.bits());
+    }
+
+    /// Get the `flags2` field as [`CodecFlags2`].
+    pub fn codec_flags2(&self) -> CodecFlags2 {
+        CodecFlags2::from_bits_retain(self.flags2)
+    }
+
+    /// Set the `flags2` field from [`CodecFlags2`].
+    pub fn set_codec_flags2(&mut self, flags: CodecFlags2) {
+        unsafe { self.deref_mut().flags2 = flags.bits() };
+    }
+
+    /// Set up the encoder for the first pass of a two-pass encoding, should
+    /// be called before [`Self::open()`]. Collect [`Self::stats_out()`] for
+    /// the second pass.
+    pub fn set_first_pass(&mut self) {
+        let flags = (self.codec_flags() - CodecFlags::PASS2) | CodecFlags::PASS1;
+        self.set_codec_flags(flags);
+    }
+
+    /// Set up the encoder for the second pass of a two-pass encoding with the
+    /// statistics collected in the first pass, should be called before
+    /// [`Self::open()`].
+    pub fn set_second_pass(&mut self, stats: &CStr) -> Result<()> {
+        self.set_stats_in(stats)?;
+        let flags = (self.codec_flags() - CodecFlags::PASS1) | CodecFlags::PASS2;
+        self.set_codec_flags(flags);
+        Ok(())
+    }
+
+    /// Statistics of the first pass of a two-pass encoding (encoders with
+    /// `AV_CODEC_FLAG_PASS1` set), updated after each encoded frame. The
+    /// output of every frame should be concatenated and given to the second
+    /// pass with [`Self::set_stats_in()`].
+    pub fn stats_out(&self) -> Option<&CStr> {
+        NonNull::new(self.stats_out).map(|x| unsafe { CStr::from_ptr(x.as_ptr()) })
+    }
+
+    /// Set the concatenated [`Self::stats_out()`] of the first pass for the
+    /// second pass of a two-pass encoding (encoders with
+    /// `AV_CODEC_FLAG_PASS2` set). Should be called before [`Self::open()`].
+    ///
+    /// The context owns a copy of `stats`, freed when it's dropped or
+    /// replaced by another call. A value written to the raw field by the
+    /// caller stays owned by the caller.
+    pub fn set_stats_in(&mut self, stats: &CStr) -> Result<()> {
+        let stats = unsafe { ffi::av_strdup(stats.as_ptr()) };
+        if stats.is_null() {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::ENOMEM)));
+        }
+        self.free_stats_in();
+        unsafe { self.deref_mut().stats_in = stats };
+        self.stats_in_owned = true;
+        Ok(())
+    }
+
+    /// Free `stats_in` if it was allocated by [`Self::set_stats_in()`].
+    fn free_stats_in(&mut self) {
+        if std::mem::take(&mut self.stats_in_owned) {
+            unsafe { ffi::av_freep(&mut self.deref_mut().stats_in as *mut _ as *mut c_void) };
+        }
+    }
+
+    /// Get the out-of-band codec configuration, e.g. the `avcC` box of H.264,
+    /// empty if there is none.
+    pub fn extradata(&self) -> &[u8] {
+        if self.extradata.is_null() || self.extradata_size <= 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.extradata, self.extradata_size as usize) }
+    }
+
+    /// Replace the extradata with a copy of `data`, e.g. the SPS and PPS a
+    /// decoder of a raw H.264 elementary stream needs before the first
+    /// packet. Should be called before [`Self::open()`], an empty `data`
+    /// removes it.
+    pub fn set_extradata(&mut self, data: &[u8]) -> Result<()> {
+        let (extradata, size) = padded_copy(data)?;
+        unsafe {
+            let context = self.deref_mut();
+            ffi::av_freep(&mut context.extradata as *mut _ as *mut c_void);
+            context.extradata = extradata;
+            context.extradata_size = size;
+        }
+        Ok(())
+    }
+
+    /// Get the header of the subtitles, e.g. the script info and the styles
+    /// of ASS, set by the decoders and used by the encoders. Empty if there
+    /// is none.
+    pub fn subtitle_header(&self) -> &[u8] {
+        if self.subtitle_header.is_null() || self.subtitle_header_size <= 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.subtitle_header, self.subtitle_header_size as usize) }
+    }
+
+    /// Replace the header of the subtitles with a copy of `header`, e.g. the
+    /// [`Self::subtitle_header()`] of a decoder for an encoder. Should be
+    /// called before [`Self::open()`].
+    pub fn set_subtitle_header(&mut self, header: &[u8]) -> Result<()> {
+        let (subtitle_header, size) = padded_copy(header)?;
+        unsafe {
+            let context = self.deref_mut();
+            ffi::av_freep(&mut context.subtitle_header as *mut _ as *mut c_void);
+            context.subtitle_header = subtitle_header;
+            context.subtitle_header_size = size;
+        }
+        Ok(())
+    }
+
     /// Trying to push a packet to current decoding_context([`AVCodecContext`]).
     pub fn send_packet(&mut self, packet: Option<&AVPacket>) -> Result<()> {
         let packet_ptr = match packet {
@@ -222,9 +778,28 @@ impl AVCodecContext {
     /// Trying to pull a frame from current decoding_context([`AVCodecContext`]).
     pub fn receive_frame(&mut self) -> Result<AVFrame> {
         let mut frame = AVFrame::new();
+        self.receive_frame_into(&mut frame)?;
+        Ok(frame)
+    }
+
+    /// Same as [`Self::receive_frame()`], receiving into `frame` instead of
+    /// allocating a new one, which matters in the hot loop of a transcode.
+    /// The previous content of `frame` is unreferenced.
+    ///
+    /// ```ignore
+    /// let mut frame = AVFrame::new();
+    /// loop {
+    ///     match decoder.receive_frame_into(&mut frame) {
+    ///         Ok(()) => encoder.send_frame(Some(&frame))?,
+    ///         Err(RsmpegError::DecoderDrainError | RsmpegError::DecoderFlushedError) => break,
+    ///         Err(e) => return Err(e),
+    ///     }
+    /// }
+    /// ```
+    pub fn receive_frame_into(&mut self, frame: &mut AVFrame) -> Result<()> {
         match unsafe { ffi::avcodec_receive_frame(self.as_mut_ptr(), frame.as_mut_ptr()) }.upgrade()
         {
-            Ok(_) => Ok(frame),
+            Ok(_) => Ok(()),
             Err(AVERROR_EAGAIN) => Err(RsmpegError::DecoderDrainError),
             Err(ffi::AVERROR_EOF) => Err(RsmpegError::DecoderFlushedError),
             Err(x) => Err(RsmpegError::ReceiveFrameError(x)),
@@ -248,10 +823,18 @@ impl AVCodecContext {
     /// Trying to pull a packet from current encoding_context([`AVCodecContext`]).
     pub fn receive_packet(&mut self) -> Result<AVPacket> {
         let mut packet = AVPacket::new();
+        self.receive_packet_into(&mut packet)?;
+        Ok(packet)
+    }
+
+    /// Same as [`Self::receive_packet()`], receiving into `packet` instead
+    /// of allocating a new one. The previous content of `packet` is
+    /// unreferenced.
+    pub fn receive_packet_into(&mut self, packet: &mut AVPacket) -> Result<()> {
         match unsafe { ffi::avcodec_receive_packet(self.as_mut_ptr(), packet.as_mut_ptr()) }
             .upgrade()
         {
-            Ok(_) => Ok(packet),
+            Ok(_) => Ok(()),
             Err(AVERROR_EAGAIN) => Err(RsmpegError::EncoderDrainError),
             Err(ffi::AVERROR_EOF) => Err(RsmpegError::EncoderFlushedError),
             Err(x) => Err(RsmpegError::ReceivePacketError(x)),
@@ -317,6 +900,55 @@ impl AVCodecContext {
         Ok(())
     }
 
+    /// Encode `subtitle` into a packet, with its `pts` and `duration` in the
+    /// time base of the encoder.
+    ///
+    /// The display times of `subtitle` are normalized before encoding: its
+    /// `pts` is moved to `start_display_time` and `start_display_time` is set
+    /// to 0, same as the `ffmpeg` command line tool does.
+    pub fn encode_subtitle_packet(&mut self, subtitle: &mut AVSubtitle) -> Result<AVPacket> {
+        if subtitle.pts == ffi::AV_NOPTS_VALUE {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let ms = AVRational { num: 1, den: 1000 };
+        let av_time_base_q = AVRational {
+            num: 1,
+            den: ffi::AV_TIME_BASE as i32,
+        };
+        unsafe {
+            let subtitle = subtitle.deref_mut();
+            subtitle.pts +=
+                ffi::av_rescale_q(subtitle.start_display_time as i64, ms, av_time_base_q);
+            subtitle.end_display_time -= subtitle.start_display_time;
+            subtitle.start_display_time = 0;
+        }
+
+        // Same as the `ffmpeg` command line tool.
+        let mut buf = vec![0u8; 1024 * 1024];
+        let size = unsafe {
+            ffi::avcodec_encode_subtitle(
+                self.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len() as i32,
+                subtitle.as_ptr(),
+            )
+        }
+        .upgrade()?;
+
+        let mut packet = AVPacket::new();
+        unsafe { ffi::av_new_packet(packet.as_mut_ptr(), size) }.upgrade()?;
+        unsafe { ptr::copy_nonoverlapping(buf.as_ptr(), packet.data, size as usize) };
+        let pts = av_rescale_q(subtitle.pts, av_time_base_q, self.time_base);
+        packet.set_pts(pts);
+        packet.set_dts(pts);
+        packet.set_duration(av_rescale_q(
+            subtitle.end_display_time as i64,
+            ms,
+            self.time_base,
+        ));
+        Ok(packet)
+    }
+
     /// Fill the codec context based on the values from the supplied codec parameters.
     ///
     /// ATTENTION: There is no codecpar field in `AVCodecContext`, this function
@@ -326,6 +958,9 @@ impl AVCodecContext {
     /// of the corresponding field in `codecpar`. Fields in current
     /// `AVCodecContext` that do not have a counterpart in given `codecpar` are
     /// not touched.
+    ///
+    /// The references returned by [`AVStream::codecpar()`] are accepted
+    /// borrowed, e.g. `&stream.codecpar()`.
     pub fn apply_codecpar(&mut self, codecpar: &AVCodecParameters) -> Result<()> {
         unsafe { ffi::avcodec_parameters_to_context(self.as_mut_ptr(), codecpar.as_ptr()) }
             .upgrade()?;
@@ -362,6 +997,33 @@ impl AVCodecContext {
         unsafe { self.deref_mut().hw_frames_ctx = hw_frames_ctx.buffer_ref.into_raw().as_ptr() };
     }
 
+    /// Reset the internal codec state / flush internal buffers. Should be
+    /// called e.g. when seeking or when switching to a different stream.
+    pub fn flush_buffers(&mut self) {
+        unsafe { ffi::avcodec_flush_buffers(self.as_mut_ptr()) }
+    }
+
+    /// Get the current [`DecodePolicy`] of the decoder.
+    pub fn decode_policy(&self) -> DecodePolicy {
+        DecodePolicy {
+            skip_loop_filter: self.skip_loop_filter,
+            skip_frame: self.skip_frame,
+            lowres: self.lowres,
+            error_concealment: self.error_concealment,
+        }
+    }
+
+    /// Apply all fields of the [`DecodePolicy`] to the decoder at once.
+    ///
+    /// Note: `lowres` only takes effect if set before [`Self::open()`].
+    pub fn set_decode_policy(&mut self, policy: &DecodePolicy) {
+        let context = unsafe { self.deref_mut() };
+        context.skip_loop_filter = policy.skip_loop_filter;
+        context.skip_frame = policy.skip_frame;
+        context.lowres = policy.lowres;
+        context.error_concealment = policy.error_concealment;
+    }
+
     /// Is hardware accelaration enabled in this codec context.
     pub fn is_hwaccel(&self) -> bool {
         // We doesn't expose the `AVHWAccel` because the documentation states:
@@ -381,6 +1043,8 @@ impl<'ctx> AVCodecContext {
 
 impl Drop for AVCodecContext {
     fn drop(&mut self) {
+        // Not freed by FFmpeg, see `Self::set_stats_in()`.
+        self.free_stats_in();
         // A pointer holder
         let mut context = self.as_mut_ptr();
         unsafe {
@@ -389,6 +1053,68 @@ impl Drop for AVCodecContext {
     }
 }
 
+/// Whether a decoder honored the requested output, see
+/// [`AVCodecContext::output_request_status()`]. `None` when nothing was
+/// requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputRequestStatus {
+    /// The sample format matches [`AVCodecContext::set_request_sample_fmt()`].
+    pub sample_fmt: Option<bool>,
+    /// The channel layout matches [`AVCodecContext::set_request_ch_layout()`].
+    pub ch_layout: Option<bool>,
+}
+
+impl OutputRequestStatus {
+    /// Whether all the requests were honored, otherwise the output has to be
+    /// resampled.
+    pub fn is_honored(&self) -> bool {
+        self.sample_fmt != Some(false) && self.ch_layout != Some(false)
+    }
+}
+
+/// Decoder options trading quality for speed, applied together with
+/// [`AVCodecContext::set_decode_policy()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodePolicy {
+    /// Skip loop filtering for the selected frames.
+    pub skip_loop_filter: ffi::AVDiscard,
+    /// Skip decoding for the selected frames.
+    pub skip_frame: ffi::AVDiscard,
+    /// Low resolution decoding, 1 -> 1/2 size, 2 -> 1/4 size.
+    pub lowres: i32,
+    /// Error concealment flags, `FF_EC_*`.
+    pub error_concealment: i32,
+}
+
+impl Default for DecodePolicy {
+    /// FFmpeg's default decoder options.
+    fn default() -> Self {
+        Self {
+            skip_loop_filter: ffi::AVDISCARD_DEFAULT,
+            skip_frame: ffi::AVDISCARD_DEFAULT,
+            lowres: 0,
+            error_concealment: (ffi::FF_EC_GUESS_MVS | ffi::FF_EC_DEBLOCK) as i32,
+        }
+    }
+}
+
+impl DecodePolicy {
+    /// Policy for decoding frames which are only needed to reach a seek
+    /// target: non-reference frames are not decoded and loop filtering is
+    /// skipped.
+    ///
+    /// Note: since loop filtering is skipped for reference frames, the frames
+    /// decoded with this policy and the frames depending on them may have
+    /// visible artifacts.
+    pub fn fast_seek() -> Self {
+        Self {
+            skip_loop_filter: ffi::AVDISCARD_ALL,
+            skip_frame: ffi::AVDISCARD_NONREF,
+            ..Default::default()
+        }
+    }
+}
+
 wrap_ref_mut!(AVSubtitle: ffi::AVSubtitle);
 
 impl Default for AVSubtitle {
@@ -415,6 +1141,18 @@ impl AVSubtitle {
     }
 }
 
+impl<'sub> AVSubtitle {
+    /// Get the rectangles of the subtitle.
+    pub fn rects(&'sub self) -> &'sub [AVSubtitleRectRef<'sub>] {
+        if self.rects.is_null() {
+            return &[];
+        }
+        // AVSubtitleRectRef <-> *const ffi::AVSubtitleRect due to repr(transparent)
+        let rects = self.rects as *const AVSubtitleRectRef<'sub>;
+        unsafe { slice::from_raw_parts(rects, self.num_rects as usize) }
+    }
+}
+
 impl Drop for AVSubtitle {
     fn drop(&mut self) {
         unsafe {
@@ -426,6 +1164,41 @@ impl Drop for AVSubtitle {
     }
 }
 
+wrap_ref!(#[repr(transparent)] AVSubtitleRect: ffi::AVSubtitleRect);
+
+impl<'rect> AVSubtitleRect {
+    /// Get the plain text, only for `SUBTITLE_TEXT` rectangles.
+    pub fn text(&'rect self) -> Option<&'rect CStr> {
+        NonNull::new(self.text).map(|x| unsafe { CStr::from_ptr(x.as_ptr()) })
+    }
+
+    /// Get the ASS dialogue line, only for `SUBTITLE_ASS` rectangles.
+    pub fn ass(&'rect self) -> Option<&'rect CStr> {
+        NonNull::new(self.ass).map(|x| unsafe { CStr::from_ptr(x.as_ptr()) })
+    }
+
+    /// Get the palette indexes of the bitmap, `linesize[0] * h` bytes, only for
+    /// `SUBTITLE_BITMAP` rectangles.
+    pub fn bitmap(&'rect self) -> Option<&'rect [u8]> {
+        if self.data[0].is_null() || self.linesize[0] <= 0 || self.h <= 0 {
+            return None;
+        }
+        let len = self.linesize[0] as usize * self.h as usize;
+        Some(unsafe { slice::from_raw_parts(self.data[0], len) })
+    }
+
+    /// Get the palette of the bitmap, `nb_colors` RGBA colors in native
+    /// endian, only for `SUBTITLE_BITMAP` rectangles.
+    pub fn palette(&'rect self) -> Option<&'rect [u32]> {
+        if self.data[1].is_null() || self.nb_colors <= 0 {
+            return None;
+        }
+        // The palette is allocated with av_malloc, which is aligned.
+        let palette = self.data[1] as *const u32;
+        Some(unsafe { slice::from_raw_parts(palette, self.nb_colors as usize) })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -452,4 +1225,510 @@ mod tests {
             println!("codec: {:?}: {:?}", codec.name(), codec.long_name());
         }
     }
+
+    #[test]
+    fn test_static_refs_are_sync() {
+        fn assert_sync<T: Send + Sync>(_: &T) {}
+        let codec = AVCodec::find_decoder(ffi::AV_CODEC_ID_H264).unwrap();
+        assert_sync(&codec);
+        assert_sync(&crate::avformat::AVOutputFormat::iterate().next().unwrap());
+        assert_sync(&crate::avformat::AVInputFormat::iterate().next().unwrap());
+        assert_sync(&crate::avfilter::AVFilter::get_by_name(cstr!("scale")).unwrap());
+        assert_sync(&crate::avutil::AVPixFmtDescriptorRef::get(ffi::AV_PIX_FMT_YUV420P).unwrap());
+
+        // Shared by reference between threads.
+        let names: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..2)
+                .map(|_| s.spawn(|| codec.name().to_owned()))
+                .collect();
+            handles.into_iter().map(|x| x.join().unwrap()).collect()
+        });
+        assert_eq!(names, [cstr!("h264"), cstr!("h264")]);
+    }
+
+    #[test]
+    fn test_set_get_format() {
+        use crate::avformat::AVFormatContextInput;
+        use std::sync::{Arc, Mutex};
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, codec) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let mut decoder = AVCodecContext::new(&codec);
+        decoder
+            .apply_codecpar(&input.streams()[video_index].codecpar())
+            .unwrap();
+        let offered = Arc::new(Mutex::new(vec![]));
+        {
+            let offered = offered.clone();
+            decoder.set_get_format(move |formats| {
+                offered.lock().unwrap().extend_from_slice(formats);
+                // The last one is a software format.
+                *formats.last().unwrap()
+            });
+        }
+        decoder.open(None).unwrap();
+
+        let frame = loop {
+            let packet = input.read_packet().unwrap().unwrap();
+            if packet.stream_index as usize != video_index {
+                continue;
+            }
+            decoder.send_packet(Some(&packet)).unwrap();
+            match decoder.receive_frame() {
+                Ok(frame) => break frame,
+                Err(RsmpegError::DecoderDrainError) => {}
+                Err(e) => panic!("{e}"),
+            }
+        };
+        let offered = offered.lock().unwrap();
+        assert!(offered.contains(&frame.format));
+        assert!(!offered.contains(&ffi::AV_PIX_FMT_NONE));
+
+        // A panicking callback fails the decoding instead of aborting.
+        let mut decoder = AVCodecContext::new(&codec);
+        decoder
+            .apply_codecpar(&input.streams()[video_index].codecpar())
+            .unwrap();
+        decoder.set_get_format(|_| panic!("no format"));
+        decoder.open(None).unwrap();
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let failed = loop {
+            let Some(packet) = input.read_packet().unwrap() else {
+                break false;
+            };
+            if packet.stream_index as usize != video_index {
+                continue;
+            }
+            let result = decoder
+                .send_packet(Some(&packet))
+                .and_then(|_| decoder.receive_frame().map(drop));
+            match result {
+                Ok(()) | Err(RsmpegError::DecoderDrainError) => {}
+                Err(_) => break true,
+            }
+        };
+        assert!(failed);
+    }
+
+    #[test]
+    fn test_open_with_and_reinit() {
+        use crate::avformat::AVFormatContextInput;
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, video_codec) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let (audio_index, audio_codec) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_AUDIO)
+            .unwrap()
+            .unwrap();
+
+        let mut decoder = AVCodecContext::new(&video_codec);
+        decoder
+            .apply_codecpar(&input.streams()[video_index].codecpar())
+            .unwrap();
+        // The codec must match the one the context was allocated for.
+        assert_eq!(
+            decoder.open_with(&audio_codec, None).err(),
+            Some(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))
+        );
+        decoder.open_with(&video_codec, None).unwrap();
+        assert_eq!(decoder.codec_id, video_codec.id);
+
+        decoder.reinit(&audio_codec);
+        decoder
+            .apply_codecpar(&input.streams()[audio_index].codecpar())
+            .unwrap();
+        decoder.open_with(&audio_codec, None).unwrap();
+        assert_eq!(decoder.codec_id, audio_codec.id);
+
+        let mut decoded = false;
+        while let Some(packet) = input.read_packet().unwrap() {
+            if packet.stream_index as usize != audio_index {
+                continue;
+            }
+            decoder.send_packet(Some(&packet)).unwrap();
+            match decoder.receive_frame() {
+                Ok(frame) => {
+                    assert!(frame.nb_samples > 0);
+                    decoded = true;
+                    break;
+                }
+                Err(RsmpegError::DecoderDrainError) => {}
+                Err(e) => panic!("{e}"),
+            }
+        }
+        assert!(decoded);
+    }
+
+    #[test]
+    fn test_subtitle_transcode() {
+        use crate::avformat::AVFormatContextInput;
+        use std::ffi::CString;
+
+        let tempdir = tempdir::TempDir::new("subtitle").unwrap();
+        let path = tempdir.path().join("sub.srt");
+        std::fs::write(&path, "1\n00:00:01,000 --> 00:00:02,500\nHello\n").unwrap();
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let mut input = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        let stream = &input.streams()[0];
+        let time_base = stream.time_base;
+        let codec = AVCodec::find_decoder(stream.codecpar().codec_id).unwrap();
+        let mut decoder = AVCodecContext::new(&codec);
+        decoder.apply_codecpar(&stream.codecpar()).unwrap();
+        decoder.set_pkt_timebase(time_base);
+        decoder.open(None).unwrap();
+
+        let mut packet = input.read_packet().unwrap().unwrap();
+        let mut subtitle = decoder.decode_subtitle(Some(&mut packet)).unwrap().unwrap();
+        assert_eq!(subtitle.rects().len(), 1);
+        let rect = &subtitle.rects()[0];
+        assert_eq!(rect.type_, ffi::SUBTITLE_ASS);
+        assert!(rect.ass().unwrap().to_str().unwrap().ends_with("Hello"));
+        assert!(rect.text().is_none());
+        assert!(rect.bitmap().is_none());
+
+        let codec = AVCodec::find_encoder_by_name(cstr!("ass")).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_time_base(AVRational { num: 1, den: 1000 });
+        encoder
+            .set_subtitle_header(decoder.subtitle_header())
+            .unwrap();
+        assert_eq!(encoder.subtitle_header(), decoder.subtitle_header());
+        encoder.open(None).unwrap();
+        let packet = encoder.encode_subtitle_packet(&mut subtitle).unwrap();
+        assert_eq!(packet.pts, 1000);
+        assert_eq!(packet.duration, 1500);
+        assert!(packet.size > 0);
+    }
+
+    #[test]
+    fn test_two_pass_stats() {
+        fn encoder() -> AVCodecContext {
+            let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_MPEG4).unwrap();
+            let mut encoder = AVCodecContext::new(&codec);
+            encoder.set_width(64);
+            encoder.set_height(64);
+            encoder.set_pix_fmt(ffi::AV_PIX_FMT_YUV420P);
+            encoder.set_time_base(AVRational { num: 1, den: 25 });
+            encoder.set_bit_rate(200_000);
+            encoder.set_codec_flags(CodecFlags::PSNR);
+            encoder
+        }
+
+        fn encode(encoder: &mut AVCodecContext, stats: &mut Vec<u8>) -> Vec<AVPacket> {
+            let mut packets = vec![];
+            for i in 0..10 {
+                let mut frame = AVFrame::new();
+                frame.set_width(64);
+                frame.set_height(64);
+                frame.set_format(ffi::AV_PIX_FMT_YUV420P);
+                frame.set_pts(i);
+                frame.alloc_buffer().unwrap();
+                for plane in 0..3 {
+                    frame.plane_data_mut(plane).unwrap().fill(i as u8 * 20);
+                }
+                encoder.send_frame(Some(&frame)).unwrap();
+                while let Ok(packet) = encoder.receive_packet() {
+                    if let Some(stats_out) = encoder.stats_out() {
+                        stats.extend_from_slice(stats_out.to_bytes());
+                    }
+                    packets.push(packet);
+                }
+            }
+            encoder.send_frame(None).unwrap();
+            while let Ok(packet) = encoder.receive_packet() {
+                if let Some(stats_out) = encoder.stats_out() {
+                    stats.extend_from_slice(stats_out.to_bytes());
+                }
+                packets.push(packet);
+            }
+            packets
+        }
+
+        let mut first = encoder();
+        first.set_first_pass();
+        assert_eq!(first.codec_flags(), CodecFlags::PSNR | CodecFlags::PASS1);
+        first.open(None).unwrap();
+        let mut stats = vec![];
+        let packets = encode(&mut first, &mut stats);
+        assert!(!stats.is_empty());
+        assert!(packets[0].is_keyframe());
+        let quality_stats = packets[0].quality_stats().unwrap();
+        assert!(quality_stats.quality > 0);
+        assert_eq!(quality_stats.pict_type, ffi::AV_PICTURE_TYPE_I);
+        assert!(!quality_stats.error.is_empty());
+
+        let mut second = encoder();
+        second.set_first_pass();
+        let stats = std::ffi::CString::new(stats).unwrap();
+        second.set_stats_in(cstr!("replaced")).unwrap();
+        second.set_second_pass(&stats).unwrap();
+        assert_eq!(second.codec_flags(), CodecFlags::PSNR | CodecFlags::PASS2);
+        second.open(None).unwrap();
+        assert_eq!(encode(&mut second, &mut vec![]).len(), packets.len());
+
+        // A `stats_in` set by the caller isn't freed with the context.
+        let mut third = encoder();
+        unsafe { third.deref_mut().stats_in = cstr!("static").as_ptr() as *mut _ };
+        drop(third);
+    }
+
+    #[test]
+    fn test_receive_into() {
+        use crate::avformat::AVFormatContextInput;
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, codec) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let mut decoder =
+            AVCodecContext::from_stream(&input.streams()[video_index], &codec).unwrap();
+        decoder.open(None).unwrap();
+        let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_MPEG4).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_width(decoder.width);
+        encoder.set_height(decoder.height);
+        encoder.set_pix_fmt(decoder.pix_fmt);
+        encoder.set_time_base(crate::avutil::ra(1, 25));
+        encoder.open(None).unwrap();
+
+        // The same frame and packet are reused for the whole transcode.
+        let (mut frame, mut out_packet) = (AVFrame::new(), AVPacket::new());
+        let (frame_ptr, out_packet_ptr) = (frame.as_ptr(), out_packet.as_ptr());
+        let (mut packets, mut frames, mut encoded) = (0, 0, 0);
+        let mut flushed = false;
+        while !flushed {
+            if let Some(packet) = input.read_packet().unwrap() {
+                if packet.stream_index as usize != video_index {
+                    continue;
+                }
+                packets += 1;
+                decoder.send_packet(Some(&packet)).unwrap();
+            } else {
+                decoder.send_packet(None).unwrap();
+                flushed = true;
+            }
+            while decoder.receive_frame_into(&mut frame).is_ok() {
+                frames += 1;
+                encoder.send_frame(Some(&frame)).unwrap();
+                while encoder.receive_packet_into(&mut out_packet).is_ok() {
+                    encoded += 1;
+                }
+            }
+        }
+        encoder.send_frame(None).unwrap();
+        while encoder.receive_packet_into(&mut out_packet).is_ok() {
+            encoded += 1;
+        }
+        assert!(matches!(
+            encoder.receive_packet_into(&mut out_packet),
+            Err(RsmpegError::EncoderFlushedError)
+        ));
+        assert!(matches!(
+            decoder.receive_frame_into(&mut frame),
+            Err(RsmpegError::DecoderFlushedError)
+        ));
+        assert_eq!(frames, packets);
+        assert_eq!(encoded, frames);
+        assert_eq!(
+            (frame.as_ptr(), out_packet.as_ptr()),
+            (frame_ptr, out_packet_ptr)
+        );
+    }
+
+    #[test]
+    fn test_from_stream() {
+        use crate::avformat::AVFormatContextInput;
+
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, codec) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let stream = &input.streams()[video_index];
+        let mut decoder = AVCodecContext::from_stream(stream, &codec).unwrap();
+        assert_eq!(decoder.width, stream.codecpar().width);
+        assert_eq!(decoder.height, stream.codecpar().height);
+        assert_eq!(decoder.pkt_timebase.num, stream.time_base.num);
+        assert_eq!(decoder.pkt_timebase.den, stream.time_base.den);
+        decoder.open(None).unwrap();
+        let encoder = AVCodec::find_encoder(codec.id).unwrap();
+        assert!(AVCodecContext::from_stream(stream, &encoder).is_err());
+
+        // Owned parameters and references are both accepted.
+        let mut decoder = AVCodecContext::new(&codec);
+        let codecpar = stream.codecpar();
+        decoder.apply_codecpar(&codecpar.clone()).unwrap();
+        decoder.apply_codecpar(&codecpar).unwrap();
+        assert_eq!(decoder.width, codecpar.width);
+    }
+
+    #[test]
+    fn test_set_extradata() {
+        use crate::avformat::AVFormatContextInput;
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, codec) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let extradata = input.streams()[video_index].codecpar().extradata().to_vec();
+
+        // Only the extradata is needed to decode H.264.
+        let mut decoder = AVCodecContext::new(&codec);
+        assert!(decoder.extradata().is_empty());
+        decoder.set_extradata(&extradata).unwrap();
+        assert_eq!(decoder.extradata(), extradata);
+        let padding = unsafe {
+            slice::from_raw_parts(
+                decoder.extradata.add(extradata.len()),
+                ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize,
+            )
+        };
+        assert!(padding.iter().all(|x| *x == 0));
+        decoder.open(None).unwrap();
+        let frame = loop {
+            let packet = input.read_packet().unwrap().unwrap();
+            if packet.stream_index as usize != video_index {
+                continue;
+            }
+            decoder.send_packet(Some(&packet)).unwrap();
+            match decoder.receive_frame() {
+                Ok(frame) => break frame,
+                Err(RsmpegError::DecoderDrainError) => continue,
+                Err(e) => panic!("{e}"),
+            }
+        };
+        assert_eq!(frame.width, input.streams()[video_index].codecpar().width);
+        assert_eq!(decoder.extract_codecpar().extradata(), extradata);
+
+        decoder.set_extradata(&[]).unwrap();
+        assert!(decoder.extradata().is_empty());
+        assert!(decoder.extradata.is_null());
+    }
+
+    #[test]
+    fn test_priv_options() {
+        let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_MPEG4).unwrap();
+        let names = codec.priv_option_names();
+        assert!(names.contains(&cstr!("motion_est")));
+        assert!(!names.contains(&cstr!("epzs")));
+
+        let values = codec.priv_option_values(cstr!("motion_est")).unwrap();
+        let names: Vec<_> = values.iter().map(|x| x.name).collect();
+        assert!(names.contains(&cstr!("zero")));
+        assert!(names.contains(&cstr!("epzs")));
+        let zero = values.iter().find(|x| x.name == cstr!("zero")).unwrap();
+        assert_eq!(zero.value, 0);
+
+        assert!(codec.priv_option_values(cstr!("__random__")).is_none());
+    }
+
+    #[test]
+    fn test_output_request() {
+        use crate::avformat::AVFormatContextInput;
+
+        let decode = |sample_fmt| {
+            let mut input = AVFormatContextInput::open(
+                cstr!("tests/assets/audios/sample1.aac"),
+                None,
+                &mut None,
+            )
+            .unwrap();
+            let (index, codec) = input
+                .find_best_stream(ffi::AVMEDIA_TYPE_AUDIO)
+                .unwrap()
+                .unwrap();
+            let mut decoder = AVCodecContext::from_stream(&input.streams()[index], &codec).unwrap();
+            assert!(decoder.supports_sample_fmt(ffi::AV_SAMPLE_FMT_FLTP));
+            assert!(!decoder.supports_sample_fmt(ffi::AV_SAMPLE_FMT_U8));
+            decoder.set_request_sample_fmt(sample_fmt);
+            let layout = AVChannelLayout::from_nb_channels(1);
+            // The AAC decoder can't downmix.
+            assert!(!decoder.set_request_ch_layout(&layout).unwrap());
+            assert_eq!(decoder.output_request_status().ch_layout, None);
+            decoder.open(None).unwrap();
+            loop {
+                let packet = input.read_packet().unwrap().unwrap();
+                decoder.send_packet(Some(&packet)).unwrap();
+                if decoder.receive_frame().is_ok() {
+                    return decoder.output_request_status();
+                }
+            }
+        };
+
+        let status = decode(ffi::AV_SAMPLE_FMT_FLTP);
+        assert_eq!(status.sample_fmt, Some(true));
+        assert!(status.is_honored());
+        let status = decode(ffi::AV_SAMPLE_FMT_U8);
+        assert_eq!(status.sample_fmt, Some(false));
+        assert!(!status.is_honored());
+        let status = decode(ffi::AV_SAMPLE_FMT_NONE);
+        assert_eq!(status, OutputRequestStatus::default());
+    }
+
+    #[test]
+    fn test_codec_capabilities() {
+        let encoder = AVCodec::find_encoder(ffi::AV_CODEC_ID_AAC).unwrap();
+        assert!(encoder.is_encoder());
+        assert!(!encoder.is_decoder());
+        assert!(encoder.media_type().is_audio());
+        assert!(encoder.has_delay());
+        assert!(!encoder.supports_variable_frame_size());
+        assert!(!encoder.is_intra_only());
+
+        let decoder = AVCodec::find_decoder(ffi::AV_CODEC_ID_MJPEG).unwrap();
+        assert!(decoder.is_decoder());
+        assert!(!decoder.is_encoder());
+        assert!(decoder.is_intra_only());
+        assert!(decoder
+            .codec_capabilities()
+            .contains(CodecCapabilities::DR1));
+
+        let pcm = AVCodec::find_encoder(ffi::AV_CODEC_ID_PCM_S16LE).unwrap();
+        assert!(pcm.supports_variable_frame_size());
+        assert!(!pcm.is_experimental());
+        assert!(!pcm.is_hardware());
+    }
+
+    #[test]
+    fn test_profile_and_level() {
+        let decoder = AVCodec::find_decoder(ffi::AV_CODEC_ID_H264).unwrap();
+        assert!(decoder.profiles().contains(&(100, cstr!("High"))));
+        assert_eq!(
+            decoder.profile_name(Profile::H264_HIGH),
+            Some(cstr!("High"))
+        );
+        assert_eq!(decoder.profile_name(Profile::UNKNOWN), None);
+
+        let encoder = AVCodec::find_encoder(ffi::AV_CODEC_ID_MPEG4).unwrap();
+        let mut encode_context = AVCodecContext::new(&encoder);
+        assert_eq!(encode_context.profile(), Profile::UNKNOWN);
+        assert_eq!(encode_context.level(), Level::UNKNOWN);
+        encode_context.set_profile(Profile::H264_HIGH);
+        encode_context.set_level(Level::h264(4, 1));
+        assert_eq!(encode_context.profile, 100);
+        assert_eq!(encode_context.level, 41);
+        encode_context.set_profile(0);
+        assert_eq!(encode_context.profile(), Profile(0));
+    }
 }
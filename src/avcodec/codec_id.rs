@@ -1 +1,243 @@
+use crate::{avcodec::profile_list, avutil::AVMediaType, ffi, shared::PointerUpgrade};
+use std::{ffi::CStr, fmt, ops::Deref, ptr::NonNull};
+
 pub use crate::ffi::AVCodecID;
+
+/// Typed wrapper of [`AVCodecID`], convertible from and into the bare integer
+/// so it can be passed wherever a codec id is expected.
+///
+/// Unlike [`AVCodec`](crate::avcodec::AVCodec), which is an implementation
+/// of a codec, e.g. `libx265`, a codec id identifies the format of the
+/// encoded data, e.g. `hevc`.
+///
+/// ```rust
+/// # use rsmpeg::{avcodec::CodecId, ffi};
+/// # use cstr::cstr;
+/// let codec_id = CodecId::from_name(cstr!("hevc")).unwrap();
+/// assert_eq!(codec_id, CodecId(ffi::AV_CODEC_ID_HEVC));
+/// assert!(codec_id.media_type().is_video());
+/// assert_eq!(codec_id.to_string(), "hevc");
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CodecId(pub AVCodecID);
+
+impl CodecId {
+    pub const NONE: Self = Self(ffi::AV_CODEC_ID_NONE);
+
+    /// Return the codec id whose descriptor has the given name, or `None` if
+    /// it's not recognized.
+    pub fn from_name(name: &CStr) -> Option<Self> {
+        AVCodecDescriptorRef::get_by_name(name).map(|x| Self(x.id))
+    }
+
+    /// Get the name of the codec id, `"unknown_codec"` if it's unknown.
+    pub fn name(&self) -> &'static CStr {
+        // `avcodec_get_name()` never returns null.
+        unsafe { CStr::from_ptr(ffi::avcodec_get_name(self.0)) }
+    }
+
+    /// Get the type of the data encoded with this codec id.
+    pub fn media_type(&self) -> AVMediaType {
+        AVMediaType(unsafe { ffi::avcodec_get_type(self.0) })
+    }
+
+    /// Get the descriptor of the codec id, or `None` if it's unknown.
+    pub fn descriptor(&self) -> Option<AVCodecDescriptorRef> {
+        AVCodecDescriptorRef::get(self.0)
+    }
+
+    /// Get the name of `profile` of this codec id, or `None` if it's not
+    /// recognized.
+    pub fn profile_name(&self, profile: impl Into<i32>) -> Option<&'static CStr> {
+        unsafe {
+            ffi::avcodec_profile_name(self.0, profile.into())
+                .upgrade()
+                .map(|x| CStr::from_ptr(x.as_ptr()))
+        }
+    }
+}
+
+impl From<AVCodecID> for CodecId {
+    fn from(codec_id: AVCodecID) -> Self {
+        Self(codec_id)
+    }
+}
+
+impl From<CodecId> for AVCodecID {
+    fn from(codec_id: CodecId) -> Self {
+        codec_id.0
+    }
+}
+
+impl fmt::Display for CodecId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name().to_string_lossy())
+    }
+}
+
+bitflags::bitflags! {
+    /// Properties of a [`AVCodecDescriptorRef`], the `AV_CODEC_PROP_*`
+    /// constants.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CodecProps: i32 {
+        /// Codec uses only intra compression.
+        const INTRA_ONLY = ffi::AV_CODEC_PROP_INTRA_ONLY as i32;
+        /// Codec supports lossy compression.
+        const LOSSY = ffi::AV_CODEC_PROP_LOSSY as i32;
+        /// Codec supports lossless compression.
+        const LOSSLESS = ffi::AV_CODEC_PROP_LOSSLESS as i32;
+        /// Codec supports frame reordering.
+        const REORDER = ffi::AV_CODEC_PROP_REORDER as i32;
+        /// Codec supports separate field coding.
+        const FIELDS = ffi::AV_CODEC_PROP_FIELDS as i32;
+        /// Subtitle codec is bitmap based.
+        const BITMAP_SUB = ffi::AV_CODEC_PROP_BITMAP_SUB as i32;
+        /// Subtitle codec is text based.
+        const TEXT_SUB = ffi::AV_CODEC_PROP_TEXT_SUB as i32;
+
+        const _ = !0;
+    }
+}
+
+/// Describes the properties of a single codec id, independently of the
+/// encoders and decoders implementing it.
+pub struct AVCodecDescriptorRef(NonNull<ffi::AVCodecDescriptor>);
+
+// The descriptors are static and immutable.
+unsafe impl Send for AVCodecDescriptorRef {}
+unsafe impl Sync for AVCodecDescriptorRef {}
+
+impl Deref for AVCodecDescriptorRef {
+    type Target = ffi::AVCodecDescriptor;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.as_ref() }
+    }
+}
+
+impl AVCodecDescriptorRef {
+    /// Return the descriptor of the given codec id or `None` if it's unknown.
+    pub fn get(id: AVCodecID) -> Option<Self> {
+        unsafe { ffi::avcodec_descriptor_get(id).upgrade().map(Self) }
+    }
+
+    /// Return the descriptor with the given name or `None` if there is none.
+    pub fn get_by_name(name: &CStr) -> Option<Self> {
+        unsafe {
+            ffi::avcodec_descriptor_get_by_name(name.as_ptr())
+                .upgrade()
+                .map(Self)
+        }
+    }
+
+    /// Iterate over all codec descriptors known to libavcodec.
+    ///
+    /// Return next descriptor or None after the last descriptor
+    pub fn next(&self) -> Option<Self> {
+        unsafe {
+            ffi::avcodec_descriptor_next(self.0.as_ptr())
+                .upgrade()
+                .map(Self)
+        }
+    }
+
+    /// Get the codec id described.
+    pub fn codec_id(&self) -> CodecId {
+        CodecId(self.id)
+    }
+
+    /// Get the name of the descriptor, e.g. `hevc`.
+    pub fn name(&self) -> &'static CStr {
+        // FFmpeg's implementation: name is always non-null
+        unsafe { CStr::from_ptr(self.name) }
+    }
+
+    /// Get the descriptive name of the descriptor.
+    pub fn long_name(&self) -> Option<&'static CStr> {
+        self.long_name
+            .upgrade()
+            .map(|x| unsafe { CStr::from_ptr(x.as_ptr()) })
+    }
+
+    /// Get the type of the data encoded with the codec id.
+    pub fn media_type(&self) -> AVMediaType {
+        AVMediaType(self.type_)
+    }
+
+    /// Get the `props` field as [`CodecProps`].
+    pub fn codec_props(&self) -> CodecProps {
+        CodecProps::from_bits_retain(self.props)
+    }
+
+    /// Get the MIME types associated with the codec id, the preferred one
+    /// first.
+    pub fn mime_types(&self) -> Vec<&'static CStr> {
+        let mut mime_types = vec![];
+        if let Some(ptr) = self.mime_types.upgrade() {
+            let mut ptr = ptr.as_ptr();
+            while let Some(mime_type) = unsafe { *ptr }.upgrade() {
+                mime_types.push(unsafe { CStr::from_ptr(mime_type.as_ptr()) });
+                ptr = unsafe { ptr.add(1) };
+            }
+        }
+        mime_types
+    }
+
+    /// Get the `(profile, name)` pairs of the profiles recognized for the
+    /// codec id, e.g. `(1, "Main")` for hevc.
+    pub fn profiles(&self) -> Vec<(i32, &'static CStr)> {
+        unsafe { profile_list(self.profiles) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avcodec::Profile;
+    use cstr::cstr;
+
+    #[test]
+    fn test_codec_id() {
+        let hevc = CodecId::from_name(cstr!("hevc")).unwrap();
+        assert_eq!(hevc, CodecId::from(ffi::AV_CODEC_ID_HEVC));
+        assert_eq!(hevc.name(), cstr!("hevc"));
+        assert!(hevc.media_type().is_video());
+        assert_eq!(AVCodecID::from(hevc), ffi::AV_CODEC_ID_HEVC);
+
+        assert_eq!(CodecId::from_name(cstr!("libx265")), None);
+        assert_eq!(CodecId::NONE.to_string(), "none");
+        assert!(CodecId(ffi::AV_CODEC_ID_AAC).media_type().is_audio());
+
+        let h264 = CodecId(ffi::AV_CODEC_ID_H264);
+        assert_eq!(h264.profile_name(Profile::H264_HIGH), Some(cstr!("High")));
+        assert_eq!(h264.profile_name(Profile::UNKNOWN), None);
+    }
+
+    #[test]
+    fn test_codec_descriptor() {
+        let hevc = CodecId(ffi::AV_CODEC_ID_HEVC).descriptor().unwrap();
+        assert_eq!(hevc.codec_id(), CodecId(ffi::AV_CODEC_ID_HEVC));
+        assert_eq!(hevc.name(), cstr!("hevc"));
+        assert!(hevc.long_name().is_some());
+        assert!(hevc
+            .codec_props()
+            .contains(CodecProps::LOSSY | CodecProps::REORDER));
+        assert!(hevc.profiles().contains(&(1, cstr!("Main"))));
+
+        let png = AVCodecDescriptorRef::get_by_name(cstr!("png")).unwrap();
+        assert_eq!(png.mime_types(), [cstr!("image/png")]);
+        assert!(png
+            .codec_props()
+            .contains(CodecProps::INTRA_ONLY | CodecProps::LOSSLESS));
+
+        let ass = CodecId(ffi::AV_CODEC_ID_ASS).descriptor().unwrap();
+        assert!(ass.media_type().is_subtitle());
+        assert!(ass.codec_props().contains(CodecProps::TEXT_SUB));
+
+        let next = AVCodecDescriptorRef::get(ffi::AV_CODEC_ID_MPEG1VIDEO)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(next.codec_id(), CodecId(ffi::AV_CODEC_ID_MPEG2VIDEO));
+    }
+}
@@ -1,12 +1,32 @@
 use crate::{
-    avcodec::AVCodecContext,
-    avutil::{AVChannelLayoutRef, AVMediaType},
+    avcodec::{AVCodecContext, AVCodecID, CodecId, Level, Profile},
+    avutil::{
+        display_rotation_get, AVChannelLayout, AVChannelLayoutRef, AVMediaType, AVRational,
+        PixelFormat, SampleFormat,
+    },
+    error::Result,
     ffi,
     shared::*,
 };
-use std::{fmt, ops::Deref, ptr::NonNull};
+use std::{
+    ffi::CStr,
+    fmt,
+    ptr::{self, NonNull},
+    slice,
+};
+
+#[cfg(feature = "ffmpeg7")]
+use crate::avutil::{display_matrix_to_bytes, display_rotation_set};
 
 wrap_ref_mut!(AVCodecParameters: ffi::AVCodecParameters);
+settable!(AVCodecParameters {
+    width: i32,
+    height: i32,
+    sample_aspect_ratio: AVRational,
+    sample_rate: i32,
+    bit_rate: i64,
+    codec_tag: u32,
+});
 
 impl AVCodecParameters {
     /// The constructor.
@@ -45,16 +65,286 @@ impl AVCodecParameters {
         AVMediaType(self.codec_type)
     }
 
+    /// Set the codec type, accepts both [`AVMediaType`] and the bare
+    /// [`ffi::AVMediaType`].
+    pub fn set_codec_type(&mut self, codec_type: impl Into<ffi::AVMediaType>) {
+        unsafe { self.deref_mut().codec_type = codec_type.into() }
+    }
+
+    /// Get the `codec_id` field as [`CodecId`].
+    pub fn codec_id(&self) -> CodecId {
+        CodecId(self.codec_id)
+    }
+
+    /// Set the codec id, accepts both [`CodecId`] and the bare
+    /// [`AVCodecID`].
+    pub fn set_codec_id(&mut self, codec_id: impl Into<AVCodecID>) {
+        unsafe { self.deref_mut().codec_id = codec_id.into() }
+    }
+
+    /// Get the `format` field of a video stream as [`PixelFormat`].
+    pub fn pixel_format(&self) -> PixelFormat {
+        PixelFormat(self.format)
+    }
+
+    /// Set the pixel format of a video stream, accepts both [`PixelFormat`]
+    /// and the bare `AVPixelFormat`.
+    pub fn set_pix_fmt(&mut self, pix_fmt: impl Into<PixelFormat>) {
+        unsafe { self.deref_mut().format = pix_fmt.into().0 }
+    }
+
+    /// Get the `format` field of an audio stream as [`SampleFormat`].
+    pub fn sample_format(&self) -> SampleFormat {
+        SampleFormat(self.format)
+    }
+
+    /// Set the sample format of an audio stream, accepts both
+    /// [`SampleFormat`] and the bare `AVSampleFormat`.
+    pub fn set_sample_fmt(&mut self, sample_fmt: impl Into<SampleFormat>) {
+        unsafe { self.deref_mut().format = sample_fmt.into().0 }
+    }
+
+    /// Replace the channel layout with a copy of `ch_layout`.
+    pub fn set_ch_layout(&mut self, ch_layout: &AVChannelLayout) {
+        // unwrap: this function only fail on OOM.
+        unsafe { ffi::av_channel_layout_copy(&mut self.deref_mut().ch_layout, ch_layout.as_ptr()) }
+            .upgrade()
+            .unwrap();
+    }
+
+    /// Get the `profile` field as [`Profile`].
+    pub fn profile(&self) -> Profile {
+        Profile(self.profile)
+    }
+
+    /// Set the profile, accepts both [`Profile`] and the bare `i32`.
+    pub fn set_profile(&mut self, profile: impl Into<Profile>) {
+        unsafe { self.deref_mut().profile = profile.into().0 }
+    }
+
+    /// Get the `level` field as [`Level`].
+    pub fn level(&self) -> Level {
+        Level(self.level)
+    }
+
+    /// Set the level, accepts both [`Level`] and the bare `i32`.
+    pub fn set_level(&mut self, level: impl Into<Level>) {
+        unsafe { self.deref_mut().level = level.into().0 }
+    }
+
+    /// Get the out-of-band codec configuration, e.g. the `avcC` box of H.264
+    /// or the `AudioSpecificConfig` of AAC, empty if there is none.
+    pub fn extradata(&self) -> &[u8] {
+        if self.extradata.is_null() || self.extradata_size <= 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.extradata, self.extradata_size as usize) }
+    }
+
+    /// Replace the extradata with a copy of `data`, e.g. the SPS and PPS of a
+    /// raw H.264 elementary stream. An empty `data` removes it.
+    pub fn set_extradata(&mut self, data: &[u8]) -> Result<()> {
+        let (extradata, size) = padded_copy(data)?;
+        unsafe {
+            let codecpar = self.deref_mut();
+            ffi::av_freep(&mut codecpar.extradata as *mut _ as _);
+            codecpar.extradata = extradata;
+            codecpar.extradata_size = size;
+        }
+        Ok(())
+    }
+
+    /// Get the header of a subtitle stream, e.g. the script info and the
+    /// styles of ASS, which is stored in the extradata. Empty for the other
+    /// media types.
+    pub fn subtitle_header(&self) -> &[u8] {
+        if self.codec_type().is_subtitle() {
+            self.extradata()
+        } else {
+            &[]
+        }
+    }
+
+    /// Check if the streams described by `self` and `other` can be joined
+    /// without re-encoding, e.g. by concatenation or at a segment rollover:
+    /// same codec, the same dimensions and pixel format for video, the same
+    /// sample format, sample rate and channel layout for audio, and the same
+    /// profile when both are known.
+    ///
+    /// The level and the extradata may differ, a decoder handles them.
+    pub fn compatible_with(&self, other: &Self) -> bool {
+        let same_profile =
+            |x: i32, y: i32| x == ffi::AV_PROFILE_UNKNOWN || y == ffi::AV_PROFILE_UNKNOWN || x == y;
+        self.codec_type == other.codec_type
+            && self.codec_id == other.codec_id
+            && self.format == other.format
+            && self.width == other.width
+            && self.height == other.height
+            && self.sample_rate == other.sample_rate
+            && self.same_ch_layout(other)
+            && same_profile(self.profile, other.profile)
+    }
+
+    /// Same as [`Self::compatible_with()`], but the profile, the level and
+    /// the extradata must be equal too, so the streams are interchangeable
+    /// for a muxer writing a single codec configuration.
+    pub fn eq_essential(&self, other: &Self) -> bool {
+        self.compatible_with(other)
+            && self.profile == other.profile
+            && self.level == other.level
+            && self.extradata() == other.extradata()
+    }
+
+    fn same_ch_layout(&self, other: &Self) -> bool {
+        unsafe { ffi::av_channel_layout_compare(&self.ch_layout, &other.ch_layout) == 0 }
+    }
+
     /// Get channel layout
     pub fn ch_layout(&self) -> AVChannelLayoutRef {
         let inner = NonNull::new(&self.ch_layout as *const _ as *mut _).unwrap();
         unsafe { AVChannelLayoutRef::from_raw(inner) }
     }
+
+    /// Get the side data of the whole stream, e.g. the display matrix or the
+    /// HDR mastering display metadata.
+    #[cfg(feature = "ffmpeg7")]
+    pub fn coded_side_data(&self) -> &[ffi::AVPacketSideData] {
+        if self.coded_side_data.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.coded_side_data, self.nb_coded_side_data as usize) }
+    }
+
+    /// Get the payload of the stream side data of `side_data_type`.
+    #[cfg(feature = "ffmpeg7")]
+    pub fn get_coded_side_data(&self, side_data_type: ffi::AVPacketSideDataType) -> Option<&[u8]> {
+        let side_data = self
+            .coded_side_data()
+            .iter()
+            .find(|x| x.type_ == side_data_type)?;
+        if side_data.data.is_null() {
+            return None;
+        }
+        Some(unsafe { slice::from_raw_parts(side_data.data, side_data.size) })
+    }
+
+    /// Add stream side data of `side_data_type`, replacing the existing one.
+    #[cfg(feature = "ffmpeg7")]
+    pub fn set_coded_side_data(
+        &mut self,
+        side_data_type: ffi::AVPacketSideDataType,
+        data: &[u8],
+    ) -> Result<()> {
+        let side_data = unsafe {
+            let this = self.deref_mut();
+            ffi::av_packet_side_data_new(
+                &mut this.coded_side_data,
+                &mut this.nb_coded_side_data,
+                side_data_type,
+                data.len(),
+                0,
+            )
+        }
+        .upgrade_or(AVERROR_ENOMEM)?;
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), side_data.as_ref().data, data.len());
+        }
+        Ok(())
+    }
+
+    /// Get the display matrix of the stream, which describes how the decoded
+    /// frames should be transformed for display.
+    #[cfg(feature = "ffmpeg7")]
+    pub fn display_matrix(&self) -> Option<[i32; 9]> {
+        let data = self.get_coded_side_data(ffi::AV_PKT_DATA_DISPLAYMATRIX)?;
+        display_matrix_from_bytes(data)
+    }
+
+    /// Get the clockwise rotation in degrees, within `[0, 360)`, to apply to
+    /// the decoded frames for display, e.g. 90 for a video shot in portrait
+    /// on most phones. `None` if the stream has no display matrix.
+    #[cfg(feature = "ffmpeg7")]
+    pub fn rotation_degrees(&self) -> Option<f64> {
+        self.display_matrix()
+            .map(|x| rotation_from_display_matrix(&x))
+    }
+
+    /// Set the display matrix to a clockwise rotation of `degrees`.
+    #[cfg(feature = "ffmpeg7")]
+    pub fn set_rotation_degrees(&mut self, degrees: f64) -> Result<()> {
+        let data = display_matrix_to_bytes(&display_rotation_set(-degrees));
+        self.set_coded_side_data(ffi::AV_PKT_DATA_DISPLAYMATRIX, &data)
+    }
+}
+
+/// Read a display matrix side data payload.
+pub(crate) fn display_matrix_from_bytes(data: &[u8]) -> Option<[i32; 9]> {
+    if data.len() < 9 * 4 {
+        return None;
+    }
+    let mut matrix = [0i32; 9];
+    for (x, bytes) in matrix.iter_mut().zip(data.chunks_exact(4)) {
+        *x = i32::from_ne_bytes(bytes.try_into().unwrap());
+    }
+    Some(matrix)
+}
+
+/// Clockwise rotation in degrees within `[0, 360)` described by a display
+/// matrix, the same as the one applied by the `ffmpeg` command line tool.
+pub(crate) fn rotation_from_display_matrix(matrix: &[i32; 9]) -> f64 {
+    let theta = -display_rotation_get(matrix);
+    if theta.is_nan() {
+        return 0.0;
+    }
+    let theta = theta.round();
+    theta - 360.0 * (theta / 360.0 + 0.9 / 360.0).floor()
 }
 
 impl fmt::Debug for AVCodecParameters {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.deref().fmt(f)
+        let mut s = f.debug_struct("AVCodecParameters");
+        s.field("codec_type", &format_args!("{}", self.codec_type()))
+            .field("codec_id", &format_args!("{}", self.codec_id()));
+        let codec_type = self.codec_type();
+        if codec_type.is_video() {
+            s.field("format", &format_args!("{}", self.pixel_format()))
+                .field("width", &self.width)
+                .field("height", &self.height);
+        } else if codec_type.is_audio() {
+            s.field("format", &format_args!("{}", self.sample_format()))
+                .field("sample_rate", &self.sample_rate)
+                .field("nb_channels", &self.ch_layout.nb_channels);
+        }
+        s.field("bit_rate", &self.bit_rate)
+            .field("profile", &self.profile)
+            .field("level", &self.level)
+            .field("extradata_size", &self.extradata_size)
+            .finish()
+    }
+}
+
+/// One line summary of the stream, the same as printed by
+/// `av_dump_format()`, e.g. `Video: h264 (High) (avc1 / 0x31637661),
+/// yuv420p(progressive), 320x180, 183 kb/s`.
+impl fmt::Display for AVCodecParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = [0; 256];
+        unsafe {
+            let mut context = ffi::avcodec_alloc_context3(ptr::null());
+            if context.is_null() {
+                return Err(fmt::Error);
+            }
+            let ret = ffi::avcodec_parameters_to_context(context, self.as_ptr());
+            if ret >= 0 {
+                ffi::avcodec_string(buf.as_mut_ptr(), buf.len() as _, context, 0);
+            }
+            ffi::avcodec_free_context(&mut context);
+            if ret < 0 {
+                return Err(fmt::Error);
+            }
+        }
+        let summary = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        f.write_str(&summary.to_string_lossy())
     }
 }
 
@@ -78,3 +368,143 @@ impl Drop for AVCodecParameters {
         unsafe { ffi::avcodec_parameters_free(&mut ptr) }
     }
 }
+
+impl AsRef<AVCodecParameters> for AVCodecParameters {
+    fn as_ref(&self) -> &AVCodecParameters {
+        self
+    }
+}
+
+impl<'a> AsRef<AVCodecParameters> for AVCodecParametersRef<'a> {
+    fn as_ref(&self) -> &AVCodecParameters {
+        self
+    }
+}
+
+impl<'a> AsRef<AVCodecParameters> for AVCodecParametersMut<'a> {
+    fn as_ref(&self) -> &AVCodecParameters {
+        self
+    }
+}
+
+/// Copy `data` into a buffer allocated with `av_malloc`, followed by
+/// `AV_INPUT_BUFFER_PADDING_SIZE` zero bytes as FFmpeg requires for the
+/// extradata and the subtitle headers. Returns a null buffer for an empty
+/// `data`.
+pub(crate) fn padded_copy(data: &[u8]) -> Result<(*mut u8, i32)> {
+    if data.is_empty() {
+        return Ok((ptr::null_mut(), 0));
+    }
+    let size = i32::try_from(data.len())?;
+    let buf = unsafe { ffi::av_mallocz(data.len() + ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize) }
+        .upgrade_or(AVERROR_ENOMEM)?
+        .as_ptr() as *mut u8;
+    unsafe { ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len()) };
+    Ok((buf, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avformat::AVFormatContextInput;
+    use cstr::cstr;
+
+    #[test]
+    fn test_compatible_with() {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let (audio_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_AUDIO)
+            .unwrap()
+            .unwrap();
+        let video = input.streams()[video_index].codecpar().clone();
+        let audio = input.streams()[audio_index].codecpar().clone();
+        assert!(!video.extradata().is_empty());
+        assert!(video.eq_essential(&video.clone()));
+        assert!(!video.compatible_with(&audio));
+        assert!(audio.eq_essential(&audio.clone()));
+
+        let mut other = video.clone();
+        unsafe { other.deref_mut().level += 1 };
+        assert!(video.compatible_with(&other));
+        assert!(!video.eq_essential(&other));
+
+        unsafe { other.deref_mut().profile = ffi::AV_PROFILE_UNKNOWN };
+        assert!(video.compatible_with(&other));
+        unsafe { other.deref_mut().width += 2 };
+        assert!(!video.compatible_with(&other));
+
+        let mut other = audio.clone();
+        unsafe { other.deref_mut().sample_rate *= 2 };
+        assert!(!audio.compatible_with(&other));
+        assert!(AVCodecParameters::new().extradata().is_empty());
+    }
+
+    #[test]
+    fn test_accessors_and_display() {
+        let mut video = AVCodecParameters::new();
+        video.set_codec_type(ffi::AVMEDIA_TYPE_VIDEO);
+        video.set_codec_id(CodecId(ffi::AV_CODEC_ID_H264));
+        video.set_pix_fmt(ffi::AV_PIX_FMT_YUV420P);
+        video.set_width(320);
+        video.set_height(180);
+        video.set_profile(Profile::H264_HIGH);
+        video.set_level(Level::h264(3, 0));
+        video.set_bit_rate(200_000);
+        assert!(video.codec_type().is_video());
+        assert_eq!(video.codec_id(), CodecId(ffi::AV_CODEC_ID_H264));
+        assert_eq!(video.pixel_format(), PixelFormat(ffi::AV_PIX_FMT_YUV420P));
+        assert_eq!((video.width, video.height), (320, 180));
+        assert_eq!(video.profile(), Profile::H264_HIGH);
+        assert_eq!(video.level(), Level(30));
+        let summary = video.to_string();
+        assert!(summary.starts_with("Video: h264 (High)"), "{summary}");
+        assert!(summary.contains("yuv420p"), "{summary}");
+        assert!(summary.contains("320x180"), "{summary}");
+        assert!(summary.contains("200 kb/s"), "{summary}");
+        let debug = format!("{video:?}");
+        assert!(debug.contains("codec_id: h264"), "{debug}");
+        assert!(
+            debug.contains("format: yuv420p, width: 320, height: 180"),
+            "{debug}"
+        );
+
+        let mut audio = AVCodecParameters::new();
+        audio.set_codec_type(AVMediaType(ffi::AVMEDIA_TYPE_AUDIO));
+        audio.set_codec_id(ffi::AV_CODEC_ID_AAC);
+        audio.set_sample_fmt(ffi::AV_SAMPLE_FMT_FLTP);
+        audio.set_sample_rate(44100);
+        audio.set_ch_layout(&AVChannelLayout::from_nb_channels(2));
+        assert_eq!(audio.sample_format(), SampleFormat(ffi::AV_SAMPLE_FMT_FLTP));
+        assert_eq!(audio.ch_layout().nb_channels, 2);
+        assert!(audio.clone().compatible_with(&audio));
+        assert_eq!(audio.to_string(), "Audio: aac, 44100 Hz, stereo, fltp");
+        let debug = format!("{audio:?}");
+        assert!(
+            debug.contains("format: fltp, sample_rate: 44100, nb_channels: 2"),
+            "{debug}"
+        );
+    }
+
+    #[test]
+    fn test_set_extradata() {
+        let mut codecpar = AVCodecParameters::new();
+        codecpar.set_extradata(b"[Script Info]").unwrap();
+        assert_eq!(codecpar.extradata(), b"[Script Info]");
+        assert_eq!(unsafe { *codecpar.extradata.add(13) }, 0);
+        assert!(codecpar.subtitle_header().is_empty());
+        unsafe { codecpar.deref_mut().codec_type = ffi::AVMEDIA_TYPE_SUBTITLE };
+        assert_eq!(codecpar.subtitle_header(), b"[Script Info]");
+
+        // Duplicated by the copies.
+        let copy = codecpar.clone();
+        codecpar.set_extradata(b"").unwrap();
+        assert!(codecpar.extradata().is_empty());
+        assert_eq!(copy.extradata(), b"[Script Info]");
+    }
+}
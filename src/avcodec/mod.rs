@@ -3,12 +3,16 @@ mod bitstream;
 mod codec;
 mod codec_id;
 mod codec_par;
+mod nal;
 mod packet;
 mod parser;
+mod profile;
 
 pub use bitstream::*;
 pub use codec::*;
 pub use codec_id::*;
 pub use codec_par::*;
+pub use nal::*;
 pub use packet::*;
 pub use parser::*;
+pub use profile::*;
@@ -0,0 +1,330 @@
+use crate::{
+    avcodec::{AVCodecID, AVPacket},
+    ffi,
+};
+
+/// Framing of NAL units in a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NalFormat {
+    /// NAL units separated by `00 00 01` / `00 00 00 01` start codes, as
+    /// produced by raw `.h264` / `.hevc` streams and MPEG-TS.
+    AnnexB,
+    /// NAL units prefixed by a big-endian length field of the given size in
+    /// bytes (1, 2 or 4), as stored in MP4/MKV (`avcC`/`hvcC`). The size is
+    /// `lengthSizeMinusOne + 1` of the codec's extradata, which is usually 4.
+    LengthPrefixed(usize),
+}
+
+/// A single NAL unit borrowed from a buffer, without its start code or length
+/// prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NalUnit<'a> {
+    /// Codec the NAL unit belongs to, used to interpret the header.
+    pub codec_id: AVCodecID,
+    /// NAL unit bytes, starting with the NAL unit header.
+    pub data: &'a [u8],
+}
+
+impl NalUnit<'_> {
+    /// Get `nal_unit_type` from the NAL unit header.
+    ///
+    /// Returns `None` if the codec is not H.264, HEVC or VVC, or if the
+    /// header is truncated.
+    pub fn nal_type(&self) -> Option<u8> {
+        match self.codec_id {
+            ffi::AV_CODEC_ID_H264 => self.data.first().map(|x| x & 0x1f),
+            ffi::AV_CODEC_ID_HEVC => self.data.first().map(|x| (x >> 1) & 0x3f),
+            ffi::AV_CODEC_ID_VVC => self.data.get(1).map(|x| x >> 3),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the NAL unit is an IDR picture (H.264) or an IRAP
+    /// picture (HEVC and VVC).
+    pub fn is_keyframe(&self) -> bool {
+        match (self.codec_id, self.nal_type()) {
+            (ffi::AV_CODEC_ID_H264, Some(x)) => x == 5,
+            (ffi::AV_CODEC_ID_HEVC, Some(x)) => (16..=23).contains(&x),
+            (ffi::AV_CODEC_ID_VVC, Some(x)) => (7..=10).contains(&x),
+            _ => false,
+        }
+    }
+}
+
+/// Iterator over the NAL units of a H.264 / HEVC / VVC buffer.
+///
+/// Iteration stops at the first malformed unit, e.g. a length prefix that
+/// runs past the end of the buffer.
+#[derive(Debug, Clone)]
+pub struct NalUnits<'a> {
+    codec_id: AVCodecID,
+    format: NalFormat,
+    remaining: &'a [u8],
+}
+
+impl<'a> NalUnits<'a> {
+    /// Iterate over the NAL units in `data`.
+    pub fn new(data: &'a [u8], codec_id: AVCodecID, format: NalFormat) -> Self {
+        let remaining = match format {
+            // Skip leading garbage and the first start code.
+            NalFormat::AnnexB => match find_start_code(data) {
+                Some(pos) => &data[pos + 3..],
+                None => &[],
+            },
+            NalFormat::LengthPrefixed(_) => data,
+        };
+        Self {
+            codec_id,
+            format,
+            remaining,
+        }
+    }
+
+    fn next_annexb(&mut self) -> Option<&'a [u8]> {
+        loop {
+            if self.remaining.is_empty() {
+                return None;
+            }
+            let (nal, rest) = match find_start_code(self.remaining) {
+                Some(pos) => (&self.remaining[..pos], &self.remaining[pos + 3..]),
+                None => (self.remaining, &[][..]),
+            };
+            self.remaining = rest;
+            // Trailing zeros belong to the next 4 byte start code.
+            let len = nal.iter().rposition(|&x| x != 0).map_or(0, |x| x + 1);
+            if len != 0 {
+                return Some(&nal[..len]);
+            }
+        }
+    }
+
+    fn next_length_prefixed(&mut self, length_size: usize) -> Option<&'a [u8]> {
+        if !matches!(length_size, 1 | 2 | 4) || self.remaining.len() < length_size {
+            self.remaining = &[];
+            return None;
+        }
+        let (prefix, rest) = self.remaining.split_at(length_size);
+        let len = prefix
+            .iter()
+            .fold(0usize, |acc, &x| (acc << 8) | x as usize);
+        if rest.len() < len {
+            self.remaining = &[];
+            return None;
+        }
+        let (nal, rest) = rest.split_at(len);
+        self.remaining = rest;
+        Some(nal)
+    }
+}
+
+impl<'a> Iterator for NalUnits<'a> {
+    type Item = NalUnit<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = match self.format {
+            NalFormat::AnnexB => self.next_annexb()?,
+            NalFormat::LengthPrefixed(length_size) => self.next_length_prefixed(length_size)?,
+        };
+        Some(NalUnit {
+            codec_id: self.codec_id,
+            data,
+        })
+    }
+}
+
+/// Find the position of the first `00 00 01` in `data`.
+fn find_start_code(data: &[u8]) -> Option<usize> {
+    data.windows(3).position(|x| x == [0, 0, 1])
+}
+
+/// A single AV1 OBU borrowed from a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Obu<'a> {
+    /// `obu_type`, e.g. 1 for a sequence header, 6 for a frame.
+    pub obu_type: u8,
+    /// `temporal_id` from the extension header, 0 if absent.
+    pub temporal_id: u8,
+    /// `spatial_id` from the extension header, 0 if absent.
+    pub spatial_id: u8,
+    /// OBU payload, without the header and size field.
+    pub payload: &'a [u8],
+}
+
+impl Obu<'_> {
+    pub const SEQUENCE_HEADER: u8 = 1;
+    pub const TEMPORAL_DELIMITER: u8 = 2;
+    pub const FRAME_HEADER: u8 = 3;
+    pub const TILE_GROUP: u8 = 4;
+    pub const METADATA: u8 = 5;
+    pub const FRAME: u8 = 6;
+    pub const REDUNDANT_FRAME_HEADER: u8 = 7;
+    pub const TILE_LIST: u8 = 8;
+    pub const PADDING: u8 = 15;
+}
+
+/// Iterator over the OBUs of an AV1 buffer in low overhead bitstream format,
+/// which is what the AV1 demuxers and encoders produce.
+///
+/// Iteration stops at the first malformed OBU.
+#[derive(Debug, Clone)]
+pub struct Obus<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Obus<'a> {
+    /// Iterate over the OBUs in `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { remaining: data }
+    }
+}
+
+impl<'a> Iterator for Obus<'a> {
+    type Item = Obu<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let obu = parse_obu(self.remaining);
+        match obu {
+            Some((obu, rest)) => {
+                self.remaining = rest;
+                Some(obu)
+            }
+            None => {
+                self.remaining = &[];
+                None
+            }
+        }
+    }
+}
+
+/// Parse one OBU, returns it and the rest of the buffer.
+fn parse_obu(data: &[u8]) -> Option<(Obu<'_>, &[u8])> {
+    let (&header, mut rest) = data.split_first()?;
+    // forbidden bit
+    if header & 0x80 != 0 {
+        return None;
+    }
+    let obu_type = (header >> 3) & 0x0f;
+    let has_extension = header & 0x04 != 0;
+    let has_size_field = header & 0x02 != 0;
+
+    let (temporal_id, spatial_id) = if has_extension {
+        let (&extension, tail) = rest.split_first()?;
+        rest = tail;
+        (extension >> 5, (extension >> 3) & 0x03)
+    } else {
+        (0, 0)
+    };
+
+    let size = if has_size_field {
+        let (size, tail) = read_leb128(rest)?;
+        rest = tail;
+        usize::try_from(size).ok()?
+    } else {
+        rest.len()
+    };
+    if rest.len() < size {
+        return None;
+    }
+    let (payload, rest) = rest.split_at(size);
+    let obu = Obu {
+        obu_type,
+        temporal_id,
+        spatial_id,
+        payload,
+    };
+    Some((obu, rest))
+}
+
+/// Read an unsigned leb128 value of at most 8 bytes.
+fn read_leb128(data: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().take(8).enumerate() {
+        value |= ((byte & 0x7f) as u64) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+impl AVPacket {
+    /// Iterate over the NAL units in the packet, `codec_id` should be the
+    /// codec of the stream the packet belongs to.
+    pub fn nal_units(&self, codec_id: AVCodecID, format: NalFormat) -> NalUnits<'_> {
+        NalUnits::new(self.data(), codec_id, format)
+    }
+
+    /// Iterate over the OBUs in an AV1 packet.
+    pub fn obus(&self) -> Obus<'_> {
+        Obus::new(self.data())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annexb() {
+        let data = [
+            0, 0, 0, 1, 0x67, 1, 2, // SPS
+            0, 0, 1, 0x68, 3, // PPS
+            0, 0, 0, 1, 0x65, 4, 0, 5, // IDR slice
+        ];
+        let nals: Vec<_> = NalUnits::new(&data, ffi::AV_CODEC_ID_H264, NalFormat::AnnexB).collect();
+        assert_eq!(nals.len(), 3);
+        assert_eq!(nals[0].data, &[0x67, 1, 2]);
+        assert_eq!(nals[0].nal_type(), Some(7));
+        assert_eq!(nals[1].data, &[0x68, 3]);
+        assert_eq!(nals[1].nal_type(), Some(8));
+        assert_eq!(nals[2].data, &[0x65, 4, 0, 5]);
+        assert!(nals[2].is_keyframe());
+
+        assert_eq!(
+            NalUnits::new(&[1, 2, 3], ffi::AV_CODEC_ID_H264, NalFormat::AnnexB).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let data = [
+            0, 0, 0, 2, 0x40, 1, // HEVC VPS
+            0, 0, 0, 3, 0x26, 1, 2, // HEVC IDR_W_RADL
+            0, 0, 0, 9, 0, // truncated
+        ];
+        let nals: Vec<_> =
+            NalUnits::new(&data, ffi::AV_CODEC_ID_HEVC, NalFormat::LengthPrefixed(4)).collect();
+        assert_eq!(nals.len(), 2);
+        assert_eq!(nals[0].nal_type(), Some(32));
+        assert!(!nals[0].is_keyframe());
+        assert_eq!(nals[1].data, &[0x26, 1, 2]);
+        assert_eq!(nals[1].nal_type(), Some(19));
+        assert!(nals[1].is_keyframe());
+    }
+
+    #[test]
+    fn test_obus() {
+        let data = [
+            0x12, 0x00, // temporal delimiter, size 0
+            0x0a, 0x02, 1, 2, // sequence header, size 2
+            0x36, 0x28, 0x01, 9, // frame with extension, size 1
+            0x78, 1, 2, // padding without size field
+        ];
+        let obus: Vec<_> = Obus::new(&data).collect();
+        assert_eq!(obus.len(), 4);
+        assert_eq!(obus[0].obu_type, Obu::TEMPORAL_DELIMITER);
+        assert!(obus[0].payload.is_empty());
+        assert_eq!(obus[1].obu_type, Obu::SEQUENCE_HEADER);
+        assert_eq!(obus[1].payload, &[1, 2]);
+        assert_eq!(obus[2].obu_type, Obu::FRAME);
+        assert_eq!(obus[2].temporal_id, 1);
+        assert_eq!(obus[2].spatial_id, 1);
+        assert_eq!(obus[2].payload, &[9]);
+        assert_eq!(obus[3].obu_type, Obu::PADDING);
+        assert_eq!(obus[3].payload, &[1, 2]);
+
+        // Size field runs past the end.
+        assert_eq!(Obus::new(&[0x0a, 0x05, 1]).count(), 0);
+    }
+}
@@ -1,6 +1,33 @@
-use std::{fmt, ptr::NonNull};
+use std::{ffi::c_void, fmt, ops::Range, ptr::NonNull, slice};
 
-use crate::{avutil::AVRational, ffi, shared::*};
+use crate::{
+    avutil::{AVBufferRef, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+    shared::*,
+};
+
+bitflags::bitflags! {
+    /// Flags of [`AVPacket`], `AV_PKT_FLAG_*`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct PacketFlags: i32 {
+        /// The packet contains a keyframe.
+        const KEY = ffi::AV_PKT_FLAG_KEY as i32;
+        /// The packet content is corrupted.
+        const CORRUPT = ffi::AV_PKT_FLAG_CORRUPT as i32;
+        /// The packet is required to maintain valid decoder state but is not
+        /// required for output and should be dropped after decoding.
+        const DISCARD = ffi::AV_PKT_FLAG_DISCARD as i32;
+        /// The packet comes from a trusted source.
+        const TRUSTED = ffi::AV_PKT_FLAG_TRUSTED as i32;
+        /// The packet contains frames that can be discarded by the decoder,
+        /// i.e. non-reference frames.
+        const DISPOSABLE = ffi::AV_PKT_FLAG_DISPOSABLE as i32;
+
+        // Keep the flags unknown to this version.
+        const _ = !0;
+    }
+}
 
 wrap!(AVPacket: ffi::AVPacket);
 settable!(AVPacket {
@@ -19,6 +46,165 @@ impl AVPacket {
         unsafe { Self::from_raw(NonNull::new(packet).unwrap()) }
     }
 
+    /// Create an [`AVPacket`] owning `data` without copying the payload.
+    ///
+    /// `AV_INPUT_BUFFER_PADDING_SIZE` zero bytes are appended to `data` as
+    /// required by FFmpeg, which may reallocate `data` if it has no spare
+    /// capacity.
+    ///
+    /// Note: if `data.len()` exceeds [`i32::MAX`], this function returns [`RsmpegError::TryFromIntError`].
+    pub fn from_data(mut data: Vec<u8>) -> Result<Self> {
+        let size = data.len();
+        data.resize(size + ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize, 0);
+        let mut data = Box::new(data);
+        let (ptr, len) = (data.as_mut_ptr(), data.len());
+        let opaque = Box::into_raw(data);
+
+        unsafe extern "C" fn free_vec(opaque: *mut c_void, _data: *mut u8) {
+            drop(unsafe { Box::from_raw(opaque as *mut Vec<u8>) });
+        }
+
+        let Some(buf) =
+            unsafe { ffi::av_buffer_create(ptr, len, Some(free_vec), opaque as *mut _, 0) }
+                .upgrade()
+        else {
+            drop(unsafe { Box::from_raw(opaque) });
+            return Err(RsmpegError::AVError(AVERROR_ENOMEM));
+        };
+        Self::from_buffer_ref(unsafe { AVBufferRef::from_raw(buf) }, size)
+    }
+
+    /// Create an [`AVPacket`] referencing the first `size` bytes of `buf`.
+    ///
+    /// `buf` should be at least `size + AV_INPUT_BUFFER_PADDING_SIZE` bytes,
+    /// with the padding zeroed, otherwise `AVERROR(EINVAL)` is returned.
+    pub fn from_buffer_ref(buf: AVBufferRef, size: usize) -> Result<Self> {
+        let padded_size = size
+            .checked_add(ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        if buf.size < padded_size {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let size = i32::try_from(size)?;
+        let mut packet = Self::new();
+        unsafe {
+            let inner = packet.deref_mut();
+            inner.data = buf.data;
+            inner.size = size;
+            inner.buf = buf.into_raw().as_ptr();
+        }
+        Ok(packet)
+    }
+
+    /// Get the payload of the packet.
+    pub fn data(&self) -> &[u8] {
+        if self.data.is_null() || self.size <= 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.data, self.size as usize) }
+        }
+    }
+
+    /// Get the payload of the packet mutably, returns `None` if the packet
+    /// is not writable, see [`Self::make_writable()`].
+    pub fn data_mut(&mut self) -> Option<&mut [u8]> {
+        if self.buf.is_null() || unsafe { ffi::av_buffer_is_writable(self.buf) } == 0 {
+            return None;
+        }
+        if self.data.is_null() || self.size <= 0 {
+            return Some(&mut []);
+        }
+        Some(unsafe { slice::from_raw_parts_mut(self.data, self.size as usize) })
+    }
+
+    /// Release the data of the packet and reset its fields to their
+    /// defaults, so the packet can be reused, e.g. by
+    /// [`AVFormatContextInput::read_packet_into()`](crate::avformat::AVFormatContextInput::read_packet_into).
+    pub fn unref(&mut self) {
+        unsafe { ffi::av_packet_unref(self.as_mut_ptr()) }
+    }
+
+    /// Ensure the data of the packet is writable, copying it if it's shared
+    /// with other packets or not reference counted.
+    pub fn make_writable(&mut self) -> Result<()> {
+        unsafe { ffi::av_packet_make_writable(self.as_mut_ptr()) }.upgrade()?;
+        Ok(())
+    }
+
+    /// Byte position of the packet in the input, `None` if unknown. Set it
+    /// with [`Self::set_pos()`], `-1` meaning unknown.
+    pub fn pos(&self) -> Option<i64> {
+        match self.pos {
+            x if x < 0 => None,
+            x => Some(x),
+        }
+    }
+
+    /// Duration of the packet in the time base of its stream, `None` if
+    /// unknown. Set it with [`Self::set_duration()`], `0` meaning unknown.
+    pub fn duration(&self) -> Option<i64> {
+        match self.duration {
+            x if x <= 0 => None,
+            x => Some(x),
+        }
+    }
+
+    /// Byte range of the packet in the input, `None` if its position is
+    /// unknown.
+    pub fn byte_range(&self) -> Option<Range<i64>> {
+        let pos = self.pos()?;
+        Some(pos..pos + self.size.max(0) as i64)
+    }
+
+    /// Get the `flags` field as [`PacketFlags`].
+    pub fn packet_flags(&self) -> PacketFlags {
+        PacketFlags::from_bits_retain(self.flags)
+    }
+
+    /// Set the `flags` field from [`PacketFlags`].
+    pub fn set_packet_flags(&mut self, flags: PacketFlags) {
+        self.set_flags(flags.bits());
+    }
+
+    /// Whether the packet contains a keyframe.
+    pub fn is_keyframe(&self) -> bool {
+        self.packet_flags().contains(PacketFlags::KEY)
+    }
+
+    /// Get the payload of the side data of `side_data_type`.
+    pub fn get_side_data(&self, side_data_type: ffi::AVPacketSideDataType) -> Option<&[u8]> {
+        let mut size = 0;
+        let data =
+            unsafe { ffi::av_packet_get_side_data(self.as_ptr(), side_data_type, &mut size) };
+        if data.is_null() {
+            return None;
+        }
+        Some(unsafe { slice::from_raw_parts(data, size) })
+    }
+
+    /// Statistics the encoder exported for this packet, `None` if the encoder
+    /// doesn't export them.
+    pub fn quality_stats(&self) -> Option<PacketQualityStats> {
+        // quality: u32le, pict_type: u8, error count: u8, reserved: u16,
+        // then error count * u64le.
+        let data = self.get_side_data(ffi::AV_PKT_DATA_QUALITY_STATS)?;
+        if data.len() < 8 {
+            return None;
+        }
+        let quality = u32::from_le_bytes(data[..4].try_into().unwrap()) as i32;
+        let nb_errors = data[5] as usize;
+        let error = data[8..]
+            .chunks_exact(8)
+            .take(nb_errors)
+            .map(|x| u64::from_le_bytes(x.try_into().unwrap()))
+            .collect();
+        Some(PacketQualityStats {
+            quality,
+            pict_type: data[4] as ffi::AVPictureType,
+            error,
+        })
+    }
+
     /// Convert valid timing fields (timestamps / durations) in a packet from
     /// one timebase to another. Timestamps with unknown values
     /// (`AV_NOPTS_VALUE`) will be ignored.
@@ -29,6 +215,19 @@ impl AVPacket {
     }
 }
 
+/// Encoder statistics of a packet, see [`AVPacket::quality_stats()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketQualityStats {
+    /// Quality of the frame in lambda units, divide it by `FF_QP2LAMBDA` to
+    /// get the quantizer.
+    pub quality: i32,
+    /// Picture type of the frame, `AV_PICTURE_TYPE_NONE` if unknown.
+    pub pict_type: ffi::AVPictureType,
+    /// Sum of squared errors of each plane, only filled when encoding with
+    /// `AV_CODEC_FLAG_PSNR`.
+    pub error: Vec<u64>,
+}
+
 impl fmt::Debug for AVPacket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("AVPacket")
@@ -43,6 +242,17 @@ impl fmt::Debug for AVPacket {
     }
 }
 
+impl Clone for AVPacket {
+    /// Create a new packet that references the same data as current packet.
+    fn clone(&self) -> Self {
+        // Only fails on no memory, so unwrap.
+        let packet = unsafe { ffi::av_packet_clone(self.as_ptr()) }
+            .upgrade()
+            .unwrap();
+        unsafe { Self::from_raw(packet) }
+    }
+}
+
 impl Default for AVPacket {
     fn default() -> Self {
         Self::new()
@@ -59,3 +269,95 @@ impl Drop for AVPacket {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_data() {
+        let mut packet = AVPacket::from_data(vec![0, 0, 0, 1, 0x65, 1, 2, 3]).unwrap();
+        assert_eq!(packet.size, 8);
+        assert_eq!(packet.data(), &[0, 0, 0, 1, 0x65, 1, 2, 3]);
+        packet.data_mut().unwrap()[5] = 9;
+        assert_eq!(packet.data()[5], 9);
+
+        let cloned = packet.clone();
+        assert_eq!(cloned.data(), packet.data());
+        // Shared with `cloned`
+        assert!(packet.data_mut().is_none());
+        packet.make_writable().unwrap();
+        packet.data_mut().unwrap()[5] = 10;
+        assert_eq!(cloned.data()[5], 9);
+        assert_eq!(packet.data()[5], 10);
+
+        let packet = AVPacket::from_data(vec![]).unwrap();
+        assert!(packet.data().is_empty());
+    }
+
+    #[test]
+    fn test_unref() {
+        let mut packet = AVPacket::from_data(vec![1, 2, 3]).unwrap();
+        packet.set_pts(10);
+        packet.set_packet_flags(PacketFlags::KEY);
+        let cloned = packet.clone();
+
+        packet.unref();
+        assert!(packet.data().is_empty());
+        assert!(packet.buf.is_null());
+        assert_eq!(packet.pts, ffi::AV_NOPTS_VALUE);
+        assert!(!packet.is_keyframe());
+        // The references are independent.
+        assert_eq!(cloned.data(), [1, 2, 3]);
+        packet.unref();
+    }
+
+    #[test]
+    fn test_pos_and_duration() {
+        let mut packet = AVPacket::from_data(vec![0; 8]).unwrap();
+        assert_eq!(packet.pos(), None);
+        assert_eq!(packet.duration(), None);
+        assert_eq!(packet.byte_range(), None);
+
+        packet.set_pos(100);
+        packet.set_duration(40);
+        assert_eq!(packet.pos(), Some(100));
+        assert_eq!(packet.duration(), Some(40));
+        assert_eq!(packet.byte_range(), Some(100..108));
+
+        packet.set_pos(-1);
+        packet.set_duration(0);
+        assert_eq!(packet.pos(), None);
+        assert_eq!(packet.duration(), None);
+    }
+
+    #[test]
+    fn test_packet_flags() {
+        let mut packet = AVPacket::new();
+        assert!(packet.packet_flags().is_empty());
+        assert!(!packet.is_keyframe());
+
+        packet.set_packet_flags(PacketFlags::KEY | PacketFlags::DISCARD);
+        assert_eq!(
+            packet.flags,
+            (ffi::AV_PKT_FLAG_KEY | ffi::AV_PKT_FLAG_DISCARD) as i32
+        );
+        assert!(packet.is_keyframe());
+
+        // Unknown bits are kept.
+        packet.set_flags(1 << 20 | ffi::AV_PKT_FLAG_KEY as i32);
+        assert_eq!(packet.packet_flags().bits(), packet.flags);
+        assert!(packet.is_keyframe());
+    }
+
+    #[test]
+    fn test_from_buffer_ref() {
+        let padding = ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize;
+        let buf = AVBufferRef::zeroed(16 + padding);
+        let packet = AVPacket::from_buffer_ref(buf, 16).unwrap();
+        assert_eq!(packet.data(), &[0; 16]);
+
+        let buf = AVBufferRef::zeroed(16);
+        assert!(AVPacket::from_buffer_ref(buf, 16).is_err());
+    }
+}
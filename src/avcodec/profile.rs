@@ -0,0 +1,175 @@
+use crate::ffi;
+use std::{ffi::CStr, slice};
+
+/// Typed wrapper of the `profile` field of [`AVCodecContext`](crate::avcodec::AVCodecContext)
+/// and [`AVCodecParameters`](crate::avcodec::AVCodecParameters), convertible
+/// from and into the bare `i32`.
+///
+/// The value of a profile only makes sense together with a codec id, the
+/// constants are prefixed by the codec they belong to.
+///
+/// ```rust
+/// # use rsmpeg::{avcodec::{CodecId, Profile}, ffi};
+/// # use cstr::cstr;
+/// let h264 = CodecId(ffi::AV_CODEC_ID_H264);
+/// assert_eq!(h264.profile_name(Profile::H264_HIGH), Some(cstr!("High")));
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Profile(pub i32);
+
+impl Profile {
+    pub const UNKNOWN: Self = Self(ffi::AV_PROFILE_UNKNOWN);
+    pub const RESERVED: Self = Self(ffi::AV_PROFILE_RESERVED);
+
+    pub const AAC_MAIN: Self = Self(ffi::AV_PROFILE_AAC_MAIN as i32);
+    pub const AAC_LOW: Self = Self(ffi::AV_PROFILE_AAC_LOW as i32);
+    pub const AAC_SSR: Self = Self(ffi::AV_PROFILE_AAC_SSR as i32);
+    pub const AAC_LTP: Self = Self(ffi::AV_PROFILE_AAC_LTP as i32);
+    pub const AAC_HE: Self = Self(ffi::AV_PROFILE_AAC_HE as i32);
+    pub const AAC_HE_V2: Self = Self(ffi::AV_PROFILE_AAC_HE_V2 as i32);
+    pub const AAC_LD: Self = Self(ffi::AV_PROFILE_AAC_LD as i32);
+    pub const AAC_ELD: Self = Self(ffi::AV_PROFILE_AAC_ELD as i32);
+
+    pub const MPEG2_422: Self = Self(ffi::AV_PROFILE_MPEG2_422 as i32);
+    pub const MPEG2_HIGH: Self = Self(ffi::AV_PROFILE_MPEG2_HIGH as i32);
+    pub const MPEG2_SS: Self = Self(ffi::AV_PROFILE_MPEG2_SS as i32);
+    pub const MPEG2_SNR_SCALABLE: Self = Self(ffi::AV_PROFILE_MPEG2_SNR_SCALABLE as i32);
+    pub const MPEG2_MAIN: Self = Self(ffi::AV_PROFILE_MPEG2_MAIN as i32);
+    pub const MPEG2_SIMPLE: Self = Self(ffi::AV_PROFILE_MPEG2_SIMPLE as i32);
+
+    pub const H264_BASELINE: Self = Self(ffi::AV_PROFILE_H264_BASELINE as i32);
+    pub const H264_CONSTRAINED_BASELINE: Self =
+        Self(ffi::AV_PROFILE_H264_CONSTRAINED_BASELINE as i32);
+    pub const H264_MAIN: Self = Self(ffi::AV_PROFILE_H264_MAIN as i32);
+    pub const H264_EXTENDED: Self = Self(ffi::AV_PROFILE_H264_EXTENDED as i32);
+    pub const H264_HIGH: Self = Self(ffi::AV_PROFILE_H264_HIGH as i32);
+    pub const H264_HIGH_10: Self = Self(ffi::AV_PROFILE_H264_HIGH_10 as i32);
+    pub const H264_HIGH_10_INTRA: Self = Self(ffi::AV_PROFILE_H264_HIGH_10_INTRA as i32);
+    pub const H264_HIGH_422: Self = Self(ffi::AV_PROFILE_H264_HIGH_422 as i32);
+    pub const H264_HIGH_422_INTRA: Self = Self(ffi::AV_PROFILE_H264_HIGH_422_INTRA as i32);
+    pub const H264_HIGH_444: Self = Self(ffi::AV_PROFILE_H264_HIGH_444 as i32);
+    pub const H264_HIGH_444_PREDICTIVE: Self =
+        Self(ffi::AV_PROFILE_H264_HIGH_444_PREDICTIVE as i32);
+    pub const H264_HIGH_444_INTRA: Self = Self(ffi::AV_PROFILE_H264_HIGH_444_INTRA as i32);
+    pub const H264_CAVLC_444: Self = Self(ffi::AV_PROFILE_H264_CAVLC_444 as i32);
+
+    pub const HEVC_MAIN: Self = Self(ffi::AV_PROFILE_HEVC_MAIN as i32);
+    pub const HEVC_MAIN_10: Self = Self(ffi::AV_PROFILE_HEVC_MAIN_10 as i32);
+    pub const HEVC_MAIN_STILL_PICTURE: Self = Self(ffi::AV_PROFILE_HEVC_MAIN_STILL_PICTURE as i32);
+    pub const HEVC_REXT: Self = Self(ffi::AV_PROFILE_HEVC_REXT as i32);
+
+    pub const VP9_0: Self = Self(ffi::AV_PROFILE_VP9_0 as i32);
+    pub const VP9_1: Self = Self(ffi::AV_PROFILE_VP9_1 as i32);
+    pub const VP9_2: Self = Self(ffi::AV_PROFILE_VP9_2 as i32);
+    pub const VP9_3: Self = Self(ffi::AV_PROFILE_VP9_3 as i32);
+
+    pub const AV1_MAIN: Self = Self(ffi::AV_PROFILE_AV1_MAIN as i32);
+    pub const AV1_HIGH: Self = Self(ffi::AV_PROFILE_AV1_HIGH as i32);
+    pub const AV1_PROFESSIONAL: Self = Self(ffi::AV_PROFILE_AV1_PROFESSIONAL as i32);
+
+    pub const PRORES_PROXY: Self = Self(ffi::AV_PROFILE_PRORES_PROXY as i32);
+    pub const PRORES_LT: Self = Self(ffi::AV_PROFILE_PRORES_LT as i32);
+    pub const PRORES_STANDARD: Self = Self(ffi::AV_PROFILE_PRORES_STANDARD as i32);
+    pub const PRORES_HQ: Self = Self(ffi::AV_PROFILE_PRORES_HQ as i32);
+    pub const PRORES_4444: Self = Self(ffi::AV_PROFILE_PRORES_4444 as i32);
+    pub const PRORES_XQ: Self = Self(ffi::AV_PROFILE_PRORES_XQ as i32);
+}
+
+impl From<i32> for Profile {
+    fn from(profile: i32) -> Self {
+        Self(profile)
+    }
+}
+
+impl From<Profile> for i32 {
+    fn from(profile: Profile) -> Self {
+        profile.0
+    }
+}
+
+/// Typed wrapper of the `level` field of [`AVCodecContext`](crate::avcodec::AVCodecContext)
+/// and [`AVCodecParameters`](crate::avcodec::AVCodecParameters), convertible
+/// from and into the bare `i32`.
+///
+/// Each codec has its own numbering of the levels, use the constructor of
+/// the codec to get the value of e.g. level 4.1.
+///
+/// ```rust
+/// # use rsmpeg::avcodec::Level;
+/// assert_eq!(Level::h264(4, 1), Level(41));
+/// assert_eq!(Level::hevc(4, 1), Level(123));
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Level(pub i32);
+
+impl Level {
+    pub const UNKNOWN: Self = Self(ffi::AV_LEVEL_UNKNOWN);
+
+    /// H.264 level `major.minor`, e.g. 41 for 4.1.
+    ///
+    /// Level 1b isn't representable, it's `Level(9)` for High profiles and
+    /// `Level(11)` with the constraint_set3 flag otherwise.
+    pub const fn h264(major: i32, minor: i32) -> Self {
+        Self(major * 10 + minor)
+    }
+
+    /// HEVC `general_level_idc` of level `major.minor`, e.g. 123 for 4.1.
+    pub const fn hevc(major: i32, minor: i32) -> Self {
+        Self(major * 30 + minor * 3)
+    }
+
+    /// AV1 `seq_level_idx` of level `major.minor`, e.g. 9 for 4.1.
+    pub const fn av1(major: i32, minor: i32) -> Self {
+        Self((major - 2) * 4 + minor)
+    }
+}
+
+impl From<i32> for Level {
+    fn from(level: i32) -> Self {
+        Self(level)
+    }
+}
+
+impl From<Level> for i32 {
+    fn from(level: Level) -> Self {
+        level.0
+    }
+}
+
+/// Collect the `(profile, name)` pairs of an array terminated by
+/// `AV_PROFILE_UNKNOWN`.
+///
+/// # Safety
+/// `ptr` needs to be null or point to such an array.
+pub(crate) unsafe fn profile_list(ptr: *const ffi::AVProfile) -> Vec<(i32, &'static CStr)> {
+    if ptr.is_null() {
+        return vec![];
+    }
+    let mut len = 0;
+    while unsafe { (*ptr.add(len)).profile } != ffi::AV_PROFILE_UNKNOWN {
+        len += 1;
+    }
+    unsafe { slice::from_raw_parts(ptr, len) }
+        .iter()
+        .map(|x| (x.profile, unsafe { CStr::from_ptr(x.name) }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(Level::h264(3, 0), Level(30));
+        assert_eq!(Level::h264(5, 2), Level(52));
+        assert_eq!(Level::hevc(5, 1), Level(153));
+        assert_eq!(Level::hevc(6, 2), Level(186));
+        assert_eq!(Level::av1(2, 0), Level(0));
+        assert_eq!(Level::av1(5, 1), Level(13));
+        assert_eq!(i32::from(Level::UNKNOWN), ffi::AV_LEVEL_UNKNOWN);
+        assert_eq!(Profile::from(100), Profile::H264_HIGH);
+    }
+}
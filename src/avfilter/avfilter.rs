@@ -1,17 +1,18 @@
 use std::{
-    ffi::CStr,
+    ffi::{c_void, CStr, CString},
     mem::{size_of, MaybeUninit},
     ptr::{self, NonNull},
 };
 
 use crate::{
-    avutil::{AVChannelLayout, AVFrame},
+    avutil::{AVChannelLayout, AVFrame, AVHWDeviceContext, AVHWDeviceContextRef},
     error::{Result, RsmpegError},
     ffi,
     shared::*,
 };
 
 wrap_ref!(AVFilter: ffi::AVFilter);
+wrap_static!(AVFilter);
 
 impl AVFilter {
     /// Get a filter definition matching the given name.
@@ -19,6 +20,11 @@ impl AVFilter {
         let filter = unsafe { ffi::avfilter_get_by_name(filter_name.as_ptr()) }.upgrade()?;
         Some(unsafe { AVFilterRef::from_raw(filter) })
     }
+
+    /// Name of the filter, e.g. `scale`.
+    pub fn name(&self) -> &CStr {
+        unsafe { CStr::from_ptr(self.name) }
+    }
 }
 
 impl Drop for AVFilter {
@@ -27,9 +33,43 @@ impl Drop for AVFilter {
     }
 }
 
-wrap_mut!(AVFilterContext: ffi::AVFilterContext);
+// Not `Send`: the filter contexts belong to their graph, which hands out
+// `AVFilterContextMut`s from a shared reference, see
+// `AVFilterGraph::get_filter()`.
+wrap_pure!(#[repr(transparent)] (AVFilterContext): ffi::AVFilterContext);
+wrap_ref_pure!((AVFilterContext, AVFilterContextRef): ffi::AVFilterContext);
+wrap_mut_pure!((AVFilterContext, AVFilterContextMut): ffi::AVFilterContext);
 
 impl AVFilterContext {
+    /// Name of the filter instance, e.g. `Parsed_scale_0` for filters added by
+    /// [`AVFilterGraph::parse_ptr()`].
+    pub fn name(&self) -> Option<&CStr> {
+        // `name` can be null.
+        NonNull::new(self.name).map(|x| unsafe { CStr::from_ptr(x.as_ptr()) })
+    }
+
+    /// Get the [`AVFilter`] this instance was created from.
+    pub fn filter(&self) -> AVFilterRef<'static> {
+        unsafe { AVFilterRef::from_raw(NonNull::new(self.filter as *mut _).unwrap()) }
+    }
+
+    /// Get the hardware device used by the filter, `None` if it's not set.
+    pub fn hw_device_ctx(&self) -> Option<AVHWDeviceContextRef<'_>> {
+        let hw_device_ctx = NonNull::new(self.hw_device_ctx)?;
+        Some(unsafe { AVHWDeviceContextRef::from_raw(hw_device_ctx) })
+    }
+
+    /// Set the hardware device used by filters creating hardware frames, e.g.
+    /// `hwupload` or `scale_cuda` fed with software frames. Should be called
+    /// before [`AVFilterGraph::config()`].
+    pub fn set_hw_device_ctx(&mut self, hw_device_ctx: AVHWDeviceContext) {
+        unsafe {
+            let context = self.deref_mut();
+            ffi::av_buffer_unref(&mut context.hw_device_ctx);
+            context.hw_device_ctx = hw_device_ctx.buffer_ref.into_raw().as_ptr();
+        }
+    }
+
     /// Set property of a [`AVFilterContext`].
     pub fn opt_set_bin<U>(&mut self, key: &CStr, value: &U) -> Result<()> {
         unsafe {
@@ -241,6 +281,48 @@ impl AVFilterGraph {
         Ok(())
     }
 
+    /// Return a human-readable description of the graph, with the filter
+    /// instances, their links and negotiated formats, useful for debugging.
+    /// Should be called after [`Self::config()`].
+    pub fn dump(&self) -> CString {
+        let mut s = unsafe { ffi::avfilter_graph_dump(self.as_ptr() as *mut _, ptr::null()) };
+        assert!(!s.is_null(), "avfilter_graph_dump() failed to allocate");
+        let result = unsafe { CStr::from_ptr(s).to_owned() };
+        unsafe {
+            ffi::av_freep(&mut s as *mut _ as *mut c_void);
+        }
+        result
+    }
+
+    /// Return slice of the filter instances in the graph.
+    pub fn filters(&self) -> &[AVFilterContextRef<'_>] {
+        // AVFilterContextRef <-> *const ffi::AVFilterContext due to
+        // repr(transparent), see `AVFormatContextInput::streams()`.
+        let filters =
+            self.filters as *const *const ffi::AVFilterContext as *const AVFilterContextRef<'_>;
+        let len = self.nb_filters as usize;
+        if len == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(filters, len) }
+    }
+
+    /// Set `hw_device_ctx` as the hardware device of all the filters in the
+    /// graph which don't have one yet, like the `-filter_hw_device` option of
+    /// the ffmpeg CLI. Should be called after the filters are added and
+    /// before [`Self::config()`].
+    pub fn set_hw_device_ctx(&self, hw_device_ctx: &AVHWDeviceContext) {
+        // ATTENTION: This takes immutable reference since it only sets a field
+        // of each filter, like `Self::create_filter_context()`.
+        for filter in self.filters() {
+            if filter.hw_device_ctx.is_null() {
+                let filter = filter.as_ptr() as *mut ffi::AVFilterContext;
+                let device = hw_device_ctx.clone().into_inner().into_raw();
+                unsafe { (*filter).hw_device_ctx = device.as_ptr() };
+            }
+        }
+    }
+
     /// Get a filter instance identified by instance name from graph.
     pub fn get_filter(&mut self, name: &CStr) -> Option<AVFilterContextMut> {
         unsafe {
@@ -298,3 +380,87 @@ impl Drop for AVFilterGraph {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_filter_graph_dump() {
+        let graph = AVFilterGraph::new();
+        assert!(graph.filters().is_empty());
+
+        let buffersrc = AVFilter::get_by_name(cstr!("buffer")).unwrap();
+        let buffersink = AVFilter::get_by_name(cstr!("buffersink")).unwrap();
+        assert_eq!(buffersrc.name(), cstr!("buffer"));
+        let mut src = graph
+            .create_filter_context(
+                &buffersrc,
+                cstr!("in"),
+                Some(cstr!(
+                    "video_size=64x48:pix_fmt=0:time_base=1/25:pixel_aspect=1/1"
+                )),
+            )
+            .unwrap();
+        let mut sink = graph
+            .create_filter_context(&buffersink, cstr!("out"), None)
+            .unwrap();
+        let outputs = AVFilterInOut::new(cstr!("in"), &mut src, 0);
+        let inputs = AVFilterInOut::new(cstr!("out"), &mut sink, 0);
+        graph
+            .parse_ptr(cstr!("scale=32:24,hflip"), Some(inputs), Some(outputs))
+            .unwrap();
+        graph.config().unwrap();
+
+        let filters = graph.filters();
+        assert_eq!(filters.len(), graph.nb_filters as usize);
+        let names: Vec<_> = filters
+            .iter()
+            .map(|x| x.filter().name().to_owned())
+            .collect();
+        assert!(names.iter().any(|x| x.as_c_str() == cstr!("scale")));
+        assert!(names.iter().any(|x| x.as_c_str() == cstr!("hflip")));
+        assert_eq!(filters[0].name(), Some(cstr!("in")));
+
+        let dump = graph.dump();
+        let dump = dump.to_str().unwrap();
+        assert!(dump.contains("Parsed_scale_0"));
+        assert!(dump.contains("32x24"));
+    }
+
+    #[test]
+    fn test_hw_device_ctx() {
+        let graph = AVFilterGraph::new();
+        let filter = AVFilter::get_by_name(cstr!("hflip")).unwrap();
+        let mut first = graph
+            .create_filter_context(&filter, cstr!("first"), None)
+            .unwrap();
+        assert!(first.hw_device_ctx().is_none());
+
+        // Needs a hardware device, skip if there is none.
+        let Some(device) = [
+            ffi::AV_HWDEVICE_TYPE_VAAPI,
+            ffi::AV_HWDEVICE_TYPE_CUDA,
+            ffi::AV_HWDEVICE_TYPE_VULKAN,
+            ffi::AV_HWDEVICE_TYPE_VIDEOTOOLBOX,
+            ffi::AV_HWDEVICE_TYPE_D3D11VA,
+        ]
+        .into_iter()
+        .find_map(|x| AVHWDeviceContext::create(x, None, None, 0).ok()) else {
+            return;
+        };
+        first.set_hw_device_ctx(device.clone());
+        assert_eq!(
+            first.hw_device_ctx().unwrap().device_type(),
+            device.device_type()
+        );
+
+        let second = graph
+            .create_filter_context(&filter, cstr!("second"), None)
+            .unwrap();
+        graph.set_hw_device_ctx(&device);
+        assert!(second.hw_device_ctx().is_some());
+        assert_eq!(first.hw_device_ctx().unwrap().data, device.data);
+    }
+}
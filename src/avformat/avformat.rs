@@ -1,29 +1,206 @@
 use std::{
-    ffi::CStr,
+    ffi::{c_void, CStr},
     os::raw::c_int,
     ptr::{self, NonNull},
+    time::Duration,
 };
 
 use crate::{
     avcodec::{
-        AVCodecParameters, AVCodecParametersMut, AVCodecParametersRef, AVCodecRef, AVPacket,
+        display_matrix_from_bytes, rotation_from_display_matrix, AVCodec, AVCodecParameters,
+        AVCodecParametersMut, AVCodecParametersRef, AVCodecRef, AVPacket,
+    },
+    avformat::{AVIOContext, AVIOContextCustom, AVIOContextDynBuf, AVIOContextURL, ProtocolPolicy},
+    avutil::{
+        av_rescale_q, display_matrix_to_bytes, display_rotation_set, opt_names, AVDictionary,
+        AVDictionaryMut, AVDictionaryRef, AVRational,
     },
-    avformat::{AVIOContext, AVIOContextCustom, AVIOContextURL},
-    avutil::{AVDictionary, AVDictionaryMut, AVDictionaryRef, AVRational},
     error::{Result, RsmpegError},
     ffi,
     shared::*,
 };
 
 /// Container of all kinds of AVIOContexts.
+#[non_exhaustive]
 pub enum AVIOContextContainer {
     Url(AVIOContextURL),
     Custom(AVIOContextCustom),
+    DynBuf(AVIOContextDynBuf),
+}
+
+bitflags::bitflags! {
+    /// Flags of [`AVFormatContextInput`] and [`AVFormatContextOutput`],
+    /// `AVFMT_FLAG_*`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct FormatFlags: i32 {
+        /// Generate missing pts even if it requires parsing future frames.
+        const GENPTS = ffi::AVFMT_FLAG_GENPTS as i32;
+        /// Ignore index.
+        const IGNIDX = ffi::AVFMT_FLAG_IGNIDX as i32;
+        /// Do not block when reading packets from input.
+        const NONBLOCK = ffi::AVFMT_FLAG_NONBLOCK as i32;
+        /// Ignore DTS on frames that contain both DTS & PTS.
+        const IGNDTS = ffi::AVFMT_FLAG_IGNDTS as i32;
+        /// Do not infer any values from other values, just return what is
+        /// stored in the container.
+        const NOFILLIN = ffi::AVFMT_FLAG_NOFILLIN as i32;
+        /// Do not use AVParsers.
+        const NOPARSE = ffi::AVFMT_FLAG_NOPARSE as i32;
+        /// Do not buffer frames when possible.
+        const NOBUFFER = ffi::AVFMT_FLAG_NOBUFFER as i32;
+        /// The caller has supplied a custom AVIOContext.
+        const CUSTOM_IO = ffi::AVFMT_FLAG_CUSTOM_IO as i32;
+        /// Discard frames marked corrupted.
+        const DISCARD_CORRUPT = ffi::AVFMT_FLAG_DISCARD_CORRUPT as i32;
+        /// Flush the AVIOContext every packet.
+        const FLUSH_PACKETS = ffi::AVFMT_FLAG_FLUSH_PACKETS as i32;
+        /// Only write platform, build and time-independent data.
+        const BITEXACT = ffi::AVFMT_FLAG_BITEXACT as i32;
+        /// Try to interleave outputted packets by dts.
+        const SORT_DTS = ffi::AVFMT_FLAG_SORT_DTS as i32;
+        /// Enable fast, but inaccurate seeks for some formats.
+        const FAST_SEEK = ffi::AVFMT_FLAG_FAST_SEEK as i32;
+        /// Add bitstream filters as requested by the muxer.
+        const AUTO_BSF = ffi::AVFMT_FLAG_AUTO_BSF as i32;
+
+        // Keep the flags unknown to this version.
+        const _ = !0;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags of [`AVFormatContextInput::seek()`], `AVSEEK_FLAG_*`. The bare
+    /// `u32` flags are also accepted.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct SeekFlags: u32 {
+        /// Seek backward.
+        const BACKWARD = ffi::AVSEEK_FLAG_BACKWARD;
+        /// Seeking based on position in bytes.
+        const BYTE = ffi::AVSEEK_FLAG_BYTE;
+        /// Seek to any frame, even non-keyframes.
+        const ANY = ffi::AVSEEK_FLAG_ANY;
+        /// Seeking based on frame number.
+        const FRAME = ffi::AVSEEK_FLAG_FRAME;
+
+        // Keep the flags unknown to this version.
+        const _ = !0;
+    }
+}
+
+impl From<u32> for SeekFlags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_retain(flags)
+    }
+}
+
+bitflags::bitflags! {
+    /// Capabilities of an [`AVInputFormat`] or an [`AVOutputFormat`],
+    /// `AVFMT_*`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct IOFormatFlags: i32 {
+        /// Demuxer will use avio_open, no opened file should be provided by
+        /// the caller.
+        const NOFILE = ffi::AVFMT_NOFILE as i32;
+        /// Needs '%d' in filename.
+        const NEEDNUMBER = ffi::AVFMT_NEEDNUMBER as i32;
+        /// The muxer/demuxer is experimental and should be used with caution.
+        const EXPERIMENTAL = ffi::AVFMT_EXPERIMENTAL as i32;
+        /// Show format stream IDs numbers.
+        const SHOW_IDS = ffi::AVFMT_SHOW_IDS as i32;
+        /// Format wants global header.
+        const GLOBALHEADER = ffi::AVFMT_GLOBALHEADER as i32;
+        /// Format does not need / have any timestamps.
+        const NOTIMESTAMPS = ffi::AVFMT_NOTIMESTAMPS as i32;
+        /// Use generic index building code.
+        const GENERIC_INDEX = ffi::AVFMT_GENERIC_INDEX as i32;
+        /// Format allows timestamp discontinuities.
+        const TS_DISCONT = ffi::AVFMT_TS_DISCONT as i32;
+        /// Format allows variable fps.
+        const VARIABLE_FPS = ffi::AVFMT_VARIABLE_FPS as i32;
+        /// Format does not need width/height.
+        const NODIMENSIONS = ffi::AVFMT_NODIMENSIONS as i32;
+        /// Format does not require any streams.
+        const NOSTREAMS = ffi::AVFMT_NOSTREAMS as i32;
+        /// Format does not allow to fall back on binary search via
+        /// read_timestamp.
+        const NOBINSEARCH = ffi::AVFMT_NOBINSEARCH as i32;
+        /// Format does not allow to fall back on generic search.
+        const NOGENSEARCH = ffi::AVFMT_NOGENSEARCH as i32;
+        /// Format does not allow seeking by bytes.
+        const NO_BYTE_SEEK = ffi::AVFMT_NO_BYTE_SEEK as i32;
+        /// Format does not require strictly increasing timestamps, but they
+        /// must still be monotonic.
+        const TS_NONSTRICT = ffi::AVFMT_TS_NONSTRICT as i32;
+        /// Format allows muxing negative timestamps.
+        const TS_NEGATIVE = ffi::AVFMT_TS_NEGATIVE as i32;
+        /// Seeking is based on PTS.
+        const SEEK_TO_PTS = ffi::AVFMT_SEEK_TO_PTS as i32;
+
+        // Keep the flags unknown to this version.
+        const _ = !0;
+    }
+}
+
+/// Layer of the demuxing stack consuming an option of
+/// [`AVFormatContextInput::open_with_report()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptionLayer {
+    /// Generic option of the format context, e.g. `probesize`.
+    Format,
+    /// Option of the protocol, e.g. `rw_timeout` of `tcp`.
+    Protocol,
+    /// Private option of the demuxer, e.g. `ignore_editlist` of `mov`.
+    Demuxer,
+}
+
+/// Which options of [`AVFormatContextInput::open_with_report()`] were
+/// consumed, and by which layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptionsReport {
+    /// Options consumed, in the order they were given.
+    pub consumed: Vec<(String, OptionLayer)>,
+    /// Options no layer recognized.
+    pub unconsumed: Vec<String>,
+    /// Unconsumed options which are codec options, e.g. `threads`. They only
+    /// apply to the decoders, pass them to [`AVCodecContext::open()`](crate::avcodec::AVCodecContext::open).
+    pub codec_options: Vec<String>,
+}
+
+impl OpenOptionsReport {
+    /// Get the layer which consumed the option `key`, `None` if it wasn't
+    /// consumed.
+    pub fn layer_of(&self, key: &str) -> Option<OptionLayer> {
+        self.consumed
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, layer)| *layer)
+    }
+}
+
+/// Callback polled by the blocking operations of libavformat, returning
+/// `true` aborts them with `AVERROR_EXIT`, e.g. when a timeout elapsed or the
+/// user cancelled.
+pub type InterruptCallback = Box<dyn FnMut() -> bool + Send>;
+
+/// `AVIOInterruptCB` calling the [`InterruptCallback`] boxed in `opaque`.
+unsafe extern "C" fn interrupt_c(opaque: *mut c_void) -> c_int {
+    let callback = unsafe { &mut *(opaque as *mut InterruptCallback) };
+    callback() as c_int
+}
+
+/// Build the `AVIOInterruptCB` calling `callback`, which must outlive it.
+pub(crate) fn interrupt_cb(callback: &mut Box<InterruptCallback>) -> ffi::AVIOInterruptCB {
+    ffi::AVIOInterruptCB {
+        callback: Some(interrupt_c),
+        opaque: &mut **callback as *mut InterruptCallback as *mut c_void,
+    }
 }
 
 wrap! {
     AVFormatContextInput: ffi::AVFormatContext,
     io_context: Option<AVIOContextContainer> = None,
+    // Called through the `interrupt_callback` of the context.
+    interrupt_handler: Option<Box<InterruptCallback>> = None,
 }
 
 impl AVFormatContextInput {
@@ -40,7 +217,68 @@ impl AVFormatContextInput {
         fmt: Option<&AVInputFormat>,
         options: &mut Option<AVDictionary>,
     ) -> Result<Self> {
-        let mut input_format_context = ptr::null_mut();
+        Self::open_impl(url, fmt, options, None)
+    }
+
+    /// Same as [`Self::open()`], with `callback` installed as the interrupt
+    /// callback of the context before opening, so the opening, the probing
+    /// of the streams and the I/O of the context can all be aborted, e.g.
+    /// when a network stream hangs. Returning `true` aborts the blocking
+    /// operation with `AVERROR_EXIT`.
+    ///
+    /// ```ignore
+    /// let cancelled = Arc::new(AtomicBool::new(false));
+    /// let token = cancelled.clone();
+    /// let deadline = Instant::now() + Duration::from_secs(5);
+    /// let input = AVFormatContextInput::open_with_interrupt(url, None, &mut None, move || {
+    ///     token.load(Ordering::Relaxed) || Instant::now() > deadline
+    /// })?;
+    /// // Later, from another thread: `cancelled.store(true, Ordering::Relaxed)`.
+    /// ```
+    pub fn open_with_interrupt(
+        url: &CStr,
+        fmt: Option<&AVInputFormat>,
+        options: &mut Option<AVDictionary>,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<Self> {
+        Self::open_impl(url, fmt, options, Some(Box::new(Box::new(callback))))
+    }
+
+    /// Same as [`Self::open()`], restricting the protocols the input and its
+    /// nested resources, e.g. the segments of a playlist, may use as set by
+    /// `policy`. Opening a url whose protocol is refused fails with
+    /// `AVERROR(EINVAL)`.
+    ///
+    /// ```ignore
+    /// let policy = ProtocolPolicy::allow(&["https", "tls", "tcp", "file"]);
+    /// let input = AVFormatContextInput::open_with_protocols(user_url, None, &mut None, &policy)?;
+    /// ```
+    pub fn open_with_protocols(
+        url: &CStr,
+        fmt: Option<&AVInputFormat>,
+        options: &mut Option<AVDictionary>,
+        policy: &ProtocolPolicy,
+    ) -> Result<Self> {
+        policy.apply(options)?;
+        Self::open_impl(url, fmt, options, None)
+    }
+
+    fn open_impl(
+        url: &CStr,
+        fmt: Option<&AVInputFormat>,
+        options: &mut Option<AVDictionary>,
+        mut interrupt_handler: Option<Box<InterruptCallback>>,
+    ) -> Result<Self> {
+        let mut input_format_context = match &mut interrupt_handler {
+            Some(handler) => {
+                let context =
+                    unsafe { ffi::avformat_alloc_context() }.upgrade_or(AVERROR_ENOMEM)?;
+                // Freed by `avformat_open_input` on failure.
+                unsafe { (*context.as_ptr()).interrupt_callback = interrupt_cb(handler) };
+                context.as_ptr()
+            }
+            None => ptr::null_mut(),
+        };
         let fmt = fmt.map(|x| x.as_ptr()).unwrap_or_else(std::ptr::null) as _;
         let mut options_ptr = options
             .as_mut()
@@ -68,6 +306,7 @@ impl AVFormatContextInput {
         // Here we can be sure that context is non null, constructing here for
         // dropping when `avformat_find_stream_info` fails.
         let mut context = unsafe { Self::from_raw(NonNull::new(input_format_context).unwrap()) };
+        context.interrupt_handler = interrupt_handler;
 
         unsafe { ffi::avformat_find_stream_info(context.as_mut_ptr(), ptr::null_mut()) }
             .upgrade()
@@ -76,9 +315,105 @@ impl AVFormatContextInput {
         Ok(context)
     }
 
+    /// Same as [`Self::open()`], but fails if any of `options` is not
+    /// recognized by the demuxer or the protocol, e.g. a typo in
+    /// `rw_timeout`, instead of silently ignoring it.
+    ///
+    /// The error is [`RsmpegError::UnrecognizedOptionsError`] listing the
+    /// unrecognized keys.
+    pub fn open_strict(
+        url: &CStr,
+        fmt: Option<&AVInputFormat>,
+        options: Option<AVDictionary>,
+    ) -> Result<Self> {
+        let mut options = options;
+        let context = Self::open(url, fmt, &mut options)?;
+        let unrecognized: Vec<_> = options
+            .iter()
+            .flatten()
+            .map(|x| x.key().to_string_lossy().into_owned())
+            .collect();
+        if !unrecognized.is_empty() {
+            return Err(RsmpegError::UnrecognizedOptionsError(unrecognized));
+        }
+        Ok(context)
+    }
+
+    /// Same as [`Self::open()`], and report which layer consumed each of
+    /// `options`, to find out why an option had no effect.
+    pub fn open_with_report(
+        url: &CStr,
+        fmt: Option<&AVInputFormat>,
+        options: Option<AVDictionary>,
+    ) -> Result<(Self, OpenOptionsReport)> {
+        let keys: Vec<_> = options
+            .iter()
+            .flatten()
+            .map(|x| x.key().to_owned())
+            .collect();
+        let mut options = options;
+        let context = Self::open(url, fmt, &mut options)?;
+        let unconsumed: Vec<_> = options
+            .iter()
+            .flatten()
+            .map(|x| x.key().to_owned())
+            .collect();
+
+        let format_class = unsafe { ffi::avformat_get_class() };
+        let protocol_class = unsafe {
+            ffi::avio_find_protocol_name(url.as_ptr())
+                .upgrade()
+                .map_or(ptr::null(), |x| ffi::avio_protocol_get_class(x.as_ptr()))
+        };
+        let demuxer_class = context.iformat().priv_class;
+        let codec_class = unsafe { ffi::avcodec_get_class() };
+        let has_option =
+            |class: *const ffi::AVClass, key: &CStr| unsafe { opt_names(class) }.contains(&key);
+
+        let mut report = OpenOptionsReport::default();
+        for key in keys {
+            let name = key.to_string_lossy().into_owned();
+            if unconsumed.contains(&key) {
+                if has_option(codec_class, &key) {
+                    report.codec_options.push(name.clone());
+                }
+                report.unconsumed.push(name);
+                continue;
+            }
+            // Same order as `avformat_open_input()`, options not found in the
+            // demuxer are consumed by nested protocols, e.g. http under hls.
+            let layer = if has_option(format_class, &key) {
+                OptionLayer::Format
+            } else if has_option(protocol_class, &key) {
+                OptionLayer::Protocol
+            } else if has_option(demuxer_class, &key) {
+                OptionLayer::Demuxer
+            } else {
+                OptionLayer::Protocol
+            };
+            report.consumed.push((name, layer));
+        }
+        Ok((context, report))
+    }
+
     /// Create a [`AVFormatContextInput`] instance from an [`AVIOContext`], and find info of
     /// all streams.
-    pub fn from_io_context(mut io_context: AVIOContextContainer) -> Result<Self> {
+    pub fn from_io_context(io_context: AVIOContextContainer) -> Result<Self> {
+        Self::from_io_context_impl(io_context, None)
+    }
+
+    /// Same as [`Self::from_io_context()`], with `url` describing where the
+    /// data comes from, e.g. `memory:thumbnail.jpg` or the url a custom
+    /// reader fetches. It's returned by [`Self::url()`] and shown by
+    /// [`Self::dump()`], and its extension helps guessing the format.
+    pub fn from_io_context_with_url(io_context: AVIOContextContainer, url: &CStr) -> Result<Self> {
+        Self::from_io_context_impl(io_context, Some(url))
+    }
+
+    fn from_io_context_impl(
+        mut io_context: AVIOContextContainer,
+        url: Option<&CStr>,
+    ) -> Result<Self> {
         let input_format_context = {
             // Only fails on no memory, so unwrap().
             // `avformat_open_input`'s documentation:
@@ -92,6 +427,7 @@ impl AVFormatContextInput {
                 (*input_format_context.as_ptr()).pb = match &mut io_context {
                     AVIOContextContainer::Url(ctx) => ctx.as_mut_ptr(),
                     AVIOContextContainer::Custom(ctx) => ctx.as_mut_ptr(),
+                    AVIOContextContainer::DynBuf(ctx) => ctx.as_mut_ptr(),
                 };
             }
             input_format_context
@@ -100,7 +436,7 @@ impl AVFormatContextInput {
         unsafe {
             ffi::avformat_open_input(
                 &mut input_format_context.as_ptr(),
-                ptr::null_mut(),
+                url.map_or(ptr::null(), |x| x.as_ptr()),
                 ptr::null_mut(),
                 ptr::null_mut(),
             )
@@ -124,11 +460,13 @@ impl AVFormatContextInput {
 
     /// Dump [`ffi::AVFormatContext`]'s info in the "FFmpeg" way.
     ///
-    /// The index and filename here is just for info printing, it really doesn't matter.
-    pub fn dump(&mut self, index: usize, filename: &CStr) -> Result<()> {
+    /// The index and filename here is just for info printing, it really doesn't matter,
+    /// e.g. `input.dump(0, input.url())`.
+    pub fn dump(&self, index: usize, filename: &CStr) -> Result<()> {
         unsafe {
-            // This input context, so the last parameter is 0
-            ffi::av_dump_format(self.as_mut_ptr(), index as i32, filename.as_ptr(), 0);
+            // This input context, so the last parameter is 0. The context is
+            // only read.
+            ffi::av_dump_format(self.as_ptr() as _, index as i32, filename.as_ptr(), 0);
         }
         Ok(())
     }
@@ -143,19 +481,132 @@ impl AVFormatContextInput {
     /// Return `Err(_)` on error, Return `Ok(None)` on EOF.
     pub fn read_packet(&mut self) -> Result<Option<AVPacket>> {
         let mut packet = AVPacket::new();
+        Ok(self.read_packet_into(&mut packet)?.then_some(packet))
+    }
+
+    /// Same as [`Self::read_packet()`], but reuses `packet` instead of
+    /// allocating a new one, which matters when demuxing many small packets,
+    /// e.g. audio only MPEG-TS. The previous content of `packet` is
+    /// released.
+    ///
+    /// Return `Ok(false)` on EOF, `packet` is then blank.
+    ///
+    /// ```ignore
+    /// let mut packet = AVPacket::new();
+    /// while input.read_packet_into(&mut packet)? {
+    ///     decoder.send_packet(Some(&packet))?;
+    /// }
+    /// ```
+    pub fn read_packet_into(&mut self, packet: &mut AVPacket) -> Result<bool> {
+        packet.unref();
         match unsafe { ffi::av_read_frame(self.as_mut_ptr(), packet.as_mut_ptr()) }.upgrade() {
-            Ok(_) => Ok(Some(packet)),
-            Err(ffi::AVERROR_EOF) => Ok(None),
+            Ok(_) => Ok(true),
+            Err(ffi::AVERROR_EOF) => Ok(false),
             Err(x) => Err(x)?,
         }
     }
 
+    /// Start playing a network-based stream, e.g. RTSP, at the current
+    /// position, resuming it after [`Self::read_pause()`].
+    ///
+    /// Returns `AVERROR(ENOSYS)` if the demuxer doesn't support it.
+    pub fn read_play(&mut self) -> Result<()> {
+        unsafe { ffi::av_read_play(self.as_mut_ptr()) }.upgrade()?;
+        Ok(())
+    }
+
+    /// Pause a network-based stream, e.g. RTSP, [`Self::read_play()`]
+    /// resumes it.
+    ///
+    /// Returns `AVERROR(ENOSYS)` if the demuxer doesn't support it.
+    pub fn read_pause(&mut self) -> Result<()> {
+        unsafe { ffi::av_read_pause(self.as_mut_ptr()) }.upgrade()?;
+        Ok(())
+    }
+
+    /// Install `callback` as the interrupt callback of the context, replacing
+    /// the previous one. It's polled while the demuxer blocks, e.g. waiting
+    /// for the packets of a network stream, and returning `true` aborts the
+    /// operation with `AVERROR_EXIT`.
+    ///
+    /// The I/O context opened by [`Self::open()`] keeps the callback of the
+    /// context at the time it was opened, use [`Self::open_with_interrupt()`]
+    /// to interrupt its reads too.
+    pub fn set_interrupt_callback(&mut self, callback: impl FnMut() -> bool + Send + 'static) {
+        let mut handler: Box<InterruptCallback> = Box::new(Box::new(callback));
+        unsafe { self.deref_mut().interrupt_callback = interrupt_cb(&mut handler) };
+        // The previous callback is dropped once it's no longer referenced.
+        self.interrupt_handler = Some(handler);
+    }
+
+    /// Remove the interrupt callback of the context.
+    pub fn clear_interrupt_callback(&mut self) {
+        unsafe {
+            self.deref_mut().interrupt_callback = ffi::AVIOInterruptCB {
+                callback: None,
+                opaque: ptr::null_mut(),
+            }
+        };
+        self.interrupt_handler = None;
+    }
+
+    /// Seek to timestamp `ts`, seeking will be done so that the point from
+    /// which all active streams can be presented successfully will be closest
+    /// to `ts` and within `min_ts..=max_ts`.
+    ///
+    /// If `stream_index` is `None`, timestamps are in `AV_TIME_BASE` units,
+    /// otherwise in the time base of the given stream.
+    pub fn seek(
+        &mut self,
+        stream_index: Option<usize>,
+        min_ts: i64,
+        ts: i64,
+        max_ts: i64,
+        flags: impl Into<SeekFlags>,
+    ) -> Result<()> {
+        let stream_index = stream_index.map_or(Ok(-1), i32::try_from)?;
+        unsafe {
+            ffi::avformat_seek_file(
+                self.as_mut_ptr(),
+                stream_index,
+                min_ts,
+                ts,
+                max_ts,
+                flags.into().bits() as i32,
+            )
+        }
+        .upgrade()?;
+        Ok(())
+    }
+
     /// Return the stream index and stream decoder if there is any "best" stream.
     /// "best" means the most likely what the user wants.
     pub fn find_best_stream(
         &self,
         media_type: ffi::AVMediaType,
     ) -> Result<Option<(usize, AVCodecRef<'static>)>> {
+        self.find_best_stream_ext(media_type, None, None)
+    }
+
+    /// Same as [`Self::find_best_stream`], but with explicit stream selection.
+    ///
+    /// `wanted_stream_nb`: user-requested stream index, or `None` for
+    /// automatic selection.
+    ///
+    /// `related_stream`: try to find a stream related (e.g. in the same
+    /// program) to this one, or `None` if none.
+    ///
+    /// Useful for multi-program inputs such as MPEG-TS, e.g. selecting the
+    /// audio stream that belongs to the program of a chosen video stream.
+    pub fn find_best_stream_ext(
+        &self,
+        media_type: ffi::AVMediaType,
+        wanted_stream_nb: Option<usize>,
+        related_stream: Option<usize>,
+    ) -> Result<Option<(usize, AVCodecRef<'static>)>> {
+        let wanted_stream_nb = wanted_stream_nb.map_or(Ok(-1), i32::try_from)?;
+        let related_stream = related_stream.map_or(Ok(-1), i32::try_from)?;
+
         // After FFmpeg 4.4 this should be changed to *const AVCodec, here we
         // preserve the backward compatibility.
         let dec = ptr::null_mut();
@@ -166,7 +617,14 @@ impl AVFormatContextInput {
         // According to ffmpeg's source code, here we legally assume that
         // `av_find_best_stream` doesn't change given `*mut AVFormatContext`.
         match unsafe {
-            ffi::av_find_best_stream(self.as_ptr() as *mut _, media_type, -1, -1, &mut dec, 0)
+            ffi::av_find_best_stream(
+                self.as_ptr() as *mut _,
+                media_type,
+                wanted_stream_nb,
+                related_stream,
+                &mut dec,
+                0,
+            )
         }
         .upgrade()
         {
@@ -179,6 +637,46 @@ impl AVFormatContextInput {
     }
 }
 
+impl AVFormatContextInput {
+    /// Get the `flags` field as [`FormatFlags`].
+    pub fn format_flags(&self) -> FormatFlags {
+        FormatFlags::from_bits_retain(self.flags)
+    }
+
+    /// Set the `flags` field from [`FormatFlags`].
+    pub fn set_format_flags(&mut self, flags: FormatFlags) {
+        unsafe { self.deref_mut().flags = flags.bits() };
+    }
+
+    /// Duration of the input, `None` if unknown.
+    pub fn duration(&self) -> Option<Duration> {
+        ts_to_duration(self.duration, AV_TIME_BASE_Q)
+    }
+
+    /// Position of the first frame of the input, `None` if unknown or
+    /// negative.
+    pub fn start_time(&self) -> Option<Duration> {
+        ts_to_duration(self.start_time, AV_TIME_BASE_Q)
+    }
+
+    /// Total stream bitrate in bit/s, `None` if unknown.
+    pub fn bit_rate(&self) -> Option<i64> {
+        (self.bit_rate > 0).then_some(self.bit_rate)
+    }
+
+    /// Get the url the input was opened with, empty if it was created from
+    /// an I/O context without url.
+    pub fn url(&self) -> &CStr {
+        context_url(self)
+    }
+
+    /// Replace the url describing the input, e.g. to name an input read from
+    /// memory in [`Self::dump()`] and in the logs of the application.
+    pub fn set_url(&mut self, url: &CStr) -> Result<()> {
+        unsafe { set_context_url(self.as_mut_ptr(), url) }
+    }
+}
+
 impl<'stream> AVFormatContextInput {
     /// Return slice of [`AVStreamRef`].
     pub fn streams(&'stream self) -> &'stream [AVStreamRef<'stream>] {
@@ -234,6 +732,43 @@ impl<'stream> AVFormatContextInput {
         unsafe { std::slice::from_raw_parts_mut(streams, len) }
     }
 
+    /// Get the stream at `index`, `None` if there is no such stream.
+    pub fn stream(&'stream self, index: usize) -> Option<&'stream AVStreamRef<'stream>> {
+        self.streams().get(index)
+    }
+
+    /// Iterate over the streams of `media_type` with their index.
+    pub fn streams_of_type(
+        &'stream self,
+        media_type: ffi::AVMediaType,
+    ) -> impl Iterator<Item = (usize, &'stream AVStreamRef<'stream>)> {
+        self.streams()
+            .iter()
+            .enumerate()
+            .filter(move |(_, x)| x.codecpar().codec_type == media_type)
+    }
+
+    /// Iterate over the video streams with their index.
+    pub fn video_streams(
+        &'stream self,
+    ) -> impl Iterator<Item = (usize, &'stream AVStreamRef<'stream>)> {
+        self.streams_of_type(ffi::AVMEDIA_TYPE_VIDEO)
+    }
+
+    /// Iterate over the audio streams with their index.
+    pub fn audio_streams(
+        &'stream self,
+    ) -> impl Iterator<Item = (usize, &'stream AVStreamRef<'stream>)> {
+        self.streams_of_type(ffi::AVMEDIA_TYPE_AUDIO)
+    }
+
+    /// Iterate over the subtitle streams with their index.
+    pub fn subtitle_streams(
+        &'stream self,
+    ) -> impl Iterator<Item = (usize, &'stream AVStreamRef<'stream>)> {
+        self.streams_of_type(ffi::AVMEDIA_TYPE_SUBTITLE)
+    }
+
     /// Get [`AVInputFormatRef`] in the [`AVFormatContextInput`].
     pub fn iformat(&'stream self) -> AVInputFormatRef<'stream> {
         // From the implementation of FFmpeg's `avformat_open_input`, we can be
@@ -250,6 +785,41 @@ impl<'stream> AVFormatContextInput {
         // `metadata` can be null.
         NonNull::new(self.metadata).map(|x| unsafe { AVDictionaryRef::from_raw(x) })
     }
+
+    /// Return slice of [`AVProgramRef`], e.g. the services of a MPEG-TS input.
+    pub fn programs(&'stream self) -> &'stream [AVProgramRef<'stream>] {
+        // AVProgramRef <-> *const ffi::AVProgram, same as `streams()`.
+        unsafe {
+            ptr_slice(
+                self.programs as *const AVProgramRef<'stream>,
+                self.nb_programs,
+            )
+        }
+    }
+
+    /// Return slice of [`AVChapterRef`].
+    pub fn chapters(&'stream self) -> &'stream [AVChapterRef<'stream>] {
+        // AVChapterRef <-> *const ffi::AVChapter, same as `streams()`.
+        unsafe {
+            ptr_slice(
+                self.chapters as *const AVChapterRef<'stream>,
+                self.nb_chapters,
+            )
+        }
+    }
+}
+
+/// Build a slice from FFmpeg's pointer array, which is null when empty.
+///
+/// # Safety
+/// `ptr` should point to `len` valid elements if not null.
+unsafe fn ptr_slice<'a, T>(ptr: *const T, len: u32) -> &'a [T] {
+    if ptr.is_null() {
+        &[]
+    } else {
+        // u32 to usize, safe
+        unsafe { std::slice::from_raw_parts(ptr, len as usize) }
+    }
 }
 
 impl Drop for AVFormatContextInput {
@@ -262,19 +832,73 @@ impl Drop for AVFormatContextInput {
 wrap! {
     AVFormatContextOutput: ffi::AVFormatContext,
     io_context: Option<AVIOContextContainer> = None,
+    // Dts of the last packet successfully written into each stream.
+    last_dts: Vec<i64> = Vec::new(),
+    dts_check: DtsCheck = DtsCheck::Off,
+}
+settable!(AVFormatContextOutput {
+    max_interleave_delta: i64,
+    output_ts_offset: i64,
+    avoid_negative_ts: i32,
+});
+
+/// Where the muxer finished initializing the streams of an
+/// [`AVFormatContextOutput`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamInit {
+    /// In [`AVFormatContextOutput::write_header()`].
+    InWriteHeader,
+    /// In [`AVFormatContextOutput::init_output()`].
+    InInitOutput,
+}
+
+/// How [`AVFormatContextOutput`] checks the dts of the written packets, see
+/// [`AVFormatContextOutput::set_dts_check()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DtsCheck {
+    /// Leave the checks to the muxer, a non monotonically increasing dts
+    /// fails with `AVERROR(EINVAL)`.
+    #[default]
+    Off,
+    /// Fail with [`RsmpegError::NonMonotonicTimestamps`] without writing the
+    /// packet.
+    Report,
+    /// Fix the timestamps like the `ffmpeg` CLI does: a packet whose dts is
+    /// not after the previous one of its stream is moved right after it, and
+    /// a pts lower than the dts is fixed.
+    Correct,
 }
 
 impl AVFormatContextOutput {
     /// Open a file and create a [`AVFormatContextOutput`] instance of that
     /// file. Give it an [`AVIOContext`] if you want custom IO.
     pub fn create(filename: &CStr, io_context: Option<AVIOContextContainer>) -> Result<Self> {
+        Self::create_impl(filename, ptr::null(), io_context)
+    }
+
+    /// Same as [`Self::create()`], but with the muxer `format` instead of the
+    /// one guessed from `filename`, e.g. for muxers sharing an extension or
+    /// outputs without file name like `mpjpeg` over HTTP.
+    pub fn create_with_format(
+        filename: &CStr,
+        format: &AVOutputFormat,
+        io_context: Option<AVIOContextContainer>,
+    ) -> Result<Self> {
+        Self::create_impl(filename, format.as_ptr(), io_context)
+    }
+
+    fn create_impl(
+        filename: &CStr,
+        format: *const ffi::AVOutputFormat,
+        io_context: Option<AVIOContextContainer>,
+    ) -> Result<Self> {
         let mut output_format_context = ptr::null_mut();
 
         // Alloc the context
         unsafe {
             ffi::avformat_alloc_output_context2(
                 &mut output_format_context,
-                ptr::null_mut(),
+                format as _,
                 ptr::null_mut(),
                 filename.as_ptr(),
             )
@@ -292,7 +916,11 @@ impl AVFormatContextOutput {
         //
         // For safeness, we don't use the user the given AVIOContext even if the
         // caller provides one.
-        if output_format_context.oformat().flags & ffi::AVFMT_NOFILE as i32 == 0 {
+        if !output_format_context
+            .oformat()
+            .format_flags()
+            .contains(IOFormatFlags::NOFILE)
+        {
             // If user provides us an `AVIOCustomContext`, use it, or we create a default one.
             let mut io_context = match io_context {
                 Some(x) => x,
@@ -304,6 +932,7 @@ impl AVFormatContextOutput {
                 output_format_context.deref_mut().pb = match &mut io_context {
                     AVIOContextContainer::Url(ctx) => ctx.as_mut_ptr(),
                     AVIOContextContainer::Custom(ctx) => ctx.as_mut_ptr(),
+                    AVIOContextContainer::DynBuf(ctx) => ctx.as_mut_ptr(),
                 };
             }
             output_format_context.io_context = Some(io_context);
@@ -312,6 +941,15 @@ impl AVFormatContextOutput {
         Ok(output_format_context)
     }
 
+    /// Free the output and return its [`AVIOContextContainer`], e.g. to get
+    /// the data written into an [`AVIOContextDynBuf`] after writing the
+    /// trailer. Returns `None` if the muxer does its own IO.
+    pub fn into_io_context(mut self) -> Option<AVIOContextContainer> {
+        // `pb` is left dangling, which is fine since `avformat_free_context`
+        // doesn't touch it.
+        self.io_context.take()
+    }
+
     /// Allocate the stream private data and write the stream header to an
     /// output media file.
     ///
@@ -319,22 +957,54 @@ impl AVFormatContextOutput {
     ///     and muxer-private options. On return this parameter will be replaced
     ///     with a dict containing options that were not found. Set this to `None`
     ///     if it's not needed.
-    pub fn write_header(&mut self, dict: &mut Option<AVDictionary>) -> Result<()> {
+    ///
+    /// Returns [`StreamInit::InInitOutput`] if the streams had already been
+    /// fully initialized by [`Self::init_output()`].
+    pub fn write_header(&mut self, dict: &mut Option<AVDictionary>) -> Result<StreamInit> {
+        self.with_options(dict, |s, options| unsafe {
+            ffi::avformat_write_header(s, options)
+        })
+    }
+
+    /// Allocate the stream private data and initialize the muxer, but don't
+    /// write the header, which is done by [`Self::write_header()`] later.
+    ///
+    /// It lets the parameters chosen by the muxer, e.g. the real `time_base`
+    /// of the streams, be read before any packet is rescaled for the output.
+    /// The `options` are the same as [`Self::write_header()`], don't pass
+    /// them again to it.
+    ///
+    /// Returns [`StreamInit::InWriteHeader`] if the muxer needs
+    /// [`Self::write_header()`] to finish initializing the streams.
+    pub fn init_output(&mut self, dict: &mut Option<AVDictionary>) -> Result<StreamInit> {
+        self.with_options(dict, |s, options| unsafe {
+            ffi::avformat_init_output(s, options)
+        })
+    }
+
+    /// Call `f` with the raw `options` of `dict`, moving back the ownership of
+    /// the options not consumed.
+    fn with_options(
+        &mut self,
+        dict: &mut Option<AVDictionary>,
+        f: impl FnOnce(*mut ffi::AVFormatContext, *mut *mut ffi::AVDictionary) -> i32,
+    ) -> Result<StreamInit> {
         let mut dict_ptr = dict
             .take()
             .map(|x| x.into_raw().as_ptr())
             .unwrap_or_else(ptr::null_mut);
 
-        let result = unsafe { ffi::avformat_write_header(self.as_mut_ptr(), &mut dict_ptr as _) };
+        let result = f(self.as_mut_ptr(), &mut dict_ptr as _);
 
         // Move back the ownership if not consumed.
         *dict = dict_ptr
             .upgrade()
             .map(|x| unsafe { AVDictionary::from_raw(x) });
 
-        result.upgrade()?;
-
-        Ok(())
+        Ok(match result.upgrade()? as u32 {
+            ffi::AVSTREAM_INIT_IN_INIT_OUTPUT => StreamInit::InInitOutput,
+            _ => StreamInit::InWriteHeader,
+        })
     }
 
     /// Write the stream trailer to an output media file and free the file
@@ -346,11 +1016,13 @@ impl AVFormatContextOutput {
 
     /// Dump [`ffi::AVFormatContext`] info in the "FFmpeg" way.
     ///
-    /// The filename here is just for info printing, it's really doesn't matter.
-    pub fn dump(&mut self, index: i32, filename: &CStr) -> Result<()> {
+    /// The filename here is just for info printing, it's really doesn't matter,
+    /// e.g. `output.dump(0, output.url())`.
+    pub fn dump(&self, index: i32, filename: &CStr) -> Result<()> {
         unsafe {
-            // This is output context, so the last parameter is 1
-            ffi::av_dump_format(self.as_mut_ptr(), index, filename.as_ptr(), 1);
+            // This is output context, so the last parameter is 1. The context
+            // is only read.
+            ffi::av_dump_format(self.as_ptr() as _, index, filename.as_ptr(), 1);
         }
         Ok(())
     }
@@ -362,8 +1034,12 @@ impl AVFormatContextOutput {
     /// interleaving the packets if the format requires it. Callers that want
     /// libavformat to handle the interleaving should call
     /// [`Self::interleaved_write_frame()`] instead of this function.
+    ///
+    /// The timestamps are checked according to [`Self::set_dts_check()`].
     pub fn write_frame(&mut self, packet: &mut AVPacket) -> Result<()> {
+        let dts = self.check_dts(packet)?;
         unsafe { ffi::av_write_frame(self.as_mut_ptr(), packet.as_mut_ptr()) }.upgrade()?;
+        self.record_dts(dts);
         Ok(())
     }
 
@@ -373,13 +1049,183 @@ impl AVFormatContextOutput {
     /// the packets in the output file are properly interleaved in the order of
     /// increasing dts. Callers doing their own interleaving should call
     /// [`Self::write_frame()`] instead of this function.
+    ///
+    /// The timestamps are checked according to [`Self::set_dts_check()`].
     pub fn interleaved_write_frame(&mut self, packet: &mut AVPacket) -> Result<()> {
+        // The packet is blank once written.
+        let dts = self.check_dts(packet)?;
         unsafe { ffi::av_interleaved_write_frame(self.as_mut_ptr(), packet.as_mut_ptr()) }
             .upgrade()?;
+        self.record_dts(dts);
+        Ok(())
+    }
+
+    /// Set how the dts of the written packets are checked against the
+    /// previous packet of their stream, [`DtsCheck::Off`] by default.
+    pub fn set_dts_check(&mut self, check: DtsCheck) {
+        self.dts_check = check;
+    }
+
+    /// Make the muxer shift the timestamps of all the streams so that the
+    /// output starts at 0, whatever the start time of the input, e.g. when
+    /// remuxing a segment cut in the middle of a file. This sets
+    /// `avoid_negative_ts` to `AVFMT_AVOID_NEG_TS_MAKE_ZERO` and resets
+    /// `output_ts_offset`, whose offset in `AV_TIME_BASE` units is applied
+    /// before the shift otherwise. Must be called before
+    /// [`Self::write_header()`].
+    pub fn start_at_zero(&mut self) {
+        self.set_output_ts_offset(0);
+        self.set_avoid_negative_ts(ffi::AVFMT_AVOID_NEG_TS_MAKE_ZERO as i32);
+    }
+
+    /// Check the dts of `packet`, whose timestamps are in the time base of
+    /// its stream, against the previous packet of the stream according to
+    /// the [`DtsCheck`], fixing it if needed. Returns the stream and the dts
+    /// to record once the packet is written.
+    fn check_dts(&mut self, packet: &mut AVPacket) -> Result<Option<(usize, i64)>> {
+        let flags = self.oformat().format_flags();
+        let stream_index = packet.stream_index as usize;
+        if flags.contains(IOFormatFlags::NOTIMESTAMPS) || stream_index >= self.nb_streams as usize {
+            return Ok(None);
+        }
+        if self.dts_check == DtsCheck::Off {
+            return Ok(Some((stream_index, packet.dts)));
+        }
+        let correct = self.dts_check == DtsCheck::Correct;
+        let codec_type = self.streams()[stream_index].codecpar().codec_type;
+        let last_dts = self
+            .last_dts
+            .get(stream_index)
+            .copied()
+            .unwrap_or(ffi::AV_NOPTS_VALUE);
+
+        if correct
+            && packet.dts != ffi::AV_NOPTS_VALUE
+            && packet.pts != ffi::AV_NOPTS_VALUE
+            && packet.dts > packet.pts
+        {
+            // Take the median of pts, dts and last_dts + 1.
+            let mut ts = [packet.pts, packet.dts, last_dts.saturating_add(1)];
+            ts.sort_unstable();
+            packet.set_pts(ts[1]);
+            packet.set_dts(ts[1]);
+        }
+
+        // Same rules as libavformat, which doesn't check the packets following
+        // a dts of 0, and lets subtitle and data streams repeat a dts.
+        if packet.dts != ffi::AV_NOPTS_VALUE && last_dts != ffi::AV_NOPTS_VALUE && last_dts != 0 {
+            let strict = !flags.contains(IOFormatFlags::TS_NONSTRICT)
+                && codec_type != ffi::AVMEDIA_TYPE_SUBTITLE
+                && codec_type != ffi::AVMEDIA_TYPE_DATA;
+            let min_dts = if strict { last_dts + 1 } else { last_dts };
+            if packet.dts < min_dts {
+                if !correct {
+                    return Err(RsmpegError::NonMonotonicTimestamps {
+                        stream_index,
+                        last_dts,
+                        dts: packet.dts,
+                    });
+                }
+                if packet.pts != ffi::AV_NOPTS_VALUE && packet.pts >= packet.dts {
+                    packet.set_pts(packet.pts.max(min_dts));
+                }
+                packet.set_dts(min_dts);
+            }
+        }
+        Ok(Some((stream_index, packet.dts)))
+    }
+
+    /// Record the dts of a packet returned by [`Self::check_dts()`] once it's
+    /// written.
+    fn record_dts(&mut self, dts: Option<(usize, i64)>) {
+        let Some((stream_index, dts)) = dts else {
+            return;
+        };
+        if dts == ffi::AV_NOPTS_VALUE {
+            return;
+        }
+        if self.last_dts.len() <= stream_index {
+            self.last_dts.resize(stream_index + 1, ffi::AV_NOPTS_VALUE);
+        }
+        self.last_dts[stream_index] = dts;
+    }
+
+    /// Set the stream index of `packet` to `stream_index`, rescale its
+    /// timestamps from `src_time_base`, e.g. the time base of the encoder, to
+    /// the time base of the stream, then write it with
+    /// [`Self::interleaved_write_frame()`].
+    ///
+    /// Returns `AVERROR(EINVAL)` if there is no stream `stream_index`.
+    pub fn write_packet_from(
+        &mut self,
+        packet: &mut AVPacket,
+        src_time_base: AVRational,
+        stream_index: usize,
+    ) -> Result<()> {
+        let time_base = self
+            .streams()
+            .get(stream_index)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?
+            .time_base;
+        packet.set_stream_index(i32::try_from(stream_index)?);
+        packet.rescale_ts(src_time_base, time_base);
+        self.interleaved_write_frame(packet)
+    }
+
+    /// Write all the packets buffered for interleaving by
+    /// [`Self::interleaved_write_frame()`] to the output.
+    pub fn interleaved_flush(&mut self) -> Result<()> {
+        unsafe { ffi::av_interleaved_write_frame(self.as_mut_ptr(), ptr::null_mut()) }.upgrade()?;
         Ok(())
     }
 }
 
+impl AVFormatContextOutput {
+    /// Get the `flags` field as [`FormatFlags`].
+    pub fn format_flags(&self) -> FormatFlags {
+        FormatFlags::from_bits_retain(self.flags)
+    }
+
+    /// Set the `flags` field from [`FormatFlags`].
+    pub fn set_format_flags(&mut self, flags: FormatFlags) {
+        unsafe { self.deref_mut().flags = flags.bits() };
+    }
+
+    /// Get the url the output was created with, which is only a name when
+    /// it's written into a custom I/O context.
+    pub fn url(&self) -> &CStr {
+        context_url(self)
+    }
+
+    /// Replace the url describing the output, e.g. to name an output written
+    /// into memory in [`Self::dump()`]. Some muxers write it into the
+    /// output, so it should be set before [`Self::write_header()`].
+    pub fn set_url(&mut self, url: &CStr) -> Result<()> {
+        unsafe { set_context_url(self.as_mut_ptr(), url) }
+    }
+}
+
+/// Get the `url` field of a format context, empty if null.
+fn context_url(context: &ffi::AVFormatContext) -> &CStr {
+    if context.url.is_null() {
+        return Default::default();
+    }
+    unsafe { CStr::from_ptr(context.url) }
+}
+
+/// Replace the `url` field of a format context, which is owned by it.
+///
+/// # Safety
+/// `context` should be a valid format context.
+unsafe fn set_context_url(context: *mut ffi::AVFormatContext, url: &CStr) -> Result<()> {
+    let url = unsafe { ffi::av_strdup(url.as_ptr()) }.upgrade_or(AVERROR_ENOMEM)?;
+    unsafe {
+        ffi::av_freep(&mut (*context).url as *mut _ as *mut c_void);
+        (*context).url = url.as_ptr();
+    }
+    Ok(())
+}
+
 impl<'stream> AVFormatContextOutput {
     /// Return slice of [`AVStreamRef`].
     pub fn streams(&'stream self) -> &'stream [AVStreamRef<'stream>] {
@@ -423,6 +1269,88 @@ impl<'stream> AVFormatContextOutput {
         }
     }
 
+    /// Get metadata of the [`AVFormatContextOutput`].
+    pub fn metadata(&'stream self) -> Option<AVDictionaryRef<'stream>> {
+        NonNull::new(self.metadata).map(|x| unsafe { AVDictionaryRef::from_raw(x) })
+    }
+
+    /// Get mutable reference of metadata of the [`AVFormatContextOutput`].
+    pub fn metadata_mut(&'stream mut self) -> Option<AVDictionaryMut<'stream>> {
+        NonNull::new(self.metadata).map(|x| unsafe { AVDictionaryMut::from_raw(x) })
+    }
+
+    /// Set metadata of the [`AVFormatContextOutput`], should be called by the
+    /// user before [`Self::write_header()`].
+    pub fn set_metadata(&mut self, dict: Option<AVDictionary>) {
+        // Drop the old_dict
+        let _ = NonNull::new(self.metadata).map(|x| unsafe { AVDictionary::from_raw(x) });
+
+        // Move in the new dict.
+        unsafe {
+            self.deref_mut().metadata = dict
+                .map(|x| x.into_raw().as_ptr())
+                .unwrap_or(ptr::null_mut());
+        }
+    }
+
+    /// Return slice of [`AVChapterRef`].
+    pub fn chapters(&'stream self) -> &'stream [AVChapterRef<'stream>] {
+        unsafe {
+            ptr_slice(
+                self.chapters as *const AVChapterRef<'stream>,
+                self.nb_chapters,
+            )
+        }
+    }
+
+    /// Add a new chapter to a media file, should be called by the user before
+    /// [`Self::write_header()`].
+    ///
+    /// `start` and `end` are in `time_base` units, `id` should be unique.
+    pub fn add_chapter(
+        &mut self,
+        id: i64,
+        time_base: AVRational,
+        start: i64,
+        end: i64,
+        metadata: Option<AVDictionary>,
+    ) -> Result<()> {
+        let chapter = unsafe { ffi::av_mallocz(std::mem::size_of::<ffi::AVChapter>()) }
+            .upgrade()
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::ENOMEM)))?
+            .cast::<ffi::AVChapter>();
+        unsafe {
+            *chapter.as_ptr() = ffi::AVChapter {
+                id,
+                time_base,
+                start,
+                end,
+                metadata: metadata
+                    .map(|x| x.into_raw().as_ptr())
+                    .unwrap_or(ptr::null_mut()),
+            };
+        }
+        // The chapter array is freed along with each chapter and its metadata
+        // in `avformat_free_context()`.
+        let ret = unsafe {
+            let context = self.deref_mut();
+            ffi::av_dynarray_add_nofree(
+                &mut context.chapters as *mut _ as *mut _,
+                &mut context.nb_chapters as *mut u32 as *mut c_int,
+                chapter.as_ptr() as *mut _,
+            )
+        }
+        .upgrade();
+        if let Err(e) = ret {
+            unsafe {
+                ffi::av_dict_free(&mut (*chapter.as_ptr()).metadata);
+                ffi::av_free(chapter.as_ptr() as *mut _);
+            }
+            return Err(RsmpegError::AVError(e));
+        }
+        Ok(())
+    }
+
     /// Add a new stream to a media file, should be called by the user before
     /// [`Self::write_header()`];
     pub fn new_stream(&'stream mut self) -> AVStreamMut<'stream> {
@@ -434,13 +1362,45 @@ impl<'stream> AVFormatContextOutput {
 
         unsafe { AVStreamMut::from_raw(new_stream) }
     }
+
+    /// Add a new stream copying the packets of the input `stream` without
+    /// transcoding, should be called by the user before
+    /// [`Self::write_header()`].
+    ///
+    /// The codec parameters, time base and average frame rate of `stream`
+    /// are copied. The codec tag is only kept if the output format maps it to
+    /// the same codec or has no tag for the codec, like `ffmpeg -c copy`
+    /// does, otherwise the muxer rejects e.g. the `mp4a` tag of a MP4 input
+    /// in a MKV output.
+    pub fn new_stream_copy(&'stream mut self, stream: &AVStreamRef) -> AVStreamMut<'stream> {
+        let mut codecpar = stream.codecpar().clone();
+        let tags = self.oformat().codec_tag;
+        let keep_tag = tags.is_null() || {
+            let mut tag = 0;
+            let tag_codec = unsafe { ffi::av_codec_get_id(tags, codecpar.codec_tag) };
+            let has_tag = unsafe { ffi::av_codec_get_tag2(tags, codecpar.codec_id, &mut tag) };
+            tag_codec == codecpar.codec_id || has_tag == 0
+        };
+        if !keep_tag {
+            codecpar.set_codec_tag(0);
+        }
+        let mut new_stream = self.new_stream();
+        new_stream.set_codecpar(codecpar);
+        new_stream.set_time_base(stream.time_base);
+        new_stream.set_avg_frame_rate(stream.avg_frame_rate);
+        new_stream
+    }
 }
 
 impl Drop for AVFormatContextOutput {
     fn drop(&mut self) {
         // Here we drop the io context, which won't be touched by
         // avformat_free_context, so let it dangling is safe.
-        if unsafe { *self.oformat }.flags & ffi::AVFMT_NOFILE as i32 == 0 {
+        if !self
+            .oformat()
+            .format_flags()
+            .contains(IOFormatFlags::NOFILE)
+        {
             if let Some(pb) = NonNull::new(self.pb) {
                 let _ = unsafe { AVIOContext::from_raw(pb) };
             }
@@ -453,6 +1413,7 @@ impl Drop for AVFormatContextOutput {
 }
 
 wrap_ref!(AVInputFormat: ffi::AVInputFormat);
+wrap_static!(AVInputFormat);
 
 impl AVInputFormat {
     /// Find [`AVInputFormat`] based on the short name of the input format.
@@ -461,9 +1422,55 @@ impl AVInputFormat {
             .upgrade()
             .map(|x| unsafe { AVInputFormatRef::from_raw(x) })
     }
+
+    /// Iterate over all registered demuxers.
+    pub fn iterate() -> AVInputFormatIter {
+        AVInputFormatIter {
+            opaque: ptr::null_mut(),
+        }
+    }
+
+    /// Short name of the demuxer, e.g. `mov,mp4,m4a,3gp,3g2,mj2`.
+    pub fn name(&self) -> &CStr {
+        unsafe { CStr::from_ptr(self.name) }
+    }
+
+    /// Descriptive name of the demuxer.
+    pub fn long_name(&self) -> Option<&CStr> {
+        optional_cstr(self.long_name)
+    }
+
+    /// Comma separated file extensions, if the demuxer defines them.
+    pub fn extensions(&self) -> Option<&CStr> {
+        optional_cstr(self.extensions)
+    }
+
+    /// Comma separated mime types, if the demuxer defines them.
+    pub fn mime_type(&self) -> Option<&CStr> {
+        optional_cstr(self.mime_type)
+    }
+
+    /// Get the `flags` field as [`IOFormatFlags`].
+    pub fn format_flags(&self) -> IOFormatFlags {
+        IOFormatFlags::from_bits_retain(self.flags)
+    }
+}
+
+pub struct AVInputFormatIter {
+    opaque: *mut c_void,
+}
+
+impl Iterator for AVInputFormatIter {
+    type Item = AVInputFormatRef<'static>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ptr = unsafe { ffi::av_demuxer_iterate(&mut self.opaque) }.upgrade()?;
+        Some(unsafe { AVInputFormatRef::from_raw(ptr) })
+    }
 }
 
 wrap_ref!(AVOutputFormat: ffi::AVOutputFormat);
+wrap_static!(AVOutputFormat);
 
 impl AVOutputFormat {
     /// Return the output format in the list of registered output formats which
@@ -482,6 +1489,79 @@ impl AVOutputFormat {
             .upgrade()
             .map(|x| unsafe { AVOutputFormatRef::from_raw(x) })
     }
+
+    /// Test if the given codec can be stored in this container.
+    ///
+    /// Returns `None` if the muxer doesn't know, `std_compliance` is one of
+    /// `FF_COMPLIANCE_*`.
+    pub fn query_codec(&self, codec_id: ffi::AVCodecID, std_compliance: i32) -> Option<bool> {
+        match unsafe { ffi::avformat_query_codec(self.as_ptr(), codec_id, std_compliance) } {
+            x if x < 0 => None,
+            x => Some(x == 1),
+        }
+    }
+
+    /// Iterate over all registered muxers.
+    pub fn iterate() -> AVOutputFormatIter {
+        AVOutputFormatIter {
+            opaque: ptr::null_mut(),
+        }
+    }
+
+    /// Short name of the muxer, e.g. `mp4`.
+    pub fn name(&self) -> &CStr {
+        unsafe { CStr::from_ptr(self.name) }
+    }
+
+    /// Descriptive name of the muxer.
+    pub fn long_name(&self) -> Option<&CStr> {
+        optional_cstr(self.long_name)
+    }
+
+    /// Comma separated file extensions, if the muxer defines them.
+    pub fn extensions(&self) -> Option<&CStr> {
+        optional_cstr(self.extensions)
+    }
+
+    /// Mime type, if the muxer defines it.
+    pub fn mime_type(&self) -> Option<&CStr> {
+        optional_cstr(self.mime_type)
+    }
+
+    /// Get the `flags` field as [`IOFormatFlags`].
+    pub fn format_flags(&self) -> IOFormatFlags {
+        IOFormatFlags::from_bits_retain(self.flags)
+    }
+
+    /// Return the ids of the registered codecs which [`Self::query_codec()`]
+    /// reports as storable in this container with `FF_COMPLIANCE_NORMAL`.
+    /// Muxers which don't know are skipped, as they return `None` for every
+    /// codec.
+    pub fn supported_codec_ids(&self) -> Vec<ffi::AVCodecID> {
+        let mut codec_ids: Vec<_> = AVCodec::iterate().map(|codec| codec.id).collect();
+        codec_ids.sort_unstable();
+        codec_ids.dedup();
+        codec_ids
+            .retain(|&id| self.query_codec(id, ffi::FF_COMPLIANCE_NORMAL as i32) == Some(true));
+        codec_ids
+    }
+}
+
+pub struct AVOutputFormatIter {
+    opaque: *mut c_void,
+}
+
+impl Iterator for AVOutputFormatIter {
+    type Item = AVOutputFormatRef<'static>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ptr = unsafe { ffi::av_muxer_iterate(&mut self.opaque) }.upgrade()?;
+        Some(unsafe { AVOutputFormatRef::from_raw(ptr) })
+    }
+}
+
+fn optional_cstr<'a>(ptr: *const std::os::raw::c_char) -> Option<&'a CStr> {
+    ptr.upgrade().map(|x| unsafe { CStr::from_ptr(x.as_ptr()) })
 }
 
 wrap_ref_mut!(#[repr(transparent)] AVStream: ffi::AVStream);
@@ -496,6 +1576,23 @@ settable!(AVStream {
 });
 
 impl AVStream {
+    /// Duration of the stream, `None` if unknown.
+    pub fn duration(&self) -> Option<Duration> {
+        ts_to_duration(self.duration, self.time_base)
+    }
+
+    /// Presentation time of the first frame of the stream, `None` if unknown
+    /// or negative.
+    pub fn start_time(&self) -> Option<Duration> {
+        ts_to_duration(self.start_time, self.time_base)
+    }
+
+    /// Average bitrate of the stream in bit/s, `None` if unknown.
+    pub fn bit_rate(&self) -> Option<i64> {
+        let bit_rate = unsafe { (*self.codecpar).bit_rate };
+        (bit_rate > 0).then_some(bit_rate)
+    }
+
     /// Guess the frame rate, based on both the container and codec information.
     ///
     /// Return None when index is not valid. Some(0/1) if no idea.
@@ -510,6 +1607,95 @@ impl AVStream {
 }
 
 impl<'stream> AVStream {
+    /// Returns true if all the `AV_DISPOSITION_*` bits of `disposition` are
+    /// set on the stream.
+    pub fn has_disposition(&self, disposition: u32) -> bool {
+        self.disposition & disposition as c_int == disposition as c_int
+    }
+
+    /// Get the payload of the stream side data of `side_data_type`, e.g.
+    /// `AV_PKT_DATA_DISPLAYMATRIX`.
+    pub fn side_data(
+        &'stream self,
+        side_data_type: ffi::AVPacketSideDataType,
+    ) -> Option<&'stream [u8]> {
+        #[cfg(feature = "ffmpeg7")]
+        {
+            let codecpar = unsafe { &*self.codecpar };
+            let side_data = unsafe {
+                ffi::av_packet_side_data_get(
+                    codecpar.coded_side_data,
+                    codecpar.nb_coded_side_data,
+                    side_data_type,
+                )
+                .upgrade()?
+                .as_ref()
+            };
+            let data = side_data.data.upgrade()?;
+            Some(unsafe { std::slice::from_raw_parts(data.as_ptr(), side_data.size) })
+        }
+        #[cfg(not(feature = "ffmpeg7"))]
+        {
+            let mut size = 0;
+            let data =
+                unsafe { ffi::av_stream_get_side_data(self.as_ptr(), side_data_type, &mut size) }
+                    .upgrade()?;
+            Some(unsafe { std::slice::from_raw_parts(data.as_ptr(), size) })
+        }
+    }
+
+    /// Get the display matrix of the stream, which describes how the decoded
+    /// frames should be transformed for display.
+    pub fn display_matrix(&self) -> Option<[i32; 9]> {
+        display_matrix_from_bytes(self.side_data(ffi::AV_PKT_DATA_DISPLAYMATRIX)?)
+    }
+
+    /// Get the clockwise rotation in degrees, within `[0, 360)`, to apply to
+    /// the decoded frames for display, e.g. 90 for a video shot in portrait
+    /// on most phones. `None` if the stream has no display matrix.
+    pub fn rotation_degrees(&self) -> Option<f64> {
+        self.display_matrix()
+            .map(|x| rotation_from_display_matrix(&x))
+    }
+
+    /// Add stream side data of `side_data_type`, replacing the existing one,
+    /// e.g. to write a display matrix before [`AVFormatContextOutput::write_header()`].
+    pub fn set_side_data(
+        &mut self,
+        side_data_type: ffi::AVPacketSideDataType,
+        data: &[u8],
+    ) -> Result<()> {
+        #[cfg(feature = "ffmpeg7")]
+        {
+            self.codecpar_mut()
+                .set_coded_side_data(side_data_type, data)
+        }
+        #[cfg(not(feature = "ffmpeg7"))]
+        {
+            let side_data = unsafe {
+                ffi::av_stream_new_side_data(self.as_mut_ptr(), side_data_type, data.len())
+            }
+            .upgrade_or(AVERROR_ENOMEM)?;
+            unsafe { ptr::copy_nonoverlapping(data.as_ptr(), side_data.as_ptr(), data.len()) };
+            Ok(())
+        }
+    }
+
+    /// Set the display matrix of the stream, see [`Self::display_matrix()`].
+    pub fn set_display_matrix(&mut self, matrix: &[i32; 9]) -> Result<()> {
+        self.set_side_data(
+            ffi::AV_PKT_DATA_DISPLAYMATRIX,
+            &display_matrix_to_bytes(matrix),
+        )
+    }
+
+    /// Set the display matrix of the stream to a clockwise rotation of
+    /// `degrees`, the inverse of [`Self::rotation_degrees()`], e.g. to keep
+    /// or correct the rotation of a phone video while remuxing.
+    pub fn set_rotation_degrees(&mut self, degrees: f64) -> Result<()> {
+        self.set_display_matrix(&display_rotation_set(-degrees))
+    }
+
     /// Get codec parameters of current stream.
     pub fn codecpar(&'stream self) -> AVCodecParametersRef<'stream> {
         // Implementation of `avformat_new_stream` tells us this cannot be Null.
@@ -559,11 +1745,480 @@ impl<'stream> AVStream {
     }
 }
 
+wrap_ref!(#[repr(transparent)] AVProgram: ffi::AVProgram);
+
+impl<'program> AVProgram {
+    /// Indexes of the streams belonging to this program.
+    pub fn stream_indexes(&'program self) -> &'program [u32] {
+        unsafe { ptr_slice(self.stream_index, self.nb_stream_indexes) }
+    }
+
+    /// Get metadata of current program.
+    pub fn metadata(&'program self) -> Option<AVDictionaryRef<'program>> {
+        NonNull::new(self.metadata).map(|x| unsafe { AVDictionaryRef::from_raw(x) })
+    }
+}
+
+wrap_ref!(#[repr(transparent)] AVChapter: ffi::AVChapter);
+
+impl<'chapter> AVChapter {
+    /// Get metadata of current chapter, e.g. its `title`.
+    pub fn metadata(&'chapter self) -> Option<AVDictionaryRef<'chapter>> {
+        NonNull::new(self.metadata).map(|x| unsafe { AVDictionaryRef::from_raw(x) })
+    }
+}
+
+const AV_TIME_BASE_Q: AVRational = AVRational {
+    num: 1,
+    den: ffi::AV_TIME_BASE as i32,
+};
+
+/// Convert `ts` in `time_base` into a [`Duration`], `None` for
+/// `AV_NOPTS_VALUE` and negative timestamps.
+fn ts_to_duration(ts: i64, time_base: AVRational) -> Option<Duration> {
+    if ts == ffi::AV_NOPTS_VALUE || ts < 0 || time_base.num <= 0 || time_base.den <= 0 {
+        return None;
+    }
+    let nanos = av_rescale_q(
+        ts,
+        time_base,
+        AVRational {
+            num: 1,
+            den: 1_000_000_000,
+        },
+    );
+    Some(Duration::from_nanos(nanos as u64))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use cstr::cstr;
 
+    #[test]
+    fn test_open_with_report() {
+        let mut options = AVDictionary::new(cstr!("probesize"), cstr!("100000"), 0);
+        for (key, value) in [
+            (cstr!("ignore_editlist"), cstr!("1")),
+            (cstr!("blocksize"), cstr!("4096")),
+            (cstr!("threads"), cstr!("2")),
+            (cstr!("foo"), cstr!("bar")),
+        ] {
+            options = options.set(key, value, 0);
+        }
+        let (input, report) = AVFormatContextInput::open_with_report(
+            cstr!("tests/assets/vids/bear.mp4"),
+            None,
+            Some(options),
+        )
+        .unwrap();
+        assert!(!input.streams().is_empty());
+        assert_eq!(
+            report.consumed,
+            [
+                ("probesize".to_string(), OptionLayer::Format),
+                ("ignore_editlist".to_string(), OptionLayer::Demuxer),
+                ("blocksize".to_string(), OptionLayer::Protocol),
+            ]
+        );
+        assert_eq!(report.layer_of("blocksize"), Some(OptionLayer::Protocol));
+        assert_eq!(report.layer_of("foo"), None);
+        assert_eq!(report.unconsumed, ["threads", "foo"]);
+        assert_eq!(report.codec_options, ["threads"]);
+    }
+
+    #[test]
+    fn test_read_pause_and_interrupt_callback() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        // Only network demuxers support pausing.
+        assert!(matches!(
+            input.read_pause(),
+            Err(RsmpegError::AVError(x)) if x == ffi::AVERROR(ffi::ENOSYS)
+        ));
+        assert!(input.read_play().is_err());
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        {
+            let calls = calls.clone();
+            input.set_interrupt_callback(move || calls.fetch_add(1, Ordering::SeqCst) >= 1);
+        }
+        let cb = input.interrupt_callback;
+        let callback = cb.callback.unwrap();
+        assert_eq!(unsafe { callback(cb.opaque) }, 0);
+        assert_eq!(unsafe { callback(cb.opaque) }, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        input.clear_interrupt_callback();
+        assert!(input.interrupt_callback.callback.is_none());
+        assert!(input.read_packet().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_open_with_interrupt() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        let url = cstr!("tests/assets/vids/bear.mp4");
+        assert!(matches!(
+            AVFormatContextInput::open_with_interrupt(url, None, &mut None, || true),
+            Err(RsmpegError::OpenInputError(ffi::AVERROR_EXIT))
+        ));
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut input = {
+            let cancelled = cancelled.clone();
+            AVFormatContextInput::open_with_interrupt(url, None, &mut None, move || {
+                cancelled.load(Ordering::Relaxed)
+            })
+            .unwrap()
+        };
+        assert!(input.read_packet().unwrap().is_some());
+        let cb = input.interrupt_callback;
+        assert_eq!(unsafe { cb.callback.unwrap()(cb.opaque) }, 0);
+        cancelled.store(true, Ordering::Relaxed);
+        assert_eq!(unsafe { cb.callback.unwrap()(cb.opaque) }, 1);
+    }
+
+    #[test]
+    fn test_open_with_protocols() {
+        let url = cstr!("tests/assets/vids/bear.mp4");
+        let mut options = None;
+        let policy = ProtocolPolicy::allow(&["file"]);
+        AVFormatContextInput::open_with_protocols(url, None, &mut options, &policy).unwrap();
+        // Consumed.
+        assert!(options.is_none());
+
+        let policy = ProtocolPolicy::deny(&["file"]);
+        assert!(matches!(
+            AVFormatContextInput::open_with_protocols(url, None, &mut None, &policy),
+            Err(RsmpegError::OpenInputError(x)) if x == ffi::AVERROR(ffi::EINVAL)
+        ));
+    }
+
+    #[test]
+    fn test_streams_by_media_type() {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let video: Vec<_> = input.video_streams().map(|(i, _)| i).collect();
+        let audio: Vec<_> = input.audio_streams().map(|(i, _)| i).collect();
+        assert_eq!(video.len(), 1);
+        assert_eq!(audio.len(), 1);
+        assert_ne!(video, audio);
+        assert_eq!(input.subtitle_streams().count(), 0);
+        for (index, stream) in input.video_streams() {
+            assert_eq!(stream.index as usize, index);
+            assert!(stream.codecpar().codec_type().is_video());
+        }
+        assert_eq!(
+            input
+                .streams_of_type(ffi::AVMEDIA_TYPE_AUDIO)
+                .next()
+                .unwrap()
+                .0,
+            audio[0]
+        );
+
+        assert_eq!(input.stream(video[0]).unwrap().index as usize, video[0]);
+        assert!(input.stream(input.nb_streams as usize).is_none());
+    }
+
+    #[test]
+    fn test_read_packet_into() {
+        let open = || {
+            AVFormatContextInput::open(cstr!("tests/assets/audios/sample1.aac"), None, &mut None)
+                .unwrap()
+        };
+        let mut input = open();
+        let expected: Vec<_> = std::iter::from_fn(|| input.read_packet().unwrap())
+            .map(|x| (x.pts, x.data().to_vec()))
+            .collect();
+        assert!(!expected.is_empty());
+
+        let mut input = open();
+        let mut packet = AVPacket::new();
+        let mut packets = vec![];
+        while input.read_packet_into(&mut packet).unwrap() {
+            packets.push((packet.pts, packet.data().to_vec()));
+        }
+        assert_eq!(packets, expected);
+        assert!(packet.data().is_empty());
+        assert!(!input.read_packet_into(&mut packet).unwrap());
+    }
+
+    #[test]
+    fn test_init_output() {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let io_context = AVIOContextDynBuf::open().unwrap();
+        let mut output = AVFormatContextOutput::create(
+            cstr!("output.mp4"),
+            Some(AVIOContextContainer::DynBuf(io_context)),
+        )
+        .unwrap();
+        {
+            let mut out_stream = output.new_stream();
+            out_stream.set_codecpar(input.streams()[video_index].codecpar().clone());
+            out_stream.set_time_base(AVRational { num: 1, den: 25 });
+        }
+        let mut options = Some(AVDictionary::new(
+            cstr!("movflags"),
+            cstr!("frag_keyframe+empty_moov"),
+            0,
+        ));
+        assert_eq!(
+            output.init_output(&mut options).unwrap(),
+            StreamInit::InInitOutput
+        );
+        assert!(options.is_none());
+        // The mov muxer raises the timescale of video tracks to at least 10000.
+        assert_eq!(output.streams()[0].time_base.den, 12800);
+        assert_eq!(
+            output.write_header(&mut None).unwrap(),
+            StreamInit::InInitOutput
+        );
+        output.write_trailer().unwrap();
+    }
+
+    #[test]
+    fn test_new_stream_copy() {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        for (name, keeps_mp4_tags) in [(cstr!("output.mp4"), true), (cstr!("output.mkv"), false)] {
+            let io_context = AVIOContextDynBuf::open().unwrap();
+            let mut output =
+                AVFormatContextOutput::create(name, Some(AVIOContextContainer::DynBuf(io_context)))
+                    .unwrap();
+            for stream in input.streams() {
+                output.new_stream_copy(stream);
+            }
+            for (stream, out_stream) in input.streams().iter().zip(output.streams()) {
+                let (codecpar, out_codecpar) = (stream.codecpar(), out_stream.codecpar());
+                assert_eq!(out_codecpar.codec_id, codecpar.codec_id);
+                assert_eq!(out_stream.time_base.den, stream.time_base.den);
+                if codecpar.codec_type == ffi::AVMEDIA_TYPE_AUDIO && !keeps_mp4_tags {
+                    // `mp4a` isn't a Matroska tag of AAC.
+                    assert_eq!(out_codecpar.codec_tag, 0);
+                } else {
+                    assert_eq!(out_codecpar.codec_tag, codecpar.codec_tag);
+                }
+            }
+            output.write_header(&mut None).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_write_packet_from() {
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let in_time_base = input.streams()[video_index].time_base;
+        let io_context = AVIOContextDynBuf::open().unwrap();
+        let mut output = AVFormatContextOutput::create(
+            cstr!("output.mp4"),
+            Some(AVIOContextContainer::DynBuf(io_context)),
+        )
+        .unwrap();
+        {
+            let mut out_stream = output.new_stream();
+            out_stream.set_codecpar(input.streams()[video_index].codecpar().clone());
+            out_stream.set_time_base(in_time_base);
+        }
+        let mut options = Some(AVDictionary::new(
+            cstr!("movflags"),
+            cstr!("frag_keyframe+empty_moov"),
+            0,
+        ));
+        output.write_header(&mut options).unwrap();
+
+        let mut last = None;
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            if packet.stream_index as usize != video_index {
+                continue;
+            }
+            last = Some(packet.clone());
+            output
+                .write_packet_from(&mut packet, in_time_base, 0)
+                .unwrap();
+        }
+        let mut last = last.unwrap();
+        last.set_dts(last.dts - 1);
+        last.set_pts(last.pts - 1);
+        // Left to the muxer by default.
+        assert!(matches!(
+            output.write_packet_from(&mut last.clone(), in_time_base, 0),
+            Err(RsmpegError::AVError(e)) if e == ffi::AVERROR(ffi::EINVAL)
+        ));
+        let last_dts = output.last_dts[0];
+        output.set_dts_check(DtsCheck::Report);
+        assert!(matches!(
+            output.write_packet_from(&mut last.clone(), in_time_base, 0),
+            Err(RsmpegError::NonMonotonicTimestamps {
+                stream_index: 0,
+                ..
+            })
+        ));
+        // Failed writes aren't recorded.
+        assert_eq!(output.last_dts[0], last_dts);
+
+        // The packet is moved right after the last one.
+        output.set_dts_check(DtsCheck::Correct);
+        output
+            .write_packet_from(&mut last.clone(), in_time_base, 0)
+            .unwrap();
+        let mut rescaled = last.clone();
+        rescaled.rescale_ts(in_time_base, output.streams()[0].time_base);
+        assert!(output.last_dts[0] > rescaled.dts);
+        assert!(output
+            .write_packet_from(&mut AVPacket::new(), in_time_base, 1)
+            .is_err());
+        output.write_trailer().unwrap();
+
+        // Like libavformat, a dts following a dts of 0 isn't checked.
+        output.last_dts[0] = 0;
+        let mut packet = AVPacket::new();
+        packet.set_dts(0);
+        output.set_dts_check(DtsCheck::Report);
+        assert_eq!(output.check_dts(&mut packet).unwrap(), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_dyn_buf_output() {
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let io_context = AVIOContextDynBuf::open().unwrap();
+        let mut output = AVFormatContextOutput::create(
+            cstr!("output.mp4"),
+            Some(AVIOContextContainer::DynBuf(io_context)),
+        )
+        .unwrap();
+        for stream in input.streams() {
+            let mut out_stream = output.new_stream();
+            out_stream.set_codecpar(stream.codecpar().clone());
+            out_stream.set_time_base(stream.time_base);
+        }
+        // The buffer isn't seekable.
+        let mut options = Some(AVDictionary::new(
+            cstr!("movflags"),
+            cstr!("frag_keyframe+empty_moov"),
+            0,
+        ));
+        output.write_header(&mut options).unwrap();
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            let index = packet.stream_index as usize;
+            packet.rescale_ts(
+                input.streams()[index].time_base,
+                output.streams()[index].time_base,
+            );
+            output.interleaved_write_frame(&mut packet).unwrap();
+        }
+        output.write_trailer().unwrap();
+
+        let Some(AVIOContextContainer::DynBuf(mut io_context)) = output.into_io_context() else {
+            panic!("no dyn buf");
+        };
+        let size = io_context.data().len();
+        let data = io_context.into_data();
+        assert_eq!(data.len(), size);
+        assert_eq!(&data[4..8], b"ftyp");
+        assert!(data.windows(4).any(|x| x == b"moof"));
+    }
+
+    #[test]
+    fn test_url() {
+        use crate::avutil::AVMem;
+
+        let data = std::fs::read("tests/assets/vids/bear.mp4").unwrap();
+        let io_context = |data: Vec<u8>| {
+            let mut current = 0;
+            AVIOContextCustom::alloc_context(
+                AVMem::new(4096),
+                false,
+                vec![],
+                Some(Box::new(move |_, buf| {
+                    let len = buf.len().min(data.len() - current);
+                    if len == 0 {
+                        return ffi::AVERROR_EOF;
+                    }
+                    buf[..len].copy_from_slice(&data[current..current + len]);
+                    current += len;
+                    len as i32
+                })),
+                None,
+                None,
+            )
+        };
+
+        let input = AVFormatContextInput::from_io_context(AVIOContextContainer::Custom(
+            io_context(data.clone()),
+        ))
+        .unwrap();
+        assert_eq!(input.url(), cstr!(""));
+
+        let mut input = AVFormatContextInput::from_io_context_with_url(
+            AVIOContextContainer::Custom(io_context(data)),
+            cstr!("memory:bear.mp4"),
+        )
+        .unwrap();
+        assert_eq!(input.url(), cstr!("memory:bear.mp4"));
+        input.dump(0, input.url()).unwrap();
+        input.set_url(cstr!("memory:renamed.mp4")).unwrap();
+        assert_eq!(input.url(), cstr!("memory:renamed.mp4"));
+
+        let mut output = AVFormatContextOutput::create(
+            cstr!("memory:output.mp4"),
+            Some(AVIOContextContainer::DynBuf(
+                AVIOContextDynBuf::open().unwrap(),
+            )),
+        )
+        .unwrap();
+        assert_eq!(output.url(), cstr!("memory:output.mp4"));
+        output.set_url(cstr!("memory:other.mp4")).unwrap();
+        assert_eq!(output.url(), cstr!("memory:other.mp4"));
+        output.dump(0, output.url()).unwrap();
+    }
+
+    #[test]
+    fn test_open_strict() {
+        let url = cstr!("tests/assets/vids/bear.mp4");
+        let options = AVDictionary::new(cstr!("probesize"), cstr!("5000000"), 0);
+        AVFormatContextInput::open_strict(url, None, Some(options)).unwrap();
+        AVFormatContextInput::open_strict(url, None, None).unwrap();
+
+        let options = AVDictionary::new(cstr!("probesize"), cstr!("5000000"), 0)
+            .set(cstr!("rw_timeut"), cstr!("1000"), 0)
+            .set(cstr!("foo"), cstr!("bar"), 0);
+        let err = AVFormatContextInput::open_strict(url, None, Some(options))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            RsmpegError::UnrecognizedOptionsError(vec!["rw_timeut".into(), "foo".into()])
+        );
+        assert_eq!(err.to_string(), "Options not recognized: rw_timeut, foo");
+        assert_eq!(err.raw_error(), Some(ffi::AVERROR_OPTION_NOT_FOUND));
+    }
+
     #[test]
     fn test_find_input_format() {
         let name = cstr!("mpeg");
@@ -583,4 +2238,285 @@ mod test {
         let name = cstr!("__random__");
         assert!(AVInputFormat::find(name).is_none());
     }
+
+    #[test]
+    fn test_format_flags() {
+        let mov = AVInputFormat::find(cstr!("mov")).unwrap();
+        assert!(mov.format_flags().contains(IOFormatFlags::NO_BYTE_SEEK));
+        let mp4 = AVOutputFormat::guess_format(Some(cstr!("mp4")), None, None).unwrap();
+        assert!(mp4.format_flags().contains(IOFormatFlags::GLOBALHEADER));
+        assert!(!mp4.format_flags().contains(IOFormatFlags::NOFILE));
+    }
+
+    #[test]
+    fn test_find_best_stream_ext() {
+        let input = AVFormatContextInput::open(
+            cstr!("tests/assets/vids/big_buck_bunny.mp4"),
+            None,
+            &mut None,
+        )
+        .unwrap();
+        let (video_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let (audio_index, _) = input
+            .find_best_stream_ext(ffi::AVMEDIA_TYPE_AUDIO, None, Some(video_index))
+            .unwrap()
+            .unwrap();
+        assert_ne!(audio_index, video_index);
+
+        // Wanted stream of the wrong media type.
+        assert!(input
+            .find_best_stream_ext(ffi::AVMEDIA_TYPE_AUDIO, Some(video_index), None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_start_at_zero() {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let tempdir = tempdir::TempDir::new("start_at_zero").unwrap();
+        for start_at_zero in [false, true] {
+            let path = tempdir.path().join(format!("output_{start_at_zero}.mkv"));
+            let path =
+                std::ffi::CString::new(path.into_os_string().into_string().unwrap()).unwrap();
+            let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+            output.new_stream_copy(&input.streams()[0]);
+            if start_at_zero {
+                output.start_at_zero();
+                assert_eq!(
+                    output.avoid_negative_ts,
+                    ffi::AVFMT_AVOID_NEG_TS_MAKE_ZERO as i32
+                );
+            }
+            output.write_header(&mut None).unwrap();
+            // Packets of a segment starting 10 seconds into the input.
+            let time_base = output.streams()[0].time_base;
+            let offset = av_rescale_q(10, AVRational { num: 1, den: 1 }, time_base);
+            let mut input =
+                AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                    .unwrap();
+            let in_time_base = input.streams()[0].time_base;
+            while let Some(mut packet) = input.read_packet().unwrap() {
+                if packet.stream_index != 0 {
+                    continue;
+                }
+                packet.rescale_ts(in_time_base, time_base);
+                packet.set_pts(packet.pts + offset);
+                packet.set_dts(packet.dts + offset);
+                output.interleaved_write_frame(&mut packet).unwrap();
+            }
+            output.write_trailer().unwrap();
+            drop(output);
+
+            let output = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+            let start_time = output.start_time().unwrap();
+            if start_at_zero {
+                assert!(start_time < std::time::Duration::from_millis(100));
+            } else {
+                assert!(start_time >= std::time::Duration::from_secs(10));
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_chapter() {
+        let tempdir = tempdir::TempDir::new("chapter").unwrap();
+        let path = tempdir.path().join("output.mkv");
+        let path = std::ffi::CString::new(path.into_os_string().into_string().unwrap()).unwrap();
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        assert!(output.chapters().is_empty());
+
+        let time_base = AVRational { num: 1, den: 1000 };
+        for i in 0..3 {
+            let title = AVDictionary::new(cstr!("title"), cstr!("chapter"), 0);
+            output
+                .add_chapter(i, time_base, i * 1000, (i + 1) * 1000, Some(title))
+                .unwrap();
+        }
+        output.add_chapter(3, time_base, 3000, 4000, None).unwrap();
+
+        let chapters = output.chapters();
+        assert_eq!(chapters.len(), 4);
+        assert_eq!(chapters[1].id, 1);
+        assert_eq!(chapters[1].start, 1000);
+        assert_eq!(chapters[1].end, 2000);
+        assert_eq!(
+            chapters[2]
+                .metadata()
+                .unwrap()
+                .get(cstr!("title"), None, 0)
+                .unwrap()
+                .value(),
+            cstr!("chapter")
+        );
+        assert!(chapters[3].metadata().is_none());
+    }
+
+    #[test]
+    fn test_programs() {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/centaur.mpg"), None, &mut None)
+                .unwrap();
+        let nb_streams = input.streams().len() as u32;
+        for program in input.programs() {
+            assert!(program.stream_indexes().iter().all(|&x| x < nb_streams));
+        }
+        assert!(input.chapters().is_empty());
+    }
+
+    #[test]
+    fn test_output_metadata() {
+        let tempdir = tempdir::TempDir::new("metadata").unwrap();
+        let path = tempdir.path().join("output.mp4");
+        let path = std::ffi::CString::new(path.into_os_string().into_string().unwrap()).unwrap();
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        assert!(output.metadata().is_none());
+
+        output.set_metadata(Some(AVDictionary::new(cstr!("title"), cstr!("foo"), 0)));
+        {
+            let metadata = output.metadata().unwrap();
+            assert_eq!(
+                metadata.get(cstr!("title"), None, 0).unwrap().value(),
+                cstr!("foo")
+            );
+        }
+
+        output.set_metadata(None);
+        assert!(output.metadata().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "ffmpeg7")]
+    fn test_rotation_degrees() {
+        let tempdir = tempdir::TempDir::new("rotation").unwrap();
+        let path = tempdir.path().join("output.mp4");
+        let path = std::ffi::CString::new(path.into_os_string().into_string().unwrap()).unwrap();
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        {
+            let in_stream = &input.streams()[video_index];
+            let mut codecpar = in_stream.codecpar().clone();
+            codecpar.set_rotation_degrees(90.0).unwrap();
+            assert_eq!(codecpar.rotation_degrees(), Some(90.0));
+            let mut out_stream = output.new_stream();
+            out_stream.set_codecpar(codecpar);
+            out_stream.set_time_base(in_stream.time_base);
+        }
+        output.write_header(&mut None).unwrap();
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            if packet.stream_index as usize != video_index {
+                continue;
+            }
+            packet.rescale_ts(
+                input.streams()[video_index].time_base,
+                output.streams()[0].time_base,
+            );
+            packet.set_stream_index(0);
+            output.interleaved_write_frame(&mut packet).unwrap();
+        }
+        output.write_trailer().unwrap();
+        drop(output);
+
+        let result = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        let stream = &result.streams()[0];
+        assert!(stream.display_matrix().is_some());
+        assert_eq!(stream.rotation_degrees(), Some(90.0));
+        assert!(!stream.has_disposition(ffi::AV_DISPOSITION_ATTACHED_PIC));
+    }
+
+    #[test]
+    fn test_set_stream_rotation() {
+        let tempdir = tempdir::TempDir::new("stream_rotation").unwrap();
+        let path = tempdir.path().join("output.mp4");
+        let path = std::ffi::CString::new(path.into_os_string().into_string().unwrap()).unwrap();
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let time_base = input.streams()[video_index].time_base;
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        {
+            let mut out_stream = output.new_stream();
+            out_stream.set_codecpar(input.streams()[video_index].codecpar().clone());
+            out_stream.set_time_base(time_base);
+            out_stream.set_rotation_degrees(90.0).unwrap();
+            // Replaces the previous display matrix.
+            out_stream.set_rotation_degrees(270.0).unwrap();
+            assert_eq!(out_stream.rotation_degrees(), Some(270.0));
+        }
+        output.write_header(&mut None).unwrap();
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            if packet.stream_index as usize == video_index {
+                output.write_packet_from(&mut packet, time_base, 0).unwrap();
+            }
+        }
+        output.write_trailer().unwrap();
+        drop(output);
+
+        let result = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        assert_eq!(result.streams()[0].rotation_degrees(), Some(270.0));
+    }
+
+    #[test]
+    fn test_duration() {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let duration = input.duration().unwrap();
+        assert!(duration > Duration::ZERO);
+        assert!(input.bit_rate().unwrap() > 0);
+
+        for stream in input.streams() {
+            let stream_duration = stream.duration().unwrap();
+            assert!(stream_duration <= duration);
+            assert!(duration - stream_duration < Duration::from_millis(100));
+            assert!(stream.bit_rate().unwrap() > 0);
+        }
+
+        assert_eq!(ts_to_duration(ffi::AV_NOPTS_VALUE, AV_TIME_BASE_Q), None);
+        assert_eq!(ts_to_duration(-1, AV_TIME_BASE_Q), None);
+        assert_eq!(
+            ts_to_duration(1001, AVRational { num: 1, den: 1000 }),
+            Some(Duration::from_millis(1001))
+        );
+    }
+
+    #[test]
+    fn test_format_iterate() {
+        let mov = AVInputFormat::iterate()
+            .find(|x| x.name().to_bytes().starts_with(b"mov,mp4"))
+            .unwrap();
+        assert!(mov.long_name().is_some());
+        assert!(mov
+            .extensions()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .split(',')
+            .any(|x| x == "mp4"));
+
+        let mp4 = AVOutputFormat::iterate()
+            .find(|x| x.name() == cstr!("mp4"))
+            .unwrap();
+        assert_eq!(mp4.extensions(), Some(cstr!("mp4")));
+        assert_eq!(mp4.mime_type(), Some(cstr!("video/mp4")));
+        let codec_ids = mp4.supported_codec_ids();
+        assert!(codec_ids.contains(&ffi::AV_CODEC_ID_AAC));
+        assert!(codec_ids.contains(&ffi::AV_CODEC_ID_MPEG4));
+    }
 }
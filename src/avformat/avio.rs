@@ -1,25 +1,37 @@
 use std::{
-    ffi::CStr,
+    ffi::{CStr, CString},
     ops::Deref,
     ptr::{self, NonNull},
     slice,
+    time::Duration,
 };
 
-use crate::{avutil::AVMem, error::*, ffi, shared::*};
+use crate::{
+    avformat::{interrupt_cb, InterruptCallback},
+    avutil::{AVDictionary, AVMem},
+    error::*,
+    ffi,
+    shared::*,
+};
 
 wrap!(AVIOContext: ffi::AVIOContext);
 
-pub struct AVIOContextURL(AVIOContext);
+pub struct AVIOContextURL {
+    io_context: AVIOContext,
+    // Called through the interrupt callback of the context, which is closed
+    // before it's dropped.
+    _interrupt_handler: Option<Box<InterruptCallback>>,
+}
 
 impl Deref for AVIOContextURL {
     type Target = AVIOContext;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.io_context
     }
 }
 impl std::ops::DerefMut for AVIOContextURL {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.io_context
     }
 }
 
@@ -30,19 +42,232 @@ impl AVIOContextURL {
     /// When the resource indicated by url has been opened in read+write mode,
     /// the [`AVIOContextURL`] can be used only for writing.
     pub fn open(url: &CStr, flags: u32) -> Result<Self> {
+        Self::open_impl(url, flags, &mut None, None)
+    }
+
+    /// Same as [`Self::open()`], with `options` for the protocol, e.g.
+    /// `rw_timeout` or `headers` of http. On return `options` is replaced
+    /// with the options that were not found.
+    pub fn open_with_options(
+        url: &CStr,
+        flags: u32,
+        options: &mut Option<AVDictionary>,
+    ) -> Result<Self> {
+        Self::open_impl(url, flags, options, None)
+    }
+
+    /// Same as [`Self::open()`], restricting the protocols as set by
+    /// `policy`, e.g. for a url given by a user.
+    pub fn open_with_protocols(url: &CStr, flags: u32, policy: &ProtocolPolicy) -> Result<Self> {
+        let mut options = None;
+        policy.apply(&mut options)?;
+        Self::open_impl(url, flags, &mut options, None)
+    }
+
+    /// Same as [`Self::open()`], with `callback` polled while opening the
+    /// resource and while reading or writing it, e.g. to give up on a dead
+    /// network peer. Returning `true` aborts the blocking operation with
+    /// `AVERROR_EXIT`.
+    pub fn open_with_interrupt(
+        url: &CStr,
+        flags: u32,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<Self> {
+        Self::open_impl(url, flags, &mut None, Some(Box::new(Box::new(callback))))
+    }
+
+    fn open_impl(
+        url: &CStr,
+        flags: u32,
+        options: &mut Option<AVDictionary>,
+        mut interrupt_handler: Option<Box<InterruptCallback>>,
+    ) -> Result<Self> {
+        let cb = interrupt_handler.as_mut().map(interrupt_cb);
+        let mut options_ptr = options
+            .as_mut()
+            .map(|x| x.as_mut_ptr())
+            .unwrap_or_else(ptr::null_mut);
         let mut io_context = ptr::null_mut();
-        unsafe { ffi::avio_open(&mut io_context, url.as_ptr(), flags as _) }.upgrade()?;
+        unsafe {
+            ffi::avio_open2(
+                &mut io_context,
+                url.as_ptr(),
+                flags as _,
+                cb.as_ref().map_or(ptr::null(), |x| x as *const _),
+                &mut options_ptr,
+            )
+        }
+        .upgrade()?;
+
+        // Forget the old options since it's ownership is transferred.
+        let mut new_options = options_ptr
+            .upgrade()
+            .map(|x| unsafe { AVDictionary::from_raw(x) });
+        std::mem::swap(options, &mut new_options);
+        std::mem::forget(new_options);
+
+        Ok(Self {
+            io_context: unsafe { AVIOContext::from_raw(NonNull::new(io_context).unwrap()) },
+            _interrupt_handler: interrupt_handler,
+        })
+    }
+}
+
+/// Restrictions of the protocols used to access a url, and of the time spent
+/// waiting for them, applied with [`AVIOContextURL::open_with_protocols()`]
+/// or [`AVFormatContextInput::open_with_protocols()`](crate::avformat::AVFormatContextInput::open_with_protocols).
+///
+/// The nested protocols are checked too, so https needs `tls` and `tcp`:
+///
+/// ```rust
+/// # use rsmpeg::avformat::ProtocolPolicy;
+/// # use std::time::Duration;
+/// let policy = ProtocolPolicy {
+///     rw_timeout: Some(Duration::from_secs(10)),
+///     ..ProtocolPolicy::allow(&["https", "tls", "tcp", "file"])
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtocolPolicy {
+    /// Protocols allowed, `protocol_whitelist`, `None` allows all of them.
+    pub whitelist: Option<Vec<String>>,
+    /// Protocols refused, `protocol_blacklist`.
+    pub blacklist: Option<Vec<String>>,
+    /// Maximum time to wait for the data of a read or a write before failing
+    /// with `AVERROR(ETIMEDOUT)`, `rw_timeout`. `None` waits forever.
+    pub rw_timeout: Option<Duration>,
+}
+
+impl ProtocolPolicy {
+    /// Policy only allowing `protocols`.
+    pub fn allow(protocols: &[&str]) -> Self {
+        Self {
+            whitelist: Some(protocols.iter().map(|x| x.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    /// Policy refusing `protocols`.
+    pub fn deny(protocols: &[&str]) -> Self {
+        Self {
+            blacklist: Some(protocols.iter().map(|x| x.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    /// Add the options enforcing the policy to `options`, replacing the
+    /// existing ones.
+    ///
+    /// Returns `AVERROR(EINVAL)` if a protocol contains a nul byte.
+    pub fn apply(&self, options: &mut Option<AVDictionary>) -> Result<()> {
+        fn set(options: &mut Option<AVDictionary>, key: &CStr, value: String) -> Result<()> {
+            let value =
+                CString::new(value).map_err(|_| RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+            *options = Some(match options.take() {
+                Some(options) => options.set(key, &value, 0),
+                None => AVDictionary::new(key, &value, 0),
+            });
+            Ok(())
+        }
+        if let Some(whitelist) = &self.whitelist {
+            let key = CStr::from_bytes_with_nul(b"protocol_whitelist\0").unwrap();
+            set(options, key, whitelist.join(","))?;
+        }
+        if let Some(blacklist) = &self.blacklist {
+            let key = CStr::from_bytes_with_nul(b"protocol_blacklist\0").unwrap();
+            set(options, key, blacklist.join(","))?;
+        }
+        if let Some(rw_timeout) = self.rw_timeout {
+            let key = CStr::from_bytes_with_nul(b"rw_timeout\0").unwrap();
+            // In microseconds.
+            set(options, key, rw_timeout.as_micros().to_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AVIOContextURL {
+    fn drop(&mut self) {
+        // Fails with `AVERROR_EXIT` if the final flush was aborted by the
+        // interrupt callback, the context is freed anyway.
+        let _ = unsafe { ffi::avio_close(self.as_mut_ptr()) };
+    }
+}
+
+/// [`AVIOContext`] writing into a growable memory buffer, e.g. to produce a
+/// file or MP4 fragments in memory.
+///
+/// ```ignore
+/// let io_context = AVIOContextDynBuf::open()?;
+/// let mut output = AVFormatContextOutput::create(
+///     cstr!("output.mp4"),
+///     Some(AVIOContextContainer::DynBuf(io_context)),
+/// )?;
+/// // Write the header, the packets and the trailer.
+/// let Some(AVIOContextContainer::DynBuf(io_context)) = output.into_io_context() else {
+///     unreachable!()
+/// };
+/// let data: Vec<u8> = io_context.into_data();
+/// ```
+pub struct AVIOContextDynBuf(AVIOContext);
+
+impl Deref for AVIOContextDynBuf {
+    type Target = AVIOContext;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for AVIOContextDynBuf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl AVIOContextDynBuf {
+    /// Create a write only [`AVIOContextDynBuf`] with an empty buffer.
+    pub fn open() -> Result<Self> {
+        let mut io_context = ptr::null_mut();
+        unsafe { ffi::avio_open_dyn_buf(&mut io_context) }.upgrade()?;
         Ok(Self(unsafe {
             AVIOContext::from_raw(NonNull::new(io_context).unwrap())
         }))
     }
+
+    /// Get the data written so far, flushing the context first.
+    pub fn data(&mut self) -> &[u8] {
+        let mut buffer = ptr::null_mut();
+        let size = unsafe { ffi::avio_get_dyn_buf(self.as_mut_ptr(), &mut buffer) };
+        if buffer.is_null() || size <= 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(buffer, size as usize) }
+        }
+    }
+
+    /// Free the context and return the data written into it.
+    pub fn into_data(mut self) -> Vec<u8> {
+        let data = self.close();
+        std::mem::forget(self);
+        data
+    }
+
+    fn close(&mut self) -> Vec<u8> {
+        let mut buffer = ptr::null_mut();
+        let size = unsafe { ffi::avio_close_dyn_buf(self.as_mut_ptr(), &mut buffer) };
+        let data = if buffer.is_null() || size <= 0 {
+            vec![]
+        } else {
+            unsafe { slice::from_raw_parts(buffer, size as usize) }.to_vec()
+        };
+        unsafe { ffi::av_free(buffer as _) };
+        data
+    }
 }
 
-impl Drop for AVIOContextURL {
+impl Drop for AVIOContextDynBuf {
     fn drop(&mut self) {
-        unsafe { ffi::avio_close(self.as_mut_ptr()) }
-            .upgrade()
-            .unwrap();
+        let _ = self.close();
     }
 }
 
@@ -74,6 +299,71 @@ pub struct Opaque {
     read_packet: Option<ReadPacketCallback>,
     write_packet: Option<WritePacketCallback>,
     seek: Option<SeekCallback>,
+    /// Writes are collected until it reaches `batch_size`, 0 disables batching.
+    batch: Vec<u8>,
+    batch_size: usize,
+    write_stats: AVIOWriteStats,
+}
+
+impl Opaque {
+    /// Returns the number of bytes passed to the write callback, 0 if `buf`
+    /// is only batched, FFmpeg only checks for errors.
+    fn write(&mut self, buf: &[u8]) -> i32 {
+        if self.batch_size == 0 {
+            return self.write_packet_raw(buf);
+        }
+        self.batch.extend_from_slice(buf);
+        if self.batch.len() < self.batch_size {
+            return 0;
+        }
+        self.flush_batch()
+    }
+
+    /// Pass the batched bytes to the write callback, calling it again after
+    /// a short write. On error, the bytes not written yet stay in the batch.
+    /// Returns `AVERROR(EIO)` if the callback writes nothing.
+    fn flush_batch(&mut self) -> i32 {
+        let mut written = 0;
+        let mut ret = 0;
+        while written < self.batch.len() {
+            // Keep the allocation for the next batch.
+            let batch = std::mem::take(&mut self.batch);
+            ret = self.write_packet_raw(&batch[written..]);
+            self.batch = batch;
+            match ret {
+                0 => ret = ffi::AVERROR(ffi::EIO),
+                ret if ret > 0 => written += (ret as usize).min(self.batch.len() - written),
+                _ => {}
+            }
+            if ret < 0 {
+                break;
+            }
+        }
+        self.batch.drain(..written);
+        match ret {
+            ret if ret < 0 => ret,
+            _ => i32::try_from(written).unwrap_or(i32::MAX),
+        }
+    }
+
+    fn write_packet_raw(&mut self, buf: &[u8]) -> i32 {
+        self.write_stats.write_calls += 1;
+        let ret = self.write_packet.as_mut().unwrap()(&mut self.data, buf);
+        if ret > 0 {
+            self.write_stats.bytes_written += ret as u64;
+        }
+        ret
+    }
+}
+
+/// Statistics of the writes of an [`AVIOContextCustom`] to its write
+/// callback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AVIOWriteStats {
+    /// Sum of the positive values returned by the write callback.
+    pub bytes_written: u64,
+    /// Number of times the write callback has been called.
+    pub write_calls: u64,
 }
 
 impl AVIOContextCustom {
@@ -106,16 +396,22 @@ impl AVIOContextCustom {
             unsafe extern "C" fn write_c(opaque: *mut c_void, data: *mut u8, len: i32) -> i32 {
                 let buf = unsafe { slice::from_raw_parts(data, len as usize) };
                 let opaque = unsafe { (opaque as *mut Opaque).as_mut() }.unwrap();
-                opaque.write_packet.as_mut().unwrap()(&mut opaque.data, buf)
+                opaque.write(buf)
             }
             #[cfg(feature = "ffmpeg7")]
             unsafe extern "C" fn write_c(opaque: *mut c_void, data: *const u8, len: i32) -> i32 {
                 let buf = unsafe { slice::from_raw_parts(data, len as usize) };
                 let opaque = unsafe { (opaque as *mut Opaque).as_mut() }.unwrap();
-                opaque.write_packet.as_mut().unwrap()(&mut opaque.data, buf)
+                opaque.write(buf)
             }
             unsafe extern "C" fn seek_c(opaque: *mut c_void, offset: i64, whence: i32) -> i64 {
                 let opaque = unsafe { (opaque as *mut Opaque).as_mut() }.unwrap();
+                // The pending bytes belong before the new position, e.g. when a
+                // muxer seeks back to patch its header.
+                let ret = opaque.flush_batch();
+                if ret < 0 {
+                    return ret as i64;
+                }
                 opaque.seek.as_mut().unwrap()(&mut opaque.data, offset, whence)
             }
 
@@ -132,6 +428,9 @@ impl AVIOContextCustom {
             read_packet,
             write_packet,
             seek,
+            batch: vec![],
+            batch_size: 0,
+            write_stats: AVIOWriteStats::default(),
         });
 
         // After reading the implementation, avio_alloc_context only fails on no
@@ -171,10 +470,58 @@ impl AVIOContextCustom {
     pub fn as_mut_data(&mut self) -> &mut Vec<u8> {
         &mut self._opaque.data
     }
+
+    /// Collect the writes into an internal buffer and call the write callback
+    /// only once at least `batch_size` bytes are pending, e.g. to send many
+    /// small MPEG-TS packets to a network sink with fewer syscalls. Setting it
+    /// to 0, the default, disables batching.
+    ///
+    /// The bytes still pending are written by [`Self::flush_batch()`], before
+    /// seeking, when the batch size is changed and when the context is
+    /// dropped.
+    pub fn set_write_batch_size(&mut self, batch_size: usize) -> Result<()> {
+        // Keep the pending bytes ahead of the next writes.
+        self._opaque.flush_batch().upgrade()?;
+        self._opaque.batch_size = batch_size;
+        if batch_size > 0 {
+            let additional = batch_size.saturating_sub(self._opaque.batch.len());
+            self._opaque.batch.reserve(additional);
+        }
+        Ok(())
+    }
+
+    /// Get the batch size set by [`Self::set_write_batch_size()`].
+    pub fn write_batch_size(&self) -> usize {
+        self._opaque.batch_size
+    }
+
+    /// Number of bytes collected but not passed to the write callback yet.
+    pub fn pending_batch_len(&self) -> usize {
+        self._opaque.batch.len()
+    }
+
+    /// Flush the [`AVIOContext`] buffer, then pass all the batched bytes to
+    /// the write callback, calling it again after a short write.
+    ///
+    /// On error, the bytes not written yet stay pending for the next flush.
+    /// Returns `AVERROR(EIO)` if the callback writes nothing.
+    pub fn flush_batch(&mut self) -> Result<()> {
+        unsafe { ffi::avio_flush(self.as_mut_ptr()) };
+        self._opaque.flush_batch().upgrade()?;
+        Ok(())
+    }
+
+    /// Get the statistics of the calls to the write callback.
+    pub fn write_stats(&self) -> AVIOWriteStats {
+        self._opaque.write_stats
+    }
 }
 
 impl Drop for AVIOContextCustom {
     fn drop(&mut self) {
+        // Errors can't be reported here, call `flush_batch()` to catch them.
+        let _ = self._opaque.flush_batch();
+
         // Recover the `AVMem` fom the buffer and drop it. We don't attach the
         // AVMem to this type because according to the documentation, the buffer
         // pointer may be changed during it's usage.
@@ -187,3 +534,251 @@ impl Drop for AVIOContextCustom {
         unsafe { ffi::avio_context_free(&mut self.as_mut_ptr()) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    };
+
+    #[test]
+    fn test_protocol_policy() {
+        let policy = ProtocolPolicy {
+            blacklist: Some(vec!["http".into(), "rtmp".into()]),
+            rw_timeout: Some(Duration::from_secs(10)),
+            ..ProtocolPolicy::allow(&["https", "tls", "tcp", "file"])
+        };
+        let mut options = Some(AVDictionary::new(
+            cstr!("protocol_whitelist"),
+            cstr!("http"),
+            0,
+        ));
+        policy.apply(&mut options).unwrap();
+        let options = options.unwrap();
+        assert_eq!(
+            options.get_str("protocol_whitelist"),
+            Some("https,tls,tcp,file")
+        );
+        assert_eq!(options.get_str("protocol_blacklist"), Some("http,rtmp"));
+        assert_eq!(options.get_str("rw_timeout"), Some("10000000"));
+        assert!(ProtocolPolicy::allow(&["fi\0le"]).apply(&mut None).is_err());
+
+        let url = cstr!("tests/assets/vids/bear.mp4");
+        AVIOContextURL::open_with_protocols(url, ffi::AVIO_FLAG_READ, &policy).unwrap();
+        assert!(AVIOContextURL::open_with_protocols(
+            url,
+            ffi::AVIO_FLAG_READ,
+            &ProtocolPolicy::allow(&["https", "tls", "tcp"])
+        )
+        .is_err());
+        assert!(AVIOContextURL::open_with_protocols(
+            url,
+            ffi::AVIO_FLAG_READ,
+            &ProtocolPolicy::deny(&["file"])
+        )
+        .is_err());
+
+        let mut options = Some(
+            AVDictionary::new(cstr!("rw_timeout"), cstr!("1000000"), 0).set(
+                cstr!("foo"),
+                cstr!("bar"),
+                0,
+            ),
+        );
+        AVIOContextURL::open_with_options(url, ffi::AVIO_FLAG_READ, &mut options).unwrap();
+        let options = options.unwrap();
+        assert_eq!(options.get_str("foo"), Some("bar"));
+        assert_eq!(options.get_str("rw_timeout"), None);
+    }
+
+    #[test]
+    fn test_open_with_interrupt() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut io_context = {
+            let cancelled = cancelled.clone();
+            AVIOContextURL::open_with_interrupt(
+                cstr!("tests/assets/vids/bear.mp4"),
+                ffi::AVIO_FLAG_READ,
+                move || cancelled.load(Ordering::Relaxed),
+            )
+            .unwrap()
+        };
+        let mut buf = [0u8; 8];
+        let ret =
+            unsafe { ffi::avio_read(io_context.as_mut_ptr(), buf.as_mut_ptr(), buf.len() as _) };
+        assert_eq!(ret, 8);
+        assert_eq!(&buf[4..], b"ftyp");
+
+        // The buffered bytes are still served, the next read is aborted.
+        cancelled.store(true, Ordering::Relaxed);
+        let mut buf = vec![0u8; 1 << 20];
+        loop {
+            let ret = unsafe {
+                ffi::avio_read(io_context.as_mut_ptr(), buf.as_mut_ptr(), buf.len() as _)
+            };
+            if ret < 0 {
+                assert_eq!(ret, ffi::AVERROR_EXIT);
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_batch() {
+        let chunks = Arc::new(Mutex::new(vec![]));
+        let chunks_ = chunks.clone();
+        let mut io_context = AVIOContextCustom::alloc_context(
+            AVMem::new(188),
+            true,
+            vec![],
+            None,
+            Some(Box::new(move |_: &mut Vec<u8>, buf: &[u8]| {
+                chunks_.lock().unwrap().push(buf.len());
+                buf.len() as _
+            })),
+            None,
+        );
+        io_context.set_write_batch_size(1000).unwrap();
+        assert_eq!(io_context.write_batch_size(), 1000);
+
+        // Flush after each TS packet, as the muxer does for live outputs.
+        let packet = [0x47u8; 188];
+        for _ in 0..10 {
+            unsafe {
+                ffi::avio_write(io_context.as_mut_ptr(), packet.as_ptr(), 188);
+                ffi::avio_flush(io_context.as_mut_ptr());
+            }
+        }
+        assert_eq!(*chunks.lock().unwrap(), [1128]);
+        assert_eq!(io_context.pending_batch_len(), 752);
+
+        io_context.flush_batch().unwrap();
+        assert_eq!(*chunks.lock().unwrap(), [1128, 752]);
+        assert_eq!(io_context.pending_batch_len(), 0);
+        assert_eq!(
+            io_context.write_stats(),
+            AVIOWriteStats {
+                bytes_written: 1880,
+                write_calls: 2,
+            }
+        );
+
+        io_context.set_write_batch_size(0).unwrap();
+        unsafe {
+            ffi::avio_write(io_context.as_mut_ptr(), packet.as_ptr(), 188);
+            ffi::avio_flush(io_context.as_mut_ptr());
+        }
+        assert_eq!(io_context.write_stats().write_calls, 3);
+        assert_eq!(io_context.write_stats().bytes_written, 2068);
+    }
+
+    #[test]
+    fn test_write_batch_short_writes() {
+        // Accepts at most 100 bytes per call, nothing once `data` is full.
+        let mut io_context = AVIOContextCustom::alloc_context(
+            AVMem::new(188),
+            true,
+            vec![],
+            None,
+            Some(Box::new(|data: &mut Vec<u8>, buf: &[u8]| {
+                let len = buf.len().min(100).min(500 - data.len());
+                data.extend_from_slice(&buf[..len]);
+                len as _
+            })),
+            None,
+        );
+        io_context.set_write_batch_size(1000).unwrap();
+        let packet = [0x47u8; 188];
+        for _ in 0..2 {
+            unsafe { ffi::avio_write(io_context.as_mut_ptr(), packet.as_ptr(), 188) };
+        }
+        io_context.flush_batch().unwrap();
+        assert_eq!(io_context.pending_batch_len(), 0);
+        assert_eq!(io_context.as_mut_data().len(), 376);
+        assert_eq!(io_context.write_stats().write_calls, 4);
+
+        // The sink is full: the bytes not written are kept.
+        unsafe { ffi::avio_write(io_context.as_mut_ptr(), packet.as_ptr(), 188) };
+        assert_eq!(
+            io_context.flush_batch(),
+            Err(RsmpegError::AVError(ffi::AVERROR(ffi::EIO)))
+        );
+        assert_eq!(io_context.as_mut_data().len(), 500);
+        assert_eq!(io_context.pending_batch_len(), 64);
+        io_context.as_mut_data().clear();
+        io_context.flush_batch().unwrap();
+        assert_eq!(io_context.pending_batch_len(), 0);
+        assert_eq!(io_context.as_mut_data().len(), 64);
+    }
+
+    #[test]
+    fn test_write_batch_seek() {
+        let mut io_context = AVIOContextCustom::alloc_context(
+            AVMem::new(4),
+            true,
+            vec![],
+            None,
+            Some(Box::new(|data: &mut Vec<u8>, buf: &[u8]| {
+                // The position is kept at the end of `data` by `seek`.
+                let pos = data.pop().unwrap_or(0) as usize;
+                let end = pos + buf.len();
+                if data.len() < end {
+                    data.resize(end, 0);
+                }
+                data[pos..end].copy_from_slice(buf);
+                data.push(end as u8);
+                buf.len() as _
+            })),
+            Some(Box::new(|data: &mut Vec<u8>, offset, whence| {
+                if whence != ffi::SEEK_SET as i32 {
+                    return ffi::AVERROR(ffi::ENOSYS) as i64;
+                }
+                data.pop();
+                data.push(offset as u8);
+                offset
+            })),
+        );
+        io_context.set_write_batch_size(100).unwrap();
+        unsafe {
+            ffi::avio_write(io_context.as_mut_ptr(), b"abcdefgh".as_ptr(), 8);
+            // Patch the header, like the mp4 and wav muxers do.
+            ffi::avio_seek(io_context.as_mut_ptr(), 0, ffi::SEEK_SET as i32);
+            ffi::avio_write(io_context.as_mut_ptr(), b"XY".as_ptr(), 2);
+            ffi::avio_flush(io_context.as_mut_ptr());
+        }
+        io_context.flush_batch().unwrap();
+        let mut data = io_context.take_data();
+        data.pop();
+        assert_eq!(data, b"XYcdefgh");
+
+        // Pending bytes are written before the ones following a batch size
+        // change.
+        let mut io_context = AVIOContextCustom::alloc_context(
+            AVMem::new(4),
+            true,
+            vec![],
+            None,
+            Some(Box::new(|data: &mut Vec<u8>, buf: &[u8]| {
+                data.extend_from_slice(buf);
+                buf.len() as _
+            })),
+            None,
+        );
+        io_context.set_write_batch_size(100).unwrap();
+        unsafe {
+            ffi::avio_write(io_context.as_mut_ptr(), b"abcd".as_ptr(), 4);
+            ffi::avio_flush(io_context.as_mut_ptr());
+        }
+        assert_eq!(io_context.pending_batch_len(), 4);
+        io_context.set_write_batch_size(0).unwrap();
+        assert_eq!(io_context.pending_batch_len(), 0);
+        unsafe {
+            ffi::avio_write(io_context.as_mut_ptr(), b"ef".as_ptr(), 2);
+            ffi::avio_flush(io_context.as_mut_ptr());
+        }
+        assert_eq!(io_context.take_data(), b"abcdef");
+    }
+}
@@ -1,6 +1,10 @@
 //! Everything related to `libavformat`.
 mod avformat;
 mod avio;
+mod tee;
+mod url;
 
 pub use avformat::*;
 pub use avio::*;
+pub use tee::*;
+pub use url::*;
@@ -0,0 +1,180 @@
+use crate::{
+    avcodec::AVPacket,
+    avformat::AVFormatContextOutput,
+    avutil::{AVDictionary, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Duplicates the packets of a single encoding session to multiple
+/// [`AVFormatContextOutput`]s, mirroring the `onfail=ignore` behaviour of
+/// FFmpeg's tee muxer.
+///
+/// Every output interleaves its packets independently. An output which fails
+/// is excluded from further writing, and its error can be retrieved by
+/// [`Self::error()`], while the other outputs keep working. All the outputs
+/// are expected to have the same streams in the same order.
+pub struct TeeOutput {
+    outputs: Vec<AVFormatContextOutput>,
+    errors: Vec<Option<RsmpegError>>,
+}
+
+impl TeeOutput {
+    /// Create a [`TeeOutput`] from the given outputs, their streams should
+    /// already be created.
+    pub fn new(outputs: Vec<AVFormatContextOutput>) -> Self {
+        let errors = outputs.iter().map(|_| None).collect();
+        Self { outputs, errors }
+    }
+
+    /// Get all the outputs, including the failed ones.
+    pub fn outputs(&self) -> &[AVFormatContextOutput] {
+        &self.outputs
+    }
+
+    /// Get all the outputs mutably, including the failed ones.
+    pub fn outputs_mut(&mut self) -> &mut [AVFormatContextOutput] {
+        &mut self.outputs
+    }
+
+    /// Get the error which made the output of given index fail, return `None`
+    /// if the output is still alive.
+    pub fn error(&self, index: usize) -> Option<&RsmpegError> {
+        self.errors.get(index)?.as_ref()
+    }
+
+    /// Get the number of outputs which haven't failed.
+    pub fn nb_alive(&self) -> usize {
+        self.errors.iter().filter(|x| x.is_none()).count()
+    }
+
+    /// Consume self and get the outputs back.
+    pub fn into_outputs(self) -> Vec<AVFormatContextOutput> {
+        self.outputs
+    }
+
+    /// Call `f` on every alive output, and mark the outputs on which `f`
+    /// fails as failed.
+    ///
+    /// Return `AVERROR(EIO)` if no output is alive after that.
+    fn for_each_alive(
+        &mut self,
+        mut f: impl FnMut(&mut AVFormatContextOutput) -> Result<()>,
+    ) -> Result<()> {
+        for (output, error) in self.outputs.iter_mut().zip(self.errors.iter_mut()) {
+            if error.is_some() {
+                continue;
+            }
+            if let Err(e) = f(output) {
+                *error = Some(e);
+            }
+        }
+        if self.nb_alive() == 0 {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EIO)));
+        }
+        Ok(())
+    }
+
+    /// Write the stream header to every alive output, see
+    /// [`AVFormatContextOutput::write_header()`].
+    ///
+    /// `options` is copied for each output, the options not consumed are
+    /// dropped.
+    pub fn write_header(&mut self, options: Option<&AVDictionary>) -> Result<()> {
+        self.for_each_alive(|output| output.write_header(&mut options.cloned()).map(|_| ()))
+    }
+
+    /// Write a packet to every alive output ensuring correct interleaving, see
+    /// [`AVFormatContextOutput::interleaved_write_frame()`].
+    ///
+    /// Timestamps of the `packet` are in `time_base`, they are rescaled to the
+    /// time base of the corresponding stream of each output. The `packet`
+    /// itself is left untouched, the data is shared among the outputs rather
+    /// than copied.
+    pub fn interleaved_write_frame(
+        &mut self,
+        packet: &AVPacket,
+        time_base: AVRational,
+    ) -> Result<()> {
+        let stream_index = packet.stream_index as usize;
+        self.for_each_alive(|output| {
+            let stream_time_base = output
+                .streams()
+                .get(stream_index)
+                .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?
+                .time_base;
+            let mut packet = packet.clone();
+            packet.rescale_ts(time_base, stream_time_base);
+            output.interleaved_write_frame(&mut packet)
+        })
+    }
+
+    /// Write the stream trailer to every alive output, see
+    /// [`AVFormatContextOutput::write_trailer()`].
+    pub fn write_trailer(&mut self) -> Result<()> {
+        self.for_each_alive(|output| output.write_trailer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avformat::AVFormatContextInput;
+    use cstr::cstr;
+    use std::ffi::CString;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_tee_output() {
+        let tempdir = TempDir::new("tee").unwrap();
+        let path = |name: &str| {
+            let path = tempdir.path().join(name);
+            CString::new(path.into_os_string().into_string().unwrap()).unwrap()
+        };
+
+        let mut input = AVFormatContextInput::open(
+            cstr!("tests/assets/vids/big_buck_bunny.mp4"),
+            None,
+            &mut None,
+        )
+        .unwrap();
+        assert!(input.streams().len() > 1);
+
+        let mut outputs = vec![];
+        for (name, nb_streams) in [("a.mp4", usize::MAX), ("b.mov", usize::MAX), ("c.mp4", 1)] {
+            let mut output = AVFormatContextOutput::create(&path(name), None).unwrap();
+            for stream in input.streams().iter().take(nb_streams) {
+                output.new_stream().set_codecpar(stream.codecpar().clone());
+            }
+            outputs.push(output);
+        }
+
+        let mut tee = TeeOutput::new(outputs);
+        tee.write_header(None).unwrap();
+        while let Some(packet) = input.read_packet().unwrap() {
+            let time_base = input.streams()[packet.stream_index as usize].time_base;
+            tee.interleaved_write_frame(&packet, time_base).unwrap();
+        }
+        tee.write_trailer().unwrap();
+
+        // The third output doesn't have the second stream.
+        assert_eq!(tee.nb_alive(), 2);
+        assert!(tee.error(0).is_none());
+        assert!(tee.error(1).is_none());
+        assert!(tee.error(2).is_some());
+        drop(tee);
+
+        assert!(
+            std::fs::metadata(tempdir.path().join("a.mp4"))
+                .unwrap()
+                .len()
+                > 0
+        );
+        assert!(
+            std::fs::metadata(tempdir.path().join("b.mov"))
+                .unwrap()
+                .len()
+                > 0
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::ffi::{CStr, CString};
+
+use crate::{
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Components of an URL, returned by [`url_split`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlComponents {
+    /// Protocol, e.g. `http`, empty if absent.
+    pub proto: CString,
+    /// Authorization, e.g. `user:password`, empty if absent.
+    pub authorization: CString,
+    /// Host name, brackets of IPv6 addresses are removed, empty if absent.
+    pub hostname: CString,
+    /// Port number, `None` if absent.
+    pub port: Option<i32>,
+    /// Path, including query and fragment, empty if absent.
+    pub path: CString,
+}
+
+/// Split an URL string into components.
+///
+/// Note: components are not unescaped, see [`url_decode`].
+pub fn url_split(url: &CStr) -> UrlComponents {
+    // No component is longer than the URL itself, so no truncation happens.
+    let size = url.to_bytes().len() + 1;
+    let mut proto = vec![0u8; size];
+    let mut authorization = vec![0u8; size];
+    let mut hostname = vec![0u8; size];
+    let mut path = vec![0u8; size];
+    let mut port = -1;
+    // Length of a `&CStr` never exceeds `i32::MAX` in practice, and
+    // `av_url_split` truncates on a short buffer rather than overflowing.
+    let size = i32::try_from(size).unwrap_or(i32::MAX);
+    unsafe {
+        ffi::av_url_split(
+            proto.as_mut_ptr() as *mut _,
+            size,
+            authorization.as_mut_ptr() as *mut _,
+            size,
+            hostname.as_mut_ptr() as *mut _,
+            size,
+            &mut port,
+            path.as_mut_ptr() as *mut _,
+            size,
+            url.as_ptr(),
+        )
+    }
+    let to_cstring = |buf: Vec<u8>| CStr::from_bytes_until_nul(&buf).unwrap().to_owned();
+    UrlComponents {
+        proto: to_cstring(proto),
+        authorization: to_cstring(authorization),
+        hostname: to_cstring(hostname),
+        port: (port >= 0).then_some(port),
+        path: to_cstring(path),
+    }
+}
+
+/// Unescape the `%XX` sequences of an URL component, and the `+` as spaces
+/// if `decode_plus` is set, e.g. for the query of a form, like FFmpeg's
+/// internal `ff_urldecode`.
+///
+/// Returns `AVERROR_INVALIDDATA` if a `%` isn't followed by two hexadecimal
+/// digits, or if the unescaped bytes aren't valid UTF-8.
+pub fn url_decode(url: &str, decode_plus: bool) -> Result<String> {
+    let invalid = || RsmpegError::AVError(ffi::AVERROR_INVALIDDATA);
+    let hex = |x: Option<&u8>| {
+        let x = *x.ok_or_else(invalid)?;
+        (x as char)
+            .to_digit(16)
+            .map(|x| x as u8)
+            .ok_or_else(invalid)
+    };
+    let mut bytes = url.as_bytes().iter();
+    let mut decoded = Vec::with_capacity(url.len());
+    while let Some(&c) = bytes.next() {
+        match c {
+            b'%' => decoded.push(hex(bytes.next())? << 4 | hex(bytes.next())?),
+            b'+' if decode_plus => decoded.push(b' '),
+            c => decoded.push(c),
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_url_split() {
+        let url = url_split(cstr!("rtsp://user:pass@[::1]:8554/live/stream?a=b%20c"));
+        assert_eq!(url.proto.as_c_str(), cstr!("rtsp"));
+        assert_eq!(url.authorization.as_c_str(), cstr!("user:pass"));
+        assert_eq!(url.hostname.as_c_str(), cstr!("::1"));
+        assert_eq!(url.port, Some(8554));
+        assert_eq!(url.path.as_c_str(), cstr!("/live/stream?a=b%20c"));
+
+        let url = url_split(cstr!("/tmp/foo.mp4"));
+        assert_eq!(url.proto.as_c_str(), cstr!(""));
+        assert_eq!(url.hostname.as_c_str(), cstr!(""));
+        assert_eq!(url.port, None);
+        assert_eq!(url.path.as_c_str(), cstr!("/tmp/foo.mp4"));
+    }
+
+    #[test]
+    fn test_url_decode() {
+        assert_eq!(
+            url_decode("a=b%20c+d%2Fe%c3%a9", false).unwrap(),
+            "a=b c+d/eé"
+        );
+        assert_eq!(url_decode("a=b%20c+d", true).unwrap(), "a=b c d");
+        assert_eq!(url_decode("", true).unwrap(), "");
+
+        let invalid = Some(RsmpegError::AVError(ffi::AVERROR_INVALIDDATA));
+        // Invalid escapes.
+        assert_eq!(url_decode("%zz", false).err(), invalid);
+        assert_eq!(url_decode("%2g", false).err(), invalid);
+        assert_eq!(url_decode("%%41", false).err(), invalid);
+        // Truncated escapes.
+        assert_eq!(url_decode("abc%", false).err(), invalid);
+        assert_eq!(url_decode("abc%4", false).err(), invalid);
+        // Not UTF-8.
+        assert_eq!(url_decode("%ff", false).err(), invalid);
+    }
+}
@@ -0,0 +1,28 @@
+use crate::ffi;
+
+/// Update the Adler-32 checksum `adler` with the bytes of `buf`.
+///
+/// Start with `1` for the standard Adler-32 of zlib, as the `adler32` hash of
+/// the `hash` muxer, or with `0` to match the checksums of the `framecrc`
+/// muxer.
+///
+/// ```rust
+/// # use rsmpeg::avutil::adler32_update;
+/// let adler = adler32_update(1, b"Wiki");
+/// assert_eq!(adler32_update(adler, b"pedia"), 0x11E60398);
+/// ```
+pub fn adler32_update(adler: u32, buf: &[u8]) -> u32 {
+    unsafe { ffi::av_adler32_update(adler, buf.as_ptr(), buf.len()) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32_update(1, b""), 1);
+        assert_eq!(adler32_update(1, b"Wikipedia"), 0x11E60398);
+        assert_eq!(adler32_update(0, b"Wikipedia"), 0x11DD0397);
+    }
+}
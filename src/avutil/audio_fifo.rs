@@ -1,4 +1,9 @@
-use crate::{error::*, ffi, shared::*};
+use crate::{
+    avutil::{AVChannelLayout, AVFrame},
+    error::*,
+    ffi,
+    shared::*,
+};
 
 wrap!(
     /// Context for an Audio FIFO Buffer.
@@ -6,7 +11,14 @@ wrap!(
     /// - Operates at the sample level rather than the byte level.
     /// - Supports multiple channels with either planar or packed sample format.
     /// - Automatic reallocation when writing to a full buffer.
-    AVAudioFifo: ffi::AVAudioFifo
+    ///
+    /// `sample_fmt` and `channels` are the parameters given to [`Self::new()`],
+    /// FFmpeg doesn't expose them. A fifo wrapped with `from_raw()` has
+    /// `AV_SAMPLE_FMT_NONE` and 0 channels, set them to the ones it was
+    /// allocated with before using the frame methods.
+    AVAudioFifo: ffi::AVAudioFifo,
+    sample_fmt: ffi::AVSampleFormat = ffi::AV_SAMPLE_FMT_NONE,
+    channels: i32 = 0,
 );
 
 impl AVAudioFifo {
@@ -15,7 +27,10 @@ impl AVAudioFifo {
         let fifo = unsafe { ffi::av_audio_fifo_alloc(sample_fmt, channels, nb_samples) }
             .upgrade()
             .unwrap();
-        unsafe { Self::from_raw(fifo) }
+        let mut fifo = unsafe { Self::from_raw(fifo) };
+        fifo.sample_fmt = sample_fmt;
+        fifo.channels = channels;
+        fifo
     }
 
     /// Reallocate an AVAudioFifo.
@@ -41,12 +56,114 @@ impl AVAudioFifo {
         Ok(())
     }
 
+    /// Write all the samples of an audio [`AVFrame`] to the AVAudioFifo.
+    ///
+    /// The AVAudioFifo will be reallocated automatically if the available space
+    /// is less than `frame.nb_samples`.
+    ///
+    /// Return `AVERROR(EINVAL)` if the sample format or the channel count of
+    /// the frame doesn't match the AVAudioFifo's, or the frame isn't allocated.
+    /// Always the case for a fifo wrapped with `from_raw()` until its
+    /// `sample_fmt` and `channels` are set.
+    pub fn write_frame(&mut self, frame: &AVFrame) -> Result<()> {
+        if frame.format != self.sample_fmt
+            || frame.ch_layout.nb_channels != self.channels
+            || frame.extended_data.is_null()
+            || frame.nb_samples < 0
+        {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        unsafe { self.write(frame.extended_data, frame.nb_samples) }
+    }
+
+    /// Read at most `nb_samples` samples from the AVAudioFifo into a newly
+    /// allocated audio [`AVFrame`], whose `ch_layout` and `sample_rate` are set
+    /// to the given ones. The `nb_samples` of the returned frame is the number
+    /// of samples actually read.
+    ///
+    /// Return `Ok(None)` if the AVAudioFifo is empty. Return `AVERROR(EINVAL)`
+    /// if the channel count of `ch_layout` doesn't match the AVAudioFifo's.
+    pub fn read_frame(
+        &mut self,
+        nb_samples: i32,
+        ch_layout: &AVChannelLayout,
+        sample_rate: i32,
+    ) -> Result<Option<AVFrame>> {
+        let Some(frame) = self.alloc_frame(nb_samples, 0, ch_layout, sample_rate)? else {
+            return Ok(None);
+        };
+        let read = unsafe { self.read(frame.extended_data, frame.nb_samples) }?;
+        debug_assert_eq!(read, frame.nb_samples);
+        Ok(Some(frame))
+    }
+
+    /// Copy at most `nb_samples` samples from the start of the AVAudioFifo
+    /// into a newly allocated audio [`AVFrame`] without consuming them, e.g.
+    /// to inspect a jitter buffer. The frame is set up as in
+    /// [`Self::read_frame()`].
+    ///
+    /// Return `Ok(None)` if the AVAudioFifo is empty. Return `AVERROR(EINVAL)`
+    /// if the channel count of `ch_layout` doesn't match the AVAudioFifo's.
+    pub fn peek_frame(
+        &self,
+        nb_samples: i32,
+        ch_layout: &AVChannelLayout,
+        sample_rate: i32,
+    ) -> Result<Option<AVFrame>> {
+        self.peek_frame_at(nb_samples, 0, ch_layout, sample_rate)
+    }
+
+    /// Same as [`Self::peek_frame()`], but skipping the first `offset`
+    /// samples of the AVAudioFifo.
+    ///
+    /// Return `Ok(None)` if there are no samples after `offset`.
+    pub fn peek_frame_at(
+        &self,
+        nb_samples: i32,
+        offset: i32,
+        ch_layout: &AVChannelLayout,
+        sample_rate: i32,
+    ) -> Result<Option<AVFrame>> {
+        let Some(frame) = self.alloc_frame(nb_samples, offset, ch_layout, sample_rate)? else {
+            return Ok(None);
+        };
+        let peeked = unsafe { self.peek_at(frame.extended_data, frame.nb_samples, offset) }?;
+        debug_assert_eq!(peeked, frame.nb_samples);
+        Ok(Some(frame))
+    }
+
+    /// Allocate a frame for the at most `nb_samples` samples available after
+    /// `offset`, `None` if there are none.
+    fn alloc_frame(
+        &self,
+        nb_samples: i32,
+        offset: i32,
+        ch_layout: &AVChannelLayout,
+        sample_rate: i32,
+    ) -> Result<Option<AVFrame>> {
+        if ch_layout.nb_channels != self.channels || nb_samples < 0 || offset < 0 {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let nb_samples = nb_samples.min(self.size() - offset);
+        if nb_samples <= 0 {
+            return Ok(None);
+        }
+
+        let mut frame = AVFrame::new();
+        frame.set_nb_samples(nb_samples);
+        frame.set_ch_layout(ch_layout.clone().into_inner());
+        frame.set_format(self.sample_fmt);
+        frame.set_sample_rate(sample_rate);
+        frame.alloc_buffer()?;
+        Ok(Some(frame))
+    }
+
     /// Peek data from an AVAudioFifo.
     ///
     /// # Safety
     /// Function is safe when the `data` points to valid sample buffer.
-    pub unsafe fn peek(&mut self, data: *const *mut u8, nb_samples: i32) -> Result<i32> {
-        let ret = unsafe { ffi::av_audio_fifo_peek(self.as_mut_ptr(), data as _, nb_samples) }
+    pub unsafe fn peek(&self, data: *const *mut u8, nb_samples: i32) -> Result<i32> {
+        let ret = unsafe { ffi::av_audio_fifo_peek(self.as_ptr() as _, data as _, nb_samples) }
             .upgrade()?;
         Ok(ret)
     }
@@ -56,14 +173,15 @@ impl AVAudioFifo {
     /// # Safety
     /// Function is safe when the `data` points to valid sample buffer.
     pub unsafe fn peek_at(
-        &mut self,
+        &self,
         data: *const *mut u8,
         nb_samples: i32,
         offset: i32,
     ) -> Result<i32> {
-        let ret =
-            unsafe { ffi::av_audio_fifo_peek_at(self.as_mut_ptr(), data as _, nb_samples, offset) }
-                .upgrade()?;
+        let ret = unsafe {
+            ffi::av_audio_fifo_peek_at(self.as_ptr() as _, data as _, nb_samples, offset)
+        }
+        .upgrade()?;
         Ok(ret)
     }
 
@@ -81,7 +199,7 @@ impl AVAudioFifo {
 
     /// Drain data from an AVAudioFifo.
     ///
-    /// Removes the data without reading it.
+    /// Removes the data without reading it, at most [`Self::size()`] samples.
     pub fn drain(&mut self, nb_samples: i32) {
         // FFI function only error when the nb_samples is negative.
         unsafe { ffi::av_audio_fifo_drain(self.as_mut_ptr(), nb_samples) }
@@ -106,13 +224,19 @@ impl AVAudioFifo {
     }
 
     /// Get the current number of samples in the [`AVAudioFifo`] available for
-    /// writing.
+    /// writing without reallocation.
     pub fn space(&self) -> i32 {
         unsafe {
             // function doesn't modify self, casting safe
             ffi::av_audio_fifo_space(self.as_ptr() as *mut _)
         }
     }
+
+    /// Get the number of samples the [`AVAudioFifo`] can hold without
+    /// reallocation, always `size() + space()`.
+    pub fn capacity(&self) -> i32 {
+        self.size() + self.space()
+    }
 }
 
 impl Drop for AVAudioFifo {
@@ -120,3 +244,86 @@ impl Drop for AVAudioFifo {
         unsafe { ffi::av_audio_fifo_free(self.as_mut_ptr()) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_frame(nb_samples: i32, nb_channels: i32, sample_fmt: ffi::AVSampleFormat) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_nb_samples(nb_samples);
+        frame.set_ch_layout(AVChannelLayout::from_nb_channels(nb_channels).into_inner());
+        frame.set_format(sample_fmt);
+        frame.set_sample_rate(44100);
+        frame.alloc_buffer().unwrap();
+        frame
+    }
+
+    #[test]
+    fn test_write_read_frame() {
+        let mut fifo = AVAudioFifo::new(ffi::AV_SAMPLE_FMT_FLTP, 2, 1);
+        fifo.write_frame(&new_frame(1024, 2, ffi::AV_SAMPLE_FMT_FLTP))
+            .unwrap();
+        fifo.write_frame(&new_frame(512, 2, ffi::AV_SAMPLE_FMT_FLTP))
+            .unwrap();
+        assert_eq!(fifo.size(), 1536);
+
+        let stereo = AVChannelLayout::from_nb_channels(2);
+        let frame = fifo.read_frame(1024, &stereo, 44100).unwrap().unwrap();
+        assert_eq!(frame.nb_samples, 1024);
+        assert_eq!(frame.format, ffi::AV_SAMPLE_FMT_FLTP);
+        assert_eq!(frame.sample_rate, 44100);
+        let frame = fifo.read_frame(1024, &stereo, 44100).unwrap().unwrap();
+        assert_eq!(frame.nb_samples, 512);
+        assert!(fifo.read_frame(1024, &stereo, 44100).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_peek_drain_reset() {
+        let mut fifo = AVAudioFifo::new(ffi::AV_SAMPLE_FMT_S16, 1, 16);
+        assert_eq!((fifo.size(), fifo.space(), fifo.capacity()), (0, 16, 16));
+
+        let mut frame = new_frame(10, 1, ffi::AV_SAMPLE_FMT_S16);
+        for (i, sample) in frame.samples_mut::<i16>(0).unwrap().iter_mut().enumerate() {
+            *sample = i as i16;
+        }
+        fifo.write_frame(&frame).unwrap();
+        assert_eq!((fifo.size(), fifo.space(), fifo.capacity()), (10, 6, 16));
+
+        let mono = AVChannelLayout::from_nb_channels(1);
+        let peeked = fifo.peek_frame(4, &mono, 44100).unwrap().unwrap();
+        assert_eq!(peeked.samples::<i16>(0).unwrap(), [0, 1, 2, 3]);
+        let peeked = fifo.peek_frame_at(4, 8, &mono, 44100).unwrap().unwrap();
+        assert_eq!(peeked.samples::<i16>(0).unwrap(), [8, 9]);
+        assert!(fifo.peek_frame_at(4, 10, &mono, 44100).unwrap().is_none());
+        assert!(fifo.peek_frame_at(4, -1, &mono, 44100).is_err());
+        assert_eq!(fifo.size(), 10);
+
+        fifo.drain(3);
+        let read = fifo.read_frame(2, &mono, 44100).unwrap().unwrap();
+        assert_eq!(read.samples::<i16>(0).unwrap(), [3, 4]);
+        assert_eq!((fifo.size(), fifo.capacity()), (5, 16));
+        fifo.drain(100);
+        assert_eq!(fifo.size(), 0);
+
+        fifo.write_frame(&frame).unwrap();
+        fifo.reset();
+        assert_eq!((fifo.size(), fifo.space()), (0, 16));
+    }
+
+    #[test]
+    fn test_frame_mismatch() {
+        let mut fifo = AVAudioFifo::new(ffi::AV_SAMPLE_FMT_FLTP, 2, 1);
+        assert!(fifo
+            .write_frame(&new_frame(1024, 2, ffi::AV_SAMPLE_FMT_S16))
+            .is_err());
+        assert!(fifo
+            .write_frame(&new_frame(1024, 1, ffi::AV_SAMPLE_FMT_FLTP))
+            .is_err());
+        assert!(fifo.write_frame(&AVFrame::new()).is_err());
+        assert_eq!(fifo.size(), 0);
+
+        let mono = AVChannelLayout::from_nb_channels(1);
+        assert!(fifo.read_frame(1024, &mono, 44100).is_err());
+    }
+}
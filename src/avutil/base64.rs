@@ -0,0 +1,63 @@
+use std::ffi::{CStr, CString};
+
+use crate::{error::*, ffi, shared::*};
+
+/// Encode data to a base64 string.
+///
+/// Note: if the encoded size exceeds [`i32::MAX`], this function returns
+/// [`RsmpegError::TryFromIntError`].
+pub fn base64_encode(data: &[u8]) -> Result<CString> {
+    // AV_BASE64_SIZE
+    let out_size = (data.len() + 2) / 3 * 4 + 1;
+    let mut out = vec![0u8; out_size];
+    unsafe {
+        ffi::av_base64_encode(
+            out.as_mut_ptr() as *mut _,
+            out_size.try_into()?,
+            data.as_ptr(),
+            data.len().try_into()?,
+        )
+    }
+    .upgrade()
+    .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+    // Remove the null terminator
+    out.pop();
+    Ok(CString::new(out).unwrap())
+}
+
+/// Decode a base64 string.
+///
+/// Returns `Err(_)` if the input is not valid base64.
+pub fn base64_decode(input: &CStr) -> Result<Vec<u8>> {
+    // AV_BASE64_DECODE_SIZE
+    let out_size = input.to_bytes().len() * 3 / 4;
+    let mut out = vec![0u8; out_size];
+    let len =
+        unsafe { ffi::av_base64_decode(out.as_mut_ptr(), input.as_ptr(), out_size.try_into()?) }
+            .upgrade()?;
+    out.truncate(len as usize);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64_encode(b"").unwrap().as_c_str(), cstr!(""));
+        assert_eq!(
+            base64_encode(b"rsmpeg").unwrap().as_c_str(),
+            cstr!("cnNtcGVn")
+        );
+        assert_eq!(
+            base64_encode(b"ffmpeg!").unwrap().as_c_str(),
+            cstr!("ZmZtcGVnIQ==")
+        );
+
+        assert_eq!(base64_decode(cstr!("cnNtcGVn")).unwrap(), b"rsmpeg");
+        assert_eq!(base64_decode(cstr!("ZmZtcGVnIQ==")).unwrap(), b"ffmpeg!");
+        assert!(base64_decode(cstr!("Zm!!")).is_err());
+    }
+}
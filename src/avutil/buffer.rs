@@ -2,7 +2,14 @@ use crate::{
     ffi,
     shared::{PointerUpgrade, RetUpgrade},
 };
-use std::os::raw::c_int;
+use std::{
+    os::raw::{c_int, c_void},
+    slice,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 wrap!(AVBufferRef: ffi::AVBufferRef);
 
@@ -49,6 +56,27 @@ impl AVBufferRef {
         unsafe { ffi::av_buffer_get_ref_count(self.as_ptr()) }
     }
 
+    /// Get the data of the buffer.
+    pub fn data(&self) -> &[u8] {
+        if self.data.is_null() {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.data, self.size) }
+        }
+    }
+
+    /// Get the data of the buffer mutably, returns `None` if the buffer is
+    /// not writable, see [`Self::make_writable()`].
+    pub fn data_mut(&mut self) -> Option<&mut [u8]> {
+        if !self.is_writable() {
+            return None;
+        }
+        if self.data.is_null() {
+            return Some(&mut []);
+        }
+        Some(unsafe { slice::from_raw_parts_mut(self.data, self.size) })
+    }
+
     /// Create a writable reference from a given buffer reference, avoiding data copy
     /// if possible.
     ///
@@ -82,6 +110,82 @@ impl Drop for AVBufferRef {
     }
 }
 
+/// Number of buffers of an [`AVBufferPool`], see [`AVBufferPool::usage()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolUsage {
+    /// Buffers allocated by the pool. The released buffers are reused
+    /// before allocating new ones, so it's the peak number of buffers in use
+    /// at once.
+    pub allocated: usize,
+}
+
+/// Counters shared by a pool and its allocator, which may outlive it.
+#[derive(Default)]
+pub struct PoolCounters {
+    allocated: AtomicUsize,
+}
+
+wrap!(AVBufferPool: ffi::AVBufferPool, _counters: Arc<PoolCounters> = Arc::default());
+
+impl AVBufferPool {
+    /// Allocate and initialize a buffer pool, whose buffers are `size` bytes
+    /// and allocated with `av_malloc()`.
+    pub fn new(size: usize) -> Self {
+        unsafe extern "C" fn alloc(opaque: *mut c_void, size: usize) -> *mut ffi::AVBufferRef {
+            let counters = unsafe { &*(opaque as *const PoolCounters) };
+            let buf = unsafe { ffi::av_buffer_alloc(size) };
+            if !buf.is_null() {
+                counters.allocated.fetch_add(1, Ordering::Relaxed);
+            }
+            buf
+        }
+
+        unsafe extern "C" fn pool_free(opaque: *mut c_void) {
+            drop(unsafe { Arc::from_raw(opaque as *const PoolCounters) });
+        }
+
+        let counters = Arc::<PoolCounters>::default();
+        let opaque = Arc::into_raw(counters.clone()) as *mut c_void;
+        // Safety: only fail on OOM.
+        let ptr = unsafe { ffi::av_buffer_pool_init2(size, opaque, Some(alloc), Some(pool_free)) }
+            .upgrade()
+            .unwrap();
+        let mut pool = unsafe { Self::from_raw(ptr) };
+        pool._counters = counters;
+        pool
+    }
+
+    /// Get a buffer from the pool, a previously returned buffer is reused if
+    /// available. The buffer returns to the pool when all its references are
+    /// dropped. Returns `None` if the allocation fails.
+    ///
+    /// Note: the data of a reused buffer is not cleared.
+    pub fn get(&self) -> Option<AVBufferRef> {
+        unsafe { ffi::av_buffer_pool_get(self.as_ptr() as *mut _) }
+            .upgrade()
+            .map(|ptr| unsafe { AVBufferRef::from_raw(ptr) })
+    }
+
+    /// Number of buffers allocated by the pool, e.g. to monitor how close a
+    /// fixed size workload gets to exhausting memory. FFmpeg doesn't report
+    /// the buffers currently in use.
+    pub fn usage(&self) -> PoolUsage {
+        PoolUsage {
+            allocated: self._counters.allocated.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for AVBufferPool {
+    /// Mark the pool as being available for freeing. It will actually be
+    /// freed only once all the allocated buffers associated with the pool are
+    /// released, so the buffers can outlive the pool.
+    fn drop(&mut self) {
+        let mut ptr = self.as_mut_ptr();
+        unsafe { ffi::av_buffer_pool_uninit(&mut ptr) }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,4 +253,54 @@ mod tests {
         assert!(buf.is_writable());
         assert!(buf2.is_writable());
     }
+
+    #[test]
+    fn test_av_buffer_data() {
+        let mut buf = AVBufferRef::zeroed(4);
+        buf.data_mut().unwrap().copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(buf.data(), &[1, 2, 3, 4]);
+
+        let buf1 = buf.clone();
+        assert!(buf.data_mut().is_none());
+        assert_eq!(buf1.data(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_av_buffer_pool() {
+        let pool = AVBufferPool::new(1024);
+        let buf = pool.get().unwrap();
+        assert_eq!(buf.size, 1024);
+        assert!(buf.is_writable());
+        let data = buf.data;
+        drop(buf);
+
+        // The released buffer is reused.
+        let buf = pool.get().unwrap();
+        assert_eq!(buf.data, data);
+        let buf1 = pool.get().unwrap();
+        assert_ne!(buf1.data, data);
+
+        // Buffers outlive the pool.
+        drop(pool);
+        assert_eq!(buf.data().len(), 1024);
+    }
+
+    #[test]
+    fn test_av_buffer_pool_usage() {
+        let pool = AVBufferPool::new(16);
+        assert_eq!(pool.usage(), PoolUsage::default());
+        let buf = pool.get().unwrap();
+        let buf1 = pool.get().unwrap();
+        let buf2 = buf1.clone();
+        assert_eq!(pool.usage(), PoolUsage { allocated: 2 });
+        drop((buf1, buf2));
+
+        // The released buffer is reused.
+        let buf1 = pool.get().unwrap();
+        assert_eq!(pool.usage(), PoolUsage { allocated: 2 });
+        let buf2 = pool.get().unwrap();
+        assert_eq!(pool.usage(), PoolUsage { allocated: 3 });
+        drop((buf, buf1, buf2));
+        assert_eq!(pool.usage(), PoolUsage { allocated: 3 });
+    }
 }
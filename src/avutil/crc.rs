@@ -0,0 +1,65 @@
+use crate::{ffi, shared::*};
+use std::ptr::NonNull;
+
+pub use crate::ffi::AVCRCId;
+
+/// One of the standard CRC tables of libavutil, selected by an
+/// [`AVCRCId`], e.g. `AV_CRC_32_IEEE` for the CRC of MPEG-TS sections.
+///
+/// The tables are static, so the checksums are consistent with the ones
+/// computed by the FFmpeg tools and muxers.
+///
+/// ```rust
+/// # use rsmpeg::{avutil::CrcTable, ffi};
+/// // CRC-32 of zlib, as the `crc32` hash of the `hash` muxer.
+/// let table = CrcTable::get(ffi::AV_CRC_32_IEEE_LE).unwrap();
+/// assert_eq!(!table.crc(!0, b"123456789"), 0xCBF43926);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct CrcTable(NonNull<ffi::AVCRC>);
+
+// The tables are immutable once initialized.
+unsafe impl Send for CrcTable {}
+unsafe impl Sync for CrcTable {}
+
+impl CrcTable {
+    /// Get the table of `crc_id`, `None` if the id is unknown or the table
+    /// failed to initialize.
+    pub fn get(crc_id: AVCRCId) -> Option<Self> {
+        if crc_id >= ffi::AV_CRC_MAX {
+            return None;
+        }
+        unsafe { ffi::av_crc_get_table(crc_id) }.upgrade().map(Self)
+    }
+
+    /// Update the checksum `crc` with the bytes of `buf`.
+    ///
+    /// The initial value and the final inversion depend on the CRC variant,
+    /// e.g. CRC-32 of zlib starts with `!0` and inverts the result, while the
+    /// CRC of MPEG-TS starts with `!0` and doesn't.
+    ///
+    /// For the big-endian tables, e.g. `AV_CRC_32_IEEE`, `crc` and the
+    /// result are byte swapped compared to the usual notation, see
+    /// `av_crc_init()`.
+    pub fn crc(&self, crc: u32, buf: &[u8]) -> u32 {
+        unsafe { ffi::av_crc(self.0.as_ptr(), crc, buf.as_ptr(), buf.len()) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crc() {
+        assert!(CrcTable::get(ffi::AV_CRC_MAX).is_none());
+
+        let crc32 = CrcTable::get(ffi::AV_CRC_32_IEEE_LE).unwrap();
+        assert_eq!(!crc32.crc(!0, b"123456789"), 0xCBF43926);
+        let crc = crc32.crc(!0, b"1234");
+        assert_eq!(!crc32.crc(crc, b"56789"), 0xCBF43926);
+
+        let mpeg2 = CrcTable::get(ffi::AV_CRC_32_IEEE).unwrap();
+        assert_eq!(mpeg2.crc(!0, b"123456789").swap_bytes(), 0x0376E6E7);
+    }
+}
@@ -6,25 +6,60 @@ use std::{
     ptr::{self, NonNull},
 };
 
+bitflags::bitflags! {
+    /// Flags of the [`AVDictionary`] functions, `AV_DICT_*`. The bare `u32`
+    /// flags are also accepted.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct DictFlags: u32 {
+        /// Only get an entry with exact-case key match.
+        const MATCH_CASE = ffi::AV_DICT_MATCH_CASE;
+        /// Return first entry in a dictionary whose first part corresponds to
+        /// the search key, ignoring the suffix of the found key string.
+        const IGNORE_SUFFIX = ffi::AV_DICT_IGNORE_SUFFIX;
+        /// Don't overwrite existing entries.
+        const DONT_OVERWRITE = ffi::AV_DICT_DONT_OVERWRITE;
+        /// If the entry already exists, append to it.
+        const APPEND = ffi::AV_DICT_APPEND;
+        /// Allow to store several equal keys in the dictionary.
+        const MULTIKEY = ffi::AV_DICT_MULTIKEY;
+
+        // Keep the flags unknown to this version.
+        const _ = !0;
+    }
+}
+
+impl From<u32> for DictFlags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_retain(flags)
+    }
+}
+
 wrap_ref_mut!(AVDictionary: ffi::AVDictionary);
 
 impl AVDictionary {
     /// Create a dictionary while calling `set()`.
-    pub fn new(key: &CStr, value: &CStr, flags: u32) -> Self {
+    pub fn new(key: &CStr, value: &CStr, flags: impl Into<DictFlags>) -> Self {
         // Since AVDictionary is a non-null pointer to ffi::AVDictionary.
         // Without a new macro `wrap_nullable`, we cannot new a Self containing
         // null pointer.
         let mut dict = ptr::null_mut();
-        unsafe { ffi::av_dict_set(&mut dict, key.as_ptr(), value.as_ptr(), flags as i32) }
-            .upgrade()
-            .unwrap();
+        unsafe {
+            ffi::av_dict_set(
+                &mut dict,
+                key.as_ptr(),
+                value.as_ptr(),
+                flags.into().bits() as i32,
+            )
+        }
+        .upgrade()
+        .unwrap();
         unsafe { Self::from_raw(NonNull::new(dict).unwrap()) }
     }
 
     /// Create a dictionary while calling `set_int()`.
-    pub fn new_int(key: &CStr, value: i64, flags: u32) -> Self {
+    pub fn new_int(key: &CStr, value: i64, flags: impl Into<DictFlags>) -> Self {
         let mut dict = ptr::null_mut();
-        unsafe { ffi::av_dict_set_int(&mut dict, key.as_ptr(), value, flags as i32) }
+        unsafe { ffi::av_dict_set_int(&mut dict, key.as_ptr(), value, flags.into().bits() as i32) }
             .upgrade()
             .unwrap();
         unsafe { Self::from_raw(NonNull::new(dict).unwrap()) }
@@ -35,7 +70,7 @@ impl AVDictionary {
         str: &CStr,
         key_val_sep: &CStr,
         pairs_sep: &CStr,
-        flags: u32,
+        flags: impl Into<DictFlags>,
     ) -> Option<Self> {
         let mut dict = ptr::null_mut();
         unsafe {
@@ -44,7 +79,7 @@ impl AVDictionary {
                 str.as_ptr(),
                 key_val_sep.as_ptr(),
                 pairs_sep.as_ptr(),
-                flags as i32,
+                flags.into().bits() as i32,
             )
         }
         .upgrade()
@@ -52,24 +87,57 @@ impl AVDictionary {
         Some(unsafe { Self::from_raw(NonNull::new(dict).unwrap()) })
     }
 
+    /// Create a dictionary from key/value pairs of Rust strings.
+    ///
+    /// Returns `None` if the iterator is empty, since an [`AVDictionary`]
+    /// always holds at least one entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any key or value contains an interior nul byte.
+    pub fn from_pairs<I, K, V>(pairs: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut dict = ptr::null_mut();
+        for (key, value) in pairs {
+            let key = CString::new(key.as_ref()).unwrap();
+            let value = CString::new(value.as_ref()).unwrap();
+            // Only error on AVERROR_ENOMEM, so unwrap
+            unsafe { ffi::av_dict_set(&mut dict, key.as_ptr(), value.as_ptr(), 0) }
+                .upgrade()
+                .unwrap();
+        }
+        NonNull::new(dict).map(|dict| unsafe { Self::from_raw(dict) })
+    }
+
     /// The set function is so strange is because adding a new entry to
     /// AVDictionary invalidates all existing entries.... So this functions
     /// consumes itself.
-    pub fn set(mut self, key: &CStr, value: &CStr, flags: u32) -> Self {
+    pub fn set(mut self, key: &CStr, value: &CStr, flags: impl Into<DictFlags>) -> Self {
         let mut dict = self.as_mut_ptr();
         // Only error on AVERROR_ENOMEM, so unwrap
-        unsafe { ffi::av_dict_set(&mut dict, key.as_ptr(), value.as_ptr(), flags as i32) }
-            .upgrade()
-            .unwrap();
+        unsafe {
+            ffi::av_dict_set(
+                &mut dict,
+                key.as_ptr(),
+                value.as_ptr(),
+                flags.into().bits() as i32,
+            )
+        }
+        .upgrade()
+        .unwrap();
         unsafe { self.set_ptr(NonNull::new(dict).unwrap()) };
         self
     }
 
     /// Similar to the `set` function.
-    pub fn set_int(mut self, key: &CStr, value: i64, flags: u32) -> Self {
+    pub fn set_int(mut self, key: &CStr, value: i64, flags: impl Into<DictFlags>) -> Self {
         let mut dict = self.as_mut_ptr();
         // Only error on AVERROR_ENOMEM, so unwrap
-        unsafe { ffi::av_dict_set_int(&mut dict, key.as_ptr(), value, flags as i32) }
+        unsafe { ffi::av_dict_set_int(&mut dict, key.as_ptr(), value, flags.into().bits() as i32) }
             .upgrade()
             .unwrap();
         unsafe { self.set_ptr(NonNull::new(dict).unwrap()) };
@@ -83,7 +151,7 @@ impl AVDictionary {
         str: &CStr,
         key_val_sep: &CStr,
         pairs_sep: &CStr,
-        flags: u32,
+        flags: impl Into<DictFlags>,
     ) -> Result<Self> {
         let mut dict = self.as_mut_ptr();
         unsafe {
@@ -92,7 +160,7 @@ impl AVDictionary {
                 str.as_ptr(),
                 key_val_sep.as_ptr(),
                 pairs_sep.as_ptr(),
-                flags as i32,
+                flags.into().bits() as i32,
             )
         }
         .upgrade()?;
@@ -101,10 +169,10 @@ impl AVDictionary {
     }
 
     /// Copy entries from one AVDictionary struct into self.
-    pub fn copy(mut self, another: &AVDictionary, flags: u32) -> Self {
+    pub fn copy(mut self, another: &AVDictionary, flags: impl Into<DictFlags>) -> Self {
         let mut dict = self.as_mut_ptr();
         // Only error on AVERROR_ENOMEM, so unwrap
-        unsafe { ffi::av_dict_copy(&mut dict, another.as_ptr(), flags as i32) }
+        unsafe { ffi::av_dict_copy(&mut dict, another.as_ptr(), flags.into().bits() as i32) }
             .upgrade()
             .unwrap();
         unsafe { self.set_ptr(NonNull::new(dict).unwrap()) };
@@ -128,6 +196,28 @@ impl AVDictionary {
 }
 
 impl<'dict> AVDictionary {
+    /// Number of entries in the dictionary.
+    pub fn len(&self) -> usize {
+        unsafe { ffi::av_dict_count(self.as_ptr()) as usize }
+    }
+
+    /// Whether the dictionary has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the value of `key` as `&str` (case sensitive match).
+    ///
+    /// Returns `None` if the key doesn't exist, or if either the key or the
+    /// value cannot be represented as the other string type.
+    pub fn get_str(&'dict self, key: &str) -> Option<&'dict str> {
+        let key = CString::new(key).ok()?;
+        let entry = self.get(&key, None, DictFlags::MATCH_CASE)?;
+        // Get the value pointer out of the entry, it lives as long as the dictionary.
+        let value = unsafe { CStr::from_ptr(entry.value) };
+        value.to_str().ok()
+    }
+
     /// Get a dictionary entry with matching key.
     ///
     /// The returned entry key or value must not be changed, or it will
@@ -139,15 +229,22 @@ impl<'dict> AVDictionary {
         &'dict self,
         key: &CStr,
         prev: Option<AVDictionaryEntryRef>,
-        flags: u32,
+        flags: impl Into<DictFlags>,
     ) -> Option<AVDictionaryEntryRef<'dict>> {
         let prev_ptr = match prev {
             Some(entry) => entry.as_ptr(),
             None => ptr::null(),
         };
-        unsafe { ffi::av_dict_get(self.as_ptr(), key.as_ptr(), prev_ptr, flags as i32) }
-            .upgrade()
-            .map(|ptr| unsafe { AVDictionaryEntryRef::from_raw(ptr) })
+        unsafe {
+            ffi::av_dict_get(
+                self.as_ptr(),
+                key.as_ptr(),
+                prev_ptr,
+                flags.into().bits() as i32,
+            )
+        }
+        .upgrade()
+        .map(|ptr| unsafe { AVDictionaryEntryRef::from_raw(ptr) })
     }
 
     /// Iterates through all entries in the dictionary by reference.
@@ -219,6 +316,7 @@ impl AVDictionaryEntry {
 mod test {
     use super::AVDictionary;
     use cstr::cstr;
+    use std::collections::HashMap;
 
     #[test]
     fn set() {
@@ -367,4 +465,33 @@ mod test {
             dict.get_string(b':', b'-').unwrap().as_c_str()
         );
     }
+
+    #[test]
+    fn from_rust_strings() {
+        let dict = AVDictionary::from_pairs(vec![
+            ("preset".to_string(), "fast".to_string()),
+            ("crf".to_string(), "23".to_string()),
+        ])
+        .unwrap();
+        assert_eq!(2, dict.len());
+        assert!(!dict.is_empty());
+        assert_eq!(Some("fast"), dict.get_str("preset"));
+        assert_eq!(Some("23"), dict.get_str("crf"));
+        assert_eq!(None, dict.get_str("tune"));
+        assert_eq!(None, dict.get_str("Preset"));
+
+        let mut map = HashMap::new();
+        map.insert("foo".to_string(), "bar".to_string());
+        map.insert("bob".to_string(), "alice".to_string());
+        let dict = AVDictionary::from_pairs(&map).unwrap();
+        assert_eq!(2, dict.len());
+        assert_eq!(Some("bar"), dict.get_str("foo"));
+        assert_eq!(Some("alice"), dict.get_str("bob"));
+
+        assert!(AVDictionary::from_pairs(Vec::<(&str, &str)>::new()).is_none());
+        assert!(AVDictionary::from_pairs(&HashMap::<String, String>::new()).is_none());
+        let dict = AVDictionary::from_pairs([("a", "b"), ("a", "c")]).unwrap();
+        assert_eq!(1, dict.len());
+        assert_eq!(Some("c"), dict.get_str("a"));
+    }
 }
@@ -0,0 +1,61 @@
+use crate::ffi;
+
+/// Extract the rotation component of a display matrix: the angle in degrees
+/// by which it rotates the frame counterclockwise, within `[-180, 180]`, or
+/// NaN if the matrix is singular.
+///
+/// Round it before use, floating point numbers are inexact.
+pub fn display_rotation_get(matrix: &[i32; 9]) -> f64 {
+    unsafe { ffi::av_display_rotation_get(matrix.as_ptr()) }
+}
+
+/// Create a display matrix describing a pure rotation by `angle` degrees,
+/// in the convention of [`display_rotation_get()`].
+///
+/// ```rust
+/// # use rsmpeg::avutil::{display_rotation_get, display_rotation_set};
+/// let matrix = display_rotation_set(90.0);
+/// assert_eq!(display_rotation_get(&matrix).round(), 90.0);
+/// ```
+pub fn display_rotation_set(angle: f64) -> [i32; 9] {
+    let mut matrix = [0; 9];
+    unsafe { ffi::av_display_rotation_set(matrix.as_mut_ptr(), angle) };
+    matrix
+}
+
+/// Flip a display matrix horizontally and/or vertically.
+pub fn display_matrix_flip(matrix: &mut [i32; 9], hflip: bool, vflip: bool) {
+    unsafe { ffi::av_display_matrix_flip(matrix.as_mut_ptr(), hflip as _, vflip as _) }
+}
+
+/// Serialize a display matrix into an `AV_PKT_DATA_DISPLAYMATRIX` side data
+/// payload.
+pub(crate) fn display_matrix_to_bytes(matrix: &[i32; 9]) -> Vec<u8> {
+    matrix.iter().flat_map(|x| x.to_ne_bytes()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display_matrix() {
+        let identity = display_rotation_set(0.0);
+        assert_eq!(identity, [1 << 16, 0, 0, 0, 1 << 16, 0, 0, 0, 1 << 30]);
+        assert_eq!(display_rotation_get(&identity), 0.0);
+        assert_eq!(
+            display_rotation_get(&display_rotation_set(-90.0)).round(),
+            -90.0
+        );
+        assert!(display_rotation_get(&[0; 9]).is_nan());
+
+        let mut matrix = identity;
+        display_matrix_flip(&mut matrix, true, false);
+        assert_eq!(matrix[0], -(1 << 16));
+        assert_eq!(matrix[4], 1 << 16);
+
+        let bytes = display_matrix_to_bytes(&identity);
+        assert_eq!(bytes.len(), 36);
+        assert_eq!(&bytes[..4], (1i32 << 16).to_ne_bytes());
+    }
+}
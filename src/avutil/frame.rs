@@ -1,11 +1,20 @@
 use crate::{
-    avutil::{av_image_fill_arrays, AVChannelLayoutRef, AVImage, AVMotionVector, AVPixelFormat},
+    avutil::{
+        av_image_fill_arrays, AVChannelLayoutRef, AVImage, AVMotionVector, AVPixFmtDescriptorRef,
+        AVPixelFormat, PixFmtFlags, PixelFormat, Sample, SampleFormat,
+    },
     error::*,
     ffi,
     shared::*,
 };
 
-use std::{fmt, mem::size_of, os::raw::c_int, ptr::NonNull, slice};
+use std::{
+    fmt,
+    mem::{align_of, size_of},
+    os::raw::c_int,
+    ptr::NonNull,
+    slice,
+};
 
 wrap!(AVFrame: ffi::AVFrame);
 settable!(AVFrame {
@@ -41,6 +50,30 @@ impl AVFrame {
         unsafe { Self::from_raw(frame) }
     }
 
+    /// Set the format of a video frame, accepts both [`PixelFormat`] and the
+    /// bare `AVPixelFormat`.
+    pub fn set_pixel_format(&mut self, pix_fmt: impl Into<PixelFormat>) {
+        self.set_format(pix_fmt.into().0)
+    }
+
+    /// Set the format of an audio frame, accepts both [`SampleFormat`] and
+    /// the bare `AVSampleFormat`.
+    pub fn set_sample_format(&mut self, sample_fmt: impl Into<SampleFormat>) {
+        self.set_format(sample_fmt.into().0)
+    }
+
+    /// Get the `format` field as [`PixelFormat`], only meaningful for video
+    /// frames.
+    pub fn pixel_format(&self) -> PixelFormat {
+        PixelFormat(self.format)
+    }
+
+    /// Get the `format` field as [`SampleFormat`], only meaningful for audio
+    /// frames.
+    pub fn sample_format(&self) -> SampleFormat {
+        SampleFormat(self.format)
+    }
+
     /// Return true if the data and buffer of current frame is allocated.
     pub fn is_allocated(&self) -> bool {
         !(self.data[0].is_null() && self.buf[0].is_null())
@@ -177,6 +210,41 @@ impl AVFrame {
         unsafe { ffi::av_hwframe_transfer_data(self.as_mut_ptr(), src.as_ptr(), 0) }.upgrade()?;
         Ok(())
     }
+
+    /// Make the frame a new reference to the data of `src` and copy its
+    /// properties, the previous data of the frame is unreferenced. The data is
+    /// copied instead if `src` isn't reference counted.
+    ///
+    /// Unlike [`Clone`], no frame is allocated, e.g. to keep the last frame of
+    /// a decode loop reusing a single frame.
+    pub fn ref_from(&mut self, src: &AVFrame) -> Result<()> {
+        self.unref();
+        unsafe { ffi::av_frame_ref(self.as_mut_ptr(), src.as_ptr()) }.upgrade()?;
+        Ok(())
+    }
+
+    /// Unreference the data of the frame and reset its fields to their
+    /// defaults, so it can be reused, e.g. by
+    /// [`AVCodecContext::receive_frame()`](crate::avcodec::AVCodecContext::receive_frame).
+    pub fn unref(&mut self) {
+        unsafe { ffi::av_frame_unref(self.as_mut_ptr()) }
+    }
+
+    /// Move the data and properties of `src` into the frame, leaving `src`
+    /// reset to its defaults. The previous data of the frame is
+    /// unreferenced.
+    pub fn move_ref_from(&mut self, src: &mut AVFrame) {
+        self.unref();
+        unsafe { ffi::av_frame_move_ref(self.as_mut_ptr(), src.as_mut_ptr()) }
+    }
+
+    /// Copy the properties of `src`, e.g. the timestamps, the color
+    /// properties and the side data, but not its data nor its size and
+    /// format.
+    pub fn copy_props_from(&mut self, src: &AVFrame) -> Result<()> {
+        unsafe { ffi::av_frame_copy_props(self.as_mut_ptr(), src.as_ptr()) }.upgrade()?;
+        Ok(())
+    }
 }
 
 impl Clone for AVFrame {
@@ -203,6 +271,145 @@ impl<'frame> AVFrame {
             .upgrade()
             .map(|side_data_ptr| unsafe { AVFrameSideDataRef::from_raw(side_data_ptr) })
     }
+
+    /// Get the number of planes of current video frame, computed from its
+    /// pixel format. Return `0` if the pixel format is unknown.
+    pub fn nb_planes(&self) -> usize {
+        unsafe { ffi::av_pix_fmt_count_planes(self.format) }
+            .upgrade()
+            .map_or(0, |x| x as usize)
+    }
+
+    /// Get the data of the given plane of current video frame, including the
+    /// padding at the end of each line.
+    ///
+    /// Return `None` if the plane doesn't exist, the frame is not allocated, or
+    /// the frame is a hardware frame or has negative linesize.
+    pub fn plane_data(&'frame self, plane: usize) -> Option<&'frame [u8]> {
+        let (linesize, height, _) = self.plane_geometry(plane)?;
+        Some(unsafe { slice::from_raw_parts(self.data[plane], linesize * height) })
+    }
+
+    /// Get the mutable data of the given plane of current video frame,
+    /// including the padding at the end of each line.
+    ///
+    /// Return `None` if the frame is not writable (call
+    /// [`Self::make_writable()`] first), or in the cases listed in
+    /// [`Self::plane_data()`].
+    pub fn plane_data_mut(&'frame mut self, plane: usize) -> Option<&'frame mut [u8]> {
+        let (linesize, height, _) = self.plane_geometry(plane)?;
+        if !self.is_writable().ok()? {
+            return None;
+        }
+        Some(unsafe { slice::from_raw_parts_mut(self.data[plane], linesize * height) })
+    }
+
+    /// Iterate over the lines of the given plane of current video frame. Each
+    /// line only contains the pixel data, the padding is stripped.
+    ///
+    /// Return `None` in the cases listed in [`Self::plane_data()`].
+    pub fn rows(&'frame self, plane: usize) -> Option<impl Iterator<Item = &'frame [u8]>> {
+        let (linesize, _, width) = self.plane_geometry(plane)?;
+        let data = self.plane_data(plane)?;
+        Some(data.chunks_exact(linesize).map(move |row| &row[..width]))
+    }
+
+    /// Iterate over the lines of the given plane of current video frame
+    /// mutably. Each line only contains the pixel data, the padding is
+    /// stripped.
+    ///
+    /// Return `None` in the cases listed in [`Self::plane_data_mut()`].
+    pub fn rows_mut(
+        &'frame mut self,
+        plane: usize,
+    ) -> Option<impl Iterator<Item = &'frame mut [u8]>> {
+        let (linesize, _, width) = self.plane_geometry(plane)?;
+        let data = self.plane_data_mut(plane)?;
+        Some(
+            data.chunks_exact_mut(linesize)
+                .map(move |row| &mut row[..width]),
+        )
+    }
+
+    /// Get the samples of the given plane of current audio frame as a typed
+    /// slice, e.g. `samples::<f32>(1)` for the right channel of a stereo
+    /// `AV_SAMPLE_FMT_FLTP` frame.
+    ///
+    /// Planar frames have one plane of `nb_samples` samples per channel,
+    /// packed frames have a single plane holding the interleaved samples of
+    /// all the channels.
+    ///
+    /// Return `None` if `T` isn't the sample type of the frame's sample
+    /// format, the plane doesn't exist, or the frame is not allocated.
+    pub fn samples<T: Sample>(&'frame self, plane: usize) -> Option<&'frame [T]> {
+        let (data, len) = self.sample_geometry::<T>(plane)?;
+        Some(unsafe { slice::from_raw_parts(data as *const T, len) })
+    }
+
+    /// Get the samples of the given plane of current audio frame as a typed
+    /// mutable slice.
+    ///
+    /// Return `None` if the frame is not writable (call
+    /// [`Self::make_writable()`] first), or in the cases listed in
+    /// [`Self::samples()`].
+    pub fn samples_mut<T: Sample>(&'frame mut self, plane: usize) -> Option<&'frame mut [T]> {
+        let (data, len) = self.sample_geometry::<T>(plane)?;
+        if !self.is_writable().ok()? {
+            return None;
+        }
+        Some(unsafe { slice::from_raw_parts_mut(data as *mut T, len) })
+    }
+
+    /// Return `(data, number of samples)` of the given plane, checking `T`
+    /// against the sample format.
+    fn sample_geometry<T: Sample>(&self, plane: usize) -> Option<(*mut u8, usize)> {
+        let sample_fmt = SampleFormat(self.format);
+        if sample_fmt.packed()?.0 != T::SAMPLE_FMT {
+            return None;
+        }
+        let nb_channels = usize::try_from(self.ch_layout.nb_channels)
+            .ok()
+            .filter(|&x| x != 0)?;
+        let nb_samples = usize::try_from(self.nb_samples).ok()?;
+        let (nb_planes, len) = if sample_fmt.is_planar() {
+            (nb_channels, nb_samples)
+        } else {
+            (1, nb_samples * nb_channels)
+        };
+        if plane >= nb_planes || self.extended_data.is_null() {
+            return None;
+        }
+        let data = unsafe { *self.extended_data.add(plane) };
+        if data.is_null() || data as usize % align_of::<T>() != 0 {
+            return None;
+        }
+        Some((data, len))
+    }
+
+    /// Return `(linesize, height, width in bytes)` of the given plane.
+    fn plane_geometry(&self, plane: usize) -> Option<(usize, usize, usize)> {
+        if plane >= self.nb_planes() || self.data[plane].is_null() {
+            return None;
+        }
+        let desc = AVPixFmtDescriptorRef::get(self.format)?;
+        if desc.is_hwaccel() {
+            return None;
+        }
+        if desc.pix_fmt_flags().contains(PixFmtFlags::PAL) && plane == 1 {
+            // The palette is always stored as 256 32 bits words.
+            return Some((256 * 4, 1, 256 * 4));
+        }
+        let linesize = usize::try_from(self.linesize[plane])
+            .ok()
+            .filter(|&x| x != 0)?;
+        let (_, height) = desc.plane_size(plane, self.width, self.height)?;
+        let width = unsafe { ffi::av_image_get_linesize(self.format, self.width, plane as i32) };
+        Some((
+            linesize,
+            usize::try_from(height).ok()?,
+            usize::try_from(width).ok()?,
+        ))
+    }
 }
 
 impl Drop for AVFrame {
@@ -281,7 +488,10 @@ impl<'frame> AVFrameSideDataRef<'frame> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{avcodec::AVCodec, avutil::AVChannelLayout};
+    use crate::{
+        avcodec::AVCodec,
+        avutil::{deinterleave, interleave, AVChannelLayout},
+    };
 
     #[test]
     fn test_get_buffer() {
@@ -316,10 +526,123 @@ mod test {
         ));
     }
 
+    #[test]
+    fn test_plane_data() {
+        let mut frame = AVFrame::new();
+        frame.set_format(ffi::AV_PIX_FMT_YUV420P);
+        frame.set_width(65);
+        frame.set_height(49);
+        assert_eq!(frame.nb_planes(), 3);
+        assert!(frame.plane_data(0).is_none());
+
+        frame.alloc_buffer().unwrap();
+        assert!(frame.plane_data(3).is_none());
+        let linesize = frame.linesize[0] as usize;
+        assert_eq!(frame.plane_data(0).unwrap().len(), linesize * 49);
+        let linesize = frame.linesize[1] as usize;
+        assert_eq!(frame.plane_data(1).unwrap().len(), linesize * 25);
+
+        for row in frame.rows_mut(0).unwrap() {
+            row.fill(42);
+        }
+        let rows: Vec<_> = frame.rows(0).unwrap().collect();
+        assert_eq!(rows.len(), 49);
+        assert!(rows.iter().all(|row| row.len() == 65));
+        assert!(rows.iter().all(|row| row.iter().all(|&x| x == 42)));
+        assert_eq!(frame.rows(2).unwrap().next().unwrap().len(), 33);
+
+        let cloned = frame.clone();
+        assert!(frame.plane_data_mut(0).is_none());
+        drop(cloned);
+        assert!(frame.plane_data_mut(0).is_some());
+    }
+
+    #[test]
+    fn test_samples() {
+        let mut frame = AVFrame::new();
+        frame.set_nb_samples(4);
+        frame.set_ch_layout(AVChannelLayout::from_nb_channels(2).into_inner());
+        frame.set_format(ffi::AV_SAMPLE_FMT_FLTP);
+        assert!(frame.samples::<f32>(0).is_none());
+
+        frame.alloc_buffer().unwrap();
+        assert!(frame.samples::<i16>(0).is_none());
+        assert!(frame.samples::<f32>(2).is_none());
+        for plane in 0..2 {
+            let samples = frame.samples_mut::<f32>(plane).unwrap();
+            assert_eq!(samples.len(), 4);
+            for (i, sample) in samples.iter_mut().enumerate() {
+                *sample = if plane == 0 { i as f32 } else { -(i as f32) };
+            }
+        }
+        let left = frame.samples::<f32>(0).unwrap();
+        let right = frame.samples::<f32>(1).unwrap();
+        assert_eq!(right, [0.0, -1.0, -2.0, -3.0]);
+        let interleaved = interleave(&[left, right]);
+        assert_eq!(interleaved, [0.0, 0.0, 1.0, -1.0, 2.0, -2.0, 3.0, -3.0]);
+        assert_eq!(deinterleave(&interleaved, 2), [left, right]);
+
+        let mut frame = AVFrame::new();
+        frame.set_nb_samples(3);
+        frame.set_ch_layout(AVChannelLayout::from_nb_channels(2).into_inner());
+        frame.set_sample_format(SampleFormat(ffi::AV_SAMPLE_FMT_S16));
+        frame.alloc_buffer().unwrap();
+        assert_eq!(frame.samples::<i16>(0).unwrap().len(), 6);
+        assert!(frame.samples::<i16>(1).is_none());
+        assert!(frame.samples::<u8>(0).is_none());
+    }
+
     #[test]
     fn test_frame_with_image_buffer() {
         let image = AVImage::new(ffi::AV_PIX_FMT_RGB24, 256, 256, 0).unwrap();
         let frame = AVFrameWithImage::new(image);
         let _: &Vec<u8> = &frame.image;
     }
+
+    #[test]
+    fn test_frame_refs() {
+        let mut src = AVFrame::new();
+        src.set_width(16);
+        src.set_height(16);
+        src.set_format(ffi::AV_PIX_FMT_GRAY8);
+        src.set_pts(42);
+        src.alloc_buffer().unwrap();
+        src.plane_data_mut(0).unwrap()[0] = 7;
+
+        let mut frame = AVFrame::new();
+        frame.ref_from(&src).unwrap();
+        assert_eq!(frame.data[0], src.data[0]);
+        assert_eq!((frame.width, frame.pts), (16, 42));
+        assert!(!src.is_writable().unwrap());
+        // Referencing again drops the previous reference.
+        frame.ref_from(&src).unwrap();
+        frame.unref();
+        assert!(frame.data[0].is_null());
+        assert_eq!(frame.width, 0);
+        assert!(src.is_writable().unwrap());
+
+        let mut props = AVFrame::new();
+        props.copy_props_from(&src).unwrap();
+        assert_eq!(props.pts, 42);
+        assert!(props.data[0].is_null());
+
+        let data = src.data[0];
+        frame.move_ref_from(&mut src);
+        assert_eq!(frame.data[0], data);
+        assert_eq!(frame.plane_data(0).unwrap()[0], 7);
+        assert!(src.data[0].is_null());
+        assert_eq!(src.pts, ffi::AV_NOPTS_VALUE);
+    }
+
+    #[test]
+    fn test_typed_format() {
+        let mut frame = AVFrame::new();
+        frame.set_pixel_format(PixelFormat(ffi::AV_PIX_FMT_NV12));
+        assert_eq!(frame.format, ffi::AV_PIX_FMT_NV12);
+        assert_eq!(frame.pixel_format().to_string(), "nv12");
+
+        frame.set_sample_format(ffi::AV_SAMPLE_FMT_FLTP);
+        assert_eq!(frame.sample_format(), SampleFormat(ffi::AV_SAMPLE_FMT_FLTP));
+        assert!(frame.sample_format().is_planar());
+    }
 }
@@ -0,0 +1,319 @@
+use crate::{
+    avutil::{AVFrame, AVPixFmtDescriptorRef, PixFmtFlags},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Bounds-checked window into a plane of a video [`AVFrame`], the lines of
+/// `width` bytes are `stride` bytes apart.
+#[derive(Debug, Clone, Copy)]
+pub struct PlaneView<'frame> {
+    data: &'frame [u8],
+    stride: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<'frame> PlaneView<'frame> {
+    /// View the given plane of `frame`, `None` if it's not readable, see
+    /// [`AVFrame::plane_data()`].
+    fn new(frame: &'frame AVFrame, desc: &AVPixFmtDescriptorRef, plane: usize) -> Option<Self> {
+        let data = frame.plane_data(plane)?;
+        let stride = usize::try_from(frame.linesize[plane]).ok()?;
+        let (_, height) = desc.plane_size(plane, frame.width, frame.height)?;
+        let width = unsafe { ffi::av_image_get_linesize(frame.format, frame.width, plane as i32) };
+        let width = usize::try_from(width).ok().filter(|&x| x <= stride)?;
+        Some(Self {
+            data,
+            stride,
+            width,
+            height: usize::try_from(height).ok()?,
+        })
+    }
+
+    /// Data of the plane, including the padding at the end of each line.
+    pub fn data(&self) -> &'frame [u8] {
+        self.data
+    }
+
+    /// Distance in bytes between the start of two lines.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Number of bytes of pixel data of each line.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of lines.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Pixel data of line `y`, `None` if it's out of the plane.
+    pub fn row(&self, y: usize) -> Option<&'frame [u8]> {
+        if y >= self.height {
+            return None;
+        }
+        let start = y * self.stride;
+        Some(&self.data[start..start + self.width])
+    }
+
+    /// Iterate over the pixel data of the lines.
+    pub fn rows(&self) -> impl Iterator<Item = &'frame [u8]> {
+        let width = self.width;
+        self.data
+            .chunks(self.stride)
+            .take(self.height)
+            .map(move |row| &row[..width])
+    }
+}
+
+/// View of a planar YUV [`AVFrame`] with `PLANES` planes:
+/// - 3 for the formats with a plane per component, e.g. `yuv420p`,
+///   `yuv444p10le`.
+/// - 4 for the same with an alpha plane, e.g. `yuva420p`.
+/// - 2 for the semi-planar formats whose chroma components are interleaved,
+///   e.g. `nv12` or `p010le`.
+///
+/// The samples of the formats deeper than 8 bits take 2 bytes, see
+/// [`Self::bit_depth()`].
+///
+/// ```ignore
+/// let view = YuvView::<3>::new(&frame)?;
+/// let luma: u64 = view.y().rows().flatten().map(|&x| x as u64).sum();
+/// let chroma_height = frame.height >> view.chroma_shift().1;
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct YuvView<'frame, const PLANES: usize = 3> {
+    planes: [PlaneView<'frame>; PLANES],
+    chroma_shift: (u32, u32),
+    bit_depth: u32,
+}
+
+impl<'frame, const PLANES: usize> YuvView<'frame, PLANES> {
+    /// View `frame`, returns `AVERROR(EINVAL)` if its pixel format isn't a
+    /// YUV format with `PLANES` planes, or if it's not allocated or a
+    /// hardware frame.
+    pub fn new(frame: &'frame AVFrame) -> Result<Self> {
+        let invalid = || RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL));
+        let desc = AVPixFmtDescriptorRef::get(frame.format).ok_or_else(invalid)?;
+        let flags = desc.pix_fmt_flags();
+        let components = desc.components();
+        let layout = match PLANES {
+            2 => components.len() == 3 && components[1].plane == 1 && components[2].plane == 1,
+            3 => components.len() == 3,
+            4 => components.len() == 4 && flags.contains(PixFmtFlags::ALPHA),
+            _ => false,
+        };
+        if !layout
+            || desc.nb_planes() != PLANES
+            || !flags.contains(PixFmtFlags::PLANAR)
+            || flags.intersects(
+                PixFmtFlags::RGB
+                    | PixFmtFlags::PAL
+                    | PixFmtFlags::BITSTREAM
+                    | PixFmtFlags::HWACCEL
+                    | PixFmtFlags::BAYER,
+            )
+        {
+            return Err(invalid());
+        }
+        let mut planes = [None; PLANES];
+        for (plane, view) in planes.iter_mut().enumerate() {
+            *view = Some(PlaneView::new(frame, &desc, plane).ok_or_else(invalid)?);
+        }
+        Ok(Self {
+            planes: planes.map(Option::unwrap),
+            chroma_shift: (desc.log2_chroma_w as u32, desc.log2_chroma_h as u32),
+            bit_depth: components[0].depth as u32,
+        })
+    }
+
+    /// All the planes, in the order of the pixel format.
+    pub fn planes(&self) -> &[PlaneView<'frame>; PLANES] {
+        &self.planes
+    }
+
+    /// Luma plane.
+    pub fn y(&self) -> PlaneView<'frame> {
+        self.planes[0]
+    }
+
+    /// `(horizontal, vertical)` log2 of the chroma subsampling, e.g. `(1, 1)`
+    /// for 4:2:0.
+    pub fn chroma_shift(&self) -> (u32, u32) {
+        self.chroma_shift
+    }
+
+    /// Number of bits of each sample, samples deeper than 8 bits take 2
+    /// bytes.
+    pub fn bit_depth(&self) -> u32 {
+        self.bit_depth
+    }
+}
+
+impl<'frame> YuvView<'frame, 2> {
+    /// Interleaved chroma plane, in the order of the pixel format, e.g. VU
+    /// for `nv21`.
+    pub fn uv(&self) -> PlaneView<'frame> {
+        self.planes[1]
+    }
+}
+
+impl<'frame> YuvView<'frame, 3> {
+    /// U (Cb) plane.
+    pub fn u(&self) -> PlaneView<'frame> {
+        self.planes[1]
+    }
+
+    /// V (Cr) plane.
+    pub fn v(&self) -> PlaneView<'frame> {
+        self.planes[2]
+    }
+}
+
+impl<'frame> YuvView<'frame, 4> {
+    /// U (Cb) plane.
+    pub fn u(&self) -> PlaneView<'frame> {
+        self.planes[1]
+    }
+
+    /// V (Cr) plane.
+    pub fn v(&self) -> PlaneView<'frame> {
+        self.planes[2]
+    }
+
+    /// Alpha plane.
+    pub fn a(&self) -> PlaneView<'frame> {
+        self.planes[3]
+    }
+}
+
+/// View of a packed RGB [`AVFrame`], e.g. `rgb24` or `bgra`, whose pixels
+/// are `bytes_per_pixel()` bytes in a single plane.
+///
+/// ```ignore
+/// let view = RgbView::new(&frame)?;
+/// let [r, g, b] = view.pixel(0, 0).unwrap()[..3] else { unreachable!() };
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RgbView<'frame> {
+    plane: PlaneView<'frame>,
+    bytes_per_pixel: usize,
+}
+
+impl<'frame> RgbView<'frame> {
+    /// View `frame`, returns `AVERROR(EINVAL)` if its pixel format isn't a
+    /// packed RGB format of whole bytes per pixel, or if it's not allocated
+    /// or a hardware frame.
+    pub fn new(frame: &'frame AVFrame) -> Result<Self> {
+        let invalid = || RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL));
+        let desc = AVPixFmtDescriptorRef::get(frame.format).ok_or_else(invalid)?;
+        let flags = desc.pix_fmt_flags();
+        let bytes_per_pixel = usize::try_from(desc.components()[0].step).unwrap_or(0);
+        if !flags.contains(PixFmtFlags::RGB)
+            || flags.intersects(
+                PixFmtFlags::PLANAR
+                    | PixFmtFlags::PAL
+                    | PixFmtFlags::BITSTREAM
+                    | PixFmtFlags::HWACCEL
+                    | PixFmtFlags::BAYER,
+            )
+            || desc.nb_planes() != 1
+            || bytes_per_pixel == 0
+        {
+            return Err(invalid());
+        }
+        Ok(Self {
+            plane: PlaneView::new(frame, &desc, 0).ok_or_else(invalid)?,
+            bytes_per_pixel,
+        })
+    }
+
+    /// The plane holding the pixels.
+    pub fn plane(&self) -> PlaneView<'frame> {
+        self.plane
+    }
+
+    /// Number of bytes of each pixel, e.g. 3 for `rgb24`.
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.bytes_per_pixel
+    }
+
+    /// Bytes of the pixel at `(x, y)`, `None` if it's out of the frame.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<&'frame [u8]> {
+        let start = x.checked_mul(self.bytes_per_pixel)?;
+        self.plane.row(y)?.get(start..start + self.bytes_per_pixel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(format: ffi::AVPixelFormat, width: i32, height: i32) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_format(format);
+        frame.set_width(width);
+        frame.set_height(height);
+        frame.alloc_buffer().unwrap();
+        frame
+    }
+
+    #[test]
+    fn test_yuv_view() {
+        let frame = frame(ffi::AV_PIX_FMT_YUV420P, 7, 5);
+        let view = YuvView::<3>::new(&frame).unwrap();
+        assert_eq!(view.chroma_shift(), (1, 1));
+        assert_eq!(view.bit_depth(), 8);
+        assert_eq!((view.y().width(), view.y().height()), (7, 5));
+        assert_eq!((view.u().width(), view.u().height()), (4, 3));
+        assert_eq!((view.v().width(), view.v().height()), (4, 3));
+        assert_eq!(view.y().stride(), frame.linesize[0] as usize);
+        assert_eq!(view.y().rows().count(), 5);
+        assert!(view.y().rows().all(|x| x.len() == 7));
+        assert!(view.u().row(2).is_some());
+        assert!(view.u().row(3).is_none());
+        assert!(YuvView::<2>::new(&frame).is_err());
+        assert!(YuvView::<4>::new(&frame).is_err());
+        assert!(RgbView::new(&frame).is_err());
+
+        let frame = self::frame(ffi::AV_PIX_FMT_YUV422P10LE, 8, 2);
+        let view = YuvView::<3>::new(&frame).unwrap();
+        assert_eq!(view.bit_depth(), 10);
+        assert_eq!(view.chroma_shift(), (1, 0));
+        assert_eq!((view.u().width(), view.u().height()), (8, 2));
+
+        let frame = self::frame(ffi::AV_PIX_FMT_NV12, 4, 4);
+        let view = YuvView::<2>::new(&frame).unwrap();
+        assert_eq!((view.uv().width(), view.uv().height()), (4, 2));
+        assert!(YuvView::<3>::new(&frame).is_err());
+
+        let frame = self::frame(ffi::AV_PIX_FMT_YUVA420P, 4, 4);
+        let view = YuvView::<4>::new(&frame).unwrap();
+        assert_eq!((view.a().width(), view.a().height()), (4, 4));
+
+        assert!(YuvView::<3>::new(&AVFrame::new()).is_err());
+    }
+
+    #[test]
+    fn test_rgb_view() {
+        let mut frame = frame(ffi::AV_PIX_FMT_RGB24, 3, 2);
+        let linesize = frame.linesize[0] as usize;
+        let data = frame.plane_data_mut(0).unwrap();
+        data[linesize + 3..linesize + 6].copy_from_slice(&[1, 2, 3]);
+        let view = RgbView::new(&frame).unwrap();
+        assert_eq!(view.bytes_per_pixel(), 3);
+        assert_eq!(view.pixel(1, 1), Some(&[1, 2, 3][..]));
+        assert!(view.pixel(3, 1).is_none());
+        assert!(view.pixel(0, 2).is_none());
+
+        let frame = self::frame(ffi::AV_PIX_FMT_BGRA, 2, 2);
+        assert_eq!(RgbView::new(&frame).unwrap().bytes_per_pixel(), 4);
+        let frame = self::frame(ffi::AV_PIX_FMT_GBRP, 2, 2);
+        assert!(RgbView::new(&frame).is_err());
+        assert!(YuvView::<3>::new(&frame).is_err());
+    }
+}
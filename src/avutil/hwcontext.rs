@@ -11,11 +11,17 @@ use std::{
     ptr::{self, NonNull},
 };
 
+/// A reference to a hardware device, cloning it adds a new reference to the
+/// same device.
+#[derive(Clone)]
 #[repr(transparent)]
 pub struct AVHWDeviceContext {
-    buffer_ref: AVBufferRef,
+    pub(crate) buffer_ref: AVBufferRef,
 }
 
+// Owned-reference types used by `AVFilterContext::hw_device_ctx()`, see `AVHWFramesContextRef`.
+wrap_ref_pure!((AVHWDeviceContext, AVHWDeviceContextRef): ffi::AVBufferRef);
+
 impl AVHWDeviceContext {
     /// Allocate an [`AVHWDeviceContext`] for a given hardware type.
     pub fn alloc(r#type: ffi::AVHWDeviceType) -> Self {
@@ -131,6 +137,20 @@ impl AVHWDeviceContext {
         }
     }
 
+    /// Type of the device.
+    pub fn device_type(&self) -> ffi::AVHWDeviceType {
+        unsafe { (*(self.buffer_ref.data as *const ffi::AVHWDeviceContext)).type_ }
+    }
+
+    /// # Safety
+    ///
+    /// This function is only save when given `raw` points to a valid AVHWDeviceContext.
+    pub unsafe fn from_raw(raw: NonNull<ffi::AVBufferRef>) -> Self {
+        Self {
+            buffer_ref: unsafe { AVBufferRef::from_raw(raw) },
+        }
+    }
+
     /// Consume self and get the underlying buffer ref.
     pub fn into_inner(self) -> AVBufferRef {
         self.buffer_ref
@@ -185,6 +205,20 @@ impl AVHWFramesContext {
         unsafe { &mut *(self.buffer_ref.data as *mut ffi::AVHWFramesContext) }
     }
 
+    /// Get the initial size of the frame pool, 0 if the pool is dynamically
+    /// sized.
+    pub fn initial_pool_size(&self) -> i32 {
+        unsafe { (*(self.buffer_ref.data as *const ffi::AVHWFramesContext)).initial_pool_size }
+    }
+
+    /// Set the initial size of the frame pool, should be called before
+    /// [`Self::init()`]. Required by the device types with fixed size pools
+    /// (e.g. D3D11 and QSV), where [`Self::get_buffer()`] fails once all the
+    /// frames are in use.
+    pub fn set_initial_pool_size(&mut self, size: i32) {
+        self.data().initial_pool_size = size;
+    }
+
     /// Allocate a new frame attached to the current AVHWFramesContext.
     ///
     /// `frame`: an empty (freshly allocated or unreffed) frame to be filled with newly allocated buffers.
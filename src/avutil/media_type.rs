@@ -1,9 +1,29 @@
+use crate::shared::PointerUpgrade;
 use rusty_ffmpeg::ffi;
+use std::{ffi::CStr, fmt};
 
 /// non exhaustive wrapper of AVMediaType
+///
+/// ```rust
+/// # use rsmpeg::{avutil::AVMediaType, ffi};
+/// let media_type = AVMediaType::from(ffi::AVMEDIA_TYPE_AUDIO);
+/// assert!(media_type.is_audio());
+/// assert_eq!(media_type.to_string(), "audio");
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct AVMediaType(pub ffi::AVMediaType);
 
 impl AVMediaType {
+    /// Return a string describing the media type, or `None` if it's unknown.
+    pub fn name(&self) -> Option<&'static CStr> {
+        unsafe {
+            ffi::av_get_media_type_string(self.0)
+                .upgrade()
+                .map(|x| CStr::from_ptr(x.as_ptr()))
+        }
+    }
+
     /// A video codec
     pub fn is_video(&self) -> bool {
         self.0 == ffi::AVMEDIA_TYPE_VIDEO
@@ -29,3 +49,24 @@ impl AVMediaType {
         self.0 == ffi::AVMEDIA_TYPE_ATTACHMENT
     }
 }
+
+impl From<ffi::AVMediaType> for AVMediaType {
+    fn from(media_type: ffi::AVMediaType) -> Self {
+        Self(media_type)
+    }
+}
+
+impl From<AVMediaType> for ffi::AVMediaType {
+    fn from(media_type: AVMediaType) -> Self {
+        media_type.0
+    }
+}
+
+impl fmt::Display for AVMediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(&name.to_string_lossy()),
+            None => f.write_str("unknown"),
+        }
+    }
+}
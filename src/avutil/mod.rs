@@ -1,11 +1,16 @@
 //! Everything related to `libavutil`.
+mod adler32;
 mod audio_fifo;
+mod base64;
 mod buffer;
 mod channel_layout;
+mod crc;
 mod dict;
+mod display;
 mod error;
 mod file;
 mod frame;
+mod frame_view;
 mod hwcontext;
 mod imgutils;
 mod media_type;
@@ -16,16 +21,22 @@ mod pixdesc;
 mod pixfmt;
 mod rational;
 mod samplefmt;
+mod timecode;
 mod timestamp;
 mod utils;
 
+pub use adler32::*;
 pub use audio_fifo::*;
+pub use base64::*;
 pub use buffer::*;
 pub use channel_layout::*;
+pub use crc::*;
 pub use dict::*;
+pub use display::*;
 pub use error::*;
 pub use file::*;
 pub use frame::*;
+pub use frame_view::*;
 pub use hwcontext::*;
 pub use imgutils::*;
 pub use media_type::*;
@@ -36,5 +47,6 @@ pub use pixdesc::*;
 pub use pixfmt::*;
 pub use rational::*;
 pub use samplefmt::*;
+pub use timecode::*;
 pub use timestamp::*;
 pub use utils::*;
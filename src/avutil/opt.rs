@@ -2,9 +2,12 @@ use crate::{
     error::Result,
     ffi,
     ffi::{AVPixelFormat, AVRational, AVSampleFormat},
-    shared::RetUpgrade,
+    shared::{PointerUpgrade, RetUpgrade},
+};
+use std::{
+    ffi::{c_double, c_int, c_void, CStr},
+    ptr,
 };
-use std::ffi::{c_double, c_int, c_void, CStr};
 
 /// - `name`: the name of the field to set
 /// - `val`: if the field is not of a string type, then the given string is parsed.
@@ -155,3 +158,82 @@ pub unsafe fn opt_set_video_rate(
     unsafe { ffi::av_opt_set_video_rate(obj, name.as_ptr(), val, search_flags) }.upgrade()?;
     Ok(())
 }
+
+/// Named constant accepted by an option, e.g. the `p4` preset of the NVENC
+/// encoders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AVOptionConst<'a> {
+    pub name: &'a CStr,
+    pub help: Option<&'a CStr>,
+    pub value: i64,
+}
+
+/// Iterate over the options of `class`, named constants included.
+///
+/// # Safety
+///
+/// `class` should be null or point to an AVClass living for `'a`.
+unsafe fn opt_iter<'a>(class: *const ffi::AVClass) -> impl Iterator<Item = &'a ffi::AVOption> {
+    let mut prev = ptr::null();
+    std::iter::from_fn(move || {
+        if class.is_null() {
+            return None;
+        }
+        // `AV_OPT_SEARCH_FAKE_OBJ` style object: a pointer to the AVClass pointer.
+        let fake_obj = &class as *const *const ffi::AVClass as *const c_void;
+        let option = unsafe { ffi::av_opt_next(fake_obj, prev) };
+        prev = option;
+        unsafe { option.as_ref() }
+    })
+}
+
+/// Return the names of the options of `class`, without the named constants.
+///
+/// # Safety
+///
+/// `class` should be null or point to an AVClass living for `'a`.
+pub unsafe fn opt_names<'a>(class: *const ffi::AVClass) -> Vec<&'a CStr> {
+    unsafe { opt_iter(class) }
+        .filter(|option| option.type_ != ffi::AV_OPT_TYPE_CONST)
+        .map(|option| unsafe { CStr::from_ptr(option.name) })
+        .collect()
+}
+
+/// Return the named constants accepted by the option `name` of `class`, e.g.
+/// the presets of an encoder. `None` if there is no such option, the list is
+/// empty if the option has no named constant, like the free form `preset`
+/// string of libx264 which is checked by the library itself.
+///
+/// # Safety
+///
+/// `class` should be null or point to an AVClass living for `'a`.
+pub unsafe fn opt_named_consts<'a>(
+    class: *const ffi::AVClass,
+    name: &CStr,
+) -> Option<Vec<AVOptionConst<'a>>> {
+    let option = unsafe { opt_iter(class) }.find(|option| {
+        option.type_ != ffi::AV_OPT_TYPE_CONST && unsafe { CStr::from_ptr(option.name) } == name
+    })?;
+    let Some(unit) = option.unit.upgrade() else {
+        return Some(vec![]);
+    };
+    let unit = unsafe { CStr::from_ptr(unit.as_ptr()) };
+    let consts = unsafe { opt_iter(class) }
+        .filter(|x| {
+            x.type_ == ffi::AV_OPT_TYPE_CONST
+                && x.unit
+                    .upgrade()
+                    .is_some_and(|x| unsafe { CStr::from_ptr(x.as_ptr()) } == unit)
+        })
+        .map(|x| AVOptionConst {
+            name: unsafe { CStr::from_ptr(x.name) },
+            help: x
+                .help
+                .upgrade()
+                .map(|x| unsafe { CStr::from_ptr(x.as_ptr()) }),
+            // Named constants always store their value in `i64`.
+            value: unsafe { x.default_val.i64_ },
+        })
+        .collect();
+    Some(consts)
+}
@@ -1,14 +1,49 @@
-use crate::{ffi, shared::PointerUpgrade};
+use crate::{
+    ffi,
+    shared::{PointerUpgrade, RetUpgrade},
+};
 
 use std::{ffi::CStr, ops::Deref, ptr::NonNull};
 
 pub use ffi::AVComponentDescriptor;
 
+bitflags::bitflags! {
+    /// Flags of a [`AVPixFmtDescriptorRef`], the `AV_PIX_FMT_FLAG_*` constants.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct PixFmtFlags: u64 {
+        /// Pixel format is big-endian.
+        const BE = ffi::AV_PIX_FMT_FLAG_BE as u64;
+        /// Pixel format has a palette in data[1], values are indexes in this
+        /// palette.
+        const PAL = ffi::AV_PIX_FMT_FLAG_PAL as u64;
+        /// All values of a component are bit-wise packed end to end.
+        const BITSTREAM = ffi::AV_PIX_FMT_FLAG_BITSTREAM as u64;
+        /// Pixel format is an HW accelerated format.
+        const HWACCEL = ffi::AV_PIX_FMT_FLAG_HWACCEL as u64;
+        /// At least one pixel component is not in the first data plane.
+        const PLANAR = ffi::AV_PIX_FMT_FLAG_PLANAR as u64;
+        /// The pixel format contains RGB-like data (as opposed to YUV/grayscale).
+        const RGB = ffi::AV_PIX_FMT_FLAG_RGB as u64;
+        /// The pixel format has an alpha channel.
+        const ALPHA = ffi::AV_PIX_FMT_FLAG_ALPHA as u64;
+        /// The pixel format is following a Bayer pattern.
+        const BAYER = ffi::AV_PIX_FMT_FLAG_BAYER as u64;
+        /// The pixel format contains IEEE-754 floating point values.
+        const FLOAT = ffi::AV_PIX_FMT_FLAG_FLOAT as u64;
+
+        const _ = !0;
+    }
+}
+
 /// Descriptor that unambiguously describes how the bits of a pixel are
 /// stored in the up to 4 data planes of an image. It also stores the
 /// subsampling factors and number of components.
 pub struct AVPixFmtDescriptorRef(NonNull<ffi::AVPixFmtDescriptor>);
 
+// The descriptors are static and immutable.
+unsafe impl Send for AVPixFmtDescriptorRef {}
+unsafe impl Sync for AVPixFmtDescriptorRef {}
+
 impl Deref for AVPixFmtDescriptorRef {
     type Target = ffi::AVPixFmtDescriptor;
     fn deref(&self) -> &Self::Target {
@@ -56,6 +91,68 @@ impl AVPixFmtDescriptorRef {
             .upgrade()
             .map(|x| unsafe { CStr::from_ptr(x.as_ptr()) })
     }
+
+    /// Get the `flags` field as [`PixFmtFlags`].
+    pub fn pix_fmt_flags(&self) -> PixFmtFlags {
+        PixFmtFlags::from_bits_retain(self.flags)
+    }
+
+    /// At least one pixel component is not in the first data plane.
+    pub fn is_planar(&self) -> bool {
+        self.pix_fmt_flags().contains(PixFmtFlags::PLANAR)
+    }
+
+    /// The pixel format contains RGB-like data.
+    pub fn is_rgb(&self) -> bool {
+        self.pix_fmt_flags().contains(PixFmtFlags::RGB)
+    }
+
+    /// The pixel format has an alpha channel.
+    pub fn has_alpha(&self) -> bool {
+        self.pix_fmt_flags().contains(PixFmtFlags::ALPHA)
+    }
+
+    /// The pixel format is an HW accelerated format, its data isn't stored
+    /// in memory.
+    pub fn is_hwaccel(&self) -> bool {
+        self.pix_fmt_flags().contains(PixFmtFlags::HWACCEL)
+    }
+
+    /// Get the descriptors of the `nb_components` components.
+    pub fn components(&self) -> &[AVComponentDescriptor] {
+        &self.comp[..self.nb_components as usize]
+    }
+
+    /// Return the number of bits per pixel used by the pixel format, padding
+    /// bits excluded.
+    pub fn bits_per_pixel(&self) -> i32 {
+        unsafe { ffi::av_get_bits_per_pixel(self.0.as_ptr()) }
+    }
+
+    /// Return the number of planes of the pixel format.
+    pub fn nb_planes(&self) -> usize {
+        unsafe { ffi::av_pix_fmt_count_planes(self.get_id()) }
+            .upgrade()
+            .map_or(0, |x| x as usize)
+    }
+
+    /// Return `(width, height)` in pixels of the given plane of an image of
+    /// `width` x `height`, chroma planes are subsampled. Return `None` if the
+    /// pixel format has no such plane.
+    pub fn plane_size(&self, plane: usize, width: i32, height: i32) -> Option<(i32, i32)> {
+        if plane >= self.nb_planes() {
+            return None;
+        }
+        // Mirrors `av_image_fill_plane_sizes()`, alpha isn't subsampled.
+        if (plane == 1 || plane == 2) && !self.pix_fmt_flags().contains(PixFmtFlags::PAL) {
+            Some((
+                -((-width) >> self.log2_chroma_w),
+                -((-height) >> self.log2_chroma_h),
+            ))
+        } else {
+            Some((width, height))
+        }
+    }
 }
 
 #[cfg(test)]
@@ -110,4 +207,28 @@ mod tests {
         let pix_fmt_desc = AVPixFmtDescriptorRef::get(pix_fmt).unwrap();
         assert_eq!(pix_fmt_desc.get_id(), pix_fmt);
     }
+
+    #[test]
+    fn test_pix_fmt_flags() {
+        let yuva = AVPixFmtDescriptorRef::get(ffi::AV_PIX_FMT_YUVA420P).unwrap();
+        assert!(yuva.is_planar());
+        assert!(yuva.has_alpha());
+        assert!(!yuva.is_rgb());
+        assert!(!yuva.is_hwaccel());
+        assert_eq!(yuva.components().len(), 4);
+        assert_eq!(yuva.bits_per_pixel(), 20);
+        assert_eq!(yuva.nb_planes(), 4);
+        assert_eq!(yuva.plane_size(0, 101, 51), Some((101, 51)));
+        assert_eq!(yuva.plane_size(1, 101, 51), Some((51, 26)));
+        assert_eq!(yuva.plane_size(3, 101, 51), Some((101, 51)));
+        assert_eq!(yuva.plane_size(4, 101, 51), None);
+
+        let rgb24 = AVPixFmtDescriptorRef::get(ffi::AV_PIX_FMT_RGB24).unwrap();
+        assert_eq!(rgb24.pix_fmt_flags(), PixFmtFlags::RGB);
+        assert_eq!(rgb24.nb_planes(), 1);
+        assert!(rgb24.components().iter().all(|x| x.depth == 8));
+
+        let vaapi = AVPixFmtDescriptorRef::get(ffi::AV_PIX_FMT_VAAPI).unwrap();
+        assert!(vaapi.is_hwaccel());
+    }
 }
@@ -1 +1,84 @@
+use crate::{avutil::AVPixFmtDescriptorRef, ffi, shared::PointerUpgrade};
+use std::{ffi::CStr, fmt};
+
 pub use crate::ffi::AVPixelFormat;
+
+/// Typed wrapper of [`AVPixelFormat`], convertible from and into the bare
+/// `i32` so it can be passed wherever a pixel format is expected.
+///
+/// ```rust
+/// # use rsmpeg::{avutil::PixelFormat, ffi};
+/// # use cstr::cstr;
+/// let pix_fmt = PixelFormat::from_name(cstr!("yuv420p")).unwrap();
+/// assert_eq!(pix_fmt, PixelFormat::from(ffi::AV_PIX_FMT_YUV420P));
+/// assert_eq!(pix_fmt.to_string(), "yuv420p");
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PixelFormat(pub AVPixelFormat);
+
+impl PixelFormat {
+    pub const NONE: Self = Self(ffi::AV_PIX_FMT_NONE);
+
+    /// Return the pixel format corresponding to name, or `None` if it's not
+    /// recognized.
+    pub fn from_name(name: &CStr) -> Option<Self> {
+        let pix_fmt = unsafe { ffi::av_get_pix_fmt(name.as_ptr()) };
+        (pix_fmt != ffi::AV_PIX_FMT_NONE).then_some(Self(pix_fmt))
+    }
+
+    /// Return the short name of the pixel format, or `None` if it's unknown.
+    pub fn name(&self) -> Option<&'static CStr> {
+        unsafe {
+            ffi::av_get_pix_fmt_name(self.0)
+                .upgrade()
+                .map(|x| CStr::from_ptr(x.as_ptr()))
+        }
+    }
+
+    /// Return the descriptor of the pixel format, or `None` if it's unknown.
+    pub fn descriptor(&self) -> Option<AVPixFmtDescriptorRef> {
+        AVPixFmtDescriptorRef::get(self.0)
+    }
+}
+
+impl From<AVPixelFormat> for PixelFormat {
+    fn from(pix_fmt: AVPixelFormat) -> Self {
+        Self(pix_fmt)
+    }
+}
+
+impl From<PixelFormat> for AVPixelFormat {
+    fn from(pix_fmt: PixelFormat) -> Self {
+        pix_fmt.0
+    }
+}
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(&name.to_string_lossy()),
+            None => f.write_str("none"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_pixel_format() {
+        let pix_fmt = PixelFormat::from(ffi::AV_PIX_FMT_RGB24);
+        assert_eq!(pix_fmt.name(), Some(cstr!("rgb24")));
+        assert_eq!(pix_fmt.descriptor().unwrap().nb_components, 3);
+        assert_eq!(PixelFormat::from_name(cstr!("rgb24")), Some(pix_fmt));
+        assert_eq!(i32::from(pix_fmt), ffi::AV_PIX_FMT_RGB24);
+
+        assert_eq!(PixelFormat::from_name(cstr!("not_a_format")), None);
+        assert_eq!(PixelFormat::NONE.name(), None);
+        assert!(PixelFormat::NONE.descriptor().is_none());
+        assert_eq!(PixelFormat::NONE.to_string(), "none");
+    }
+}
@@ -1,4 +1,8 @@
-use std::os::raw::c_int;
+use std::{
+    cmp::Ordering,
+    ops::{Add, Div, Mul, Neg, Sub},
+    os::raw::c_int,
+};
 
 use crate::ffi;
 
@@ -86,3 +90,205 @@ pub fn av_rescale_q(a: i64, bq: AVRational, cq: AVRational) -> i64 {
 pub fn av_rescale_q_rnd(a: i64, bq: AVRational, cq: AVRational, rnd: u32) -> i64 {
     unsafe { ffi::av_rescale_q_rnd(a, bq, cq, rnd as _) }
 }
+
+/// Rescale a 64-bit integer with rounding to nearest.
+///
+/// The operation is mathematically equivalent to `a * b / c`, but writing that
+/// directly can overflow.
+#[inline]
+pub fn av_rescale(a: i64, b: i64, c: i64) -> i64 {
+    unsafe { ffi::av_rescale(a, b, c) }
+}
+
+/// Rescale a 64-bit integer with specified rounding, e.g.
+/// `AV_ROUND_UP | AV_ROUND_PASS_MINMAX`.
+///
+/// The operation is mathematically equivalent to `a * b / c`, but writing that
+/// directly can overflow.
+#[inline]
+pub fn av_rescale_rnd(a: i64, b: i64, c: i64, rnd: u32) -> i64 {
+    unsafe { ffi::av_rescale_rnd(a, b, c, rnd as _) }
+}
+
+/// [`AVRational`] with arithmetic operators and comparison, e.g. for
+/// timestamp math. Arithmetic is done by FFmpeg, so results are reduced and
+/// clipped to 32-bit numerator and denominator like [`av_mul_q`].
+///
+/// Comparing a `0/0` rational returns `None`, it's neither equal to nor
+/// ordered with anything.
+#[derive(Debug, Clone, Copy)]
+pub struct Rational(pub AVRational);
+
+impl Rational {
+    /// return `Rational: num / den`;
+    pub const fn new(num: i32, den: i32) -> Self {
+        Self(ra(num, den))
+    }
+
+    /// Convert a double precision floating point number, see [`av_d2q`].
+    pub fn from_f64(d: f64, max: c_int) -> Self {
+        Self(av_d2q(d, max))
+    }
+
+    pub const fn num(self) -> i32 {
+        self.0.num
+    }
+
+    pub const fn den(self) -> i32 {
+        self.0.den
+    }
+
+    /// Convert to a double, see [`av_q2d`].
+    pub fn to_f64(self) -> f64 {
+        av_q2d(self.0)
+    }
+
+    /// Returns `1 / self`.
+    pub const fn inv(self) -> Self {
+        Self(av_inv_q(self.0))
+    }
+
+    /// Returns true if `self` is nearer to `q` than `other`, see
+    /// [`av_nearer_q`].
+    pub fn is_nearer_to(self, q: Rational, other: Rational) -> bool {
+        av_nearer_q(q.0, self.0, other.0) > 0
+    }
+
+    /// Rescale `ts` from the time base `self` to the time base `to`, rounding
+    /// to nearest.
+    pub fn rescale(self, ts: i64, to: Rational) -> i64 {
+        av_rescale_q(ts, self.0, to.0)
+    }
+}
+
+impl From<AVRational> for Rational {
+    fn from(q: AVRational) -> Self {
+        Self(q)
+    }
+}
+
+impl From<Rational> for AVRational {
+    fn from(q: Rational) -> Self {
+        q.0
+    }
+}
+
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.0.num, self.0.den)
+    }
+}
+
+impl PartialEq for Rational {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match av_cmp_q(self.0, other.0) {
+            0 => Some(Ordering::Equal),
+            c_int::MIN => None,
+            x if x < 0 => Some(Ordering::Less),
+            _ => Some(Ordering::Greater),
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(av_add_q(self.0, rhs.0))
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(av_sub_q(self.0, rhs.0))
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self(av_mul_q(self.0, rhs.0))
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        Self(av_div_q(self.0, rhs.0))
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.0.num, self.0.den)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rational_arithmetic() {
+        let a = Rational::new(1, 3);
+        let b = Rational::new(1, 6);
+        assert_eq!(a + b, Rational::new(1, 2));
+        assert_eq!((a + b).num(), 1);
+        assert_eq!((a + b).den(), 2);
+        assert_eq!(a - b, b);
+        assert_eq!(a * b, Rational::new(1, 18));
+        assert_eq!(a / b, Rational::new(2, 1));
+        assert_eq!(-a, Rational::new(-1, 3));
+        assert_eq!(a.inv(), Rational::new(3, 1));
+        assert_eq!(Rational::new(2, 6), a);
+        assert_eq!(a.to_string(), "1/3");
+        assert_eq!(Rational::from_f64(0.5, 100), Rational::new(1, 2));
+        assert!((a.to_f64() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rational_comparison() {
+        let a = Rational::new(1, 3);
+        let b = Rational::new(1, 6);
+        assert!(b < a);
+        assert!(Rational::new(-1, 3) < b);
+        assert!(Rational::new(1, 0) > a);
+        let undefined = Rational::new(0, 0);
+        assert_eq!(undefined.partial_cmp(&a), None);
+        assert_ne!(undefined, undefined);
+
+        assert!(Rational::new(1, 4).is_nearer_to(b, a));
+        assert!(!a.is_nearer_to(b, Rational::new(1, 4)));
+    }
+
+    #[test]
+    fn test_rescale() {
+        assert_eq!(av_rescale(3, 1, 2), 2);
+        assert_eq!(av_rescale_rnd(3, 1, 2, ffi::AV_ROUND_DOWN), 1);
+        assert_eq!(av_rescale_rnd(3, 1, 2, ffi::AV_ROUND_UP), 2);
+        assert_eq!(
+            av_rescale_rnd(
+                ffi::AV_NOPTS_VALUE,
+                1,
+                2,
+                ffi::AV_ROUND_UP | ffi::AV_ROUND_PASS_MINMAX
+            ),
+            ffi::AV_NOPTS_VALUE
+        );
+        assert_eq!(
+            av_rescale_q_rnd(1, ra(1, 3), ra(1, 2), ffi::AV_ROUND_DOWN),
+            0
+        );
+        assert_eq!(
+            Rational::new(1, 1000).rescale(1500, Rational::new(1, 90000)),
+            135000
+        );
+    }
+}
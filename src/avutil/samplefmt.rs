@@ -1,6 +1,7 @@
 use crate::{ffi, shared::*};
 use std::{
     ffi::CStr,
+    fmt,
     num::NonZeroI32,
     ptr::{self, NonNull},
 };
@@ -108,6 +109,160 @@ pub fn sample_fmt_is_planar(sample_fmt: AVSampleFormat) -> bool {
     unsafe { ffi::av_sample_fmt_is_planar(sample_fmt) == 1 }
 }
 
+/// Typed wrapper of [`AVSampleFormat`], convertible from and into the bare
+/// `i32` so it can be passed wherever a sample format is expected.
+///
+/// ```rust
+/// # use rsmpeg::{avutil::SampleFormat, ffi};
+/// # use cstr::cstr;
+/// let sample_fmt = SampleFormat::from_name(cstr!("s16p")).unwrap();
+/// assert_eq!(sample_fmt, SampleFormat::from(ffi::AV_SAMPLE_FMT_S16P));
+/// assert_eq!(sample_fmt.to_string(), "s16p");
+/// assert!(sample_fmt.is_planar());
+/// assert_eq!(sample_fmt.packed(), Some(SampleFormat(ffi::AV_SAMPLE_FMT_S16)));
+/// assert_eq!(sample_fmt.bytes_per_sample(), Some(2));
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SampleFormat(pub AVSampleFormat);
+
+impl SampleFormat {
+    pub const NONE: Self = Self(ffi::AV_SAMPLE_FMT_NONE);
+
+    /// Return the sample format corresponding to name, or `None` if it's not
+    /// recognized.
+    pub fn from_name(name: &CStr) -> Option<Self> {
+        get_sample_fmt(name).map(Self)
+    }
+
+    /// Return the name of the sample format, or `None` if it's unknown.
+    pub fn name(&self) -> Option<&'static CStr> {
+        get_sample_fmt_name(self.0)
+    }
+
+    /// Get the packed alternative form of the sample format.
+    pub fn packed(&self) -> Option<Self> {
+        get_packed_sample_fmt(self.0).map(Self)
+    }
+
+    /// Get the planar alternative form of the sample format.
+    pub fn planar(&self) -> Option<Self> {
+        get_planar_sample_fmt(self.0).map(Self)
+    }
+
+    /// Return number of bytes per sample, `None` when the sample format is
+    /// unknown.
+    pub fn bytes_per_sample(&self) -> Option<usize> {
+        get_bytes_per_sample(self.0)
+    }
+
+    /// Check if the sample format is planar.
+    pub fn is_planar(&self) -> bool {
+        sample_fmt_is_planar(self.0)
+    }
+}
+
+impl From<AVSampleFormat> for SampleFormat {
+    fn from(sample_fmt: AVSampleFormat) -> Self {
+        Self(sample_fmt)
+    }
+}
+
+impl From<SampleFormat> for AVSampleFormat {
+    fn from(sample_fmt: SampleFormat) -> Self {
+        sample_fmt.0
+    }
+}
+
+impl fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(&name.to_string_lossy()),
+            None => f.write_str("none"),
+        }
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Rust type of the samples of a packed sample format and of its planar
+/// form, e.g. `f32` for `AV_SAMPLE_FMT_FLT` and `AV_SAMPLE_FMT_FLTP`.
+///
+/// Used to access the audio data of a frame as typed slices, see
+/// [`AVFrame::samples()`](crate::avutil::AVFrame::samples).
+pub trait Sample: Copy + private::Sealed {
+    /// The packed sample format whose samples are of this type.
+    const SAMPLE_FMT: AVSampleFormat;
+}
+
+macro_rules! impl_sample {
+    ($($ty:ty => $sample_fmt:ident),* $(,)?) => {
+        $(
+            impl private::Sealed for $ty {}
+            impl Sample for $ty {
+                const SAMPLE_FMT: AVSampleFormat = ffi::$sample_fmt;
+            }
+        )*
+    };
+}
+
+impl_sample! {
+    u8 => AV_SAMPLE_FMT_U8,
+    i16 => AV_SAMPLE_FMT_S16,
+    i32 => AV_SAMPLE_FMT_S32,
+    i64 => AV_SAMPLE_FMT_S64,
+    f32 => AV_SAMPLE_FMT_FLT,
+    f64 => AV_SAMPLE_FMT_DBL,
+}
+
+/// Interleave the samples of one plane per channel, as stored by planar
+/// sample formats, into the layout of the packed formats.
+///
+/// # Panics
+/// Panics if the planes don't have the same length.
+///
+/// ```rust
+/// # use rsmpeg::avutil::interleave;
+/// let left = [1i16, 2, 3];
+/// let right = [-1i16, -2, -3];
+/// assert_eq!(interleave(&[&left, &right]), [1, -1, 2, -2, 3, -3]);
+/// ```
+pub fn interleave<T: Sample>(planes: &[&[T]]) -> Vec<T> {
+    let nb_samples = planes.first().map_or(0, |x| x.len());
+    assert!(
+        planes.iter().all(|x| x.len() == nb_samples),
+        "planes of different lengths"
+    );
+    (0..nb_samples)
+        .flat_map(|i| planes.iter().map(move |x| x[i]))
+        .collect()
+}
+
+/// Split packed samples of `nb_channels` channels into one plane per
+/// channel, as stored by planar sample formats. An incomplete trailing group
+/// of samples is dropped.
+///
+/// # Panics
+/// Panics if `nb_channels` is 0.
+///
+/// ```rust
+/// # use rsmpeg::avutil::deinterleave;
+/// let planes = deinterleave(&[1.0f32, -1.0, 2.0, -2.0], 2);
+/// assert_eq!(planes, [[1.0, 2.0], [-1.0, -2.0]]);
+/// ```
+pub fn deinterleave<T: Sample>(samples: &[T], nb_channels: usize) -> Vec<Vec<T>> {
+    assert!(nb_channels > 0, "no channel");
+    let mut planes = vec![Vec::with_capacity(samples.len() / nb_channels); nb_channels];
+    for group in samples.chunks_exact(nb_channels) {
+        for (plane, &sample) in planes.iter_mut().zip(group) {
+            plane.push(sample);
+        }
+    }
+    planes
+}
+
 // The `nb_samples` of `AVSamples` is the capacity rather than length.
 // `nb_channels` and `audio_data.len()`(which is nb_planes) is only the same
 // when the audio sample format in planar.
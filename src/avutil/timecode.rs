@@ -0,0 +1,150 @@
+use std::{
+    ffi::{CStr, CString},
+    fmt,
+    os::raw::c_char,
+    ptr,
+};
+
+use crate::{avutil::AVRational, error::*, ffi, shared::*};
+
+/// SMPTE timecode of a frame rate, mapping frame numbers to `HH:MM:SS:FF`
+/// strings (`HH:MM:SS;FF` for drop frame timecodes), wrapping
+/// [`ffi::AVTimecode`].
+///
+/// ```ignore
+/// let timecode = Timecode::from_string(ra(30000, 1001), "01:00:00;00")?;
+/// assert!(timecode.is_drop_frame());
+/// assert_eq!(timecode.to_string_at(1800), "01:01:00;02");
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct Timecode(ffi::AVTimecode);
+
+impl Timecode {
+    /// Timecode of `rate` whose first frame is `start_frame`, counted from
+    /// `00:00:00:00`.
+    ///
+    /// Returns `AVERROR(EINVAL)` if the frame rate isn't supported, or if
+    /// drop frame is requested for a rate other than 30000/1001 or
+    /// 60000/1001.
+    pub fn new(rate: AVRational, drop_frame: bool, start_frame: i32) -> Result<Self> {
+        let flags = if drop_frame {
+            ffi::AV_TIMECODE_FLAG_DROPFRAME as i32
+        } else {
+            0
+        };
+        let mut tc = Self::zeroed();
+        unsafe { ffi::av_timecode_init(&mut tc.0, rate, flags, start_frame, ptr::null_mut()) }
+            .upgrade()?;
+        Ok(tc)
+    }
+
+    /// Parse the `HH:MM:SS:FF` timecode of the first frame of a `rate`
+    /// stream, a `;` or `.` before the frames means drop frame.
+    pub fn from_string(rate: AVRational, timecode: &str) -> Result<Self> {
+        let timecode =
+            CString::new(timecode).map_err(|_| RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        let mut tc = Self::zeroed();
+        unsafe {
+            ffi::av_timecode_init_from_string(&mut tc.0, rate, timecode.as_ptr(), ptr::null_mut())
+        }
+        .upgrade()?;
+        Ok(tc)
+    }
+
+    fn zeroed() -> Self {
+        Self(ffi::AVTimecode {
+            start: 0,
+            flags: 0,
+            rate: AVRational { num: 0, den: 1 },
+            fps: 0,
+        })
+    }
+
+    /// Frame rate of the timecode.
+    pub fn rate(&self) -> AVRational {
+        self.0.rate
+    }
+
+    /// Frame number of the first frame, counted from `00:00:00:00`.
+    pub fn start_frame(&self) -> i32 {
+        self.0.start
+    }
+
+    /// Whether frame numbers are skipped to keep the timecode in sync with
+    /// the wall clock at 29.97 or 59.94 fps.
+    pub fn is_drop_frame(&self) -> bool {
+        self.0.flags & ffi::AV_TIMECODE_FLAG_DROPFRAME != 0
+    }
+
+    /// Timecode string of frame `framenum`, relative to the first frame.
+    pub fn to_string_at(&self, framenum: i32) -> String {
+        let mut buf = [0 as c_char; ffi::AV_TIMECODE_STR_SIZE as usize];
+        unsafe { ffi::av_timecode_make_string(&self.0, buf.as_mut_ptr(), framenum) };
+        unsafe { CStr::from_ptr(buf.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Frame number of `timecode`, relative to the first frame, e.g. to
+    /// align clips on the timecodes of an edit decision list. Negative if
+    /// it's before the first frame.
+    pub fn frame_number(&self, timecode: &str) -> Result<i64> {
+        let other = Self::from_string(self.0.rate, timecode)?;
+        Ok(other.0.start as i64 - self.0.start as i64)
+    }
+
+    /// The raw [`ffi::AVTimecode`].
+    pub fn as_raw(&self) -> &ffi::AVTimecode {
+        &self.0
+    }
+}
+
+impl From<Timecode> for ffi::AVTimecode {
+    fn from(tc: Timecode) -> Self {
+        tc.0
+    }
+}
+
+impl fmt::Debug for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timecode")
+            .field("start", &self.to_string_at(0))
+            .field("rate", &(self.0.rate.num, self.0.rate.den))
+            .finish()
+    }
+}
+
+impl fmt::Display for Timecode {
+    /// The timecode of the first frame.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_at(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avutil::ra;
+
+    #[test]
+    fn test_timecode() {
+        let tc = Timecode::from_string(ra(25, 1), "01:00:00:00").unwrap();
+        assert!(!tc.is_drop_frame());
+        assert_eq!(tc.start_frame(), 25 * 3600);
+        assert_eq!(tc.to_string(), "01:00:00:00");
+        assert_eq!(tc.to_string_at(26), "01:00:01:01");
+        assert_eq!(tc.frame_number("01:00:01:01").unwrap(), 26);
+        assert_eq!(tc.frame_number("00:59:59:24").unwrap(), -1);
+        assert!(tc.frame_number("garbage").is_err());
+
+        let tc = Timecode::from_string(ra(30000, 1001), "00:00:59;29").unwrap();
+        assert!(tc.is_drop_frame());
+        // Frames 0 and 1 of each minute but every tenth are skipped.
+        assert_eq!(tc.to_string_at(1), "00:01:00;02");
+
+        let tc = Timecode::new(ra(24, 1), false, 24).unwrap();
+        assert_eq!(tc.to_string(), "00:00:01:00");
+        assert!(Timecode::new(ra(24, 1), true, 0).is_err());
+    }
+}
@@ -6,29 +6,33 @@ use std::{
 };
 use thiserror::Error;
 
-use crate::{avutil::err2str, ffi, shared::AVERROR_EAGAIN};
+use crate::{
+    avutil::err2str,
+    ffi,
+    shared::{AVERROR_EAGAIN, AVERROR_ENOMEM},
+};
 
 /// All the error variants of rsmpeg.
 #[non_exhaustive]
 #[derive(Error, Debug, Eq, PartialEq)]
 pub enum RsmpegError {
-    #[error("AVERROR({0}): `{}`", err2str(*.0).unwrap_or_else(|| "Unknown error code.".to_string()))]
+    #[error("AVERROR({0}): `{}`", err_msg(*.0))]
     AVError(c_int),
 
     // --------- Unstablized error type below ------
 
     // FFmpeg errors
-    #[error("Cannot open input file. ({0})")]
+    #[error("Cannot open input file. ({0}: `{}`)", err_msg(*.0))]
     OpenInputError(c_int),
-    #[error("Cannot find stream information. ({0})")]
+    #[error("Cannot find stream information. ({0}: `{}`)", err_msg(*.0))]
     FindStreamInfoError(c_int),
 
     // Decoder errors
-    #[error("Send packet to a codec context failed. ({0})")]
+    #[error("Send packet to a codec context failed. ({0}: `{}`)", err_msg(*.0))]
     SendPacketError(c_int),
     #[error("Decoder isn't accepting input, try to receive several frames and send again.")]
     DecoderFullError,
-    #[error("Receive frame from a codec context failed. ({0})")]
+    #[error("Receive frame from a codec context failed. ({0}: `{}`)", err_msg(*.0))]
     ReceiveFrameError(c_int),
     #[error("Decoder have no frame currently, Try send new input.")]
     DecoderDrainError,
@@ -36,11 +40,11 @@ pub enum RsmpegError {
     DecoderFlushedError,
 
     // Encoder errors
-    #[error("Send frame to a codec context failed. ({0})")]
+    #[error("Send frame to a codec context failed. ({0}: `{}`)", err_msg(*.0))]
     SendFrameError(c_int),
     #[error("Encoder isn't accepting input, try to receive several packets and send again.")]
     SendFrameAgainError,
-    #[error("Receive packet from a codec context failed. ({0})")]
+    #[error("Receive packet from a codec context failed. ({0}: `{}`)", err_msg(*.0))]
     ReceivePacketError(c_int),
     #[error("Encoder have no packet currently, Try send new input.")]
     EncoderDrainError,
@@ -54,12 +58,12 @@ pub enum RsmpegError {
     BitstreamDrainError,
     #[error("Bitstream filter is already flushed")]
     BitstreamFlushedError,
-    #[error("Send packet to a bitstream filter context failed. ({0})")]
+    #[error("Send packet to a bitstream filter context failed. ({0}: `{}`)", err_msg(*.0))]
     BitstreamSendPacketError(c_int),
-    #[error("Receive packet from a bitstream filter context failed. ({0})")]
+    #[error("Receive packet from a bitstream filter context failed. ({0}: `{}`)", err_msg(*.0))]
     BitstreamReceivePacketError(c_int),
 
-    #[error("Pulling filtered frame from filters failed ({0})")]
+    #[error("Pulling filtered frame from filters failed ({0}: `{}`)", err_msg(*.0))]
     BufferSinkGetFrameError(c_int),
     #[error("No frames are available at this point")]
     BufferSinkDrainError,
@@ -68,18 +72,47 @@ pub enum RsmpegError {
 
     #[error("AVFrame buffer double allocating.")]
     AVFrameDoubleAllocatingError,
-    #[error("AVFrame buffer allocating with incorrect parameters. ({0})")]
+    #[error("AVFrame buffer allocating with incorrect parameters. ({0}: `{}`)", err_msg(*.0))]
     AVFrameInvalidAllocatingError(c_int),
 
     #[error("{0}")]
     TryFromIntError(TryFromIntError),
 
+    #[error("Options not recognized: {}", .0.join(", "))]
+    UnrecognizedOptionsError(Vec<String>),
+
+    #[error("Non monotonically increasing dts in stream {stream_index}: {dts} after {last_dts}")]
+    NonMonotonicTimestamps {
+        stream_index: usize,
+        last_dts: i64,
+        dts: i64,
+    },
+
     // Non exhaustive
     #[error("Unknown error.")]
     Unknown,
 }
 
+/// Description of an AVERROR code, used in the error messages.
+fn err_msg(err: c_int) -> String {
+    err2str(err).unwrap_or_else(|| "Unknown error code.".to_string())
+}
+
 impl RsmpegError {
+    /// Get the [`AVErrorKind`] of the underlying AVERROR code, `None` if the
+    /// error doesn't come from FFmpeg.
+    #[must_use]
+    pub fn kind(&self) -> Option<AVErrorKind> {
+        self.raw_error().map(AVErrorKind::from_raw)
+    }
+
+    /// Get FFmpeg's description of the underlying AVERROR code, rendered by
+    /// `av_strerror`.
+    #[must_use]
+    pub fn description(&self) -> Option<String> {
+        self.raw_error().and_then(err2str)
+    }
+
     #[must_use]
     pub fn raw_error(&self) -> Option<c_int> {
         match self {
@@ -108,11 +141,127 @@ impl RsmpegError {
             | Self::EncoderFlushedError
             | Self::BitstreamFlushedError => Some(ffi::AVERROR_EOF),
 
+            Self::UnrecognizedOptionsError(_) => Some(ffi::AVERROR_OPTION_NOT_FOUND),
+
+            Self::NonMonotonicTimestamps { .. } => Some(ffi::AVERROR(ffi::EINVAL)),
+
             Self::AVFrameDoubleAllocatingError | Self::TryFromIntError(_) | Self::Unknown => None,
         }
     }
 }
 
+/// Structured kinds of the AVERROR codes, so errors can be matched without
+/// comparing negative errno constants.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AVErrorKind {
+    /// `AVERROR(EAGAIN)`: output is not available in the current state, more
+    /// input should be sent or more output should be received.
+    Again,
+    /// `AVERROR_EOF`: end of file, or the codec is fully flushed.
+    Eof,
+    /// `AVERROR(EINVAL)`
+    InvalidArgument,
+    /// `AVERROR(ENOMEM)`
+    OutOfMemory,
+    /// `AVERROR(EIO)`
+    Io,
+    /// `AVERROR(ENOENT)`, e.g. the input file doesn't exist.
+    NotFound,
+    /// `AVERROR(EACCES)` and `AVERROR(EPERM)`
+    PermissionDenied,
+    /// `AVERROR(ETIMEDOUT)`
+    TimedOut,
+    /// `AVERROR(ENOSYS)`
+    Unsupported,
+    /// `AVERROR_INVALIDDATA`: invalid data found when processing input.
+    InvalidData,
+    /// `AVERROR_BSF_NOT_FOUND`
+    BsfNotFound,
+    /// `AVERROR_DECODER_NOT_FOUND`
+    DecoderNotFound,
+    /// `AVERROR_DEMUXER_NOT_FOUND`
+    DemuxerNotFound,
+    /// `AVERROR_ENCODER_NOT_FOUND`
+    EncoderNotFound,
+    /// `AVERROR_FILTER_NOT_FOUND`
+    FilterNotFound,
+    /// `AVERROR_MUXER_NOT_FOUND`
+    MuxerNotFound,
+    /// `AVERROR_OPTION_NOT_FOUND`
+    OptionNotFound,
+    /// `AVERROR_PROTOCOL_NOT_FOUND`
+    ProtocolNotFound,
+    /// `AVERROR_STREAM_NOT_FOUND`
+    StreamNotFound,
+    /// `AVERROR_BUFFER_TOO_SMALL`
+    BufferTooSmall,
+    /// `AVERROR_EXIT`: immediate exit was requested, e.g. by an interrupt
+    /// callback.
+    Exit,
+    /// `AVERROR_EXTERNAL`: generic error in an external library.
+    External,
+    /// `AVERROR_PATCHWELCOME`: not yet implemented in FFmpeg.
+    PatchWelcome,
+    /// `AVERROR_EXPERIMENTAL`: requested feature is flagged experimental.
+    Experimental,
+    /// `AVERROR_BUG` and `AVERROR_BUG2`: internal bug in FFmpeg.
+    Bug,
+    /// `AVERROR_HTTP_*`, with the status code, `4XX` and `5XX` are reported
+    /// as 400 and 500.
+    Http(u16),
+    /// Any other code, including other errnos.
+    Other(c_int),
+}
+
+impl AVErrorKind {
+    /// Classify a raw AVERROR code.
+    pub fn from_raw(err: c_int) -> Self {
+        const EINVAL: c_int = ffi::AVERROR(ffi::EINVAL);
+        const EIO: c_int = ffi::AVERROR(ffi::EIO);
+        const ENOENT: c_int = ffi::AVERROR(ffi::ENOENT);
+        const EACCES: c_int = ffi::AVERROR(ffi::EACCES);
+        const EPERM: c_int = ffi::AVERROR(ffi::EPERM);
+        const ETIMEDOUT: c_int = ffi::AVERROR(ffi::ETIMEDOUT);
+        const ENOSYS: c_int = ffi::AVERROR(ffi::ENOSYS);
+
+        match err {
+            AVERROR_EAGAIN => Self::Again,
+            ffi::AVERROR_EOF => Self::Eof,
+            EINVAL => Self::InvalidArgument,
+            AVERROR_ENOMEM => Self::OutOfMemory,
+            EIO => Self::Io,
+            ENOENT => Self::NotFound,
+            EACCES | EPERM => Self::PermissionDenied,
+            ETIMEDOUT => Self::TimedOut,
+            ENOSYS => Self::Unsupported,
+            ffi::AVERROR_INVALIDDATA => Self::InvalidData,
+            ffi::AVERROR_BSF_NOT_FOUND => Self::BsfNotFound,
+            ffi::AVERROR_DECODER_NOT_FOUND => Self::DecoderNotFound,
+            ffi::AVERROR_DEMUXER_NOT_FOUND => Self::DemuxerNotFound,
+            ffi::AVERROR_ENCODER_NOT_FOUND => Self::EncoderNotFound,
+            ffi::AVERROR_FILTER_NOT_FOUND => Self::FilterNotFound,
+            ffi::AVERROR_MUXER_NOT_FOUND => Self::MuxerNotFound,
+            ffi::AVERROR_OPTION_NOT_FOUND => Self::OptionNotFound,
+            ffi::AVERROR_PROTOCOL_NOT_FOUND => Self::ProtocolNotFound,
+            ffi::AVERROR_STREAM_NOT_FOUND => Self::StreamNotFound,
+            ffi::AVERROR_BUFFER_TOO_SMALL => Self::BufferTooSmall,
+            ffi::AVERROR_EXIT => Self::Exit,
+            ffi::AVERROR_EXTERNAL => Self::External,
+            ffi::AVERROR_PATCHWELCOME => Self::PatchWelcome,
+            ffi::AVERROR_EXPERIMENTAL => Self::Experimental,
+            ffi::AVERROR_BUG | ffi::AVERROR_BUG2 => Self::Bug,
+            ffi::AVERROR_HTTP_BAD_REQUEST => Self::Http(400),
+            ffi::AVERROR_HTTP_UNAUTHORIZED => Self::Http(401),
+            ffi::AVERROR_HTTP_FORBIDDEN => Self::Http(403),
+            ffi::AVERROR_HTTP_NOT_FOUND => Self::Http(404),
+            ffi::AVERROR_HTTP_OTHER_4XX => Self::Http(400),
+            ffi::AVERROR_HTTP_SERVER_ERROR => Self::Http(500),
+            x => Self::Other(x),
+        }
+    }
+}
+
 /// Overall result of Rsmpeg functions
 pub type Result<T, E = RsmpegError> = std::result::Result<T, E>;
 
@@ -130,3 +279,50 @@ impl From<TryFromIntError> for RsmpegError {
         Self::TryFromIntError(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(
+            RsmpegError::DecoderDrainError.kind(),
+            Some(AVErrorKind::Again)
+        );
+        assert_eq!(
+            RsmpegError::EncoderFlushedError.kind(),
+            Some(AVErrorKind::Eof)
+        );
+        assert_eq!(
+            RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)).kind(),
+            Some(AVErrorKind::InvalidArgument)
+        );
+        assert_eq!(
+            RsmpegError::OpenInputError(ffi::AVERROR_PROTOCOL_NOT_FOUND).kind(),
+            Some(AVErrorKind::ProtocolNotFound)
+        );
+        assert_eq!(
+            AVErrorKind::from_raw(ffi::AVERROR_HTTP_NOT_FOUND),
+            AVErrorKind::Http(404)
+        );
+        assert_eq!(AVErrorKind::from_raw(-12345), AVErrorKind::Other(-12345));
+        assert_eq!(RsmpegError::Unknown.kind(), None);
+    }
+
+    #[test]
+    fn test_error_description() {
+        assert_eq!(
+            RsmpegError::DecoderFlushedError.description().unwrap(),
+            "End of file"
+        );
+        assert_eq!(
+            RsmpegError::SendPacketError(ffi::AVERROR_INVALIDDATA).to_string(),
+            format!(
+                "Send packet to a codec context failed. ({}: `Invalid data found when processing input`)",
+                ffi::AVERROR_INVALIDDATA
+            )
+        );
+        assert!(RsmpegError::Unknown.description().is_none());
+    }
+}
@@ -14,9 +14,15 @@ pub mod avcodec;
 pub mod avfilter;
 pub mod avformat;
 pub mod avutil;
+pub mod log;
+pub mod pipeline;
+pub mod prelude;
 pub mod swresample;
 pub mod swscale;
 
 pub mod error;
 
+mod version;
+pub use version::*;
+
 pub use shared::UnsafeDerefMut;
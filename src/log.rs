@@ -0,0 +1,186 @@
+//! Route the log messages of FFmpeg into the [`log`](::log) crate, instead of
+//! stderr.
+//!
+//! ```ignore
+//! env_logger::init();
+//! rsmpeg::log::init();
+//! // FFmpeg messages are now records whose target is `ffmpeg`.
+//! ```
+use std::{
+    cell::RefCell,
+    ffi::CStr,
+    os::raw::{c_char, c_int, c_void},
+};
+
+use crate::ffi;
+
+/// Target of the records of the FFmpeg messages.
+pub const TARGET: &str = "ffmpeg";
+
+/// Install the callback forwarding the FFmpeg log messages to the `log`
+/// facade, and only let FFmpeg format the messages enabled by
+/// [`::log::max_level()`].
+///
+/// Each record is a line of FFmpeg, prefixed by the name of the context
+/// logging it if any, e.g. `h264: no frame!`. Call it after the logger is
+/// installed, or call [`set_level()`] when the maximum level changes.
+pub fn init() {
+    set_level(::log::max_level());
+    unsafe { ffi::av_log_set_callback(Some(callback)) };
+}
+
+/// Restore the default FFmpeg callback, writing to stderr.
+pub fn reset() {
+    unsafe { ffi::av_log_set_callback(Some(ffi::av_log_default_callback)) };
+}
+
+/// Set the level of the messages FFmpeg produces, the other ones are
+/// discarded before being formatted.
+pub fn set_level(level: ::log::LevelFilter) {
+    unsafe { ffi::av_log_set_level(av_level(level)) };
+}
+
+/// FFmpeg level (`AV_LOG_*`) of the messages enabled by `level`.
+fn av_level(level: ::log::LevelFilter) -> c_int {
+    (match level {
+        ::log::LevelFilter::Off => ffi::AV_LOG_QUIET,
+        ::log::LevelFilter::Error => ffi::AV_LOG_ERROR as i32,
+        ::log::LevelFilter::Warn => ffi::AV_LOG_WARNING as i32,
+        ::log::LevelFilter::Info => ffi::AV_LOG_INFO as i32,
+        ::log::LevelFilter::Debug => ffi::AV_LOG_DEBUG as i32,
+        ::log::LevelFilter::Trace => ffi::AV_LOG_TRACE as i32,
+    }) as c_int
+}
+
+/// Level of a message logged by FFmpeg at `level`, `None` for
+/// `AV_LOG_QUIET`.
+fn log_level(level: c_int) -> Option<::log::Level> {
+    Some(match level {
+        x if x < 0 => return None,
+        x if x <= ffi::AV_LOG_ERROR as c_int => ::log::Level::Error,
+        x if x <= ffi::AV_LOG_WARNING as c_int => ::log::Level::Warn,
+        x if x <= ffi::AV_LOG_INFO as c_int => ::log::Level::Info,
+        x if x <= ffi::AV_LOG_DEBUG as c_int => ::log::Level::Debug,
+        _ => ::log::Level::Trace,
+    })
+}
+
+thread_local! {
+    /// Start of the line being logged by the thread, FFmpeg often logs a
+    /// line in several calls.
+    static LINE: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Append `message` to the pending line, return the line if it's complete.
+fn push_line(line: &mut String, message: &str) -> Option<String> {
+    line.push_str(message);
+    if !line.ends_with('\n') {
+        return None;
+    }
+    let complete = line.trim_end_matches(['\r', '\n']).to_string();
+    line.clear();
+    Some(complete)
+}
+
+/// Name of the context logging a message, e.g. `h264` for a decoder.
+unsafe fn item_name(avcl: *mut c_void) -> Option<String> {
+    let class = unsafe { (avcl as *const *const ffi::AVClass).as_ref() }?;
+    let class = unsafe { class.as_ref() }?;
+    let name = unsafe { class.item_name?(avcl) };
+    if name.is_null() {
+        return None;
+    }
+    Some(
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+unsafe extern "C" fn callback(
+    avcl: *mut c_void,
+    level: c_int,
+    fmt: *const c_char,
+    vl: ffi::va_list,
+) {
+    // Filtered by the callback, like the default one does.
+    if level > unsafe { ffi::av_log_get_level() } {
+        return;
+    }
+    let Some(log_level) = log_level(level) else {
+        return;
+    };
+    if log_level > ::log::max_level() {
+        return;
+    }
+    // Same size as the line of the default callback, longer messages are
+    // truncated.
+    let mut buf = [0 as c_char; 1024];
+    let mut print_prefix = 0;
+    unsafe {
+        ffi::av_log_format_line2(
+            avcl,
+            level,
+            fmt,
+            vl,
+            buf.as_mut_ptr(),
+            buf.len() as c_int,
+            &mut print_prefix,
+        )
+    };
+    let message = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy();
+    let Some(line) = LINE.with(|x| push_line(&mut x.borrow_mut(), &message)) else {
+        return;
+    };
+    match unsafe { item_name(avcl) } {
+        Some(name) => ::log::log!(target: TARGET, log_level, "{name}: {line}"),
+        None => ::log::log!(target: TARGET, log_level, "{line}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(log_level(ffi::AV_LOG_QUIET), None);
+        assert_eq!(
+            log_level(ffi::AV_LOG_PANIC as c_int),
+            Some(::log::Level::Error)
+        );
+        assert_eq!(
+            log_level(ffi::AV_LOG_WARNING as c_int),
+            Some(::log::Level::Warn)
+        );
+        assert_eq!(
+            log_level(ffi::AV_LOG_VERBOSE as c_int),
+            Some(::log::Level::Debug)
+        );
+        assert_eq!(
+            log_level(ffi::AV_LOG_TRACE as c_int),
+            Some(::log::Level::Trace)
+        );
+        for level in [
+            ::log::LevelFilter::Error,
+            ::log::LevelFilter::Warn,
+            ::log::LevelFilter::Info,
+            ::log::LevelFilter::Debug,
+            ::log::LevelFilter::Trace,
+        ] {
+            assert_eq!(log_level(av_level(level)).unwrap(), level);
+        }
+        assert_eq!(av_level(::log::LevelFilter::Off), ffi::AV_LOG_QUIET);
+    }
+
+    #[test]
+    fn test_push_line() {
+        let mut line = String::new();
+        assert_eq!(push_line(&mut line, "Stream #0:0"), None);
+        assert_eq!(
+            push_line(&mut line, ": Video: h264\r\n").as_deref(),
+            Some("Stream #0:0: Video: h264")
+        );
+        assert!(line.is_empty());
+    }
+}
@@ -66,10 +66,32 @@ macro_rules! wrap_pure {
             }
         }
 
+    };
+}
+
+/// Mark a wrapper `Send`.
+///
+/// The FFmpeg structs have no thread affinity, and the callbacks stored in the
+/// wrappers are required to be `Send`, so an owned wrapper can be moved to
+/// another thread. They aren't `Sync` since most of them are mutated by the
+/// FFmpeg functions taking a const pointer, e.g. the internal buffers of a
+/// codec context, apart from the static read-only ones marked with
+/// `wrap_static!`.
+macro_rules! wrap_send {
+    ($wrapped_type: ident) => {
         unsafe impl Send for $wrapped_type {}
     };
 }
 
+/// Mark a wrapper of the static read-only data of FFmpeg, e.g. the codecs
+/// or the formats, `Sync`: they are never mutated nor freed, so they can be
+/// shared between threads.
+macro_rules! wrap_static {
+    ($wrapped_type: ident) => {
+        unsafe impl Sync for $wrapped_type {}
+    };
+}
+
 /// Wrapping with XXXRef -> XXX.
 macro_rules! wrap_ref_pure {
     (($wrapped_type: ident, $wrapped_ref: ident): $ffi_type: ty) => {
@@ -106,7 +128,8 @@ macro_rules! wrap_ref_pure {
             }
         }
 
-        unsafe impl<'a> Send for $wrapped_ref<'a> {}
+        // `Send` and `Sync` like `&'a $wrapped_type`: a reference can be sent
+        // to another thread if the value it borrows can be shared.
     };
 }
 
@@ -117,7 +140,7 @@ macro_rules! wrap_mut_pure {
         #[repr(transparent)]
         pub struct $wrapped_mut<'a> {
             inner: std::mem::ManuallyDrop<$wrapped_type>,
-            _marker: std::marker::PhantomData<&'a $wrapped_type>,
+            _marker: std::marker::PhantomData<&'a mut $wrapped_type>,
         }
 
         impl<'a> std::ops::Deref for $wrapped_mut<'a> {
@@ -153,7 +176,7 @@ macro_rules! wrap_mut_pure {
             }
         }
 
-        unsafe impl<'a> Send for $wrapped_mut<'a> {}
+        // `Send` and `Sync` like `&'a mut $wrapped_type`.
     };
 }
 
@@ -166,6 +189,7 @@ macro_rules! wrap_ref_mut {
     ) => {
         paste::paste! {
             wrap_pure!($(#[$meta])* ($name): $ffi_type $(,$attach: $attach_type = $attach_default)*);
+            wrap_send!($name);
             wrap_ref_pure!(($name, [<$name Ref>]): $ffi_type);
             wrap_mut_pure!(($name, [<$name Mut>]): $ffi_type);
         }
@@ -181,25 +205,12 @@ macro_rules! wrap_ref {
     ) => {
         paste::paste! {
             wrap_pure!($(#[$meta])* ($name): $ffi_type $(,$attach: $attach_type = $attach_default)*);
+            wrap_send!($name);
             wrap_ref_pure!(($name, [<$name Ref>]): $ffi_type);
         }
     };
 }
 
-/// Wrapping with XXXMut, XXX -> XXX.
-macro_rules! wrap_mut {
-    (
-        $(#[$meta:meta])*
-        $name: ident: $ffi_type: ty
-        $(,$attach: ident: $attach_type: ty = $attach_default: expr)* $(,)?
-    ) => {
-        paste::paste! {
-            wrap_pure!($(#[$meta])* ($name): $ffi_type $(,$attach: $attach_type = $attach_default)*);
-            wrap_mut_pure!(($name, [<$name Mut>]): $ffi_type);
-        }
-    };
-}
-
 /// Wrapping with XXX -> XXX.
 macro_rules! wrap {
     (
@@ -209,6 +220,7 @@ macro_rules! wrap {
     ) => {
         paste::paste! {
             wrap_pure!($(#[$meta])* ($name): $ffi_type $(,$attach: $attach_type = $attach_default)*);
+            wrap_send!($name);
         }
     };
 }
@@ -0,0 +1,335 @@
+use std::{
+    ffi::{CStr, CString},
+    fmt::Write,
+};
+
+use crate::{
+    avcodec::AVCodecContext,
+    avfilter::{AVFilter, AVFilterGraph, AVFilterInOut},
+    avformat::AVFormatContextOutput,
+    avutil::{av_rescale_q, AVFrame, AVRational, PixelFormat},
+    error::{Result, RsmpegError},
+    ffi,
+    shared::UnsafeDerefMut,
+};
+
+/// One rendition of an [`AbrLadder`]: an opened video encoder and the output
+/// its packets are written to.
+pub struct AbrRendition {
+    /// Opened encoder, the input frames are scaled to its size and converted
+    /// into its pixel format.
+    pub encoder: AVCodecContext,
+    /// Output whose header has already been written.
+    pub output: AVFormatContextOutput,
+    /// Index of the stream of `output` receiving the packets.
+    pub stream_index: usize,
+}
+
+impl AbrRendition {
+    /// Rendition writing into the first stream of `output`.
+    pub fn new(encoder: AVCodecContext, output: AVFormatContextOutput) -> Self {
+        Self {
+            encoder,
+            output,
+            stream_index: 0,
+        }
+    }
+}
+
+/// Properties of the input frames the filter graph is configured for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InputProps {
+    width: i32,
+    height: i32,
+    format: i32,
+}
+
+/// Encodes decoded video frames into several renditions at once, e.g. the
+/// 1080p, 720p and 480p variants of an ABR ladder.
+///
+/// Each frame is pushed once, a `split` and `scale` filter graph produces the
+/// frame of every rendition, which is encoded and written to its output. The
+/// filter graph is created on the first frame, the following frames must have
+/// the same size and pixel format.
+///
+/// Frames without a time base are expected to have their pts in the time base
+/// of the first encoder.
+///
+/// ```ignore
+/// let mut ladder = AbrLadder::new(vec![
+///     AbrRendition::new(encoder_1080p, output_1080p),
+///     AbrRendition::new(encoder_720p, output_720p),
+/// ])?;
+/// while let Some(frame) = decoder.decode_frame()? {
+///     ladder.push_frame(&frame)?;
+/// }
+/// ladder.finish()?;
+/// ```
+pub struct AbrLadder {
+    renditions: Vec<AbrRendition>,
+    graph: Option<(AVFilterGraph, InputProps)>,
+}
+
+impl AbrLadder {
+    /// Create a ladder of `renditions`, returns `AVERROR(EINVAL)` if there is
+    /// none.
+    pub fn new(renditions: Vec<AbrRendition>) -> Result<Self> {
+        if renditions.is_empty() {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        Ok(Self {
+            renditions,
+            graph: None,
+        })
+    }
+
+    /// Get the renditions.
+    pub fn renditions(&self) -> &[AbrRendition] {
+        &self.renditions
+    }
+
+    /// Scale, encode and write `frame` into every rendition.
+    ///
+    /// Returns `AVERROR(EINVAL)` if the size or the pixel format of `frame`
+    /// differs from the first frame pushed.
+    pub fn push_frame(&mut self, frame: &AVFrame) -> Result<()> {
+        let props = InputProps {
+            width: frame.width,
+            height: frame.height,
+            format: frame.format,
+        };
+        match &self.graph {
+            Some((_, x)) if *x != props => {
+                return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))
+            }
+            Some(_) => {}
+            None => {
+                let graph = self.init_graph(frame)?;
+                self.graph = Some((graph, props));
+            }
+        }
+
+        self.graph
+            .as_mut()
+            .unwrap()
+            .0
+            .get_filter(CStr::from_bytes_with_nul(b"in\0").unwrap())
+            .unwrap()
+            .buffersrc_add_frame(Some(frame.clone()), None)?;
+        self.drain()
+    }
+
+    /// Flush the filter graph and all the encoders, then write the trailer of
+    /// every output.
+    pub fn finish(mut self) -> Result<()> {
+        if let Some((graph, _)) = &mut self.graph {
+            graph
+                .get_filter(CStr::from_bytes_with_nul(b"in\0").unwrap())
+                .unwrap()
+                .buffersrc_add_frame(None, None)?;
+            self.drain()?;
+        }
+        for rendition in &mut self.renditions {
+            encode(rendition, None)?;
+            rendition.output.write_trailer()?;
+        }
+        Ok(())
+    }
+
+    /// Encode the frames available from every branch of the filter graph.
+    fn drain(&mut self) -> Result<()> {
+        let graph = &mut self.graph.as_mut().unwrap().0;
+        for (i, rendition) in self.renditions.iter_mut().enumerate() {
+            let name = CString::new(format!("out{i}")).unwrap();
+            let mut sink = graph.get_filter(&name).unwrap();
+            loop {
+                let mut frame = match sink.buffersink_get_frame(None) {
+                    Ok(frame) => frame,
+                    Err(RsmpegError::BufferSinkDrainError | RsmpegError::BufferSinkEofError) => {
+                        break
+                    }
+                    Err(e) => return Err(e),
+                };
+                let time_base = rendition.encoder.time_base;
+                frame.set_pts(av_rescale_q(frame.pts, sink.get_time_base(), time_base));
+                frame.set_time_base(time_base);
+                frame.set_pict_type(ffi::AV_PICTURE_TYPE_NONE);
+                encode(rendition, Some(&frame))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn init_graph(&self, frame: &AVFrame) -> Result<AVFilterGraph> {
+        if frame.width <= 0 || frame.height <= 0 {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let time_base = match frame.time_base {
+            AVRational { num: 0, .. } | AVRational { den: 0, .. } => {
+                self.renditions[0].encoder.time_base
+            }
+            x => x,
+        };
+        let sample_aspect_ratio = match frame.sample_aspect_ratio {
+            AVRational { den: 0, .. } => AVRational { num: 0, den: 1 },
+            x => x,
+        };
+        let args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+            frame.width,
+            frame.height,
+            frame.format,
+            time_base.num,
+            time_base.den,
+            sample_aspect_ratio.num,
+            sample_aspect_ratio.den,
+        );
+        let args = CString::new(args).unwrap();
+
+        // split=2[s0][s1];[s0]scale=1280:720,format=pix_fmts=yuv420p[out0];...
+        let mut filter_spec = format!("split={}", self.renditions.len());
+        for i in 0..self.renditions.len() {
+            write!(filter_spec, "[s{i}]").unwrap();
+        }
+        for (i, rendition) in self.renditions.iter().enumerate() {
+            let encoder = &rendition.encoder;
+            let pix_fmt = PixelFormat(encoder.pix_fmt)
+                .name()
+                .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+            write!(
+                filter_spec,
+                ";[s{i}]scale={}:{},format=pix_fmts={}[out{i}]",
+                encoder.width,
+                encoder.height,
+                pix_fmt.to_string_lossy(),
+            )
+            .unwrap();
+        }
+        let filter_spec = CString::new(filter_spec).unwrap();
+
+        let graph = AVFilterGraph::new();
+        {
+            let buffersrc =
+                AVFilter::get_by_name(CStr::from_bytes_with_nul(b"buffer\0").unwrap()).unwrap();
+            let buffersink =
+                AVFilter::get_by_name(CStr::from_bytes_with_nul(b"buffersink\0").unwrap()).unwrap();
+            let in_name = CStr::from_bytes_with_nul(b"in\0").unwrap();
+            let mut src = graph.create_filter_context(&buffersrc, in_name, Some(&args))?;
+            let outputs = AVFilterInOut::new(in_name, &mut src, 0);
+
+            // Chain the sinks from the last one, so `inputs` starts with out0.
+            let mut inputs: Option<AVFilterInOut> = None;
+            for i in (0..self.renditions.len()).rev() {
+                let name = CString::new(format!("out{i}")).unwrap();
+                let mut sink = graph.create_filter_context(&buffersink, &name, None)?;
+                let mut inout = AVFilterInOut::new(&name, &mut sink, 0);
+                if let Some(next) = inputs.take() {
+                    unsafe { inout.deref_mut().next = next.into_raw().as_ptr() };
+                }
+                inputs = Some(inout);
+            }
+            graph.parse_ptr(&filter_spec, inputs, Some(outputs))?;
+        }
+        graph.config()?;
+        Ok(graph)
+    }
+}
+
+/// Send `frame` to the encoder of `rendition`, `None` flushes it, and write
+/// the packets received.
+fn encode(rendition: &mut AbrRendition, frame: Option<&AVFrame>) -> Result<()> {
+    rendition.encoder.send_frame(frame)?;
+    let time_base = rendition.output.streams()[rendition.stream_index].time_base;
+    loop {
+        let mut packet = match rendition.encoder.receive_packet() {
+            Ok(packet) => packet,
+            Err(RsmpegError::EncoderDrainError | RsmpegError::EncoderFlushedError) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        packet.rescale_ts(rendition.encoder.time_base, time_base);
+        packet.set_stream_index(rendition.stream_index as i32);
+        rendition.output.write_frame(&mut packet)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        avcodec::{AVCodec, AVPacket},
+        avformat::AVFormatContextInput,
+        avutil::ra,
+    };
+
+    fn rendition(path: &CStr, width: i32, height: i32) -> AbrRendition {
+        let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_MJPEG).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_width(width);
+        encoder.set_height(height);
+        encoder.set_pix_fmt(codec.pix_fmts().unwrap()[0]);
+        encoder.set_time_base(ra(1, 25));
+        encoder.open(None).unwrap();
+
+        let mut output = AVFormatContextOutput::create(path, None).unwrap();
+        {
+            let mut stream = output.new_stream();
+            stream.set_codecpar(encoder.extract_codecpar());
+            stream.set_time_base(encoder.time_base);
+        }
+        output.write_header(&mut None).unwrap();
+        AbrRendition::new(encoder, output)
+    }
+
+    fn gray_frame(width: i32, height: i32, pts: i64) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_width(width);
+        frame.set_height(height);
+        frame.set_format(ffi::AV_PIX_FMT_YUV420P);
+        frame.set_pts(pts);
+        frame.alloc_buffer().unwrap();
+        for plane in 0..3 {
+            frame.plane_data_mut(plane).unwrap().fill(128);
+        }
+        frame
+    }
+
+    #[test]
+    fn test_abr_ladder() {
+        let tempdir = tempdir::TempDir::new("abr_ladder").unwrap();
+        let sizes = [(128, 96), (64, 48), (32, 24)];
+        let paths: Vec<_> = (0..sizes.len())
+            .map(|i| {
+                let path = tempdir.path().join(format!("out{i}.mkv"));
+                CString::new(path.to_str().unwrap()).unwrap()
+            })
+            .collect();
+
+        assert!(AbrLadder::new(vec![]).is_err());
+        let renditions = paths
+            .iter()
+            .zip(sizes)
+            .map(|(path, (w, h))| rendition(path, w, h))
+            .collect();
+        let mut ladder = AbrLadder::new(renditions).unwrap();
+        assert_eq!(ladder.renditions().len(), 3);
+        for pts in 0..10 {
+            ladder.push_frame(&gray_frame(256, 192, pts)).unwrap();
+        }
+        assert!(ladder.push_frame(&gray_frame(64, 64, 10)).is_err());
+        ladder.finish().unwrap();
+
+        for (path, (width, height)) in paths.iter().zip(sizes) {
+            let mut input = AVFormatContextInput::open(path, None, &mut None).unwrap();
+            {
+                let codecpar = input.streams()[0].codecpar();
+                assert_eq!(codecpar.width, width);
+                assert_eq!(codecpar.height, height);
+            }
+            let mut packets: Vec<AVPacket> = vec![];
+            while let Some(packet) = input.read_packet().unwrap() {
+                packets.push(packet);
+            }
+            assert_eq!(packets.len(), 10);
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Stream analysis based on demuxing only, without decoding.
+use crate::{
+    avcodec::AVPacket,
+    avformat::AVFormatContextInput,
+    avutil::{av_q2d, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Properties of a demuxed packet, timestamps are in the time base of its
+/// stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketInfo {
+    pub pts: i64,
+    pub dts: i64,
+    pub duration: i64,
+    pub size: usize,
+    pub is_key: bool,
+}
+
+impl PacketInfo {
+    /// Timestamp of the packet, dts if set, pts otherwise.
+    fn ts(&self) -> Option<i64> {
+        match (self.dts, self.pts) {
+            (ffi::AV_NOPTS_VALUE, ffi::AV_NOPTS_VALUE) => None,
+            (ffi::AV_NOPTS_VALUE, x) | (x, _) => Some(x),
+        }
+    }
+}
+
+/// Iterator over the packets of one stream of an input, created by
+/// [`packet_infos()`].
+pub struct PacketInfos<'a> {
+    input: &'a mut AVFormatContextInput,
+    stream_index: usize,
+    /// Reused for all the packets.
+    packet: AVPacket,
+}
+
+impl Iterator for PacketInfos<'_> {
+    type Item = Result<PacketInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.input.read_packet_into(&mut self.packet) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+            let packet = &self.packet;
+            if packet.stream_index as usize != self.stream_index {
+                continue;
+            }
+            return Some(Ok(PacketInfo {
+                pts: packet.pts,
+                dts: packet.dts,
+                duration: packet.duration,
+                size: packet.size as usize,
+                is_key: packet.is_keyframe(),
+            }));
+        }
+    }
+}
+
+/// Iterate over the packets of stream `stream_index` from the current
+/// position of `input`, the packets of the other streams are skipped.
+pub fn packet_infos(
+    input: &mut AVFormatContextInput,
+    stream_index: usize,
+) -> Result<PacketInfos<'_>> {
+    if stream_index >= input.streams().len() {
+        return Err(RsmpegError::AVError(ffi::AVERROR_STREAM_NOT_FOUND));
+    }
+    Ok(PacketInfos {
+        input,
+        stream_index,
+        packet: AVPacket::new(),
+    })
+}
+
+/// Packets of a stream with the GOP related statistics, see
+/// [`gop_structure()`].
+#[derive(Debug, Clone)]
+pub struct GopStructure {
+    /// Time base of the packet timestamps.
+    pub time_base: AVRational,
+    /// Duration of the stream in [`Self::time_base`], `None` if unknown.
+    pub duration: Option<i64>,
+    /// Packets in demuxing order.
+    pub packets: Vec<PacketInfo>,
+}
+
+impl GopStructure {
+    /// Number of packets of each GOP, a GOP starts at a key packet. Packets
+    /// before the first key packet are counted as a GOP.
+    pub fn gop_lengths(&self) -> Vec<usize> {
+        let mut lengths = vec![];
+        for (i, packet) in self.packets.iter().enumerate() {
+            if i == 0 || packet.is_key {
+                lengths.push(0);
+            }
+            *lengths.last_mut().unwrap() += 1;
+        }
+        lengths
+    }
+
+    /// Indexes of the key packets in [`Self::packets`].
+    pub fn keyframe_indexes(&self) -> Vec<usize> {
+        self.packets
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.is_key)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Total size of the packets in bytes.
+    pub fn total_size(&self) -> usize {
+        self.packets.iter().map(|x| x.size).sum()
+    }
+
+    /// Bitrate in bits per second over consecutive windows of `interval`
+    /// seconds, starting at the first timestamp. Packets without timestamps
+    /// are ignored.
+    ///
+    /// The windows cover [`Self::duration`], or the sum of the packet
+    /// durations if unknown, the packets after it, e.g. following a
+    /// timestamp jump, are counted in the last window. Empty if `interval`
+    /// isn't a positive number or makes more than 2^20 windows.
+    pub fn bitrate_series(&self, interval: f64) -> Vec<f64> {
+        if !(interval.is_finite() && interval > 0.0) {
+            return vec![];
+        }
+        let time_base = av_q2d(self.time_base);
+        let Some(start) = self.packets.iter().find_map(|x| x.ts()) else {
+            return vec![];
+        };
+        let duration = self
+            .duration
+            .unwrap_or_else(|| self.packets.iter().map(|x| x.duration.max(0)).sum());
+        let windows = (duration.max(0) as f64 * time_base / interval).ceil();
+        if windows > MAX_WINDOWS as f64 {
+            return vec![];
+        }
+        let max_index = (windows as usize).max(1) - 1;
+        let mut series = vec![];
+        for packet in &self.packets {
+            let Some(ts) = packet.ts() else {
+                continue;
+            };
+            let seconds = (ts - start) as f64 * time_base;
+            if seconds < 0.0 {
+                continue;
+            }
+            let index = ((seconds / interval) as usize).min(max_index);
+            if series.len() <= index {
+                series.resize(index + 1, 0.0);
+            }
+            series[index] += packet.size as f64 * 8.0;
+        }
+        series.iter_mut().for_each(|x| *x /= interval);
+        series
+    }
+}
+
+/// Maximum number of windows of [`GopStructure::bitrate_series()`].
+const MAX_WINDOWS: usize = 1 << 20;
+
+/// Demux the stream `stream_index` of `input` from its current position to
+/// the end, and collect its packets for GOP analysis.
+pub fn gop_structure(
+    input: &mut AVFormatContextInput,
+    stream_index: usize,
+) -> Result<GopStructure> {
+    let packets = packet_infos(input, stream_index)?.collect::<Result<Vec<_>>>()?;
+    let stream = &input.streams()[stream_index];
+    Ok(GopStructure {
+        time_base: stream.time_base,
+        duration: (stream.duration != ffi::AV_NOPTS_VALUE).then_some(stream.duration),
+        packets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_gop_structure() {
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (stream_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let gop = gop_structure(&mut input, stream_index).unwrap();
+
+        assert!(!gop.packets.is_empty());
+        assert!(gop.packets[0].is_key);
+        let lengths = gop.gop_lengths();
+        assert_eq!(lengths.len(), gop.keyframe_indexes().len());
+        assert_eq!(lengths.iter().sum::<usize>(), gop.packets.len());
+
+        let series = gop.bitrate_series(0.5);
+        assert!(!series.is_empty());
+        let total_bits: f64 = series.iter().map(|x| x * 0.5).sum();
+        assert_eq!(total_bits as usize, gop.total_size() * 8);
+    }
+
+    #[test]
+    fn test_gop_lengths() {
+        let packet = |is_key| PacketInfo {
+            pts: 0,
+            dts: 0,
+            duration: 1,
+            size: 1,
+            is_key,
+        };
+        let gop = GopStructure {
+            time_base: AVRational { num: 1, den: 25 },
+            duration: None,
+            packets: vec![
+                packet(false),
+                packet(true),
+                packet(false),
+                packet(false),
+                packet(true),
+            ],
+        };
+        assert_eq!(gop.gop_lengths(), [1, 3, 1]);
+        assert_eq!(gop.keyframe_indexes(), [1, 4]);
+    }
+
+    #[test]
+    fn test_bitrate_series_bounds() {
+        let packet = |pts| PacketInfo {
+            pts,
+            dts: pts,
+            duration: 1,
+            size: 1,
+            is_key: true,
+        };
+        let mut gop = GopStructure {
+            time_base: AVRational { num: 1, den: 1 },
+            duration: Some(4),
+            packets: vec![packet(0), packet(1), packet(2), packet(3)],
+        };
+        for interval in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(gop.bitrate_series(interval).is_empty());
+        }
+        assert_eq!(gop.bitrate_series(2.0), [8.0, 8.0]);
+
+        // A timestamp jump is counted in the last window.
+        gop.packets.push(packet(i64::MAX / 2));
+        assert_eq!(gop.bitrate_series(2.0), [8.0, 12.0]);
+        gop.duration = None;
+        assert_eq!(gop.bitrate_series(1.0), [8.0, 8.0, 8.0, 8.0, 8.0]);
+        gop.packets.push(packet(ffi::AV_NOPTS_VALUE));
+        assert_eq!(gop.bitrate_series(1.0).len(), 6);
+        assert!(gop.bitrate_series(1e-300).is_empty());
+    }
+}
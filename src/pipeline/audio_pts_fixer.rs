@@ -0,0 +1,205 @@
+use crate::{
+    avutil::{av_rescale_q, AVFrame, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Checks that consecutive audio frames are contiguous, i.e. that the pts of
+/// each frame is the pts of the previous one plus its `nb_samples`, and
+/// repairs the frames which aren't.
+///
+/// Differences are measured in samples against the expected position:
+/// - Gaps and overlaps up to `tolerance` samples are fixed by moving the
+///   frame to the expected pts.
+/// - Gaps up to `max_silence` samples are filled with a silent frame.
+/// - Larger gaps and larger overlaps, e.g. a timestamp jumping backward, are
+///   considered as discontinuities, the frame is kept as is and the
+///   following frames are checked against it.
+///
+/// Frames without pts are placed at the expected position.
+///
+/// ```ignore
+/// // Stream in 1/48000, fix jitter up to 1ms and fill gaps up to 1s.
+/// let mut fixer = AudioPtsFixer::new(stream.time_base, 48, 48000);
+/// while let Some(frame) = decoder.decode_frame()? {
+///     for frame in fixer.fix_frame(frame)? {
+///         encoder.send_frame(Some(&frame))?;
+///     }
+/// }
+/// ```
+pub struct AudioPtsFixer {
+    time_base: AVRational,
+    tolerance: i64,
+    max_silence: i64,
+    /// Expected position of the next frame, in samples.
+    next_sample: Option<i64>,
+    adjusted: u64,
+    silence_samples: u64,
+    discontinuities: u64,
+}
+
+impl AudioPtsFixer {
+    /// Create a fixer of frames whose pts are in `time_base`, `tolerance`
+    /// and `max_silence` are in samples.
+    pub fn new(time_base: AVRational, tolerance: i64, max_silence: i64) -> Self {
+        Self {
+            time_base,
+            tolerance,
+            max_silence,
+            next_sample: None,
+            adjusted: 0,
+            silence_samples: 0,
+            discontinuities: 0,
+        }
+    }
+
+    /// Number of frames whose pts has been changed.
+    pub fn adjusted(&self) -> u64 {
+        self.adjusted
+    }
+
+    /// Number of silent samples inserted, per channel.
+    pub fn silence_samples(&self) -> u64 {
+        self.silence_samples
+    }
+
+    /// Number of gaps too large to be filled and of overlaps too large to be
+    /// fixed.
+    pub fn discontinuities(&self) -> u64 {
+        self.discontinuities
+    }
+
+    /// Check `frame` against the previous ones, return it with a fixed pts,
+    /// preceded by a silent frame if a gap is filled.
+    ///
+    /// Returns `AVERROR(EINVAL)` if `frame` has no sample rate.
+    pub fn fix_frame(&mut self, mut frame: AVFrame) -> Result<Vec<AVFrame>> {
+        if frame.sample_rate <= 0 {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let sample_time_base = AVRational {
+            num: 1,
+            den: frame.sample_rate,
+        };
+        let position = match frame.pts {
+            ffi::AV_NOPTS_VALUE => None,
+            pts => Some(av_rescale_q(pts, self.time_base, sample_time_base)),
+        };
+
+        let mut frames = vec![];
+        let start = match (self.next_sample, position) {
+            (None, position) => position.unwrap_or(0),
+            (Some(expected), None) => {
+                self.adjusted += 1;
+                expected
+            }
+            (Some(expected), Some(position)) => {
+                let gap = position - expected;
+                if gap == 0 {
+                    position
+                } else if gap.abs() <= self.tolerance {
+                    self.adjusted += 1;
+                    expected
+                } else if gap > 0 && gap <= self.max_silence {
+                    let mut silence = silent_frame(&frame, gap as i32)?;
+                    silence.set_pts(av_rescale_q(expected, sample_time_base, self.time_base));
+                    frames.push(silence);
+                    self.silence_samples += gap as u64;
+                    position
+                } else {
+                    self.discontinuities += 1;
+                    position
+                }
+            }
+        };
+        frame.set_pts(av_rescale_q(start, sample_time_base, self.time_base));
+        self.next_sample = Some(start + frame.nb_samples as i64);
+        frames.push(frame);
+        Ok(frames)
+    }
+}
+
+/// Allocate a silent frame of `nb_samples` with the audio parameters of
+/// `frame`.
+fn silent_frame(frame: &AVFrame, nb_samples: i32) -> Result<AVFrame> {
+    let mut silence = AVFrame::new();
+    silence.set_nb_samples(nb_samples);
+    silence.set_format(frame.format);
+    silence.set_sample_rate(frame.sample_rate);
+    silence.set_ch_layout(frame.ch_layout().clone().into_inner());
+    silence.set_time_base(frame.time_base);
+    silence.alloc_buffer()?;
+    unsafe {
+        ffi::av_samples_set_silence(
+            silence.extended_data,
+            0,
+            nb_samples,
+            silence.ch_layout.nb_channels,
+            silence.format,
+        )
+    };
+    Ok(silence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avutil::{ra, AVChannelLayout};
+
+    fn frame(pts: i64, nb_samples: i32) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_nb_samples(nb_samples);
+        frame.set_format(ffi::AV_SAMPLE_FMT_S16);
+        frame.set_sample_rate(1000);
+        frame.set_ch_layout(AVChannelLayout::from_nb_channels(1).into_inner());
+        frame.set_pts(pts);
+        frame.alloc_buffer().unwrap();
+        unsafe { std::ptr::write_bytes(frame.data[0], 1, nb_samples as usize * 2) };
+        frame
+    }
+
+    #[test]
+    fn test_audio_pts_fixer() {
+        // pts in 1/2000, two ticks per sample.
+        let mut fixer = AudioPtsFixer::new(ra(1, 2000), 2, 50);
+        let mut outputs = vec![];
+        for (pts, nb_samples) in [
+            (200, 100), // first frame, samples [100, 200)
+            (400, 100), // contiguous
+            (604, 100), // gap of 2 samples, moved back
+            (796, 100), // overlap of 2 samples, moved forward
+            (1100, 50), // gap of 50 samples, filled
+            (ffi::AV_NOPTS_VALUE, 50),
+            (5000, 10), // discontinuity
+            (5020, 10),
+            (4000, 10), // overlap of 520 samples, discontinuity
+        ] {
+            for frame in fixer.fix_frame(frame(pts, nb_samples)).unwrap() {
+                outputs.push((frame.pts, frame.nb_samples));
+            }
+        }
+        assert_eq!(
+            outputs,
+            [
+                (200, 100),
+                (400, 100),
+                (600, 100),
+                (800, 100),
+                (1000, 50),
+                (1100, 50),
+                (1200, 50),
+                (5000, 10),
+                (5020, 10),
+                (4000, 10)
+            ]
+        );
+        assert_eq!(fixer.adjusted(), 3);
+        assert_eq!(fixer.silence_samples(), 50);
+        assert_eq!(fixer.discontinuities(), 2);
+
+        let silence = silent_frame(&frame(0, 10), 10).unwrap();
+        let samples = unsafe { std::slice::from_raw_parts(silence.data[0], 20) };
+        assert!(samples.iter().all(|&x| x == 0));
+        assert!(fixer.fix_frame(AVFrame::new()).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    avcodec::AVPacket,
+    avformat::{AVFormatContextInput, SeekFlags},
+    avutil::{av_rescale_q, AVFrame, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+const AV_TIME_BASE_Q: AVRational = AVRational {
+    num: 1,
+    den: ffi::AV_TIME_BASE as i32,
+};
+
+/// A point of the input from which an interrupted transcode can be restarted:
+/// a keyframe of a reference stream, usually the video one.
+///
+/// A checkpoint can be persisted with its `Display` implementation and parsed
+/// back with `FromStr`, e.g. `0:48000:1234567`.
+///
+/// Decoders, filters and encoders restart from scratch at a checkpoint, so
+/// the output of the resumed transcode overlaps the output written before the
+/// interruption, which [`OverlapTrimmer`] drops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Index of the reference stream in the input.
+    pub stream_index: usize,
+    /// dts of the keyframe (pts if the dts is unknown), in the time base of
+    /// the reference stream.
+    pub ts: i64,
+    /// Byte position of the keyframe in the input, -1 if unknown.
+    pub pos: i64,
+}
+
+impl Checkpoint {
+    /// Seek `input` so that the next packet of the reference stream is the
+    /// keyframe of the checkpoint.
+    ///
+    /// Seeks by timestamp, and falls back to the byte position if the
+    /// demuxer can't seek by timestamp.
+    pub fn seek(&self, input: &mut AVFormatContextInput) -> Result<()> {
+        let result = input.seek(Some(self.stream_index), i64::MIN, self.ts, self.ts, 0);
+        match result {
+            Err(_) if self.pos >= 0 => {
+                input.seek(None, self.pos, self.pos, self.pos, SeekFlags::BYTE)
+            }
+            x => x,
+        }
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.stream_index, self.ts, self.pos)
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = RsmpegError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL));
+        let mut fields = s.trim().split(':');
+        let mut next = || fields.next().ok_or_else(invalid);
+        let checkpoint = Self {
+            stream_index: next()?.parse().map_err(|_| invalid())?,
+            ts: next()?.parse().map_err(|_| invalid())?,
+            pos: next()?.parse().map_err(|_| invalid())?,
+        };
+        if fields.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(checkpoint)
+    }
+}
+
+/// Records the [`Checkpoint`]s of an input while it's being demuxed.
+///
+/// ```ignore
+/// let mut tracker = CheckpointTracker::new(video_index);
+/// while let Some(packet) = input.read_packet()? {
+///     if let Some(checkpoint) = tracker.observe(&packet) {
+///         // Persist the checkpoint along with the output written so far.
+///     }
+///     // ...
+/// }
+/// ```
+pub struct CheckpointTracker {
+    stream_index: usize,
+    last: Option<Checkpoint>,
+}
+
+impl CheckpointTracker {
+    /// Record the keyframes of the stream at `stream_index`.
+    pub fn new(stream_index: usize) -> Self {
+        Self {
+            stream_index,
+            last: None,
+        }
+    }
+
+    /// Feed a packet read from the input. Returns the new checkpoint if the
+    /// packet is a keyframe of the reference stream.
+    pub fn observe(&mut self, packet: &AVPacket) -> Option<Checkpoint> {
+        if packet.stream_index as usize != self.stream_index || !packet.is_keyframe() {
+            return None;
+        }
+        let ts = match packet.dts {
+            ffi::AV_NOPTS_VALUE => packet.pts,
+            x => x,
+        };
+        if ts == ffi::AV_NOPTS_VALUE {
+            return None;
+        }
+        let checkpoint = Checkpoint {
+            stream_index: self.stream_index,
+            ts,
+            pos: packet.pos,
+        };
+        self.last = Some(checkpoint);
+        Some(checkpoint)
+    }
+
+    /// The latest checkpoint, `None` before the first keyframe.
+    pub fn last(&self) -> Option<Checkpoint> {
+        self.last
+    }
+}
+
+/// Drops what a resumed transcode outputs again: packets and frames before
+/// the point the output had reached when the transcode was interrupted.
+pub struct OverlapTrimmer {
+    resume_from: i64,
+}
+
+impl OverlapTrimmer {
+    /// Keep what starts at or after `resume_from`, in `AV_TIME_BASE` units,
+    /// e.g. the end of the last packet written to the output.
+    pub fn new(resume_from: i64) -> Self {
+        Self { resume_from }
+    }
+
+    /// Point from which packets and frames are kept, in `AV_TIME_BASE` units.
+    pub fn resume_from(&self) -> i64 {
+        self.resume_from
+    }
+
+    /// Returns true if `ts`, in `time_base`, is not before the resume point.
+    /// Unknown timestamps are kept.
+    pub fn keep(&self, ts: i64, time_base: AVRational) -> bool {
+        ts == ffi::AV_NOPTS_VALUE || av_rescale_q(ts, time_base, AV_TIME_BASE_Q) >= self.resume_from
+    }
+
+    /// Returns true if `packet`, with timestamps in `time_base`, should be
+    /// written.
+    pub fn keep_packet(&self, packet: &AVPacket, time_base: AVRational) -> bool {
+        self.keep(packet.pts, time_base)
+    }
+
+    /// Returns true if `frame`, with timestamps in `time_base`, should be
+    /// processed.
+    pub fn keep_frame(&self, frame: &AVFrame, time_base: AVRational) -> bool {
+        let ts = match frame.best_effort_timestamp {
+            ffi::AV_NOPTS_VALUE => frame.pts,
+            x => x,
+        };
+        self.keep(ts, time_base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avutil::ra;
+    use cstr::cstr;
+
+    #[test]
+    fn test_checkpoint_string() {
+        let checkpoint = Checkpoint {
+            stream_index: 1,
+            ts: 48000,
+            pos: -1,
+        };
+        assert_eq!(checkpoint.to_string(), "1:48000:-1");
+        assert_eq!("1:48000:-1\n".parse::<Checkpoint>().unwrap(), checkpoint);
+        assert!("1:48000".parse::<Checkpoint>().is_err());
+        assert!("1:48000:0:0".parse::<Checkpoint>().is_err());
+        assert!("a:48000:0".parse::<Checkpoint>().is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_resume() {
+        let url = cstr!("tests/assets/vids/bear.mp4");
+        let mut input = AVFormatContextInput::open(url, None, &mut None).unwrap();
+        let (video_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let mut tracker = CheckpointTracker::new(video_index);
+        assert_eq!(tracker.last(), None);
+        let mut checkpoints = vec![];
+        while let Some(packet) = input.read_packet().unwrap() {
+            if let Some(checkpoint) = tracker.observe(&packet) {
+                checkpoints.push(checkpoint);
+            }
+        }
+        let last = *checkpoints.last().unwrap();
+        assert_eq!(tracker.last(), Some(last));
+
+        // Resume from the last keyframe in a new input.
+        let checkpoint: Checkpoint = last.to_string().parse().unwrap();
+        let mut input = AVFormatContextInput::open(url, None, &mut None).unwrap();
+        checkpoint.seek(&mut input).unwrap();
+        let mut tracker = CheckpointTracker::new(video_index);
+        let packet = loop {
+            let packet = input.read_packet().unwrap().unwrap();
+            if packet.stream_index as usize == video_index {
+                break packet;
+            }
+        };
+        assert_eq!(tracker.observe(&packet), Some(checkpoint));
+    }
+
+    #[test]
+    fn test_overlap_trimmer() {
+        let trimmer = OverlapTrimmer::new(1_000_000);
+        assert_eq!(trimmer.resume_from(), 1_000_000);
+        assert!(!trimmer.keep(999, ra(1, 1000)));
+        assert!(trimmer.keep(1000, ra(1, 1000)));
+        assert!(trimmer.keep(ffi::AV_NOPTS_VALUE, ra(1, 1000)));
+
+        let mut packet = AVPacket::new();
+        packet.set_pts(89_999);
+        assert!(!trimmer.keep_packet(&packet, ra(1, 90000)));
+        packet.set_pts(90_000);
+        assert!(trimmer.keep_packet(&packet, ra(1, 90000)));
+
+        let mut frame = AVFrame::new();
+        frame.set_pts(24);
+        assert!(!trimmer.keep_frame(&frame, ra(1, 25)));
+        frame.set_pts(25);
+        assert!(trimmer.keep_frame(&frame, ra(1, 25)));
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    avcodec::{AVCodecContext, AVPacket},
+    avutil::AVFrame,
+    error::{Result, RsmpegError},
+    ffi,
+    pipeline::{pump, pump_flush, SendReceive},
+};
+
+/// A decoder running on its own thread, see [`CodecWorker`].
+pub type DecoderWorker = CodecWorker<AVCodecContext, AVPacket>;
+
+/// An encoder running on its own thread, see [`CodecWorker`].
+pub type EncoderWorker = CodecWorker<AVCodecContext, AVFrame>;
+
+/// Runs a decoder, an encoder or a bitstream filter on a thread of its own,
+/// fed with inputs of type `I` through a channel, e.g. to decode and encode
+/// in parallel.
+///
+/// The worker owns the context and drives its send/receive API with
+/// [`pump()`], so the `EAGAIN` dance never reaches the caller. The inputs go
+/// through a queue of at most `capacity` items, [`Self::send()`] blocks while
+/// it's full. The outputs go through an unbounded queue, so a single thread
+/// can send all the inputs before receiving the outputs without deadlocking,
+/// drain it with [`Self::try_receive()`] while sending to bound the memory.
+///
+/// ```ignore
+/// let mut decoder = DecoderWorker::new(decoder, 16);
+/// let mut encoder = EncoderWorker::new(encoder, 4);
+/// while let Some(packet) = input.read_packet()? {
+///     decoder.send(packet)?;
+///     while let Some(frame) = decoder.try_receive()? {
+///         encoder.send(frame)?;
+///     }
+///     while let Some(packet) = encoder.try_receive()? {
+///         output.interleaved_write_frame(&mut { packet })?;
+///     }
+/// }
+/// decoder.finish();
+/// while let Some(frame) = decoder.receive()? {
+///     encoder.send(frame)?;
+/// }
+/// encoder.finish();
+/// while let Some(packet) = encoder.receive()? {
+///     output.interleaved_write_frame(&mut { packet })?;
+/// }
+/// ```
+pub struct CodecWorker<C: SendReceive<I>, I> {
+    /// `None` once finished.
+    inputs: Option<SyncSender<I>>,
+    /// `None` while dropping, to stop the worker at its next output.
+    outputs: Option<Receiver<Result<C::Output>>>,
+    handle: Option<JoinHandle<C>>,
+}
+
+impl<C, I> CodecWorker<C, I>
+where
+    C: SendReceive<I> + Send + 'static,
+    C::Output: Send + 'static,
+    I: Send + 'static,
+{
+    /// Spawn a thread driving the opened `context`, with a queue of at most
+    /// `capacity` inputs.
+    pub fn new(mut context: C, capacity: usize) -> Self {
+        let (inputs, input_receiver) = mpsc::sync_channel(capacity);
+        let (output_sender, outputs) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            if let Err(e) = run(&mut context, input_receiver, &output_sender) {
+                let _ = output_sender.send(Err(e));
+            }
+            context
+        });
+        Self {
+            inputs: Some(inputs),
+            outputs: Some(outputs),
+            handle: Some(handle),
+        }
+    }
+
+    /// Queue `input`, blocking while the queue is full.
+    ///
+    /// Returns `AVERROR(EINVAL)` after [`Self::finish()`], and
+    /// `AVERROR(EPIPE)` if the worker stopped on an error, which is returned
+    /// by [`Self::receive()`].
+    pub fn send(&self, input: I) -> Result<()> {
+        self.inputs
+            .as_ref()
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?
+            .send(input)
+            .map_err(|_| RsmpegError::AVError(ffi::AVERROR(ffi::EPIPE)))
+    }
+
+    /// Signal the end of the inputs, the context is flushed once the queued
+    /// inputs are processed.
+    pub fn finish(&mut self) {
+        self.inputs = None;
+    }
+
+    /// Wait for the next output.
+    ///
+    /// Returns `Ok(None)` once the worker is finished and all its outputs
+    /// received. The error stopping the worker is returned after the outputs
+    /// produced before it.
+    pub fn receive(&self) -> Result<Option<C::Output>> {
+        match self.outputs.as_ref().unwrap().recv() {
+            Ok(x) => x.map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Get the next output without waiting, `Ok(None)` if none is available
+    /// yet.
+    pub fn try_receive(&self) -> Result<Option<C::Output>> {
+        match self.outputs.as_ref().unwrap().try_recv() {
+            Ok(x) => x.map(Some),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => Ok(None),
+        }
+    }
+
+    /// Finish the worker and get the context back once flushed, e.g. to reuse
+    /// it after [`AVCodecContext::flush_buffers()`]. The outputs not received
+    /// yet are dropped.
+    pub fn join(mut self) -> C {
+        self.stop().unwrap()
+    }
+
+    fn stop(&mut self) -> Option<C> {
+        self.inputs = None;
+        // The worker stops at its next output.
+        self.outputs = None;
+        let handle = self.handle.take()?;
+        Some(handle.join().unwrap())
+    }
+}
+
+impl<C: SendReceive<I>, I> Drop for CodecWorker<C, I> {
+    fn drop(&mut self) {
+        self.inputs = None;
+        self.outputs = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Process the inputs until the queue is closed, then flush.
+fn run<C: SendReceive<I>, I>(
+    context: &mut C,
+    inputs: Receiver<I>,
+    outputs: &Sender<Result<C::Output>>,
+) -> Result<()> {
+    let mut on_output = |output| {
+        outputs
+            .send(Ok(output))
+            // The receiver is gone, the outputs are not needed anymore.
+            .map_err(|_| RsmpegError::AVError(ffi::AVERROR_EXIT))
+    };
+    let result = pump(context, inputs, &mut on_output)
+        .and_then(|_| pump_flush::<C, I>(context, &mut on_output));
+    match result {
+        Err(RsmpegError::AVError(ffi::AVERROR_EXIT)) => Ok(()),
+        x => x,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{avcodec::AVCodec, avformat::AVFormatContextInput, avutil::ra};
+    use cstr::cstr;
+
+    #[test]
+    fn test_codec_workers() {
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, decoder) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let stream = &input.streams()[video_index];
+        let mut decoder_context = AVCodecContext::from_stream(stream, &decoder).unwrap();
+        decoder_context.open(None).unwrap();
+
+        let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_MPEG4).unwrap();
+        let mut encoder_context = AVCodecContext::new(&codec);
+        encoder_context.set_width(decoder_context.width);
+        encoder_context.set_height(decoder_context.height);
+        encoder_context.set_pix_fmt(decoder_context.pix_fmt);
+        encoder_context.set_time_base(ra(1, 25));
+        encoder_context.open(None).unwrap();
+
+        let mut decoder = DecoderWorker::new(decoder_context, 2);
+        let mut encoder = EncoderWorker::new(encoder_context, 2);
+        let (mut packets, mut frames, mut encoded) = (0, 0, 0);
+        while let Some(packet) = input.read_packet().unwrap() {
+            if packet.stream_index as usize != video_index {
+                continue;
+            }
+            packets += 1;
+            decoder.send(packet).unwrap();
+            while let Some(frame) = decoder.try_receive().unwrap() {
+                frames += 1;
+                encoder.send(frame).unwrap();
+            }
+            while encoder.try_receive().unwrap().is_some() {
+                encoded += 1;
+            }
+        }
+        decoder.finish();
+        assert!(decoder.send(AVPacket::new()).is_err());
+        while let Some(frame) = decoder.receive().unwrap() {
+            frames += 1;
+            encoder.send(frame).unwrap();
+        }
+        encoder.finish();
+        while encoder.receive().unwrap().is_some() {
+            encoded += 1;
+        }
+        assert_eq!(frames, packets);
+        assert_eq!(encoded, frames);
+        assert!(decoder.receive().unwrap().is_none());
+
+        // The context is given back.
+        let decoder = decoder.join();
+        assert_eq!(decoder.codec_type, ffi::AVMEDIA_TYPE_VIDEO);
+
+        // An encoder which isn't opened fails, stopping the worker.
+        let mut worker = EncoderWorker::new(AVCodecContext::new(&codec), 1);
+        worker.send(AVFrame::new()).unwrap();
+        worker.finish();
+        assert_eq!(
+            worker.receive().err(),
+            Some(RsmpegError::SendFrameError(ffi::AVERROR(ffi::EINVAL)))
+        );
+        assert!(worker.receive().unwrap().is_none());
+    }
+}
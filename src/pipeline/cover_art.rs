@@ -0,0 +1,195 @@
+use std::ffi::CStr;
+
+use crate::{
+    avcodec::{AVCodec, AVCodecContext, AVPacket, PacketFlags},
+    avformat::{AVFormatContextInput, AVFormatContextOutput},
+    avutil::{AVDictionary, AVFrame, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// An image embedded as an attached picture (cover art), e.g. in MP3, MP4 or
+/// FLAC files.
+///
+/// ```ignore
+/// let cover = CoverArt::open(cstr!("cover.jpg"))?;
+/// // Add the audio streams first, then:
+/// cover.add_stream(&mut output, Some(cstr!("Album cover")))?;
+/// output.write_header(&mut None)?;
+/// cover.write(&mut output)?;
+/// // Write the audio packets.
+/// ```
+pub struct CoverArt {
+    packet: AVPacket,
+    codec_id: ffi::AVCodecID,
+    width: i32,
+    height: i32,
+    stream_index: Option<usize>,
+}
+
+impl CoverArt {
+    /// Read an image file (e.g. JPEG or PNG) without re-encoding it.
+    pub fn open(url: &CStr) -> Result<Self> {
+        let mut input = AVFormatContextInput::open(url, None, &mut None)?;
+        let (codec_id, width, height) = {
+            let stream = input
+                .streams()
+                .first()
+                .ok_or(RsmpegError::AVError(ffi::AVERROR_STREAM_NOT_FOUND))?;
+            let codecpar = stream.codecpar();
+            if !codecpar.codec_type().is_video() {
+                return Err(RsmpegError::AVError(ffi::AVERROR_INVALIDDATA));
+            }
+            (codecpar.codec_id, codecpar.width, codecpar.height)
+        };
+        let packet = input
+            .read_packet()?
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_INVALIDDATA))?;
+        Ok(Self {
+            packet,
+            codec_id,
+            width,
+            height,
+            stream_index: None,
+        })
+    }
+
+    /// Encode `frame` into an image with the given encoder, e.g.
+    /// `AV_CODEC_ID_MJPEG` for a `yuvj420p` frame or `AV_CODEC_ID_PNG` for a
+    /// `rgb24` frame.
+    pub fn encode(frame: &AVFrame, codec_id: ffi::AVCodecID) -> Result<Self> {
+        let codec = AVCodec::find_encoder(codec_id)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_ENCODER_NOT_FOUND))?;
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_width(frame.width);
+        encoder.set_height(frame.height);
+        encoder.set_pix_fmt(frame.format);
+        encoder.set_time_base(AVRational { num: 1, den: 25 });
+        encoder.open(None)?;
+
+        encoder.send_frame(Some(frame))?;
+        encoder.send_frame(None)?;
+        let packet = encoder.receive_packet()?;
+        Ok(Self {
+            packet,
+            codec_id,
+            width: frame.width,
+            height: frame.height,
+            stream_index: None,
+        })
+    }
+
+    /// Codec of the image.
+    pub fn codec_id(&self) -> ffi::AVCodecID {
+        self.codec_id
+    }
+
+    /// Encoded image.
+    pub fn packet(&self) -> &AVPacket {
+        &self.packet
+    }
+
+    /// Add the attached picture stream to `output`, should be called before
+    /// [`AVFormatContextOutput::write_header()`]. `title` is stored as the
+    /// picture description by the muxers supporting it.
+    ///
+    /// Returns the index of the new stream.
+    pub fn add_stream(
+        &mut self,
+        output: &mut AVFormatContextOutput,
+        title: Option<&CStr>,
+    ) -> Result<usize> {
+        // The picture type used by ID3v2 and FLAC.
+        let metadata = AVDictionary::new(
+            CStr::from_bytes_with_nul(b"comment\0").unwrap(),
+            CStr::from_bytes_with_nul(b"Cover (front)\0").unwrap(),
+            0,
+        );
+        let metadata = match title {
+            Some(title) => metadata.set(CStr::from_bytes_with_nul(b"title\0").unwrap(), title, 0),
+            None => metadata,
+        };
+
+        let mut stream = output.new_stream();
+        unsafe {
+            let codecpar = stream.codecpar_mut().as_mut_ptr();
+            (*codecpar).codec_type = ffi::AVMEDIA_TYPE_VIDEO;
+            (*codecpar).codec_id = self.codec_id;
+            (*codecpar).width = self.width;
+            (*codecpar).height = self.height;
+        }
+        stream.set_disposition(ffi::AV_DISPOSITION_ATTACHED_PIC as i32);
+        stream.set_time_base(AVRational { num: 1, den: 90000 });
+        stream.set_metadata(Some(metadata));
+        let index = stream.index as usize;
+        self.stream_index = Some(index);
+        Ok(index)
+    }
+
+    /// Write the picture to `output`, should be called right after
+    /// [`AVFormatContextOutput::write_header()`], since some muxers (e.g.
+    /// MP3) hold the audio packets back until the picture is written.
+    pub fn write(&self, output: &mut AVFormatContextOutput) -> Result<()> {
+        let index = self
+            .stream_index
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        let mut packet = self.packet.clone();
+        packet.set_stream_index(index as i32);
+        packet.set_packet_flags(packet.packet_flags() | PacketFlags::KEY);
+        packet.set_pts(0);
+        packet.set_dts(0);
+        packet.set_duration(0);
+        packet.set_pos(-1);
+        output.interleaved_write_frame(&mut packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_cover_art() {
+        let tempdir = tempdir::TempDir::new("cover").unwrap();
+        let path = tempdir.path().join("out.m4a");
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let mut input = AVFormatContextInput::open(
+            cstr!("tests/assets/audios/sample1_short.aac"),
+            None,
+            &mut None,
+        )
+        .unwrap();
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        {
+            let mut stream = output.new_stream();
+            stream.set_codecpar(input.streams()[0].codecpar().clone());
+            stream.set_time_base(input.streams()[0].time_base);
+        }
+
+        let mut cover = CoverArt::open(cstr!("tests/assets/pics/bear.jpg")).unwrap();
+        assert_eq!(cover.codec_id(), ffi::AV_CODEC_ID_MJPEG);
+        let index = cover.add_stream(&mut output, Some(cstr!("Bear"))).unwrap();
+        output.write_header(&mut None).unwrap();
+        cover.write(&mut output).unwrap();
+
+        let in_time_base = input.streams()[0].time_base;
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            packet.rescale_ts(in_time_base, output.streams()[0].time_base);
+            output.interleaved_write_frame(&mut packet).unwrap();
+        }
+        output.write_trailer().unwrap();
+        drop(output);
+
+        let result = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        let stream = &result.streams()[index];
+        assert_eq!(
+            stream.disposition & ffi::AV_DISPOSITION_ATTACHED_PIC as i32,
+            ffi::AV_DISPOSITION_ATTACHED_PIC as i32
+        );
+        assert_eq!(stream.codecpar().codec_id, ffi::AV_CODEC_ID_MJPEG);
+        assert_eq!(stream.attached_pic.size, cover.packet().size);
+    }
+}
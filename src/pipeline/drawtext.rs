@@ -0,0 +1,150 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::growing_input::io_error;
+use crate::error::Result;
+
+/// Makes the names of the files unique within the process.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Text file read by the `drawtext` filter with `textfile=` and `reload=`,
+/// for overlays updated while encoding, e.g. a scoreboard or a ticker.
+///
+/// The text is replaced atomically: it's written to a temporary file next
+/// to the text file which is then renamed over it, so the filter never reads
+/// a partially written text. The file is removed when dropped.
+///
+/// ```ignore
+/// let text = DrawTextFile::new("0 - 0")?;
+/// let spec = CString::new(format!("drawtext={}:fontsize=48", text.filter_args(1)))?;
+/// graph.parse_ptr(&spec, Some(inputs), Some(outputs))?;
+/// // Later, from any thread:
+/// text.set_text("1 - 0")?;
+/// ```
+pub struct DrawTextFile {
+    path: PathBuf,
+}
+
+impl DrawTextFile {
+    /// Create a text file with a unique name in the temporary directory of
+    /// the system, containing `text`.
+    pub fn new(text: &str) -> Result<Self> {
+        Self::new_in(env::temp_dir(), text)
+    }
+
+    /// Create a text file with a unique name in `dir`, containing `text`.
+    ///
+    /// The file has to exist before the filter is initialized, hence the
+    /// initial text.
+    pub fn new_in(dir: impl AsRef<Path>, text: &str) -> Result<Self> {
+        let path = loop {
+            let path = dir.as_ref().join(format!(
+                "rsmpeg-drawtext-{}-{}.txt",
+                process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            // Never take over a file of someone else.
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => break path,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(io_error(e)),
+            }
+        };
+        let file = Self { path };
+        file.set_text(text)?;
+        Ok(file)
+    }
+
+    /// Path of the text file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Replace the text, the filter picks it up at its next reload.
+    pub fn set_text(&self, text: &str) -> Result<()> {
+        // Unique, so concurrent updates don't write into the same file.
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(format!(".{}.tmp", COUNTER.fetch_add(1, Ordering::Relaxed)));
+        let temp_path = PathBuf::from(temp_path);
+        let result = File::create(&temp_path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .and_then(|()| fs::rename(&temp_path, &self.path));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp_path);
+            return Err(io_error(e));
+        }
+        Ok(())
+    }
+
+    /// Options of the `drawtext` filter reading the text file every `reload`
+    /// frames, escaped to be embedded in a filter graph description, e.g.
+    /// `drawtext={args}:fontsize=48`.
+    ///
+    /// The text is drawn as is: `%{...}` sequences aren't expanded.
+    pub fn filter_args(&self, reload: u32) -> String {
+        let path = self.path.to_string_lossy();
+        format!(
+            "textfile={}:reload={}:expansion=none",
+            escape(&escape(&path, ":"), "[],;"),
+            reload.max(1),
+        )
+    }
+}
+
+impl Drop for DrawTextFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Backslash escape `value` for one level of the filter graph syntax, whose
+/// special characters besides `\` and `'` are `special`.
+fn escape(value: &str, special: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || c == '\'' || special.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drawtext_file() {
+        let tempdir = tempdir::TempDir::new("drawtext").unwrap();
+        let text = DrawTextFile::new_in(tempdir.path(), "0 - 0").unwrap();
+        let other = DrawTextFile::new_in(tempdir.path(), "").unwrap();
+        assert_ne!(text.path(), other.path());
+        assert_eq!(fs::read_to_string(text.path()).unwrap(), "0 - 0");
+
+        text.set_text("100%{pts}").unwrap();
+        assert_eq!(fs::read_to_string(text.path()).unwrap(), "100%{pts}");
+        // No temporary file left behind.
+        assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 2);
+
+        let path = text.path().to_path_buf();
+        drop(text);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_escape() {
+        let path = r"C:\it's [1],2;";
+        assert_eq!(escape(path, ":"), r"C\:\\it\'s [1],2;");
+        assert_eq!(
+            escape(&escape(path, ":"), "[],;"),
+            r"C\\:\\\\it\\\'s \[1\]\,2\;"
+        );
+    }
+}
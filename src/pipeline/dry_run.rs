@@ -0,0 +1,247 @@
+use std::ffi::CStr;
+
+use crate::{
+    avcodec::{AVCodecContext, AVCodecParameters, CodecFlags},
+    avformat::{AVFormatContextOutput, AVIOContextContainer, AVIOContextCustom, IOFormatFlags},
+    avutil::{AVDictionary, AVMem, AVRational},
+    error::Result,
+    ffi,
+};
+
+/// An output stream checked by [`dry_run()`].
+pub struct DryRunStream {
+    /// Encoder configured as for the real job, but not opened.
+    pub encoder: AVCodecContext,
+    /// Options of the encoder, e.g. `preset` or `crf`.
+    pub options: Option<AVDictionary>,
+}
+
+impl DryRunStream {
+    /// Stream encoded by `encoder` without options.
+    pub fn new(encoder: AVCodecContext) -> Self {
+        Self {
+            encoder,
+            options: None,
+        }
+    }
+
+    /// Set the options of the encoder.
+    pub fn with_options(mut self, options: AVDictionary) -> Self {
+        self.options = Some(options);
+        self
+    }
+}
+
+/// Parameters of an output stream resolved by [`dry_run()`].
+#[derive(Debug, Clone)]
+pub struct DryRunStreamReport {
+    /// Name of the encoder, e.g. `libx264`.
+    pub encoder: String,
+    /// Parameters of the encoded stream, e.g. the profile or the extradata
+    /// set by the encoder.
+    pub codecpar: AVCodecParameters,
+    /// Time base of the encoder.
+    pub encoder_time_base: AVRational,
+    /// Time base of the stream chosen by the muxer, the packets have to be
+    /// rescaled into it.
+    pub stream_time_base: AVRational,
+    /// Number of samples per channel of the audio frames the encoder
+    /// expects, 0 if it accepts any number.
+    pub frame_size: i32,
+    /// Options of the encoder which were not recognized.
+    pub unrecognized_options: Vec<String>,
+}
+
+/// Report of a successful [`dry_run()`].
+#[derive(Debug, Clone)]
+pub struct DryRunReport {
+    /// Name of the muxer, e.g. `mp4`.
+    pub format: String,
+    /// The output streams, in the order of the [`DryRunStream`]s.
+    pub streams: Vec<DryRunStreamReport>,
+    /// Options of the muxer which were not recognized.
+    pub unrecognized_options: Vec<String>,
+}
+
+/// Check the configuration of a job without processing any media: open the
+/// encoders of `streams` and initialize the muxer guessed from `url` with
+/// `options`, then report the parameters they resolved.
+///
+/// Nothing is written: the output of the muxer is discarded and `url` is
+/// only used to guess the muxer, so services can reject a bad job quickly,
+/// before fetching its input.
+///
+/// Fails with the error the real job would hit at its start, e.g. an
+/// unsupported pixel format, an invalid option value or a codec the muxer
+/// can't store. The unrecognized options don't fail, they are listed in the
+/// report.
+///
+/// ```ignore
+/// let report = dry_run(
+///     cstr!("output.mp4"),
+///     vec![DryRunStream::new(video_encoder).with_options(x264_options)],
+///     None,
+/// )?;
+/// if !report.streams[0].unrecognized_options.is_empty() {
+///     // Reject the job.
+/// }
+/// ```
+pub fn dry_run(
+    url: &CStr,
+    streams: Vec<DryRunStream>,
+    options: Option<AVDictionary>,
+) -> Result<DryRunReport> {
+    // Seekable so the muxers requiring it, e.g. mp4, accept it.
+    let io_context = AVIOContextCustom::alloc_context(
+        AVMem::new(4096),
+        true,
+        vec![],
+        None,
+        Some(Box::new(|_, buf| buf.len() as i32)),
+        Some(Box::new(|_, offset, whence| match whence {
+            x if x == ffi::SEEK_SET as i32 => offset,
+            _ => ffi::AVERROR(ffi::ENOSYS) as i64,
+        })),
+    );
+    let mut output =
+        AVFormatContextOutput::create(url, Some(AVIOContextContainer::Custom(io_context)))?;
+    let global_header = output
+        .oformat()
+        .format_flags()
+        .contains(IOFormatFlags::GLOBALHEADER);
+
+    let mut reports = vec![];
+    for DryRunStream {
+        mut encoder,
+        options,
+    } in streams
+    {
+        if global_header {
+            encoder.set_codec_flags(encoder.codec_flags() | CodecFlags::GLOBAL_HEADER);
+        }
+        let unrecognized = encoder.open(options)?;
+        let codecpar = encoder.extract_codecpar();
+        {
+            let mut stream = output.new_stream();
+            stream.set_codecpar(codecpar.clone());
+            stream.set_time_base(encoder.time_base);
+        }
+        reports.push(DryRunStreamReport {
+            encoder: encoder.codec().name().to_string_lossy().into_owned(),
+            codecpar,
+            encoder_time_base: encoder.time_base,
+            stream_time_base: encoder.time_base,
+            frame_size: encoder.frame_size,
+            unrecognized_options: keys(&unrecognized),
+        });
+    }
+
+    let mut options = options;
+    output.init_output(&mut options)?;
+    for (report, stream) in reports.iter_mut().zip(output.streams()) {
+        report.stream_time_base = stream.time_base;
+    }
+    Ok(DryRunReport {
+        format: output.oformat().name().to_string_lossy().into_owned(),
+        streams: reports,
+        unrecognized_options: keys(&options),
+    })
+}
+
+fn keys(options: &Option<AVDictionary>) -> Vec<String> {
+    options
+        .iter()
+        .flatten()
+        .map(|x| x.key().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        avcodec::AVCodec,
+        avutil::{ra, AVChannelLayout},
+        error::RsmpegError,
+    };
+    use cstr::cstr;
+
+    fn video_encoder(codec_id: ffi::AVCodecID, pix_fmt: ffi::AVPixelFormat) -> AVCodecContext {
+        let codec = AVCodec::find_encoder(codec_id).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_width(64);
+        encoder.set_height(48);
+        encoder.set_pix_fmt(pix_fmt);
+        encoder.set_time_base(ra(1, 25));
+        encoder
+    }
+
+    fn audio_encoder() -> AVCodecContext {
+        let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_AAC).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_sample_fmt(ffi::AV_SAMPLE_FMT_FLTP);
+        encoder.set_sample_rate(48000);
+        encoder.set_ch_layout(AVChannelLayout::from_nb_channels(2).into_inner());
+        encoder.set_time_base(ra(1, 48000));
+        encoder
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let tempdir = tempdir::TempDir::new("dry_run").unwrap();
+        let path = tempdir.path().join("output.mp4");
+        let url = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+
+        let report = dry_run(
+            &url,
+            vec![
+                DryRunStream::new(video_encoder(
+                    ffi::AV_CODEC_ID_MPEG4,
+                    ffi::AV_PIX_FMT_YUV420P,
+                ))
+                .with_options(AVDictionary::new(cstr!("foo"), cstr!("bar"), 0)),
+                DryRunStream::new(audio_encoder()),
+            ],
+            Some(AVDictionary::new(cstr!("movflags"), cstr!("faststart"), 0)),
+        )
+        .unwrap();
+        assert!(!path.exists());
+        assert_eq!(report.format, "mp4");
+        assert!(report.unrecognized_options.is_empty());
+        let [video, audio] = &report.streams[..] else {
+            panic!("{report:?}");
+        };
+        assert_eq!(video.encoder, "mpeg4");
+        assert_eq!(video.unrecognized_options, ["foo"]);
+        assert_eq!((video.codecpar.width, video.codecpar.height), (64, 48));
+        // Global headers requested by the muxer.
+        assert!(!video.codecpar.extradata().is_empty());
+        assert_eq!(video.stream_time_base.den % 25, 0);
+        assert_eq!(audio.encoder, "aac");
+        assert_eq!(audio.frame_size, 1024);
+        assert_eq!(audio.codecpar.sample_rate, 48000);
+
+        // The muxer can't store the codec.
+        assert!(dry_run(
+            cstr!("output.mp4"),
+            vec![DryRunStream::new(video_encoder(
+                ffi::AV_CODEC_ID_PCX,
+                ffi::AV_PIX_FMT_RGB24,
+            ))],
+            None,
+        )
+        .is_err());
+        // The encoder doesn't support the pixel format.
+        assert!(matches!(
+            dry_run(
+                cstr!("output.mkv"),
+                vec![DryRunStream::new(video_encoder(
+                    ffi::AV_CODEC_ID_MPEG4,
+                    ffi::AV_PIX_FMT_RGB24,
+                ))],
+                None,
+            ),
+            Err(RsmpegError::AVError(_))
+        ));
+    }
+}
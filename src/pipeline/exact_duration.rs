@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use crate::{
+    avcodec::AVPacket,
+    avformat::{AVFormatContextInput, AVStreamRef},
+    avutil::{av_rescale_q, AVRational},
+    error::Result,
+    ffi,
+};
+
+/// How hard [`exact_duration()`] works to find the durations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DurationEffort {
+    /// Only use the durations stored in the container, nothing is read. The
+    /// durations FFmpeg estimated from the bitrate are discarded.
+    Metadata,
+    /// Use the durations stored in the container, scan the packets of the
+    /// whole input if one of them is missing or estimated from the bitrate,
+    /// e.g. for raw ADTS or some FLV files.
+    #[default]
+    Auto,
+    /// Always scan the packets of the whole input, for containers whose
+    /// metadata can't be trusted.
+    Scan,
+}
+
+/// Where the duration of a stream comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationSource {
+    /// The metadata of the container.
+    Metadata,
+    /// The timestamps and durations of the packets.
+    Packets,
+}
+
+/// Duration of a stream found by [`exact_duration()`].
+#[derive(Debug, Clone, Copy)]
+pub struct StreamDuration {
+    /// Time base of `start_time` and `duration`, the one of the stream.
+    pub time_base: AVRational,
+    /// Timestamp of the first frame.
+    pub start_time: i64,
+    /// Time from the start of the first frame to the end of the last one.
+    pub duration: i64,
+    /// Where the duration comes from.
+    pub source: DurationSource,
+}
+
+impl StreamDuration {
+    /// Timestamp of the end of the last frame.
+    pub fn end_time(&self) -> i64 {
+        self.start_time + self.duration
+    }
+
+    /// The duration as a [`Duration`].
+    pub fn to_duration(&self) -> Duration {
+        to_duration(self.duration, self.time_base)
+    }
+}
+
+/// Durations of the streams of an input, see [`exact_duration()`].
+#[derive(Debug, Clone)]
+pub struct ExactDuration {
+    /// Duration of each stream, by stream index, `None` if unknown.
+    pub streams: Vec<Option<StreamDuration>>,
+}
+
+impl ExactDuration {
+    /// Time from the earliest start to the latest end of the streams, the
+    /// duration to display for the whole input. `None` if no duration is
+    /// known.
+    pub fn total(&self) -> Option<Duration> {
+        let (start, end) = self.streams.iter().flatten().fold(None, |acc, x| {
+            let start = av_rescale_q(x.start_time, x.time_base, NANOSECOND);
+            let end = av_rescale_q(x.end_time(), x.time_base, NANOSECOND);
+            Some(match acc {
+                Some((a, b)) => (start.min(a), end.max(b)),
+                None => (start, end),
+            })
+        })?;
+        Some(Duration::from_nanos((end - start).max(0) as u64))
+    }
+}
+
+/// Find the precise duration of each stream of `input`, with the given
+/// `effort`.
+///
+/// The container durations are often missing or estimated from the bitrate,
+/// e.g. for raw ADTS audio, so they are off by seconds. Scanning the packets
+/// demuxes the whole input without decoding it: the duration of a stream
+/// goes from the first timestamp to the end of its last packet, so it's
+/// frame accurate.
+///
+/// After a scan `input` is seeked back to its start, if it's not seekable it
+/// has to be opened again to be read.
+///
+/// ```ignore
+/// let mut input = AVFormatContextInput::open(cstr!("song.aac"), None, &mut None)?;
+/// let durations = exact_duration(&mut input, DurationEffort::Auto)?;
+/// println!("{:?}", durations.total());
+/// ```
+pub fn exact_duration(
+    input: &mut AVFormatContextInput,
+    effort: DurationEffort,
+) -> Result<ExactDuration> {
+    let from_bitrate = input.duration_estimation_method == ffi::AVFMT_DURATION_FROM_BITRATE;
+    let mut streams: Vec<_> = input
+        .streams()
+        .iter()
+        .map(|stream| match effort {
+            DurationEffort::Scan => None,
+            _ if from_bitrate => None,
+            _ => metadata_duration(stream),
+        })
+        .collect();
+    if effort == DurationEffort::Metadata || streams.iter().all(Option::is_some) {
+        return Ok(ExactDuration { streams });
+    }
+
+    let scanned = scan(input)?;
+    for (duration, scanned) in streams.iter_mut().zip(scanned) {
+        if duration.is_none() {
+            *duration = scanned;
+        }
+    }
+    // Best effort, the durations are valid anyway.
+    let _ = input.seek(None, i64::MIN, 0, i64::MAX, 0);
+    Ok(ExactDuration { streams })
+}
+
+fn metadata_duration(stream: &AVStreamRef) -> Option<StreamDuration> {
+    if stream.duration == ffi::AV_NOPTS_VALUE || stream.duration <= 0 {
+        return None;
+    }
+    Some(StreamDuration {
+        time_base: stream.time_base,
+        start_time: match stream.start_time {
+            ffi::AV_NOPTS_VALUE => 0,
+            x => x,
+        },
+        duration: stream.duration,
+        source: DurationSource::Metadata,
+    })
+}
+
+/// Demux all the packets of `input` and find the time range of each stream.
+fn scan(input: &mut AVFormatContextInput) -> Result<Vec<Option<StreamDuration>>> {
+    let frame_durations: Vec<_> = input.streams().iter().map(frame_duration).collect();
+    let mut ranges: Vec<Option<(i64, i64)>> = vec![None; frame_durations.len()];
+    let mut packet = AVPacket::new();
+    while input.read_packet_into(&mut packet)? {
+        let index = packet.stream_index as usize;
+        let Some(range) = ranges.get_mut(index) else {
+            // Stream added while demuxing.
+            continue;
+        };
+        let ts = match (packet.pts, packet.dts) {
+            (ffi::AV_NOPTS_VALUE, ffi::AV_NOPTS_VALUE) => continue,
+            (ffi::AV_NOPTS_VALUE, x) | (x, _) => x,
+        };
+        let duration = match packet.duration {
+            0 => frame_durations[index],
+            x => x,
+        };
+        *range = Some(match *range {
+            Some((start, end)) => (start.min(ts), end.max(ts + duration)),
+            None => (ts, ts + duration),
+        });
+    }
+    Ok(input
+        .streams()
+        .iter()
+        .zip(ranges)
+        .map(|(stream, range)| {
+            let (start, end) = range?;
+            Some(StreamDuration {
+                time_base: stream.time_base,
+                start_time: start,
+                duration: end - start,
+                source: DurationSource::Packets,
+            })
+        })
+        .collect())
+}
+
+/// Duration of a frame of `stream` in its time base, used for the packets
+/// without duration, 0 if unknown.
+fn frame_duration(stream: &AVStreamRef) -> i64 {
+    let codecpar = stream.codecpar();
+    let frame_time = match codecpar.codec_type {
+        ffi::AVMEDIA_TYPE_AUDIO if codecpar.frame_size > 0 && codecpar.sample_rate > 0 => {
+            AVRational {
+                num: codecpar.frame_size,
+                den: codecpar.sample_rate,
+            }
+        }
+        ffi::AVMEDIA_TYPE_VIDEO if stream.avg_frame_rate.num > 0 => AVRational {
+            num: stream.avg_frame_rate.den,
+            den: stream.avg_frame_rate.num,
+        },
+        _ => return 0,
+    };
+    av_rescale_q(1, frame_time, stream.time_base)
+}
+
+const NANOSECOND: AVRational = AVRational {
+    num: 1,
+    den: 1_000_000_000,
+};
+
+fn to_duration(ts: i64, time_base: AVRational) -> Duration {
+    Duration::from_nanos(av_rescale_q(ts, time_base, NANOSECOND).max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_exact_duration() {
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/audios/sample1.aac"), None, &mut None)
+                .unwrap();
+        let scanned = exact_duration(&mut input, DurationEffort::Scan).unwrap();
+        let stream = scanned.streams[0].unwrap();
+        assert_eq!(stream.source, DurationSource::Packets);
+        let total = scanned.total().unwrap().as_secs_f64();
+        assert!((total - stream.to_duration().as_secs_f64()).abs() < 1e-6);
+        // Seeked back to the start.
+        assert!(input.read_packet().unwrap().is_some());
+        // Whole AAC frames.
+        let packets = std::iter::from_fn(|| input.read_packet().unwrap()).count() + 1;
+        let samples = av_rescale_q(
+            stream.duration,
+            stream.time_base,
+            AVRational {
+                num: 1,
+                den: input.streams()[0].codecpar().sample_rate,
+            },
+        );
+        assert_eq!(samples, packets as i64 * 1024);
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let metadata = exact_duration(&mut input, DurationEffort::Auto).unwrap();
+        assert!(metadata
+            .streams
+            .iter()
+            .all(|x| x.unwrap().source == DurationSource::Metadata));
+        let scanned = exact_duration(&mut input, DurationEffort::Scan).unwrap();
+        assert!(scanned
+            .streams
+            .iter()
+            .all(|x| x.unwrap().source == DurationSource::Packets));
+        let total = scanned.total().unwrap().as_secs_f64();
+        assert!((total - metadata.total().unwrap().as_secs_f64()).abs() < 0.1);
+    }
+}
@@ -0,0 +1,284 @@
+use std::{cmp::Ordering, ffi::CStr};
+
+use crate::{
+    avcodec::{AVCodecContext, AVPacket},
+    avfilter::AVFilterGraph,
+    avformat::AVFormatContextOutput,
+    avutil::{av_rescale_q, AVFrame, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Filter graph whose output feeds the encoder of a [`FinalizeStream`].
+pub struct FinalizeFilter<'a> {
+    pub graph: &'a mut AVFilterGraph,
+    /// Name of the `buffer` or `abuffer` filter the frames are pushed into.
+    pub src: &'a CStr,
+    /// Name of the `buffersink` or `abuffersink` filter the encoder frames
+    /// are pulled from.
+    pub sink: &'a CStr,
+}
+
+/// An output stream finished by [`finalize_all()`].
+pub struct FinalizeStream<'a> {
+    pub encoder: &'a mut AVCodecContext,
+    /// Filter graph in front of the encoder, `None` if the frames are sent to
+    /// the encoder directly.
+    pub filter: Option<FinalizeFilter<'a>>,
+    /// Index of the stream of the output receiving the packets.
+    pub stream_index: usize,
+}
+
+impl<'a> FinalizeStream<'a> {
+    /// Stream whose frames are sent to `encoder` directly.
+    pub fn new(encoder: &'a mut AVCodecContext, stream_index: usize) -> Self {
+        Self {
+            encoder,
+            filter: None,
+            stream_index,
+        }
+    }
+
+    /// Set the filter graph in front of the encoder.
+    pub fn with_filter(
+        mut self,
+        graph: &'a mut AVFilterGraph,
+        src: &'a CStr,
+        sink: &'a CStr,
+    ) -> Self {
+        self.filter = Some(FinalizeFilter { graph, src, sink });
+        self
+    }
+}
+
+/// Finish an output once all the input frames have been sent: flush the
+/// filter graphs and the encoders of all the `streams`, write the remaining
+/// packets and the trailer.
+///
+/// The packets coming out of the encoders are written in dts order across
+/// the streams, rather than stream after stream, so the end of the output is
+/// correctly interleaved and none of the last seconds is dropped by the
+/// muxer.
+///
+/// The timestamps of the frames pulled from the filter graphs are rescaled
+/// into the time base of the encoders, the audio sinks need a frame size
+/// matching the encoders, see `av_buffersink_set_frame_size`.
+///
+/// ```ignore
+/// finalize_all(
+///     &mut output,
+///     vec![
+///         FinalizeStream::new(&mut video_encoder, 0).with_filter(
+///             &mut video_graph,
+///             cstr!("in"),
+///             cstr!("out"),
+///         ),
+///         FinalizeStream::new(&mut audio_encoder, 1),
+///     ],
+/// )?;
+/// ```
+pub fn finalize_all(
+    output: &mut AVFormatContextOutput,
+    streams: Vec<FinalizeStream<'_>>,
+) -> Result<()> {
+    let mut packets = vec![];
+    for stream in streams {
+        let time_base = output
+            .streams()
+            .get(stream.stream_index)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?
+            .time_base;
+        let encoder = stream.encoder;
+        let mut stream_packets = vec![];
+        if let Some(filter) = stream.filter {
+            let graph = filter.graph;
+            graph
+                .get_filter(filter.src)
+                .ok_or(RsmpegError::AVError(ffi::AVERROR_FILTER_NOT_FOUND))?
+                .buffersrc_add_frame(None, None)?;
+            let mut sink = graph
+                .get_filter(filter.sink)
+                .ok_or(RsmpegError::AVError(ffi::AVERROR_FILTER_NOT_FOUND))?;
+            loop {
+                let mut frame = match sink.buffersink_get_frame(None) {
+                    Ok(frame) => frame,
+                    Err(RsmpegError::BufferSinkDrainError | RsmpegError::BufferSinkEofError) => {
+                        break
+                    }
+                    Err(e) => return Err(e),
+                };
+                if frame.pts != ffi::AV_NOPTS_VALUE {
+                    frame.set_pts(av_rescale_q(
+                        frame.pts,
+                        sink.get_time_base(),
+                        encoder.time_base,
+                    ));
+                }
+                frame.set_time_base(encoder.time_base);
+                frame.set_pict_type(ffi::AV_PICTURE_TYPE_NONE);
+                encode(encoder, Some(&frame), &mut stream_packets)?;
+            }
+        }
+        encode(encoder, None, &mut stream_packets)?;
+
+        for mut packet in stream_packets {
+            packet.rescale_ts(encoder.time_base, time_base);
+            packet.set_stream_index(stream.stream_index as i32);
+            packets.push((packet, time_base));
+        }
+    }
+
+    packets.sort_by(|(a, a_tb), (b, b_tb)| compare_dts(a, *a_tb, b, *b_tb));
+    for (mut packet, _) in packets {
+        output.interleaved_write_frame(&mut packet)?;
+    }
+    output.interleaved_flush()?;
+    output.write_trailer()
+}
+
+/// Send `frame` to `encoder`, `None` flushes it, and collect the packets
+/// received.
+fn encode(
+    encoder: &mut AVCodecContext,
+    frame: Option<&AVFrame>,
+    packets: &mut Vec<AVPacket>,
+) -> Result<()> {
+    encoder.send_frame(frame)?;
+    loop {
+        match encoder.receive_packet() {
+            Ok(packet) => packets.push(packet),
+            Err(RsmpegError::EncoderDrainError | RsmpegError::EncoderFlushedError) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Order packets of different streams by dts, packets without dts first.
+fn compare_dts(a: &AVPacket, a_tb: AVRational, b: &AVPacket, b_tb: AVRational) -> Ordering {
+    match (a.dts, b.dts) {
+        (ffi::AV_NOPTS_VALUE, ffi::AV_NOPTS_VALUE) => Ordering::Equal,
+        (ffi::AV_NOPTS_VALUE, _) => Ordering::Less,
+        (_, ffi::AV_NOPTS_VALUE) => Ordering::Greater,
+        (a_dts, b_dts) => unsafe { ffi::av_compare_ts(a_dts, a_tb, b_dts, b_tb) }.cmp(&0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        avcodec::AVCodec,
+        avfilter::{AVFilter, AVFilterInOut},
+        avformat::AVFormatContextInput,
+        avutil::ra,
+    };
+    use cstr::cstr;
+    use std::ffi::CString;
+
+    fn encoder(codec_id: ffi::AVCodecID, max_b_frames: i32) -> AVCodecContext {
+        let codec = AVCodec::find_encoder(codec_id).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_width(64);
+        encoder.set_height(48);
+        encoder.set_pix_fmt(codec.pix_fmts().unwrap()[0]);
+        encoder.set_time_base(ra(1, 25));
+        encoder.set_max_b_frames(max_b_frames);
+        encoder.open(None).unwrap();
+        encoder
+    }
+
+    fn frame(pts: i64, pix_fmt: ffi::AVPixelFormat) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_width(64);
+        frame.set_height(48);
+        frame.set_format(pix_fmt);
+        frame.set_pts(pts);
+        frame.alloc_buffer().unwrap();
+        for plane in 0..frame.nb_planes() {
+            frame.plane_data_mut(plane).unwrap().fill(pts as u8);
+        }
+        frame
+    }
+
+    fn null_graph(encoder: &AVCodecContext) -> AVFilterGraph {
+        let graph = AVFilterGraph::new();
+        {
+            let buffersrc = AVFilter::get_by_name(cstr!("buffer")).unwrap();
+            let buffersink = AVFilter::get_by_name(cstr!("buffersink")).unwrap();
+            let args = CString::new(format!(
+                "video_size=64x48:pix_fmt={}:time_base=1/25:pixel_aspect=1/1",
+                encoder.pix_fmt
+            ))
+            .unwrap();
+            let mut src = graph
+                .create_filter_context(&buffersrc, cstr!("in"), Some(&args))
+                .unwrap();
+            let mut sink = graph
+                .create_filter_context(&buffersink, cstr!("out"), None)
+                .unwrap();
+            let outputs = AVFilterInOut::new(cstr!("in"), &mut src, 0);
+            let inputs = AVFilterInOut::new(cstr!("out"), &mut sink, 0);
+            graph
+                .parse_ptr(cstr!("null"), Some(inputs), Some(outputs))
+                .unwrap();
+            graph.config().unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_finalize_all() {
+        let tempdir = tempdir::TempDir::new("finalizer").unwrap();
+        let path = tempdir.path().join("output.mkv");
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let mut mpeg4 = encoder(ffi::AV_CODEC_ID_MPEG4, 2);
+        let mut mjpeg = encoder(ffi::AV_CODEC_ID_MJPEG, 0);
+        let mut graph = null_graph(&mjpeg);
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        for encoder in [&mpeg4, &mjpeg] {
+            let mut stream = output.new_stream();
+            stream.set_codecpar(encoder.extract_codecpar());
+            stream.set_time_base(encoder.time_base);
+        }
+        output.write_header(&mut None).unwrap();
+
+        for pts in 0..10 {
+            let mut packets = vec![];
+            let mpeg4_frame = frame(pts, mpeg4.pix_fmt);
+            encode(&mut mpeg4, Some(&mpeg4_frame), &mut packets).unwrap();
+            for mut packet in packets {
+                output
+                    .write_packet_from(&mut packet, mpeg4.time_base, 0)
+                    .unwrap();
+            }
+            // Frames of the second stream are left in the filter graph.
+            graph
+                .get_filter(cstr!("in"))
+                .unwrap()
+                .buffersrc_add_frame(Some(frame(pts, mjpeg.pix_fmt)), None)
+                .unwrap();
+        }
+
+        finalize_all(
+            &mut output,
+            vec![
+                FinalizeStream::new(&mut mpeg4, 0),
+                FinalizeStream::new(&mut mjpeg, 1).with_filter(
+                    &mut graph,
+                    cstr!("in"),
+                    cstr!("out"),
+                ),
+            ],
+        )
+        .unwrap();
+        drop(output);
+
+        let mut input = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        let mut counts = [0; 2];
+        while let Some(packet) = input.read_packet().unwrap() {
+            counts[packet.stream_index as usize] += 1;
+        }
+        assert_eq!(counts, [10, 10]);
+    }
+}
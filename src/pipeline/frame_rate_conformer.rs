@@ -0,0 +1,162 @@
+use crate::{
+    avutil::{av_inv_q, av_rescale_q_rnd, AVFrame, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Converts a variable frame rate stream of frames into a constant frame
+/// rate one by duplicating and dropping frames, like the `fps` filter but
+/// without a filter graph.
+///
+/// The output frames have the time base `1 / target_fps` and consecutive pts
+/// starting at the slot of the first frame. Each input frame is assigned to
+/// the nearest slot, it's repeated until the slot of the next frame and
+/// dropped when the next frame lands in the same slot.
+///
+/// The input frames must have their `time_base` set, e.g. to the time base
+/// of the stream they are decoded from.
+///
+/// ```ignore
+/// let mut conformer = FrameRateConformer::new(ra(30, 1));
+/// while let Some(mut frame) = decoder.decode_frame()? {
+///     frame.set_time_base(stream.time_base);
+///     for frame in conformer.push_frame(frame)? {
+///         encoder.send_frame(Some(&frame))?;
+///     }
+/// }
+/// if let Some(frame) = conformer.flush() {
+///     encoder.send_frame(Some(&frame))?;
+/// }
+/// ```
+pub struct FrameRateConformer {
+    time_base: AVRational,
+    /// Last frame pushed, not output yet.
+    pending: Option<AVFrame>,
+    /// Pts of the next output frame.
+    next_pts: i64,
+    duplicated: u64,
+    dropped: u64,
+}
+
+impl FrameRateConformer {
+    /// Create a conformer outputting `target_fps` frames per second.
+    pub fn new(target_fps: AVRational) -> Self {
+        Self {
+            time_base: av_inv_q(target_fps),
+            pending: None,
+            next_pts: 0,
+            duplicated: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Time base of the output frames, the inverse of the target frame rate.
+    pub fn time_base(&self) -> AVRational {
+        self.time_base
+    }
+
+    /// Number of copies output in addition to the input frames.
+    pub fn duplicated(&self) -> u64 {
+        self.duplicated
+    }
+
+    /// Number of input frames which haven't been output.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Push a frame, return the frames of the slots before the one of
+    /// `frame`, which is kept until the next call.
+    ///
+    /// Returns `AVERROR(EINVAL)` if `frame` has no pts or no time base.
+    pub fn push_frame(&mut self, frame: AVFrame) -> Result<Vec<AVFrame>> {
+        if frame.pts == ffi::AV_NOPTS_VALUE || frame.time_base.num == 0 || frame.time_base.den == 0
+        {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let slot = av_rescale_q_rnd(
+            frame.pts,
+            frame.time_base,
+            self.time_base,
+            ffi::AV_ROUND_NEAR_INF | ffi::AV_ROUND_PASS_MINMAX,
+        );
+
+        let mut frames = vec![];
+        match self.pending.replace(frame) {
+            None => self.next_pts = slot,
+            Some(_) if slot <= self.next_pts => self.dropped += 1,
+            Some(previous) => {
+                for pts in self.next_pts..slot {
+                    if pts != self.next_pts {
+                        self.duplicated += 1;
+                    }
+                    frames.push(self.retime(&previous, pts));
+                }
+                self.next_pts = slot;
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Return the frame kept by the last [`Self::push_frame()`], output once.
+    pub fn flush(&mut self) -> Option<AVFrame> {
+        let frame = self.pending.take()?;
+        let frame = self.retime(&frame, self.next_pts);
+        self.next_pts += 1;
+        Some(frame)
+    }
+
+    fn retime(&self, frame: &AVFrame, pts: i64) -> AVFrame {
+        let mut frame = frame.clone();
+        frame.set_pts(pts);
+        frame.set_time_base(self.time_base);
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avutil::ra;
+
+    fn frame(pts: i64) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_width(16);
+        frame.set_height(16);
+        frame.set_format(ffi::AV_PIX_FMT_GRAY8);
+        frame.set_pts(pts);
+        frame.set_time_base(ra(1, 1000));
+        frame.alloc_buffer().unwrap();
+        frame.plane_data_mut(0).unwrap().fill((pts / 10) as u8);
+        frame
+    }
+
+    #[test]
+    fn test_frame_rate_conformer() {
+        let mut conformer = FrameRateConformer::new(ra(10, 1));
+        assert_eq!(conformer.time_base().den, 10);
+
+        // Slots of the frames: 1, 1, 2, 5, 6, 6
+        let mut outputs = vec![];
+        for pts in [50, 120, 210, 480, 560, 610] {
+            for frame in conformer.push_frame(frame(pts)).unwrap() {
+                outputs.push((frame.pts, frame.plane_data(0).unwrap()[0]));
+                assert_eq!(frame.time_base.den, 10);
+            }
+        }
+        let last = conformer.flush().unwrap();
+        outputs.push((last.pts, last.plane_data(0).unwrap()[0]));
+        assert!(conformer.flush().is_none());
+
+        assert_eq!(
+            outputs,
+            [(1, 12), (2, 21), (3, 21), (4, 21), (5, 48), (6, 61)]
+        );
+        assert_eq!(conformer.duplicated(), 2);
+        assert_eq!(conformer.dropped(), 2);
+
+        let mut no_pts = frame(0);
+        no_pts.set_pts(ffi::AV_NOPTS_VALUE);
+        assert!(conformer.push_frame(no_pts).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::ffi::{CStr, CString};
+
+use crate::{
+    avfilter::{AVFilter, AVFilterGraph, AVFilterInOut},
+    avutil::{AVFrame, AVPixFmtDescriptorRef, AVPixelFormat},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Region of the input frame kept by [`TransformSpec::crop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CropRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Clockwise rotation applied by [`FrameTransformer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+/// Transform applied by [`FrameTransformer`], the steps are applied in the
+/// order crop, scale, rotate and pixel format conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TransformSpec {
+    /// Region to keep, `None` to keep the whole frame.
+    pub crop: Option<CropRect>,
+    /// Size `(width, height)` of the (cropped) frame after scaling, before
+    /// rotation. `None` to keep the size.
+    pub scale: Option<(i32, i32)>,
+    pub rotate: Rotation,
+    /// Pixel format of the output frames, `None` to keep the input one.
+    pub pix_fmt: Option<AVPixelFormat>,
+}
+
+impl TransformSpec {
+    /// Filter graph description of the transform.
+    fn filter_spec(&self) -> Result<CString> {
+        let mut filters = vec![];
+        if let Some(CropRect {
+            x,
+            y,
+            width,
+            height,
+        }) = self.crop
+        {
+            filters.push(format!("crop={width}:{height}:{x}:{y}:exact=1"));
+        }
+        if let Some((width, height)) = self.scale {
+            filters.push(format!("scale={width}:{height}"));
+        }
+        match self.rotate {
+            Rotation::Rotate0 => {}
+            Rotation::Rotate90 => filters.push("transpose=clock".into()),
+            Rotation::Rotate180 => filters.push("hflip,vflip".into()),
+            Rotation::Rotate270 => filters.push("transpose=cclock".into()),
+        }
+        if let Some(pix_fmt) = self.pix_fmt {
+            let descriptor = AVPixFmtDescriptorRef::get(pix_fmt)
+                .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+            filters.push(format!(
+                "format=pix_fmts={}",
+                descriptor.name().to_string_lossy()
+            ));
+        }
+        if filters.is_empty() {
+            filters.push("null".into());
+        }
+        Ok(CString::new(filters.join(",")).unwrap())
+    }
+}
+
+/// Properties of the input frames the filter graph is configured for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InputProps {
+    width: i32,
+    height: i32,
+    format: i32,
+}
+
+/// Applies a combined crop, scale, rotate and pixel format conversion to
+/// video frames.
+///
+/// The underlying filter graph is created on the first frame and reused for
+/// the following ones, it's only rebuilt when the size or pixel format of the
+/// input frames changes.
+///
+/// ```ignore
+/// let mut transformer = FrameTransformer::new(TransformSpec {
+///     scale: Some((320, 180)),
+///     rotate: Rotation::Rotate90,
+///     pix_fmt: Some(ffi::AV_PIX_FMT_RGB24),
+///     ..Default::default()
+/// });
+/// let rotated = transformer.transform(&frame)?;
+/// ```
+pub struct FrameTransformer {
+    spec: TransformSpec,
+    graph: Option<(AVFilterGraph, InputProps)>,
+}
+
+impl FrameTransformer {
+    /// Create a transformer applying `spec`.
+    pub fn new(spec: TransformSpec) -> Self {
+        Self { spec, graph: None }
+    }
+
+    /// Get the transform applied.
+    pub fn spec(&self) -> &TransformSpec {
+        &self.spec
+    }
+
+    /// Transform a software video frame, the pts and the other properties of
+    /// the frame are kept.
+    pub fn transform(&mut self, frame: &AVFrame) -> Result<AVFrame> {
+        let props = InputProps {
+            width: frame.width,
+            height: frame.height,
+            format: frame.format,
+        };
+        let graph = match &mut self.graph {
+            Some((graph, x)) if *x == props => graph,
+            graph => {
+                let new_graph = Self::init_graph(&self.spec, frame)?;
+                &mut graph.insert((new_graph, props)).0
+            }
+        };
+
+        graph
+            .get_filter(CStr::from_bytes_with_nul(b"in\0").unwrap())
+            .unwrap()
+            .buffersrc_add_frame(Some(frame.clone()), None)?;
+        graph
+            .get_filter(CStr::from_bytes_with_nul(b"out\0").unwrap())
+            .unwrap()
+            .buffersink_get_frame(None)
+    }
+
+    fn init_graph(spec: &TransformSpec, frame: &AVFrame) -> Result<AVFilterGraph> {
+        if frame.width <= 0 || frame.height <= 0 {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let time_base = match frame.time_base {
+            ffi::AVRational { num: 0, .. } | ffi::AVRational { den: 0, .. } => ffi::AVRational {
+                num: 1,
+                den: ffi::AV_TIME_BASE as i32,
+            },
+            x => x,
+        };
+        let sample_aspect_ratio = match frame.sample_aspect_ratio {
+            ffi::AVRational { den: 0, .. } => ffi::AVRational { num: 0, den: 1 },
+            x => x,
+        };
+        let args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+            frame.width,
+            frame.height,
+            frame.format,
+            time_base.num,
+            time_base.den,
+            sample_aspect_ratio.num,
+            sample_aspect_ratio.den,
+        );
+        let args = CString::new(args).unwrap();
+        let filter_spec = spec.filter_spec()?;
+
+        let graph = AVFilterGraph::new();
+        {
+            let buffersrc =
+                AVFilter::get_by_name(CStr::from_bytes_with_nul(b"buffer\0").unwrap()).unwrap();
+            let buffersink =
+                AVFilter::get_by_name(CStr::from_bytes_with_nul(b"buffersink\0").unwrap()).unwrap();
+            let in_name = CStr::from_bytes_with_nul(b"in\0").unwrap();
+            let out_name = CStr::from_bytes_with_nul(b"out\0").unwrap();
+            let mut src = graph.create_filter_context(&buffersrc, in_name, Some(&args))?;
+            let mut sink = graph.create_filter_context(&buffersink, out_name, None)?;
+
+            let outputs = AVFilterInOut::new(in_name, &mut src, 0);
+            let inputs = AVFilterInOut::new(out_name, &mut sink, 0);
+            graph.parse_ptr(&filter_spec, Some(inputs), Some(outputs))?;
+        }
+        graph.config()?;
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_frame(width: i32, height: i32) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_width(width);
+        frame.set_height(height);
+        frame.set_format(ffi::AV_PIX_FMT_YUV420P);
+        frame.set_pts(42);
+        frame.alloc_buffer().unwrap();
+        frame
+    }
+
+    #[test]
+    fn test_filter_spec() {
+        assert_eq!(
+            TransformSpec::default()
+                .filter_spec()
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "null"
+        );
+        let spec = TransformSpec {
+            crop: Some(CropRect {
+                x: 2,
+                y: 4,
+                width: 10,
+                height: 20,
+            }),
+            scale: Some((40, 30)),
+            rotate: Rotation::Rotate270,
+            pix_fmt: Some(ffi::AV_PIX_FMT_RGB24),
+        };
+        assert_eq!(
+            spec.filter_spec().unwrap().to_str().unwrap(),
+            "crop=10:20:2:4:exact=1,scale=40:30,transpose=cclock,format=pix_fmts=rgb24"
+        );
+    }
+
+    #[test]
+    fn test_frame_transformer() {
+        let mut transformer = FrameTransformer::new(TransformSpec {
+            crop: Some(CropRect {
+                x: 0,
+                y: 0,
+                width: 64,
+                height: 32,
+            }),
+            scale: Some((32, 16)),
+            rotate: Rotation::Rotate90,
+            pix_fmt: Some(ffi::AV_PIX_FMT_RGB24),
+        });
+        for _ in 0..3 {
+            let output = transformer.transform(&gray_frame(128, 64)).unwrap();
+            assert_eq!(output.width, 16);
+            assert_eq!(output.height, 32);
+            assert_eq!(output.format, ffi::AV_PIX_FMT_RGB24);
+            assert_eq!(output.pts, 42);
+        }
+
+        // Input size changes, the filter graph is rebuilt.
+        let output = transformer.transform(&gray_frame(96, 96)).unwrap();
+        assert_eq!(output.width, 16);
+        assert_eq!(output.height, 32);
+    }
+}
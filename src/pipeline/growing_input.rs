@@ -0,0 +1,230 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    avcodec::AVPacket,
+    avformat::{AVFormatContextInput, AVIOContextContainer, AVIOContextCustom},
+    avutil::AVMem,
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// How [`GrowingInput`] waits for new data at the end of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrowingOptions {
+    /// Delay before the first retry, doubled on each retry.
+    pub initial_backoff: Duration,
+    /// Maximum delay between two retries.
+    pub max_backoff: Duration,
+    /// Give up once no data has been appended for this long, `None` to wait
+    /// forever.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for GrowingOptions {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            idle_timeout: None,
+        }
+    }
+}
+
+/// Input of a file which is still being written by another process, e.g. a
+/// live recording in MPEG-TS or fragmented MP4.
+///
+/// While `follow` is enabled, reaching the end of the file doesn't end the
+/// input: reading is retried with an exponential backoff until new data is
+/// appended or the idle timeout expires. The waiting happens in the IO layer,
+/// so the demuxer never sees a truncated packet. Formats which need the end
+/// of the file to be demuxed, e.g. MP4 with the `moov` box at the end, can't
+/// be followed.
+///
+/// ```ignore
+/// let mut input = GrowingInput::open("recording.ts", GrowingOptions {
+///     idle_timeout: Some(Duration::from_secs(10)),
+///     ..Default::default()
+/// })?;
+/// while let Some(packet) = input.read_packet()? {
+///     // ...
+/// }
+/// ```
+pub struct GrowingInput {
+    input: AVFormatContextInput,
+    follow: Arc<AtomicBool>,
+}
+
+impl GrowingInput {
+    /// Open the local file at `path` and follow it.
+    pub fn open(path: impl AsRef<Path>, options: GrowingOptions) -> Result<Self> {
+        let mut file = File::open(path).map_err(io_error)?;
+        let mut seek_file = file.try_clone().map_err(io_error)?;
+        let follow = Arc::new(AtomicBool::new(true));
+
+        let read_follow = follow.clone();
+        let io_context = AVIOContextCustom::alloc_context(
+            AVMem::new(4096),
+            false,
+            vec![],
+            Some(Box::new(move |_, buf| {
+                let mut backoff = options.initial_backoff;
+                let idle_since = Instant::now();
+                loop {
+                    match file.read(buf) {
+                        Ok(0) => {}
+                        Ok(len) => return len as i32,
+                        Err(_) => return ffi::AVERROR(ffi::EIO),
+                    }
+                    if !read_follow.load(Ordering::Relaxed) {
+                        return ffi::AVERROR_EOF;
+                    }
+                    let idle = idle_since.elapsed();
+                    let delay = match options.idle_timeout {
+                        Some(timeout) if idle >= timeout => return ffi::AVERROR_EOF,
+                        Some(timeout) => backoff.min(timeout - idle),
+                        None => backoff,
+                    };
+                    thread::sleep(delay);
+                    backoff = (backoff * 2).min(options.max_backoff);
+                }
+            })),
+            None,
+            Some(Box::new(move |_, offset, whence| {
+                if whence & ffi::AVSEEK_SIZE as i32 != 0 {
+                    return match seek_file.metadata() {
+                        Ok(x) => x.len() as i64,
+                        Err(_) => -1,
+                    };
+                }
+                let pos = match (whence & !(ffi::AVSEEK_FORCE as i32)) as u32 {
+                    ffi::SEEK_SET => SeekFrom::Start(offset as u64),
+                    ffi::SEEK_CUR => SeekFrom::Current(offset),
+                    ffi::SEEK_END => SeekFrom::End(offset),
+                    _ => return -1,
+                };
+                // The read and seek callbacks share the file offset.
+                match seek_file.seek(pos) {
+                    Ok(x) => x as i64,
+                    Err(_) => -1,
+                }
+            })),
+        );
+        let input =
+            AVFormatContextInput::from_io_context(AVIOContextContainer::Custom(io_context))?;
+        Ok(Self { input, follow })
+    }
+
+    /// Whether to wait for more data at the end of the file, enabled by
+    /// default. Disable it once the writer is known to be done, to read the
+    /// rest of the file without waiting.
+    pub fn set_follow(&self, follow: bool) {
+        self.follow.store(follow, Ordering::Relaxed);
+    }
+
+    /// Get the underlying input, e.g. to get the streams.
+    pub fn input(&self) -> &AVFormatContextInput {
+        &self.input
+    }
+
+    /// Read the next packet, waiting for more data at the end of the file if
+    /// `follow` is enabled.
+    ///
+    /// Returns `Ok(None)` at the end of the file if `follow` is disabled or
+    /// the idle timeout expired.
+    pub fn read_packet(&mut self) -> Result<Option<AVPacket>> {
+        self.input.read_packet()
+    }
+}
+
+/// Convert an I/O error to `AVERROR(errno)`, `AVERROR(EIO)` if it has no OS
+/// error.
+pub(super) fn io_error(err: io::Error) -> RsmpegError {
+    let errno = err.raw_os_error().map_or(ffi::EIO, |x| x as u32);
+    RsmpegError::AVError(ffi::AVERROR(errno))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avformat::AVFormatContextOutput;
+    use cstr::cstr;
+    use std::{ffi::CString, fs, io::Write};
+
+    #[test]
+    fn test_growing_input() {
+        let tempdir = tempdir::TempDir::new("growing").unwrap();
+        let full_path = tempdir.path().join("full.ts");
+        let full_path_c = CString::new(full_path.to_str().unwrap()).unwrap();
+
+        // Remux to MPEG-TS, which can be demuxed while being written.
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let mut output = AVFormatContextOutput::create(&full_path_c, None).unwrap();
+        for stream in input.streams() {
+            output.new_stream_copy(stream);
+        }
+        output.write_header(&mut None).unwrap();
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            let index = packet.stream_index as usize;
+            packet.rescale_ts(
+                input.streams()[index].time_base,
+                output.streams()[index].time_base,
+            );
+            output.interleaved_write_frame(&mut packet).unwrap();
+        }
+        output.write_trailer().unwrap();
+        drop(output);
+
+        let mut expected = vec![];
+        let mut full = AVFormatContextInput::open(&full_path_c, None, &mut None).unwrap();
+        while let Some(packet) = full.read_packet().unwrap() {
+            expected.push((packet.stream_index, packet.pts, packet.size));
+        }
+
+        // Write the first half of the file, then append the rest later.
+        let data = fs::read(&full_path).unwrap();
+        let split = data.len() / 2 + 100;
+        let path = tempdir.path().join("growing.ts");
+        fs::write(&path, &data[..split]).unwrap();
+        let writer = {
+            let path = path.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(200));
+                let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+                file.write_all(&data[split..]).unwrap();
+            })
+        };
+
+        let options = GrowingOptions {
+            idle_timeout: Some(Duration::from_millis(500)),
+            ..Default::default()
+        };
+        let mut growing = GrowingInput::open(&path, options).unwrap();
+        let mut packets = vec![];
+        while let Some(packet) = growing.read_packet().unwrap() {
+            packets.push((packet.stream_index, packet.pts, packet.size));
+        }
+        writer.join().unwrap();
+        assert_eq!(packets, expected);
+    }
+
+    #[test]
+    fn test_growing_input_no_follow() {
+        let options = GrowingOptions::default();
+        let mut growing = GrowingInput::open("tests/assets/vids/centaur.mpg", options).unwrap();
+        // Without an idle timeout, this would wait forever.
+        growing.set_follow(false);
+        while growing.read_packet().unwrap().is_some() {}
+    }
+}
@@ -0,0 +1,209 @@
+use std::ptr;
+
+use crate::{
+    avutil::{AVFrame, AVPixelFormat, PixelFormat},
+    error::{Result, RsmpegError},
+    ffi,
+    shared::RetUpgrade,
+    swscale::SwsContext,
+};
+
+/// Turns the frames of a hardware decoder into software frames of a given
+/// pixel format, for consumers only accepting frames in system memory.
+///
+/// For each frame:
+/// - Frames in GPU memory are transferred to system memory, directly in the
+///   requested pixel format if the device supports it, in the format chosen
+///   by the device otherwise, e.g. NV12 or P010.
+/// - Software frames whose pixel format differs from the requested one are
+///   converted with `swscale`.
+/// - Other frames are returned as is.
+///
+/// So the same code path handles the hardware decoders and their software
+/// fallback.
+///
+/// ```ignore
+/// let mut downloader = HwFrameDownloader::new(ffi::AV_PIX_FMT_YUV420P);
+/// while let Some(frame) = decoder.decode_frame()? {
+///     let frame = downloader.download(frame)?;
+///     encoder.send_frame(Some(&frame))?;
+/// }
+/// ```
+pub struct HwFrameDownloader {
+    pix_fmt: Option<AVPixelFormat>,
+    sws_flags: u32,
+    sws: Option<SwsContext>,
+}
+
+impl HwFrameDownloader {
+    /// Create a downloader outputting `pix_fmt` frames.
+    pub fn new(pix_fmt: impl Into<AVPixelFormat>) -> Self {
+        Self {
+            pix_fmt: Some(pix_fmt.into()),
+            sws_flags: ffi::SWS_BICUBIC,
+            sws: None,
+        }
+    }
+
+    /// Create a downloader only transferring the frames to system memory,
+    /// keeping the format chosen by the device.
+    pub fn download_only() -> Self {
+        Self {
+            pix_fmt: None,
+            sws_flags: ffi::SWS_BICUBIC,
+            sws: None,
+        }
+    }
+
+    /// Pixel format of the output frames, `None` if it's chosen by the
+    /// device.
+    pub fn pix_fmt(&self) -> Option<PixelFormat> {
+        self.pix_fmt.map(PixelFormat)
+    }
+
+    /// Set the `SWS_*` flags of the conversions, `SWS_BICUBIC` by default.
+    pub fn set_sws_flags(&mut self, flags: u32) {
+        self.sws_flags = flags;
+    }
+
+    /// Return `frame` in system memory and in the requested pixel format, the
+    /// pts and the other properties of the frame are kept.
+    pub fn download(&mut self, frame: AVFrame) -> Result<AVFrame> {
+        let frame = if frame.hw_frames_ctx.is_null() {
+            frame
+        } else {
+            let mut sw_frame = AVFrame::new();
+            if let Some(pix_fmt) = self.pix_fmt {
+                if transfer_formats(&frame)?.contains(&pix_fmt) {
+                    sw_frame.set_format(pix_fmt);
+                }
+            }
+            sw_frame.hwframe_transfer_data(&frame)?;
+            copy_props(&mut sw_frame, &frame)?;
+            sw_frame
+        };
+        match self.pix_fmt {
+            Some(pix_fmt) if pix_fmt != frame.format => self.convert(&frame, pix_fmt),
+            _ => Ok(frame),
+        }
+    }
+
+    /// Convert `frame` into `pix_fmt`, keeping its size.
+    fn convert(&mut self, frame: &AVFrame, pix_fmt: AVPixelFormat) -> Result<AVFrame> {
+        let (width, height, format) = (frame.width, frame.height, frame.format);
+        let sws = match self.sws.take() {
+            Some(sws) => sws.get_cached_context(
+                width,
+                height,
+                format,
+                width,
+                height,
+                pix_fmt,
+                self.sws_flags,
+                None,
+                None,
+                None,
+            ),
+            None => SwsContext::get_context(
+                width,
+                height,
+                format,
+                width,
+                height,
+                pix_fmt,
+                self.sws_flags,
+                None,
+                None,
+                None,
+            ),
+        }
+        .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        let sws = self.sws.insert(sws);
+
+        let mut output = AVFrame::new();
+        output.set_width(width);
+        output.set_height(height);
+        output.set_format(pix_fmt);
+        output.alloc_buffer()?;
+        sws.scale_frame(frame, 0, height, &mut output)?;
+        copy_props(&mut output, frame)?;
+        Ok(output)
+    }
+}
+
+/// Pixel formats a hardware frame can be transferred into.
+fn transfer_formats(frame: &AVFrame) -> Result<Vec<AVPixelFormat>> {
+    let mut formats = ptr::null_mut();
+    unsafe {
+        ffi::av_hwframe_transfer_get_formats(
+            frame.hw_frames_ctx,
+            ffi::AV_HWFRAME_TRANSFER_DIRECTION_FROM,
+            &mut formats,
+            0,
+        )
+    }
+    .upgrade()?;
+    let mut result = vec![];
+    if !formats.is_null() {
+        let mut ptr = formats;
+        while unsafe { *ptr } != ffi::AV_PIX_FMT_NONE {
+            result.push(unsafe { *ptr });
+            ptr = unsafe { ptr.add(1) };
+        }
+        unsafe { ffi::av_free(formats as _) };
+    }
+    Ok(result)
+}
+
+/// Copy the metadata fields of `src`, e.g. pts and color properties.
+fn copy_props(dst: &mut AVFrame, src: &AVFrame) -> Result<()> {
+    unsafe { ffi::av_frame_copy_props(dst.as_mut_ptr(), src.as_ptr()) }.upgrade()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_frame(pts: i64) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_width(32);
+        frame.set_height(16);
+        frame.set_format(ffi::AV_PIX_FMT_YUV420P);
+        frame.set_pts(pts);
+        frame.alloc_buffer().unwrap();
+        for plane in 0..3 {
+            frame.plane_data_mut(plane).unwrap().fill(128);
+        }
+        frame
+    }
+
+    #[test]
+    fn test_hw_frame_downloader() {
+        let mut downloader = HwFrameDownloader::new(ffi::AV_PIX_FMT_RGB24);
+        assert_eq!(
+            downloader.pix_fmt(),
+            Some(PixelFormat(ffi::AV_PIX_FMT_RGB24))
+        );
+        for pts in 0..3 {
+            let frame = downloader.download(gray_frame(pts)).unwrap();
+            assert_eq!(frame.format, ffi::AV_PIX_FMT_RGB24);
+            assert_eq!((frame.width, frame.height), (32, 16));
+            assert_eq!(frame.pts, pts);
+            let pixel = &frame.plane_data(0).unwrap()[..3];
+            assert!(pixel.iter().all(|x| x.abs_diff(128) <= 4));
+        }
+
+        let mut downloader = HwFrameDownloader::new(ffi::AV_PIX_FMT_YUV420P);
+        let frame = gray_frame(5);
+        let data = frame.data[0];
+        let frame = downloader.download(frame).unwrap();
+        assert_eq!(frame.data[0], data);
+
+        let mut downloader = HwFrameDownloader::download_only();
+        assert_eq!(downloader.pix_fmt(), None);
+        let frame = downloader.download(gray_frame(7)).unwrap();
+        assert_eq!(frame.format, ffi::AV_PIX_FMT_YUV420P);
+        assert_eq!(frame.pts, 7);
+    }
+}
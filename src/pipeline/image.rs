@@ -0,0 +1,140 @@
+//! One-shot encoding and decoding of still images, e.g. PNG or JPEG.
+use crate::{
+    avcodec::{AVCodec, AVCodecContext, CodecFlags},
+    avformat::{AVFormatContextInput, AVIOContextContainer, AVIOContextCustom},
+    avutil::{AVFrame, AVMem, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+    pipeline::StreamDecoder,
+    shared::*,
+    swscale::SwsContext,
+};
+
+/// Encode `frame` into an image with the encoder of `codec_id`, e.g.
+/// `AV_CODEC_ID_PNG` or `AV_CODEC_ID_MJPEG`. The frame is converted first
+/// if its pixel format isn't supported by the encoder.
+///
+/// `quality` is the quantizer scale for lossy encoders (2 to 31 for JPEG,
+/// lower is better) and the compression level for lossless ones (0 to 9 for
+/// PNG, higher is smaller). `None` uses the encoder defaults.
+pub fn encode_image(
+    frame: &AVFrame,
+    codec_id: ffi::AVCodecID,
+    quality: Option<i32>,
+) -> Result<Vec<u8>> {
+    let codec = AVCodec::find_encoder(codec_id)
+        .ok_or(RsmpegError::AVError(ffi::AVERROR_ENCODER_NOT_FOUND))?;
+    let pix_fmt = match codec.pix_fmts() {
+        Some(pix_fmts) if !pix_fmts.contains(&frame.format) => *pix_fmts
+            .first()
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?,
+        _ => frame.format,
+    };
+    let mut frame = if pix_fmt == frame.format {
+        frame.clone()
+    } else {
+        convert_frame(frame, pix_fmt)?
+    };
+
+    let mut encoder = AVCodecContext::new(&codec);
+    encoder.set_width(frame.width);
+    encoder.set_height(frame.height);
+    encoder.set_pix_fmt(pix_fmt);
+    encoder.set_time_base(AVRational { num: 1, den: 25 });
+    if let Some(quality) = quality {
+        encoder.set_codec_flags(encoder.codec_flags() | CodecFlags::QSCALE);
+        unsafe {
+            encoder.deref_mut().global_quality = quality * ffi::FF_QP2LAMBDA as i32;
+            encoder.deref_mut().compression_level = quality;
+            frame.deref_mut().quality = quality * ffi::FF_QP2LAMBDA as i32;
+        }
+    }
+    encoder.open(None)?;
+
+    encoder.send_frame(Some(&frame))?;
+    encoder.send_frame(None)?;
+    let packet = encoder.receive_packet()?;
+    Ok(packet.data().to_vec())
+}
+
+/// Decode an image file held in memory, e.g. PNG or JPEG, into a frame.
+pub fn decode_image(data: &[u8]) -> Result<AVFrame> {
+    let mut current = 0;
+    let io_context = AVIOContextCustom::alloc_context(
+        AVMem::new(4096),
+        false,
+        data.to_vec(),
+        Some(Box::new(move |data, buf| {
+            let right = data.len().min(current + buf.len());
+            if right <= current {
+                return ffi::AVERROR_EOF;
+            }
+            let read_len = right - current;
+            buf[..read_len].copy_from_slice(&data[current..right]);
+            current = right;
+            read_len as i32
+        })),
+        None,
+        None,
+    );
+    let input = AVFormatContextInput::from_io_context(AVIOContextContainer::Custom(io_context))?;
+    let mut decoder = StreamDecoder::from_input(input, ffi::AVMEDIA_TYPE_VIDEO)?
+        .ok_or(RsmpegError::AVError(ffi::AVERROR_STREAM_NOT_FOUND))?;
+    decoder
+        .decode_frame()?
+        .ok_or(RsmpegError::AVError(ffi::AVERROR_INVALIDDATA))
+}
+
+/// Convert `frame` into `pix_fmt`, keeping its size.
+fn convert_frame(frame: &AVFrame, pix_fmt: ffi::AVPixelFormat) -> Result<AVFrame> {
+    let mut context = SwsContext::get_context(
+        frame.width,
+        frame.height,
+        frame.format,
+        frame.width,
+        frame.height,
+        pix_fmt,
+        ffi::SWS_BICUBIC,
+        None,
+        None,
+        None,
+    )
+    .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+    let mut output = AVFrame::new();
+    output.set_width(frame.width);
+    output.set_height(frame.height);
+    output.set_format(pix_fmt);
+    output.alloc_buffer()?;
+    context.scale_frame(frame, 0, frame.height, &mut output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_roundtrip() {
+        let jpeg = std::fs::read("tests/assets/pics/bear.jpg").unwrap();
+        let frame = decode_image(&jpeg).unwrap();
+        assert!(frame.width > 0 && frame.height > 0);
+
+        let png = encode_image(&frame, ffi::AV_CODEC_ID_PNG, Some(9)).unwrap();
+        assert_eq!(&png[..4], b"\x89PNG");
+        let decoded = decode_image(&png).unwrap();
+        assert_eq!(decoded.width, frame.width);
+        assert_eq!(decoded.height, frame.height);
+        assert_ne!(decoded.format, frame.format);
+
+        // A lower quantizer gives a bigger image.
+        let jpeg_hq = encode_image(&decoded, ffi::AV_CODEC_ID_MJPEG, Some(2)).unwrap();
+        let jpeg_lq = encode_image(&decoded, ffi::AV_CODEC_ID_MJPEG, Some(20)).unwrap();
+        assert_eq!(&jpeg_hq[..2], b"\xff\xd8");
+        assert!(jpeg_hq.len() > jpeg_lq.len());
+    }
+
+    #[test]
+    fn test_decode_invalid_image() {
+        assert!(decode_image(b"not an image").is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+
+use crate::{
+    avcodec::AVPacket,
+    avformat::AVFormatContextOutput,
+    avutil::{av_rescale_q, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+const AV_TIME_BASE_Q: AVRational = AVRational {
+    num: 1,
+    den: ffi::AV_TIME_BASE as i32,
+};
+
+/// What [`InterleaveLimiter::write()`] does when writing a packet would
+/// exceed the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterleaveOverflow {
+    /// Don't write the packet and return `AVERROR(ENOBUFS)`, the caller should
+    /// feed [`InterleaveLimiter::lagging_stream()`] before retrying.
+    Error,
+    /// Flush the interleaving queue of the muxer, then write the packet. The
+    /// packets already buffered are written out of order.
+    Flush,
+}
+
+/// Bounds the memory used by the interleaving queue of
+/// [`AVFormatContextOutput::interleaved_write_frame()`].
+///
+/// The muxer holds a packet back until every stream has a packet with a
+/// later dts, or until `max_interleave_delta` is exceeded, so a stalled
+/// stream makes the queue grow without bound. The limiter estimates the
+/// buffered bytes from the size and dts of the written packets.
+///
+/// ```ignore
+/// let mut limiter = InterleaveLimiter::new(64 << 20, InterleaveOverflow::Error);
+/// match limiter.write(&mut output, &mut packet) {
+///     Err(e) if e.raw_error() == Some(ffi::AVERROR(ffi::ENOBUFS)) => {
+///         // Read from `limiter.lagging_stream()` first.
+///     }
+///     x => x?,
+/// }
+/// ```
+pub struct InterleaveLimiter {
+    max_buffered_bytes: usize,
+    overflow: InterleaveOverflow,
+    /// Latest dts of each stream, in `AV_TIME_BASE` units.
+    last_dts: Vec<Option<i64>>,
+    /// dts (in `AV_TIME_BASE` units) and size of the packets estimated to be
+    /// buffered, in writing order.
+    buffered: VecDeque<(i64, usize)>,
+    buffered_bytes: usize,
+}
+
+impl InterleaveLimiter {
+    /// Limit the interleaving queue to about `max_buffered_bytes`.
+    pub fn new(max_buffered_bytes: usize, overflow: InterleaveOverflow) -> Self {
+        Self {
+            max_buffered_bytes,
+            overflow,
+            last_dts: vec![],
+            buffered: VecDeque::new(),
+            buffered_bytes: 0,
+        }
+    }
+
+    /// Estimated size in bytes of the packets buffered by the muxer.
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes
+    }
+
+    /// Index of the stream holding back the interleaving queue, i.e. the one
+    /// with the earliest dts. Returns `None` before any packet is written.
+    pub fn lagging_stream(&self) -> Option<usize> {
+        if self.buffered.is_empty() {
+            return None;
+        }
+        self.last_dts
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, x)| x.unwrap_or(i64::MIN))
+            .map(|(i, _)| i)
+    }
+
+    /// Write `packet` with [`AVFormatContextOutput::interleaved_write_frame()`],
+    /// the timestamps of `packet` should be in the time base of its output
+    /// stream.
+    pub fn write(
+        &mut self,
+        output: &mut AVFormatContextOutput,
+        packet: &mut AVPacket,
+    ) -> Result<()> {
+        let index = packet.stream_index as usize;
+        let time_base = output
+            .streams()
+            .get(index)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?
+            .time_base;
+        let size = packet.size as usize;
+        if self.buffered_bytes + size > self.max_buffered_bytes && !self.buffered.is_empty() {
+            match self.overflow {
+                InterleaveOverflow::Error => {
+                    return Err(RsmpegError::AVError(ffi::AVERROR(ffi::ENOBUFS)))
+                }
+                InterleaveOverflow::Flush => {
+                    output.interleaved_flush()?;
+                    self.buffered.clear();
+                    self.buffered_bytes = 0;
+                }
+            }
+        }
+
+        let dts = match packet.dts {
+            ffi::AV_NOPTS_VALUE => None,
+            x => Some(av_rescale_q(x, time_base, AV_TIME_BASE_Q)),
+        };
+        output.interleaved_write_frame(packet)?;
+
+        let nb_streams = output.streams().len();
+        self.last_dts.resize(nb_streams, None);
+        if let Some(dts) = dts {
+            self.last_dts[index] = Some(dts);
+            self.buffered.push_back((dts, size));
+            self.buffered_bytes += size;
+        }
+        self.release(output);
+        Ok(())
+    }
+
+    /// Forget the packets the muxer has written.
+    fn release(&mut self, output: &AVFormatContextOutput) {
+        let streams = output.streams();
+        // Attachments are not interleaved.
+        let interleaved = |i: usize| {
+            let stream = &streams[i];
+            stream.codecpar().codec_type != ffi::AVMEDIA_TYPE_ATTACHMENT
+                && stream.disposition & ffi::AV_DISPOSITION_ATTACHED_PIC as i32 == 0
+        };
+        let mut threshold = (0..self.last_dts.len())
+            .filter(|&i| interleaved(i))
+            .map(|i| self.last_dts[i].unwrap_or(i64::MIN))
+            .min()
+            .unwrap_or(i64::MIN);
+        if output.max_interleave_delta > 0 {
+            let max_dts = self.last_dts.iter().flatten().max().copied();
+            if let Some(max_dts) = max_dts {
+                threshold = threshold.max(max_dts - output.max_interleave_delta);
+            }
+        }
+
+        let mut remaining = VecDeque::with_capacity(self.buffered.len());
+        for (dts, size) in self.buffered.drain(..) {
+            if dts <= threshold {
+                self.buffered_bytes -= size;
+            } else {
+                remaining.push_back((dts, size));
+            }
+        }
+        self.buffered = remaining;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avformat::AVFormatContextInput;
+    use cstr::cstr;
+    use std::ffi::CString;
+
+    fn open_output(
+        tempdir: &tempdir::TempDir,
+    ) -> (AVFormatContextInput, AVFormatContextOutput, usize) {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let path = tempdir.path().join("out.mkv");
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        for stream in input.streams() {
+            output.new_stream_copy(stream);
+        }
+        // Disable the time based flushing of the muxer.
+        output.set_max_interleave_delta(0);
+        output.write_header(&mut None).unwrap();
+        let (video_index, _) = input.video_streams().next().unwrap();
+        (input, output, video_index)
+    }
+
+    #[test]
+    fn test_interleave_limiter_error() {
+        let tempdir = tempdir::TempDir::new("interleave").unwrap();
+        let (mut input, mut output, video_index) = open_output(&tempdir);
+        let mut limiter = InterleaveLimiter::new(20_000, InterleaveOverflow::Error);
+
+        // Only write the video stream, so the audio stream stalls.
+        let mut result = Ok(());
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            if packet.stream_index as usize != video_index {
+                continue;
+            }
+            let index = packet.stream_index as usize;
+            packet.rescale_ts(
+                input.streams()[index].time_base,
+                output.streams()[index].time_base,
+            );
+            result = limiter.write(&mut output, &mut packet);
+            if result.is_err() {
+                break;
+            }
+            assert!(limiter.buffered_bytes() <= 20_000);
+        }
+        assert_eq!(
+            result.unwrap_err(),
+            RsmpegError::AVError(ffi::AVERROR(ffi::ENOBUFS))
+        );
+        assert_ne!(limiter.lagging_stream(), Some(video_index));
+    }
+
+    #[test]
+    fn test_interleave_limiter_flush() {
+        let tempdir = tempdir::TempDir::new("interleave").unwrap();
+        let (mut input, mut output, _) = open_output(&tempdir);
+        let mut limiter = InterleaveLimiter::new(20_000, InterleaveOverflow::Flush);
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            let index = packet.stream_index as usize;
+            packet.rescale_ts(
+                input.streams()[index].time_base,
+                output.streams()[index].time_base,
+            );
+            let size = packet.size as usize;
+            limiter.write(&mut output, &mut packet).unwrap();
+            assert!(limiter.buffered_bytes() <= 20_000 + size);
+        }
+        output.write_trailer().unwrap();
+    }
+}
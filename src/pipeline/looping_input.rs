@@ -0,0 +1,154 @@
+use std::ffi::CStr;
+
+use crate::{
+    avcodec::AVPacket,
+    avformat::AVFormatContextInput,
+    avutil::{av_rescale_q, AVRational},
+    error::Result,
+    ffi,
+};
+
+const AV_TIME_BASE_Q: AVRational = AVRational {
+    num: 1,
+    den: ffi::AV_TIME_BASE as i32,
+};
+
+/// Input which is played in a loop: on EOF, it seeks back to the start and
+/// offsets the timestamps of the following packets, so they stay monotonic.
+///
+/// Useful for test signal generation and playout services.
+pub struct LoopingInput {
+    input: AVFormatContextInput,
+    max_loops: Option<u64>,
+    /// Number of completed loops.
+    loops: u64,
+    /// Timestamp offset of the current loop, in `AV_TIME_BASE` units.
+    offset: i64,
+    /// Start of the input, in `AV_TIME_BASE` units.
+    start: i64,
+    /// End of the latest packet of current loop, in `AV_TIME_BASE` units,
+    /// without offset.
+    end: i64,
+    /// Whether the current loop has returned any packet.
+    has_packet: bool,
+}
+
+impl LoopingInput {
+    /// Loop the given input.
+    pub fn new(input: AVFormatContextInput) -> Self {
+        let start = match input.start_time {
+            ffi::AV_NOPTS_VALUE => 0,
+            x => x,
+        };
+        Self {
+            input,
+            max_loops: None,
+            loops: 0,
+            offset: 0,
+            start,
+            end: start,
+            has_packet: false,
+        }
+    }
+
+    /// Open `url` and loop it.
+    pub fn open(url: &CStr) -> Result<Self> {
+        let input = AVFormatContextInput::open(url, None, &mut None)?;
+        Ok(Self::new(input))
+    }
+
+    /// Limit the number of times the input is played, `None` (the default)
+    /// for infinite looping.
+    pub fn set_max_loops(&mut self, max_loops: Option<u64>) {
+        self.max_loops = max_loops;
+    }
+
+    /// Number of completed loops.
+    pub fn loop_count(&self) -> u64 {
+        self.loops
+    }
+
+    /// Get the underlying input, e.g. to get the streams.
+    pub fn input(&self) -> &AVFormatContextInput {
+        &self.input
+    }
+
+    /// Read the next packet, with timestamps offset by the duration of the
+    /// completed loops.
+    ///
+    /// Returns `Ok(None)` once `max_loops` loops are played, or if the input
+    /// contains no packet at all.
+    pub fn read_packet(&mut self) -> Result<Option<AVPacket>> {
+        loop {
+            if let Some(mut packet) = self.input.read_packet()? {
+                self.has_packet = true;
+                let time_base = self.input.streams()[packet.stream_index as usize].time_base;
+                self.update_end(&packet, time_base);
+                self.apply_offset(&mut packet, time_base);
+                return Ok(Some(packet));
+            }
+
+            self.loops += 1;
+            if !self.has_packet || self.max_loops.is_some_and(|x| self.loops >= x) {
+                return Ok(None);
+            }
+            self.input.seek(None, i64::MIN, self.start, i64::MAX, 0)?;
+            self.offset += self.end - self.start;
+            self.end = self.start;
+            self.has_packet = false;
+        }
+    }
+
+    fn update_end(&mut self, packet: &AVPacket, time_base: AVRational) {
+        let ts = match (packet.pts, packet.dts) {
+            (ffi::AV_NOPTS_VALUE, ffi::AV_NOPTS_VALUE) => return,
+            (ffi::AV_NOPTS_VALUE, x) | (x, _) => x,
+        };
+        let end = av_rescale_q(ts + packet.duration, time_base, AV_TIME_BASE_Q);
+        self.end = self.end.max(end);
+    }
+
+    fn apply_offset(&self, packet: &mut AVPacket, time_base: AVRational) {
+        if self.offset == 0 {
+            return;
+        }
+        let offset = av_rescale_q(self.offset, AV_TIME_BASE_Q, time_base);
+        if packet.pts != ffi::AV_NOPTS_VALUE {
+            packet.set_pts(packet.pts + offset);
+        }
+        if packet.dts != ffi::AV_NOPTS_VALUE {
+            packet.set_dts(packet.dts + offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_looping_input() {
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let mut count = 0;
+        while input.read_packet().unwrap().is_some() {
+            count += 1;
+        }
+
+        let mut looping = LoopingInput::open(cstr!("tests/assets/vids/bear.mp4")).unwrap();
+        looping.set_max_loops(Some(3));
+        let nb_streams = looping.input().streams().len();
+        let mut last_dts = vec![i64::MIN; nb_streams];
+        let mut looped_count = 0;
+        while let Some(packet) = looping.read_packet().unwrap() {
+            let index = packet.stream_index as usize;
+            assert!(packet.dts > last_dts[index]);
+            last_dts[index] = packet.dts;
+            looped_count += 1;
+        }
+        assert_eq!(looped_count, count * 3);
+        assert_eq!(looping.loop_count(), 3);
+    }
+}
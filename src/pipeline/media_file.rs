@@ -0,0 +1,111 @@
+use std::ffi::CStr;
+
+use crate::{
+    avutil::{av_rescale_q, AVFrame, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+    pipeline::StreamDecoder,
+};
+
+const AV_TIME_BASE_Q: AVRational = AVRational {
+    num: 1,
+    den: ffi::AV_TIME_BASE as i32,
+};
+
+/// A media file opened for extracting snapshots of its video.
+///
+/// ```ignore
+/// let mut file = MediaFile::open(cstr!("movie.mp4"))?;
+/// // Frame at 1.5 seconds.
+/// let frame = file.snapshot_at(1_500_000)?;
+/// ```
+pub struct MediaFile {
+    decoder: StreamDecoder,
+}
+
+impl MediaFile {
+    /// Open `url` and the decoder of its best video stream.
+    pub fn open(url: &CStr) -> Result<Self> {
+        let decoder = StreamDecoder::open(url, ffi::AVMEDIA_TYPE_VIDEO)?
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_STREAM_NOT_FOUND))?;
+        Ok(Self { decoder })
+    }
+
+    /// Get the demuxer and decoder of the video stream.
+    pub fn decoder(&mut self) -> &mut StreamDecoder {
+        &mut self.decoder
+    }
+
+    /// Duration of the file in `AV_TIME_BASE` units, if known.
+    pub fn duration(&self) -> Option<i64> {
+        match self.decoder.input().duration {
+            ffi::AV_NOPTS_VALUE => None,
+            x => Some(x),
+        }
+    }
+
+    /// Decode the video frame displayed at `timestamp`, in `AV_TIME_BASE`
+    /// units from the start of the file.
+    ///
+    /// Returns the first frame not before `timestamp`, or the last frame of
+    /// the video if `timestamp` is past its end.
+    pub fn snapshot_at(&mut self, timestamp: i64) -> Result<AVFrame> {
+        let stream_index = self.decoder.stream_index();
+        let stream = &self.decoder.input().streams()[stream_index];
+        let time_base = stream.time_base;
+        let start_time = match stream.start_time {
+            ffi::AV_NOPTS_VALUE => 0,
+            x => x,
+        };
+        let ts = start_time + av_rescale_q(timestamp.max(0), AV_TIME_BASE_Q, time_base);
+
+        self.decoder.seek(ts)?;
+        let mut last = None;
+        while let Some(frame) = self.decoder.decode_frame()? {
+            let frame_ts = match frame.best_effort_timestamp {
+                ffi::AV_NOPTS_VALUE => frame.pts,
+                x => x,
+            };
+            if frame_ts == ffi::AV_NOPTS_VALUE || frame_ts >= ts {
+                return Ok(frame);
+            }
+            last = Some(frame);
+        }
+        last.ok_or(RsmpegError::AVError(ffi::AVERROR_EOF))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_snapshot_at() {
+        let mut file = MediaFile::open(cstr!("tests/assets/vids/bear.mp4")).unwrap();
+        let duration = file.duration().unwrap();
+        let stream_index = file.decoder().stream_index();
+        let time_base = file.decoder().input().streams()[stream_index].time_base;
+
+        let first = file.snapshot_at(0).unwrap();
+        let middle = file.snapshot_at(duration / 2).unwrap();
+        assert!(middle.best_effort_timestamp > first.best_effort_timestamp);
+        assert!(
+            av_rescale_q(middle.best_effort_timestamp, time_base, AV_TIME_BASE_Q)
+                >= duration / 2 - 1
+        );
+
+        // Seeking backward works as well.
+        let again = file.snapshot_at(0).unwrap();
+        assert_eq!(again.best_effort_timestamp, first.best_effort_timestamp);
+
+        // Past the end, the last frame is returned.
+        let last = file.snapshot_at(duration * 2).unwrap();
+        assert!(last.best_effort_timestamp > middle.best_effort_timestamp);
+    }
+
+    #[test]
+    fn test_snapshot_no_video() {
+        assert!(MediaFile::open(cstr!("tests/assets/audios/sample1_short.aac")).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    ffi::{CStr, CString},
+    io,
+};
+
+use crate::{
+    avcodec::{AVPacket, PacketFlags},
+    avformat::{
+        AVFormatContextOutput, AVIOContextContainer, AVIOContextCustom, AVOutputFormat, FormatFlags,
+    },
+    avutil::{AVDictionary, AVFrame, AVMem, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+    pipeline::image::encode_image,
+};
+
+/// A Motion JPEG stream sent as a `multipart/x-mixed-replace` body with the
+/// `mpjpeg` muxer, which browsers display as a live picture, e.g. the
+/// preview of a camera.
+///
+/// The body is written through a callback, e.g. into the socket of an HTTP
+/// response whose `Content-Type` header is [`Self::content_type()`]. Each
+/// part is flushed as soon as it's pushed.
+///
+/// ```ignore
+/// // Send the headers of the response before the body.
+/// write!(socket, "HTTP/1.1 200 OK\r\nContent-Type: {}\r\n\r\n", MjpegStream::content_type("frame"))?;
+/// let mut stream = MjpegStream::new(1280, 720, "frame", move |data| socket.write_all(data))?;
+/// while let Some(frame) = decoder.decode_frame()? {
+///     stream.push_frame(&frame, Some(5))?;
+/// }
+/// stream.finish()?;
+/// ```
+pub struct MjpegStream {
+    output: AVFormatContextOutput,
+    pts: i64,
+}
+
+impl MjpegStream {
+    /// Start a stream of `width`x`height` pictures, whose parts are
+    /// separated by `boundary`, writing the body through `write`.
+    ///
+    /// An error of `write` fails the current push with `AVERROR(EIO)`, e.g.
+    /// when the client went away.
+    pub fn new(
+        width: i32,
+        height: i32,
+        boundary: &str,
+        mut write: impl FnMut(&[u8]) -> io::Result<()> + Send + 'static,
+    ) -> Result<Self> {
+        let boundary_tag =
+            CString::new(boundary).map_err(|_| RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        let mpjpeg = CStr::from_bytes_with_nul(b"mpjpeg\0").unwrap();
+        let format = AVOutputFormat::guess_format(Some(mpjpeg), None, None)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_MUXER_NOT_FOUND))?;
+        let io_context = AVIOContextCustom::alloc_context(
+            AVMem::new(4096),
+            true,
+            vec![],
+            None,
+            Some(Box::new(move |_, buf| match write(buf) {
+                Ok(()) => buf.len() as i32,
+                Err(_) => ffi::AVERROR(ffi::EIO),
+            })),
+            None,
+        );
+        let mut output = AVFormatContextOutput::create_with_format(
+            mpjpeg,
+            &format,
+            Some(AVIOContextContainer::Custom(io_context)),
+        )?;
+        output.set_format_flags(output.format_flags() | FormatFlags::FLUSH_PACKETS);
+        {
+            let mut stream = output.new_stream();
+            unsafe {
+                let codecpar = stream.codecpar_mut().as_mut_ptr();
+                (*codecpar).codec_type = ffi::AVMEDIA_TYPE_VIDEO;
+                (*codecpar).codec_id = ffi::AV_CODEC_ID_MJPEG;
+                (*codecpar).width = width;
+                (*codecpar).height = height;
+            }
+            stream.set_time_base(AVRational { num: 1, den: 25 });
+        }
+        let mut options = Some(AVDictionary::new(
+            CStr::from_bytes_with_nul(b"boundary_tag\0").unwrap(),
+            &boundary_tag,
+            0,
+        ));
+        output.write_header(&mut options)?;
+        Ok(Self { output, pts: 0 })
+    }
+
+    /// Value of the `Content-Type` header of the HTTP response carrying a
+    /// stream whose parts are separated by `boundary`, to be sent before the
+    /// stream is started.
+    pub fn content_type(boundary: &str) -> String {
+        format!("multipart/x-mixed-replace;boundary={boundary}")
+    }
+
+    /// Send an encoded JPEG picture as the next part of the stream.
+    pub fn push_jpeg(&mut self, jpeg: &[u8]) -> Result<()> {
+        let mut packet = AVPacket::from_data(jpeg.to_vec())?;
+        packet.set_stream_index(0);
+        packet.set_pts(self.pts);
+        packet.set_dts(self.pts);
+        packet.set_packet_flags(PacketFlags::KEY);
+        self.output.write_frame(&mut packet)?;
+        self.pts += 1;
+        Ok(())
+    }
+
+    /// Encode `frame` into JPEG and send it as the next part of the stream,
+    /// `quality` is the same as in [`encode_image()`].
+    pub fn push_frame(&mut self, frame: &AVFrame, quality: Option<i32>) -> Result<()> {
+        let jpeg = encode_image(frame, ffi::AV_CODEC_ID_MJPEG, quality)?;
+        self.push_jpeg(&jpeg)
+    }
+
+    /// Number of pictures sent.
+    pub fn frame_count(&self) -> i64 {
+        self.pts
+    }
+
+    /// End the stream, flushing the last bytes written.
+    pub fn finish(mut self) -> Result<()> {
+        self.output.write_trailer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::image::decode_image;
+    use std::sync::{Arc, Mutex};
+
+    fn count(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|x| *x == needle)
+            .count()
+    }
+
+    #[test]
+    fn test_mjpeg_stream() {
+        let jpeg = std::fs::read("tests/assets/pics/bear.jpg").unwrap();
+        let frame = decode_image(&jpeg).unwrap();
+        let body = Arc::new(Mutex::new(vec![]));
+        assert_eq!(
+            MjpegStream::content_type("rsmpeg"),
+            "multipart/x-mixed-replace;boundary=rsmpeg"
+        );
+
+        let mut stream = {
+            let body = body.clone();
+            MjpegStream::new(frame.width, frame.height, "rsmpeg", move |data| {
+                body.lock().unwrap().extend_from_slice(data);
+                Ok(())
+            })
+            .unwrap()
+        };
+        stream.push_jpeg(&jpeg).unwrap();
+        // Flushed right away.
+        assert_eq!(count(&body.lock().unwrap(), b"Content-type: image/jpeg"), 1);
+        stream.push_frame(&frame, Some(5)).unwrap();
+        assert_eq!(stream.frame_count(), 2);
+        stream.finish().unwrap();
+
+        let body = body.lock().unwrap();
+        assert!(body.starts_with(b"--rsmpeg\r\n"));
+        assert_eq!(count(&body, b"Content-type: image/jpeg"), 2);
+        assert_eq!(count(&body, b"--rsmpeg"), 3);
+        assert_eq!(count(&body, &jpeg), 1);
+
+        // The header of the muxer is empty, nothing is written before the
+        // first part.
+        let mut stream = MjpegStream::new(16, 16, "broken", |_| {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        })
+        .unwrap();
+        assert_eq!(
+            stream.push_jpeg(&jpeg),
+            Err(RsmpegError::AVError(ffi::AVERROR(ffi::EIO)))
+        );
+    }
+}
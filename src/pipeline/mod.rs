@@ -0,0 +1,68 @@
+//! High level helpers built on top of the FFmpeg wrappers, covering common
+//! demuxing, decoding and muxing workflows.
+pub mod analysis;
+pub mod image;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+
+mod abr_ladder;
+mod audio_pts_fixer;
+mod checkpoint;
+mod codec_worker;
+mod cover_art;
+mod drawtext;
+mod dry_run;
+mod exact_duration;
+mod finalizer;
+mod frame_rate_conformer;
+mod frame_transformer;
+mod growing_input;
+mod hw_frame_downloader;
+mod interleave_limiter;
+mod looping_input;
+mod media_file;
+mod mjpeg_stream;
+mod parallel_encoder;
+mod pass_log;
+mod pcm_writer;
+mod prefetching_reader;
+mod progress;
+mod pump;
+mod queue;
+mod seek_index;
+mod stream_decoder;
+mod subtitle_sidecar;
+mod timecode_track;
+mod track_map;
+mod track_tags;
+
+pub use abr_ladder::*;
+pub use audio_pts_fixer::*;
+pub use checkpoint::*;
+pub use codec_worker::*;
+pub use cover_art::*;
+pub use drawtext::*;
+pub use dry_run::*;
+pub use exact_duration::*;
+pub use finalizer::*;
+pub use frame_rate_conformer::*;
+pub use frame_transformer::*;
+pub use growing_input::*;
+pub use hw_frame_downloader::*;
+pub use interleave_limiter::*;
+pub use looping_input::*;
+pub use media_file::*;
+pub use mjpeg_stream::*;
+pub use parallel_encoder::*;
+pub use pass_log::*;
+pub use pcm_writer::*;
+pub use prefetching_reader::*;
+pub use progress::*;
+pub use pump::*;
+pub use queue::*;
+pub use seek_index::*;
+pub use stream_decoder::*;
+pub use subtitle_sidecar::*;
+pub use timecode_track::*;
+pub use track_map::*;
+pub use track_tags::*;
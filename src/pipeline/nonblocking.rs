@@ -0,0 +1,269 @@
+//! Async wrappers of the pipeline operations, enabled by the `async`
+//! feature, for calling rsmpeg from async handlers, e.g. of a web service.
+//!
+//! The blocking FFmpeg work runs on a thread of its own and is awaited with
+//! a [`BlockingTask`], which works with any executor. Dropping or
+//! [cancelling](BlockingTask::cancel) the task asks the work to stop, the
+//! I/O of the inputs it opens is interrupted with `AVERROR_EXIT`.
+//!
+//! ```ignore
+//! async fn thumbnail(url: CString) -> Result<AVFrame> {
+//!     let frames = extract_frames(url, vec![1_000_000]).await?;
+//!     Ok(frames.into_iter().next().unwrap())
+//! }
+//! ```
+use std::{
+    ffi::CString,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+use crate::{
+    avformat::{AVFormatContextInput, AVFormatContextOutput},
+    avutil::AVFrame,
+    error::{Result, RsmpegError},
+    ffi,
+    pipeline::{transcode_tracks_with_progress, MediaFile, Progress, TrackMap},
+};
+
+/// Cooperative cancellation of a [`BlockingTask`], checked by its work.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Ask the work to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the work should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fail with `AVERROR_EXIT` if the work should stop, the error of the
+    /// operations interrupted by [`Self::interrupt_callback()`].
+    pub fn check(&self) -> Result<()> {
+        match self.is_cancelled() {
+            true => Err(RsmpegError::AVError(ffi::AVERROR_EXIT)),
+            false => Ok(()),
+        }
+    }
+
+    /// Interrupt callback aborting the I/O of a context once cancelled, see
+    /// [`AVFormatContextInput::open_with_interrupt()`].
+    pub fn interrupt_callback(&self) -> impl FnMut() -> bool + Send + 'static {
+        let token = self.clone();
+        move || token.is_cancelled()
+    }
+}
+
+enum TaskState<T> {
+    Running(Option<Waker>),
+    Done(thread::Result<Result<T>>),
+    Taken,
+}
+
+/// Blocking work running on a thread of its own, see [`spawn_blocking()`].
+///
+/// Resolves to the result of the work, the panics of the work are resumed
+/// when polled. Dropping the task cancels the work, without waiting for it.
+pub struct BlockingTask<T> {
+    state: Arc<Mutex<TaskState<T>>>,
+    token: CancelToken,
+}
+
+impl<T> BlockingTask<T> {
+    /// Ask the work to stop, it then resolves to its result so far, usually
+    /// `AVERROR_EXIT`.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// The token of the work, e.g. to cancel it from elsewhere.
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.token
+    }
+}
+
+impl<T> Future for BlockingTask<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match std::mem::replace(&mut *state, TaskState::Taken) {
+            TaskState::Running(_) => {
+                *state = TaskState::Running(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+            TaskState::Done(Ok(result)) => Poll::Ready(result),
+            TaskState::Done(Err(payload)) => {
+                drop(state);
+                panic::resume_unwind(payload)
+            }
+            TaskState::Taken => panic!("BlockingTask polled after completion"),
+        }
+    }
+}
+
+impl<T> Drop for BlockingTask<T> {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+/// Run `work` on a new thread and await its result, `work` should stop
+/// early once its [`CancelToken`] is cancelled.
+pub fn spawn_blocking<T, F>(work: F) -> BlockingTask<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&CancelToken) -> Result<T> + Send + 'static,
+{
+    let state = Arc::new(Mutex::new(TaskState::Running(None)));
+    let token = CancelToken::default();
+    {
+        let (state, token) = (state.clone(), token.clone());
+        thread::spawn(move || {
+            let result: thread::Result<Result<T>> =
+                panic::catch_unwind(AssertUnwindSafe(|| work(&token)));
+            let previous = std::mem::replace(&mut *state.lock().unwrap(), TaskState::Done(result));
+            if let TaskState::Running(Some(waker)) = previous {
+                waker.wake();
+            }
+        });
+    }
+    BlockingTask { state, token }
+}
+
+/// Open and probe `url`, the opening is aborted if the task is cancelled,
+/// e.g. when a network input hangs.
+pub fn probe(url: CString) -> BlockingTask<AVFormatContextInput> {
+    spawn_blocking(move |token| {
+        AVFormatContextInput::open_with_interrupt(&url, None, &mut None, token.interrupt_callback())
+    })
+}
+
+/// Transcode `input_url` into `output_url` with
+/// [`transcode_tracks()`](crate::pipeline::transcode_tracks),
+/// following the tracks `map` makes from the opened input.
+///
+/// ```ignore
+/// transcode(input_url, output_url, |input| {
+///     TrackMap::all(input).encode(0, TrackEncoding::new(ffi::AV_CODEC_ID_AAC))
+/// })
+/// .await?;
+/// ```
+pub fn transcode<M>(input_url: CString, output_url: CString, map: M) -> BlockingTask<()>
+where
+    M: FnOnce(&AVFormatContextInput) -> TrackMap + Send + 'static,
+{
+    transcode_with_progress(input_url, output_url, map, |_| {})
+}
+
+/// Same as [`transcode()`], calling `on_progress` on the transcoding thread,
+/// see [`transcode_tracks_with_progress()`], e.g. to forward the progress to
+/// a channel of the async runtime.
+pub fn transcode_with_progress<M, P>(
+    input_url: CString,
+    output_url: CString,
+    map: M,
+    on_progress: P,
+) -> BlockingTask<()>
+where
+    M: FnOnce(&AVFormatContextInput) -> TrackMap + Send + 'static,
+    P: FnMut(&Progress) + Send + 'static,
+{
+    spawn_blocking(move |token| {
+        let mut input = AVFormatContextInput::open_with_interrupt(
+            &input_url,
+            None,
+            &mut None,
+            token.interrupt_callback(),
+        )?;
+        let map = map(&input);
+        let mut output = AVFormatContextOutput::create(&output_url, None)?;
+        transcode_tracks_with_progress(&mut input, &mut output, map, &mut None, on_progress)
+    })
+}
+
+/// Decode the video frames of `url` displayed at `timestamps`, in
+/// `AV_TIME_BASE` units, see [`MediaFile::snapshot_at()`].
+pub fn extract_frames(url: CString, timestamps: Vec<i64>) -> BlockingTask<Vec<AVFrame>> {
+    spawn_blocking(move |token| {
+        let mut file = MediaFile::open(&url)?;
+        timestamps
+            .into_iter()
+            .map(|timestamp| {
+                token.check()?;
+                file.snapshot_at(timestamp)
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, task::Wake, time::Duration};
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(x) => return x,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_spawn_blocking() {
+        assert_eq!(block_on(spawn_blocking(|_| Ok(42))).unwrap(), 42);
+
+        let (sender, receiver) = mpsc::channel();
+        let task = spawn_blocking(move |token| {
+            while !token.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            sender.send(()).unwrap();
+            token.check()
+        });
+        task.cancel();
+        assert!(matches!(
+            block_on(task),
+            Err(RsmpegError::AVError(ffi::AVERROR_EXIT))
+        ));
+        receiver.recv().unwrap();
+
+        let result =
+            panic::catch_unwind(|| block_on(spawn_blocking(|_| -> Result<()> { panic!() })));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_probe_and_extract_frames() {
+        let url = CString::new("tests/assets/vids/bear.mp4").unwrap();
+        let input = block_on(probe(url.clone())).unwrap();
+        assert_eq!(input.streams().len(), 2);
+        let frames = block_on(extract_frames(url, vec![0, 500_000])).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].pts < frames[1].pts);
+    }
+}
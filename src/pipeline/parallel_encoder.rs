@@ -0,0 +1,270 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    avcodec::{AVCodecContext, AVPacket},
+    avutil::AVFrame,
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+type PacketResult = Result<(usize, AVPacket)>;
+
+struct Worker {
+    /// `None` once the encoder has been flushed.
+    frames: Option<SyncSender<AVFrame>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Runs several opened encoders on their own threads, e.g. the renditions of
+/// an ABR ladder or the streams of an output.
+///
+/// Each encoder has a bounded queue of frames, so [`Self::send_frame()`]
+/// blocks while an encoder lags behind. The packets of all the encoders are
+/// funneled into a single queue, tagged with the index of their encoder, in
+/// encoding order for each encoder. The packet queue isn't bounded: drain it
+/// with [`Self::try_receive_packet()`] while sending frames.
+///
+/// ```ignore
+/// let mut encoder = ParallelEncoder::new(vec![encoder_1080p, encoder_720p], 8);
+/// while let Some(frame) = decoder.decode_frame()? {
+///     encoder.send_frame(0, Some(frame_1080p))?;
+///     encoder.send_frame(1, Some(frame_720p))?;
+///     while let Some((index, packet)) = encoder.try_receive_packet()? {
+///         // ...
+///     }
+/// }
+/// encoder.send_frame(0, None)?;
+/// encoder.send_frame(1, None)?;
+/// while let Some((index, packet)) = encoder.receive_packet()? {
+///     // ...
+/// }
+/// ```
+pub struct ParallelEncoder {
+    workers: Vec<Worker>,
+    packets: Receiver<PacketResult>,
+}
+
+impl ParallelEncoder {
+    /// Spawn a thread for each of the opened `encoders`, each with a queue of
+    /// at most `queue_size` frames.
+    pub fn new(encoders: Vec<AVCodecContext>, queue_size: usize) -> Self {
+        let (packet_sender, packets) = mpsc::channel();
+        let workers = encoders
+            .into_iter()
+            .enumerate()
+            .map(|(index, encoder)| {
+                let (frames, frame_receiver) = mpsc::sync_channel(queue_size);
+                let packet_sender = packet_sender.clone();
+                let handle = thread::spawn(move || {
+                    if let Err(e) = encode(index, encoder, frame_receiver, &packet_sender) {
+                        let _ = packet_sender.send(Err(e));
+                    }
+                });
+                Worker {
+                    frames: Some(frames),
+                    handle: Some(handle),
+                }
+            })
+            .collect();
+        Self { workers, packets }
+    }
+
+    /// Number of encoders.
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns true if there is no encoder.
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    /// Queue `frame` to the encoder at `index`, blocking while its queue is
+    /// full. `None` flushes the encoder, no frame can be sent to it after
+    /// that.
+    ///
+    /// Returns `AVERROR(EINVAL)` if there is no such encoder or it has been
+    /// flushed, and `AVERROR(EPIPE)` if the encoder stopped on an error, which
+    /// is returned by [`Self::receive_packet()`].
+    pub fn send_frame(&mut self, index: usize, frame: Option<AVFrame>) -> Result<()> {
+        let frames = &mut self
+            .workers
+            .get_mut(index)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?
+            .frames;
+        match frame {
+            Some(frame) => frames
+                .as_ref()
+                .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?
+                .send(frame)
+                .map_err(|_| RsmpegError::AVError(ffi::AVERROR(ffi::EPIPE))),
+            None => {
+                // Closing the queue makes the worker flush its encoder.
+                *frames = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Wait for the next packet of any encoder.
+    ///
+    /// Returns `Ok(None)` once all the encoders have been flushed and all
+    /// their packets received.
+    pub fn receive_packet(&mut self) -> Result<Option<(usize, AVPacket)>> {
+        match self.packets.recv() {
+            Ok(x) => x.map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Get the next packet of any encoder without waiting.
+    ///
+    /// Returns `Ok(None)` if no packet is available yet.
+    pub fn try_receive_packet(&mut self) -> Result<Option<(usize, AVPacket)>> {
+        match self.packets.try_recv() {
+            Ok(x) => x.map(Some),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => Ok(None),
+        }
+    }
+}
+
+impl Drop for ParallelEncoder {
+    fn drop(&mut self) {
+        for worker in &mut self.workers {
+            worker.frames = None;
+        }
+        for worker in &mut self.workers {
+            if let Some(handle) = worker.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+/// Encode the frames of `frames` until the queue is closed, then flush.
+fn encode(
+    index: usize,
+    mut encoder: AVCodecContext,
+    frames: Receiver<AVFrame>,
+    packets: &Sender<PacketResult>,
+) -> Result<()> {
+    while let Ok(frame) = frames.recv() {
+        encoder.send_frame(Some(&frame))?;
+        receive_packets(index, &mut encoder, packets)?;
+    }
+    encoder.send_frame(None)?;
+    receive_packets(index, &mut encoder, packets)
+}
+
+fn receive_packets(
+    index: usize,
+    encoder: &mut AVCodecContext,
+    packets: &Sender<PacketResult>,
+) -> Result<()> {
+    loop {
+        match encoder.receive_packet() {
+            Ok(packet) => {
+                // The receiver is gone, the packets are not needed anymore.
+                if packets.send(Ok((index, packet))).is_err() {
+                    return Ok(());
+                }
+            }
+            Err(RsmpegError::EncoderDrainError | RsmpegError::EncoderFlushedError) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        avcodec::AVCodec,
+        avutil::{ra, AVPixFmtDescriptorRef},
+    };
+
+    fn open_encoder(width: i32, height: i32) -> AVCodecContext {
+        let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_MJPEG).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_width(width);
+        encoder.set_height(height);
+        encoder.set_pix_fmt(codec.pix_fmts().unwrap()[0]);
+        encoder.set_time_base(ra(1, 25));
+        encoder.open(None).unwrap();
+        encoder
+    }
+
+    fn gray_frame(encoder: &AVCodecContext, pts: i64) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_width(encoder.width);
+        frame.set_height(encoder.height);
+        frame.set_format(encoder.pix_fmt);
+        frame.set_pts(pts);
+        frame.alloc_buffer().unwrap();
+        let log2_chroma_h = AVPixFmtDescriptorRef::get(encoder.pix_fmt)
+            .unwrap()
+            .log2_chroma_h;
+        for (i, (plane, linesize)) in frame.data.iter().zip(frame.linesize).take(3).enumerate() {
+            let rows = if i == 0 {
+                encoder.height
+            } else {
+                -((-encoder.height) >> log2_chroma_h)
+            };
+            unsafe { std::ptr::write_bytes(*plane, 128, (linesize * rows) as usize) };
+        }
+        frame
+    }
+
+    #[test]
+    fn test_parallel_encoder() {
+        let sizes = [(320, 240), (160, 120), (64, 48)];
+        let encoders: Vec<_> = sizes.iter().map(|&(w, h)| open_encoder(w, h)).collect();
+        let frames: Vec<Vec<_>> = encoders
+            .iter()
+            .map(|encoder| (0..10).map(|pts| gray_frame(encoder, pts)).collect())
+            .collect();
+
+        let mut parallel = ParallelEncoder::new(encoders, 2);
+        assert_eq!(parallel.len(), 3);
+        let mut packets = vec![vec![]; sizes.len()];
+        for pts in 0..10 {
+            for (index, frames) in frames.iter().enumerate() {
+                parallel
+                    .send_frame(index, Some(frames[pts].clone()))
+                    .unwrap();
+            }
+            while let Some((index, packet)) = parallel.try_receive_packet().unwrap() {
+                packets[index].push((packet.pts, packet.size));
+            }
+        }
+        for index in 0..sizes.len() {
+            parallel.send_frame(index, None).unwrap();
+        }
+        assert!(parallel.send_frame(0, Some(frames[0][0].clone())).is_err());
+        assert!(parallel.send_frame(3, None).is_err());
+        while let Some((index, packet)) = parallel.receive_packet().unwrap() {
+            packets[index].push((packet.pts, packet.size));
+        }
+
+        for packets in &packets {
+            let pts: Vec<_> = packets.iter().map(|x| x.0).collect();
+            assert_eq!(pts, (0..10).collect::<Vec<_>>());
+        }
+        // Bigger renditions give bigger images.
+        assert!(packets[0][0].1 > packets[1][0].1);
+        assert!(packets[1][0].1 > packets[2][0].1);
+    }
+
+    #[test]
+    fn test_parallel_encoder_error() {
+        let encoder = open_encoder(64, 48);
+        let mut parallel = ParallelEncoder::new(vec![encoder], 1);
+        // A frame without data is rejected by the encoder.
+        parallel.send_frame(0, Some(AVFrame::new())).unwrap();
+        assert!(parallel.receive_packet().is_err());
+        assert!(parallel.send_frame(0, Some(AVFrame::new())).is_err());
+    }
+}
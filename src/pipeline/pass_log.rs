@@ -0,0 +1,157 @@
+use std::{
+    ffi::CString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    avcodec::AVCodecContext,
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+use super::growing_input::io_error;
+
+/// Statistics file of a two-pass encoding, like the `-passlogfile` of the
+/// ffmpeg CLI.
+///
+/// ```ignore
+/// let mut log = PassLog::new("encode-0.log");
+/// log.setup_first_pass(&mut encoder)?;
+/// encoder.open(None)?;
+/// // After each received packet and at the end of the first pass:
+/// log.write(&encoder)?;
+///
+/// // Second pass, with a new encoder:
+/// log.setup_second_pass(&mut encoder)?;
+/// encoder.open(None)?;
+/// ```
+pub struct PassLog {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl PassLog {
+    /// Use the statistics file at `path`, nothing is touched until one of the
+    /// passes is set up.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            file: None,
+        }
+    }
+
+    /// Path of the statistics file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Truncate the statistics file and set up `encoder` for the first pass,
+    /// should be called before [`AVCodecContext::open()`].
+    pub fn setup_first_pass(&mut self, encoder: &mut AVCodecContext) -> Result<()> {
+        self.file = Some(File::create(&self.path).map_err(io_error)?);
+        encoder.set_first_pass();
+        Ok(())
+    }
+
+    /// Append the statistics of the frames encoded since the last call,
+    /// should be called after each packet received from the first pass
+    /// encoder, including the ones received while flushing.
+    pub fn write(&mut self, encoder: &AVCodecContext) -> Result<()> {
+        let file = self
+            .file
+            .as_mut()
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        if let Some(stats) = encoder.stats_out() {
+            file.write_all(stats.to_bytes()).map_err(io_error)?;
+        }
+        Ok(())
+    }
+
+    /// Read the statistics file and set up `encoder` for the second pass,
+    /// should be called before [`AVCodecContext::open()`].
+    pub fn setup_second_pass(&mut self, encoder: &mut AVCodecContext) -> Result<()> {
+        // Make sure the first pass is fully written.
+        if let Some(mut file) = self.file.take() {
+            file.flush().map_err(io_error)?;
+        }
+        let stats = fs::read(&self.path).map_err(io_error)?;
+        let stats =
+            CString::new(stats).map_err(|_| RsmpegError::AVError(ffi::AVERROR_INVALIDDATA))?;
+        encoder.set_second_pass(&stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        avcodec::{AVCodec, CodecFlags},
+        avutil::{AVFrame, AVRational},
+    };
+
+    fn encode(encoder: &mut AVCodecContext, mut log: Option<&mut PassLog>) -> usize {
+        let mut packets = 0;
+        for i in 0..10 {
+            let mut frame = AVFrame::new();
+            frame.set_width(64);
+            frame.set_height(64);
+            frame.set_format(ffi::AV_PIX_FMT_YUV420P);
+            frame.set_pts(i);
+            frame.alloc_buffer().unwrap();
+            for plane in 0..3 {
+                frame.plane_data_mut(plane).unwrap().fill(i as u8 * 20);
+            }
+            encoder.send_frame(Some(&frame)).unwrap();
+            while encoder.receive_packet().is_ok() {
+                if let Some(log) = log.as_deref_mut() {
+                    log.write(encoder).unwrap();
+                }
+                packets += 1;
+            }
+        }
+        encoder.send_frame(None).unwrap();
+        while encoder.receive_packet().is_ok() {
+            if let Some(log) = log.as_deref_mut() {
+                log.write(encoder).unwrap();
+            }
+            packets += 1;
+        }
+        packets
+    }
+
+    fn encoder() -> AVCodecContext {
+        let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_MPEG4).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_width(64);
+        encoder.set_height(64);
+        encoder.set_pix_fmt(ffi::AV_PIX_FMT_YUV420P);
+        encoder.set_time_base(AVRational { num: 1, den: 25 });
+        encoder.set_bit_rate(200_000);
+        encoder
+    }
+
+    #[test]
+    fn test_pass_log() {
+        let tempdir = tempdir::TempDir::new("pass_log").unwrap();
+        let mut log = PassLog::new(tempdir.path().join("pass-0.log"));
+
+        let mut first = encoder();
+        assert!(log.write(&first).is_err());
+        log.setup_first_pass(&mut first).unwrap();
+        assert!(first.codec_flags().contains(CodecFlags::PASS1));
+        first.open(None).unwrap();
+        let packets = encode(&mut first, Some(&mut log));
+        assert!(fs::metadata(log.path()).unwrap().len() > 0);
+
+        let mut second = encoder();
+        log.setup_second_pass(&mut second).unwrap();
+        assert!(second.codec_flags().contains(CodecFlags::PASS2));
+        second.open(None).unwrap();
+        assert_eq!(encode(&mut second, None), packets);
+
+        let mut missing = PassLog::new(tempdir.path().join("missing.log"));
+        assert!(missing.setup_second_pass(&mut encoder()).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use std::{ffi::CStr, slice};
+
+use crate::{
+    avcodec::{AVCodec, AVCodecContext},
+    avformat::AVFormatContextOutput,
+    avutil::{get_bytes_per_sample, get_packed_sample_fmt, AVChannelLayout, AVFrame, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Writes uncompressed audio into a WAV or AIFF file, the container is
+/// guessed from the file extension of the url.
+///
+/// Samples are stored in the interleaved variant of the given sample format,
+/// e.g. `AV_SAMPLE_FMT_S16` for `AV_SAMPLE_FMT_S16P`, with the byte order of
+/// the container.
+///
+/// ```ignore
+/// let mut writer = PcmWriter::create(
+///     cstr!("out.wav"),
+///     ffi::AV_SAMPLE_FMT_S16,
+///     16000,
+///     &AVChannelLayout::from_nb_channels(1),
+/// )?;
+/// writer.write_samples(&samples)?;
+/// writer.finish()?;
+/// ```
+pub struct PcmWriter {
+    output: AVFormatContextOutput,
+    encoder: AVCodecContext,
+    next_pts: i64,
+}
+
+impl PcmWriter {
+    /// Create the file at `url` and write its header.
+    pub fn create(
+        url: &CStr,
+        sample_fmt: ffi::AVSampleFormat,
+        sample_rate: i32,
+        ch_layout: &AVChannelLayout,
+    ) -> Result<Self> {
+        let sample_fmt = get_packed_sample_fmt(sample_fmt)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        let mut output = AVFormatContextOutput::create(url, None)?;
+        let big_endian = output.oformat().name().to_bytes() == b"aiff";
+        let codec_id = unsafe { ffi::av_get_pcm_codec(sample_fmt, big_endian as i32) };
+        let codec = AVCodec::find_encoder(codec_id)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_ENCODER_NOT_FOUND))?;
+
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_sample_fmt(sample_fmt);
+        encoder.set_sample_rate(sample_rate);
+        encoder.set_ch_layout(ch_layout.clone().into_inner());
+        encoder.set_time_base(AVRational {
+            num: 1,
+            den: sample_rate,
+        });
+        encoder.open(None)?;
+
+        {
+            let mut stream = output.new_stream();
+            stream.set_codecpar(encoder.extract_codecpar());
+            stream.set_time_base(encoder.time_base);
+        }
+        output.write_header(&mut None)?;
+
+        Ok(Self {
+            output,
+            encoder,
+            next_pts: 0,
+        })
+    }
+
+    /// Sample format of the samples stored in the file.
+    pub fn sample_fmt(&self) -> ffi::AVSampleFormat {
+        self.encoder.sample_fmt
+    }
+
+    /// Number of samples per channel written so far.
+    pub fn samples_written(&self) -> i64 {
+        self.next_pts
+    }
+
+    /// Write a decoded audio frame, it's converted into
+    /// [`Self::sample_fmt()`] if needed. The frame must have the sample rate
+    /// and the channel layout given on creation.
+    pub fn write_frame(&mut self, frame: &AVFrame) -> Result<()> {
+        if frame.sample_rate != self.encoder.sample_rate
+            || !frame.ch_layout().equal(&self.encoder.ch_layout())?
+        {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let mut frame = frame.to_sample_fmt(self.encoder.sample_fmt)?;
+        frame.set_pts(self.next_pts);
+        self.encode(Some(&frame))?;
+        self.next_pts += frame.nb_samples as i64;
+        Ok(())
+    }
+
+    /// Write interleaved samples in [`Self::sample_fmt()`] with native byte
+    /// order. A trailing partial sample is ignored.
+    pub fn write_samples(&mut self, data: &[u8]) -> Result<()> {
+        let nb_channels = self.encoder.ch_layout.nb_channels as usize;
+        let bytes_per_sample = get_bytes_per_sample(self.encoder.sample_fmt)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        let nb_samples = data.len() / (bytes_per_sample * nb_channels);
+        if nb_samples == 0 {
+            return Ok(());
+        }
+
+        let mut frame = AVFrame::new();
+        frame.set_nb_samples(nb_samples as i32);
+        frame.set_format(self.encoder.sample_fmt);
+        frame.set_sample_rate(self.encoder.sample_rate);
+        frame.set_ch_layout(self.encoder.ch_layout().clone().into_inner());
+        frame.alloc_buffer()?;
+        let len = nb_samples * bytes_per_sample * nb_channels;
+        unsafe { slice::from_raw_parts_mut(frame.data[0], len) }.copy_from_slice(&data[..len]);
+        frame.set_pts(self.next_pts);
+        self.encode(Some(&frame))?;
+        self.next_pts += nb_samples as i64;
+        Ok(())
+    }
+
+    /// Flush the encoder and write the trailer, which fixes up the sizes in
+    /// the header.
+    pub fn finish(mut self) -> Result<()> {
+        self.encode(None)?;
+        self.output.write_trailer()
+    }
+
+    fn encode(&mut self, frame: Option<&AVFrame>) -> Result<()> {
+        self.encoder.send_frame(frame)?;
+        let time_base = self.output.streams()[0].time_base;
+        loop {
+            let mut packet = match self.encoder.receive_packet() {
+                Ok(packet) => packet,
+                Err(RsmpegError::EncoderDrainError | RsmpegError::EncoderFlushedError) => {
+                    return Ok(())
+                }
+                Err(e) => return Err(e),
+            };
+            packet.rescale_ts(self.encoder.time_base, time_base);
+            packet.set_stream_index(0);
+            self.output.write_frame(&mut packet)?;
+        }
+    }
+}
+
+/// Write audio `frames` into a WAV file at `url`. The sample format, sample
+/// rate and channel layout are taken from the first frame, the others must
+/// match them.
+pub fn write_pcm_to_wav<'a>(
+    url: &CStr,
+    frames: impl IntoIterator<Item = &'a AVFrame>,
+) -> Result<()> {
+    let mut frames = frames.into_iter();
+    let first = frames
+        .next()
+        .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+    let mut writer = PcmWriter::create(url, first.format, first.sample_rate, &first.ch_layout())?;
+    writer.write_frame(first)?;
+    for frame in frames {
+        writer.write_frame(frame)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avformat::AVFormatContextInput;
+    use std::ffi::CString;
+
+    fn sine_frame(nb_samples: i32, offset: i32) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_nb_samples(nb_samples);
+        frame.set_format(ffi::AV_SAMPLE_FMT_FLTP);
+        frame.set_sample_rate(8000);
+        frame.set_ch_layout(AVChannelLayout::from_nb_channels(2).into_inner());
+        frame.alloc_buffer().unwrap();
+        for plane in 0..2 {
+            let samples = unsafe {
+                slice::from_raw_parts_mut(frame.data[plane] as *mut f32, nb_samples as usize)
+            };
+            for (i, sample) in samples.iter_mut().enumerate() {
+                *sample = ((offset + i as i32) as f32 * 0.05).sin() * 0.5;
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn test_write_pcm_to_wav() {
+        let tempdir = tempdir::TempDir::new("pcm_writer").unwrap();
+        let path = tempdir.path().join("out.wav");
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let frames = [
+            sine_frame(1024, 0),
+            sine_frame(1024, 1024),
+            sine_frame(500, 2048),
+        ];
+        write_pcm_to_wav(&path, &frames).unwrap();
+
+        let input = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        let codecpar = input.streams()[0].codecpar();
+        assert_eq!(codecpar.codec_id, ffi::AV_CODEC_ID_PCM_F32LE);
+        assert_eq!(codecpar.sample_rate, 8000);
+        assert_eq!(codecpar.ch_layout.nb_channels, 2);
+        // 44 bytes of header, 2548 stereo float samples.
+        let size = std::fs::metadata(path.to_str().unwrap()).unwrap().len();
+        assert!(size >= 2548 * 2 * 4 + 44);
+
+        assert!(write_pcm_to_wav(&path, []).is_err());
+    }
+
+    #[test]
+    fn test_write_samples_to_aiff() {
+        let tempdir = tempdir::TempDir::new("pcm_writer").unwrap();
+        let path = tempdir.path().join("out.aiff");
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let mut writer = PcmWriter::create(
+            &path,
+            ffi::AV_SAMPLE_FMT_S16P,
+            16000,
+            &AVChannelLayout::from_nb_channels(1),
+        )
+        .unwrap();
+        assert_eq!(writer.sample_fmt(), ffi::AV_SAMPLE_FMT_S16);
+        let samples: Vec<u8> = (0..1600i16).flat_map(|x| x.to_ne_bytes()).collect();
+        writer.write_samples(&samples).unwrap();
+        writer.write_samples(&samples[..3]).unwrap();
+        assert_eq!(writer.samples_written(), 1601);
+        assert!(writer.write_frame(&sine_frame(10, 0)).is_err());
+        writer.finish().unwrap();
+
+        let input = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        let codecpar = input.streams()[0].codecpar();
+        assert_eq!(codecpar.codec_id, ffi::AV_CODEC_ID_PCM_S16BE);
+        assert_eq!(codecpar.sample_rate, 16000);
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    avcodec::AVPacket,
+    avformat::AVFormatContextInput,
+    error::{Result, RsmpegError},
+    ffi,
+    pipeline::{DropPolicy, PacketQueue},
+};
+
+/// Reads the packets of an input on a background thread, ahead of their
+/// consumer, so a slow network read doesn't stall e.g. a render loop.
+///
+/// At most `capacity` packets are read ahead, the reading thread waits for
+/// the consumer afterwards. The reader owns the input, read what's needed
+/// from its streams, e.g. the codec parameters, before creating it, or
+/// [`Self::stop()`] it to get the input back.
+///
+/// ```ignore
+/// let reader = PrefetchingReader::new(input, 256);
+/// loop {
+///     match reader.try_next_packet() {
+///         Ok(Some(packet)) => decoder.send_packet(Some(&packet))?,
+///         Ok(None) => break,
+///         // Nothing read yet, render the next frame anyway.
+///         Err(e) if e.raw_error() == Some(AVERROR_EAGAIN) => {}
+///         Err(e) => return Err(e),
+///     }
+///     render();
+/// }
+/// ```
+pub struct PrefetchingReader {
+    queue: PacketQueue,
+    error: Arc<Mutex<Option<RsmpegError>>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<AVFormatContextInput>>,
+}
+
+impl PrefetchingReader {
+    /// Start reading `input` ahead by at most `capacity` packets, at least
+    /// one.
+    pub fn new(mut input: AVFormatContextInput, capacity: usize) -> Self {
+        let queue = PacketQueue::new(capacity, DropPolicy::Block);
+        let error = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (queue, error, stop) = (queue.clone(), error.clone(), stop.clone());
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match input.read_packet() {
+                        Ok(Some(packet)) => {
+                            // Closed by `stop()`.
+                            if queue.push(packet).is_err() {
+                                break;
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            *error.lock().unwrap() = Some(e);
+                            break;
+                        }
+                    }
+                }
+                queue.close();
+                input
+            })
+        };
+        Self {
+            queue,
+            error,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Wait for the next packet.
+    ///
+    /// Returns `Ok(None)` at the end of the input. If reading failed, the
+    /// error is returned once after the packets read before it.
+    pub fn next_packet(&self) -> Result<Option<AVPacket>> {
+        match self.queue.pop() {
+            Some(packet) => Ok(Some(packet)),
+            None => self.end(),
+        }
+    }
+
+    /// Get the next packet without waiting, same as [`Self::next_packet()`]
+    /// but returns `AVERROR(EAGAIN)` if it hasn't been read yet.
+    pub fn try_next_packet(&self) -> Result<Option<AVPacket>> {
+        if let Some(packet) = self.queue.try_pop() {
+            return Ok(Some(packet));
+        }
+        if !self.queue.is_closed() {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EAGAIN)));
+        }
+        // Packets may have been pushed right before closing.
+        match self.queue.try_pop() {
+            Some(packet) => Ok(Some(packet)),
+            None => self.end(),
+        }
+    }
+
+    /// Number of packets read ahead.
+    pub fn buffered(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Stop reading and get the input back, e.g. to seek it before reading
+    /// it with a new reader. The packets read ahead are dropped.
+    ///
+    /// Waits for the read in progress, to stop a blocked network read, open
+    /// the input with an interrupt callback, see
+    /// [`AVFormatContextInput::open_with_interrupt()`].
+    pub fn stop(mut self) -> AVFormatContextInput {
+        self.join().unwrap()
+    }
+
+    fn end(&self) -> Result<Option<AVPacket>> {
+        match self.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    fn join(&mut self) -> Option<AVFormatContextInput> {
+        let handle = self.handle.take()?;
+        self.stop.store(true, Ordering::Relaxed);
+        // Wakes the reading thread up if it waits for room.
+        self.queue.close();
+        Some(handle.join().unwrap())
+    }
+}
+
+impl Drop for PrefetchingReader {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::AVERROR_EAGAIN;
+    use cstr::cstr;
+
+    fn open() -> AVFormatContextInput {
+        AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None).unwrap()
+    }
+
+    #[test]
+    fn test_prefetching_reader() {
+        let mut input = open();
+        let expected: Vec<_> = std::iter::from_fn(|| input.read_packet().unwrap())
+            .map(|x| (x.stream_index, x.pts))
+            .collect();
+
+        let reader = PrefetchingReader::new(open(), 4);
+        let mut packets = vec![];
+        loop {
+            match reader.try_next_packet() {
+                Ok(Some(packet)) => packets.push((packet.stream_index, packet.pts)),
+                Ok(None) => break,
+                Err(e) if e.raw_error() == Some(AVERROR_EAGAIN) => {
+                    assert!(reader.buffered() <= 4);
+                    thread::yield_now();
+                }
+                Err(e) => panic!("{e}"),
+            }
+        }
+        assert_eq!(packets, expected);
+        assert!(reader.next_packet().unwrap().is_none());
+
+        let reader = PrefetchingReader::new(open(), 2);
+        let first = reader.next_packet().unwrap().unwrap();
+        assert_eq!((first.stream_index, first.pts), expected[0]);
+        // The reading thread is blocked by the full queue.
+        let mut input = reader.stop();
+        let next = input.read_packet().unwrap().unwrap();
+        let position = expected
+            .iter()
+            .position(|x| *x == (next.stream_index, next.pts))
+            .unwrap();
+        assert!((1..=4).contains(&position));
+
+        // Dropped while reading.
+        drop(PrefetchingReader::new(open(), 1));
+    }
+}
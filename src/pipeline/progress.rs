@@ -0,0 +1,222 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    avcodec::AVPacket,
+    avformat::AVFormatContextOutput,
+    avutil::{av_rescale_q, AVRational},
+    ffi,
+};
+
+/// Progress of a job processing media, e.g. a transcode, see
+/// [`ProgressTracker`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Media time processed, from the earliest timestamp to the end of the
+    /// latest packet.
+    pub processed: Duration,
+    /// Estimated duration of the whole input, `None` if unknown.
+    pub total: Option<Duration>,
+    /// Wall clock time since the start of the job.
+    pub elapsed: Duration,
+    /// Media time processed per second of wall clock time, e.g. `2.0` for
+    /// twice as fast as real time.
+    pub speed: f64,
+    /// Video frames processed per second of wall clock time.
+    pub fps: f64,
+    /// Video frames processed.
+    pub frames: u64,
+    /// Bytes written to the output.
+    pub bytes: u64,
+    /// Average bitrate of the output in bit/s.
+    pub bit_rate: f64,
+}
+
+impl Progress {
+    /// Processed part of the input, between 0 and 1, `None` if its duration
+    /// is unknown.
+    pub fn ratio(&self) -> Option<f64> {
+        let total = self.total.filter(|x| !x.is_zero())?;
+        Some((self.processed.as_secs_f64() / total.as_secs_f64()).min(1.0))
+    }
+
+    /// Estimated wall clock time until the end, `None` if the duration of the
+    /// input is unknown or nothing has been processed yet.
+    pub fn remaining(&self) -> Option<Duration> {
+        let total = self.total?;
+        if self.speed <= 0.0 {
+            return None;
+        }
+        let left = total.saturating_sub(self.processed).as_secs_f64();
+        Some(Duration::from_secs_f64(left / self.speed))
+    }
+}
+
+/// Computes the [`Progress`] of a job from the timestamps of the packets it
+/// processes.
+///
+/// ```ignore
+/// let mut tracker = ProgressTracker::new(input_duration);
+/// while let Some(packet) = input.read_packet()? {
+///     let stream = &input.streams()[packet.stream_index as usize];
+///     tracker.push_packet(&packet, stream.time_base, is_video);
+///     // ...
+///     tracker.set_bytes(output_bytes);
+///     if let Some(progress) = tracker.poll() {
+///         println!("{:.1}x", progress.speed);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    start: Instant,
+    interval: Duration,
+    last_report: Option<Instant>,
+    total: Option<Duration>,
+    /// Earliest timestamp and latest end, in nanoseconds.
+    range: Option<(i64, i64)>,
+    frames: u64,
+    bytes: u64,
+}
+
+impl ProgressTracker {
+    /// Track a job starting now, on an input lasting `total` if known.
+    /// [`Self::poll()`] reports at most every 500 milliseconds.
+    pub fn new(total: Option<Duration>) -> Self {
+        Self {
+            start: Instant::now(),
+            interval: Duration::from_millis(500),
+            last_report: None,
+            total,
+            range: None,
+            frames: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Set the minimum time between two reports of [`Self::poll()`].
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Account for a processed packet whose timestamps are in `time_base`,
+    /// `video` counts it as a frame. Packets without timestamps only count as
+    /// frames.
+    pub fn push_packet(&mut self, packet: &AVPacket, time_base: AVRational, video: bool) {
+        if video {
+            self.frames += 1;
+        }
+        let ts = match (packet.pts, packet.dts) {
+            (ffi::AV_NOPTS_VALUE, ffi::AV_NOPTS_VALUE) => return,
+            (ffi::AV_NOPTS_VALUE, x) | (x, _) => x,
+        };
+        let start = av_rescale_q(ts, time_base, NANOSECOND);
+        let end = av_rescale_q(ts + packet.duration.max(0), time_base, NANOSECOND);
+        self.range = Some(match self.range {
+            Some((a, b)) => (a.min(start), b.max(end)),
+            None => (start, end),
+        });
+    }
+
+    /// Set the number of bytes written to the output so far.
+    pub fn set_bytes(&mut self, bytes: u64) {
+        self.bytes = bytes;
+    }
+
+    /// The current progress.
+    pub fn progress(&self) -> Progress {
+        let elapsed = self.start.elapsed();
+        let processed = self.range.map_or(Duration::ZERO, |(start, end)| {
+            Duration::from_nanos((end - start).max(0) as u64)
+        });
+        let per_second = |x: f64| match elapsed.as_secs_f64() {
+            secs if secs > 0.0 => x / secs,
+            _ => 0.0,
+        };
+        let bit_rate = match processed.as_secs_f64() {
+            secs if secs > 0.0 => self.bytes as f64 * 8.0 / secs,
+            _ => 0.0,
+        };
+        Progress {
+            processed,
+            total: self.total,
+            elapsed,
+            speed: per_second(processed.as_secs_f64()),
+            fps: per_second(self.frames as f64),
+            frames: self.frames,
+            bytes: self.bytes,
+            bit_rate,
+        }
+    }
+
+    /// The current progress if the last one was reported at least the
+    /// interval ago, `None` otherwise.
+    pub fn poll(&mut self) -> Option<Progress> {
+        let now = Instant::now();
+        if matches!(self.last_report, Some(x) if now - x < self.interval) {
+            return None;
+        }
+        self.last_report = Some(now);
+        Some(self.progress())
+    }
+}
+
+/// Number of bytes written to the I/O context of `output`, 0 for the formats
+/// without file.
+pub(super) fn output_bytes(output: &AVFormatContextOutput) -> u64 {
+    let Some(pb) = (unsafe { output.pb.as_ref() }) else {
+        return 0;
+    };
+    // Same as `avio_tell()` of a write context.
+    let buffered = unsafe { pb.buf_ptr.offset_from(pb.buffer) };
+    (pb.pos + buffered as i64).max(0) as u64
+}
+
+const NANOSECOND: AVRational = AVRational {
+    num: 1,
+    den: 1_000_000_000,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(pts: i64, duration: i64) -> AVPacket {
+        let mut packet = AVPacket::new();
+        packet.set_pts(pts);
+        packet.set_dts(pts);
+        packet.set_duration(duration);
+        packet
+    }
+
+    #[test]
+    fn test_progress_tracker() {
+        let mut tracker = ProgressTracker::new(Some(Duration::from_secs(10)))
+            .with_interval(Duration::from_secs(3600));
+        assert_eq!(tracker.progress().processed, Duration::ZERO);
+        assert_eq!(tracker.progress().ratio(), Some(0.0));
+        assert!(tracker.poll().is_some());
+        assert!(tracker.poll().is_none());
+
+        let video = AVRational { num: 1, den: 25 };
+        let audio = AVRational { num: 1, den: 1000 };
+        for pts in 0..50 {
+            tracker.push_packet(&packet(pts, 1), video, true);
+        }
+        tracker.push_packet(&packet(1000, 1500), audio, false);
+        tracker.push_packet(&packet(ffi::AV_NOPTS_VALUE, 0), video, true);
+        tracker.set_bytes(25_000);
+        let progress = tracker.progress();
+        assert_eq!(progress.processed, Duration::from_millis(2500));
+        assert_eq!(progress.frames, 51);
+        assert_eq!(progress.bytes, 25_000);
+        assert_eq!(progress.bit_rate, 80_000.0);
+        assert_eq!(progress.ratio(), Some(0.25));
+        assert!(progress.speed > 0.0 && progress.fps > 0.0);
+        assert!(progress.remaining().is_some());
+
+        let progress = ProgressTracker::new(None).progress();
+        assert_eq!(progress.ratio(), None);
+        assert_eq!(progress.remaining(), None);
+    }
+}
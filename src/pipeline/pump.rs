@@ -0,0 +1,269 @@
+use crate::{
+    avcodec::{AVBSFContext, AVCodecContext, AVPacket},
+    avutil::AVFrame,
+    error::{Result, RsmpegError},
+    ffi,
+    shared::AVERROR_EAGAIN,
+};
+
+/// A context turning inputs of type `I` into outputs with the send/receive
+/// API of FFmpeg: decoders, encoders and bitstream filters. Driven by
+/// [`pump()`] and [`pump_flush()`].
+pub trait SendReceive<I> {
+    type Output;
+
+    /// Send `input`, `None` signals the end of the stream.
+    fn send(&mut self, input: Option<&mut I>) -> Result<()>;
+
+    /// Receive the next output.
+    fn receive(&mut self) -> Result<Self::Output>;
+}
+
+/// Decoder.
+impl SendReceive<AVPacket> for AVCodecContext {
+    type Output = AVFrame;
+
+    fn send(&mut self, input: Option<&mut AVPacket>) -> Result<()> {
+        self.send_packet(input.map(|x| &*x))
+    }
+
+    fn receive(&mut self) -> Result<AVFrame> {
+        self.receive_frame()
+    }
+}
+
+/// Encoder.
+impl SendReceive<AVFrame> for AVCodecContext {
+    type Output = AVPacket;
+
+    fn send(&mut self, input: Option<&mut AVFrame>) -> Result<()> {
+        self.send_frame(input.map(|x| &*x))
+    }
+
+    fn receive(&mut self) -> Result<AVPacket> {
+        self.receive_packet()
+    }
+}
+
+impl SendReceive<AVPacket> for AVBSFContext {
+    type Output = AVPacket;
+
+    fn send(&mut self, input: Option<&mut AVPacket>) -> Result<()> {
+        self.send_packet(input)
+    }
+
+    fn receive(&mut self) -> Result<AVPacket> {
+        let mut packet = AVPacket::new();
+        self.receive_packet(&mut packet)?;
+        Ok(packet)
+    }
+}
+
+/// Send all the `inputs` to `context`, passing every output to `on_output`
+/// as soon as it's available.
+///
+/// When `context` is full, its outputs are received before sending the
+/// input again, and it's drained after each input, so the outputs come out
+/// in order and no `*FullError` or `*DrainError` reaches the caller. The end
+/// of the stream isn't signaled, call [`pump_flush()`] after the last input.
+///
+/// An error of `on_output` stops the pump and is returned.
+///
+/// ```ignore
+/// while let Some(packet) = input.read_packet()? {
+///     if packet.stream_index as usize == video_index {
+///         pump(&mut decoder, Some(packet), |frame| {
+///             pump(&mut encoder, Some(frame), |packet| output.write_frame(&mut { packet }))
+///         })?;
+///     }
+/// }
+/// ```
+pub fn pump<C, I>(
+    context: &mut C,
+    inputs: impl IntoIterator<Item = I>,
+    mut on_output: impl FnMut(C::Output) -> Result<()>,
+) -> Result<()>
+where
+    C: SendReceive<I> + ?Sized,
+{
+    for mut input in inputs {
+        send(context, Some(&mut input), &mut on_output)?;
+    }
+    Ok(())
+}
+
+/// Signal the end of the stream to `context` and pass all the outputs it
+/// still buffers to `on_output`. Flushing an already flushed context does
+/// nothing.
+///
+/// The input type can't be inferred, e.g. `pump_flush::<_, AVPacket>(&mut
+/// decoder, on_frame)`.
+pub fn pump_flush<C, I>(
+    context: &mut C,
+    mut on_output: impl FnMut(C::Output) -> Result<()>,
+) -> Result<()>
+where
+    C: SendReceive<I> + ?Sized,
+{
+    send(context, None, &mut on_output)
+}
+
+/// Send `input` to `context` until it's accepted, then drain it.
+fn send<C, I>(
+    context: &mut C,
+    mut input: Option<&mut I>,
+    on_output: &mut impl FnMut(C::Output) -> Result<()>,
+) -> Result<()>
+where
+    C: SendReceive<I> + ?Sized,
+{
+    loop {
+        match context.send(input.as_deref_mut()) {
+            Ok(()) => break,
+            Err(e) if e.raw_error() == Some(AVERROR_EAGAIN) => {
+                // Neither accepting inputs nor producing outputs breaks the
+                // API contract, fail instead of spinning.
+                if receive_all(context, on_output)? == 0 {
+                    return Err(e);
+                }
+            }
+            // Already flushed.
+            Err(e) if input.is_none() && e.raw_error() == Some(ffi::AVERROR_EOF) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    receive_all(context, on_output)?;
+    Ok(())
+}
+
+/// Receive the outputs of `context` until it needs more input or is flushed,
+/// returns the number of outputs received.
+fn receive_all<C, I>(
+    context: &mut C,
+    on_output: &mut impl FnMut(C::Output) -> Result<()>,
+) -> Result<usize>
+where
+    C: SendReceive<I> + ?Sized,
+{
+    let mut count = 0;
+    loop {
+        match context.receive() {
+            Ok(output) => {
+                on_output(output)?;
+                count += 1;
+            }
+            Err(e) if is_drained(&e) => return Ok(count),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn is_drained(e: &RsmpegError) -> bool {
+    matches!(e.raw_error(), Some(AVERROR_EAGAIN | ffi::AVERROR_EOF))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        avcodec::{AVBSFContextUninit, AVBitStreamFilter, AVCodec},
+        avformat::AVFormatContextInput,
+        avutil::ra,
+    };
+    use cstr::cstr;
+
+    fn open_bear() -> (AVFormatContextInput, usize, AVCodecContext) {
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (index, codec) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        let mut decoder = AVCodecContext::from_stream(&input.streams()[index], &codec).unwrap();
+        decoder.open(None).unwrap();
+        (input, index, decoder)
+    }
+
+    #[test]
+    fn test_pump_decoder_and_encoder() {
+        let (mut input, index, mut decoder) = open_bear();
+        let codec = AVCodec::find_encoder(ffi::AV_CODEC_ID_MPEG4).unwrap();
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_width(decoder.width);
+        encoder.set_height(decoder.height);
+        encoder.set_pix_fmt(decoder.pix_fmt);
+        encoder.set_time_base(ra(1, 30));
+        encoder.set_max_b_frames(2);
+        encoder.open(None).unwrap();
+
+        let (mut frames, mut packets) = (0, 0);
+        let mut on_frame = |mut frame: AVFrame| {
+            frames += 1;
+            frame.set_pts(frames);
+            frame.set_pict_type(ffi::AV_PICTURE_TYPE_NONE);
+            pump(&mut encoder, Some(frame), |_| {
+                packets += 1;
+                Ok(())
+            })
+        };
+        while let Some(packet) = input.read_packet().unwrap() {
+            if packet.stream_index as usize == index {
+                pump(&mut decoder, Some(packet), &mut on_frame).unwrap();
+            }
+        }
+        pump_flush::<_, AVPacket>(&mut decoder, &mut on_frame).unwrap();
+        pump_flush::<_, AVPacket>(&mut decoder, &mut on_frame).unwrap();
+        pump_flush::<_, AVFrame>(&mut encoder, |_| {
+            packets += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert!(frames > 0);
+        assert_eq!(packets, frames);
+    }
+
+    #[test]
+    fn test_pump_bsf_and_errors() {
+        let (mut input, index, _) = open_bear();
+        let bsf = AVBitStreamFilter::find_by_name(cstr!("h264_mp4toannexb")).unwrap();
+        let mut bsf_context = {
+            let mut bsf_context = AVBSFContextUninit::new(&bsf);
+            let stream = &input.streams()[index];
+            bsf_context.set_par_in(&stream.codecpar());
+            bsf_context.set_time_base_in(stream.time_base);
+            bsf_context.init().unwrap()
+        };
+
+        let packets: Vec<_> = std::iter::from_fn(|| input.read_packet().unwrap())
+            .filter(|x| x.stream_index as usize == index)
+            .collect();
+        let count = packets.len();
+        let mut outputs = vec![];
+        pump(&mut bsf_context, packets, |packet| {
+            outputs.push(packet);
+            Ok(())
+        })
+        .unwrap();
+        pump_flush::<_, AVPacket>(&mut bsf_context, |packet| {
+            outputs.push(packet);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(outputs.len(), count);
+        // Annex B start code.
+        assert_eq!(&outputs[0].data()[..4], [0, 0, 0, 1]);
+
+        // Errors of the callback stop the pump.
+        let (mut input, index, mut decoder) = open_bear();
+        let packets = std::iter::from_fn(|| input.read_packet().unwrap())
+            .filter(|x| x.stream_index as usize == index);
+        let mut calls = 0;
+        let result = pump(&mut decoder, packets, |_| {
+            calls += 1;
+            Err(RsmpegError::AVError(ffi::AVERROR_EXIT))
+        });
+        assert_eq!(result, Err(RsmpegError::AVError(ffi::AVERROR_EXIT)));
+        assert_eq!(calls, 1);
+    }
+}
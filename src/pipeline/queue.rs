@@ -0,0 +1,348 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+};
+
+use crate::{
+    avcodec::AVPacket,
+    avutil::AVFrame,
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// What a [`BoundedQueue`] does when an item is pushed while it's full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Wait until the consumer makes room, nothing is dropped.
+    #[default]
+    Block,
+    /// Drop the oldest queued item.
+    DropOldest,
+    /// Drop the incoming item if it's not a key item, then keep dropping the
+    /// non-key items until the next key item, as they depend on the dropped
+    /// one. An incoming key item replaces the oldest key item together with
+    /// the non-key items following it, i.e. the oldest whole GOP.
+    DropNonKey,
+}
+
+/// Items of a [`BoundedQueue`].
+pub trait QueueItem: Send {
+    /// Whether the item can be used without the previous ones, used by
+    /// [`DropPolicy::DropNonKey`].
+    fn is_key(&self) -> bool;
+}
+
+impl QueueItem for AVPacket {
+    fn is_key(&self) -> bool {
+        self.is_keyframe()
+    }
+}
+
+impl QueueItem for AVFrame {
+    fn is_key(&self) -> bool {
+        self.flags & ffi::AV_FRAME_FLAG_KEY as i32 != 0 || self.pict_type == ffi::AV_PICTURE_TYPE_I
+    }
+}
+
+/// Counters of a [`BoundedQueue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueueStats {
+    /// Items passed to [`BoundedQueue::push()`], including the dropped
+    /// ones.
+    pub pushed: u64,
+    /// Items returned to the consumer.
+    pub popped: u64,
+    /// Items dropped by the [`DropPolicy`].
+    pub dropped: u64,
+    /// Items currently queued.
+    pub len: usize,
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    stats: QueueStats,
+    /// Dropping the non-key items until the next key item.
+    skipping: bool,
+    closed: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+/// A queue of at most `capacity` items between a producer and a consumer
+/// thread, whose behavior when full is set by a [`DropPolicy`].
+///
+/// With [`DropPolicy::Block`] a slow consumer slows the producer down, the
+/// other policies keep the producer, e.g. a live capture, running and drop
+/// items instead, so the latency stays bounded. The drops are counted in
+/// [`Self::stats()`].
+///
+/// The queue is a cheap handle, clone it to share it between the threads.
+///
+/// ```ignore
+/// let queue = PacketQueue::new(64, DropPolicy::DropNonKey);
+/// let producer = queue.clone();
+/// thread::spawn(move || {
+///     while let Some(packet) = input.read_packet()? {
+///         producer.push(packet)?;
+///     }
+///     producer.close();
+/// });
+/// while let Some(packet) = queue.pop() {
+///     // ...
+/// }
+/// println!("dropped {} packets", queue.stats().dropped);
+/// ```
+pub struct BoundedQueue<T> {
+    shared: Arc<Shared<T>>,
+    capacity: usize,
+    policy: DropPolicy,
+}
+
+/// A [`BoundedQueue`] of packets.
+pub type PacketQueue = BoundedQueue<AVPacket>;
+
+/// A [`BoundedQueue`] of frames.
+pub type FrameQueue = BoundedQueue<AVFrame>;
+
+impl<T> Clone for BoundedQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            capacity: self.capacity,
+            policy: self.policy,
+        }
+    }
+}
+
+impl<T: QueueItem> BoundedQueue<T> {
+    /// Create a queue holding at most `capacity` items, at least one.
+    pub fn new(capacity: usize, policy: DropPolicy) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    items: VecDeque::with_capacity(capacity),
+                    stats: QueueStats::default(),
+                    skipping: false,
+                    closed: false,
+                }),
+                not_empty: Condvar::new(),
+                not_full: Condvar::new(),
+            }),
+            capacity,
+            policy,
+        }
+    }
+
+    /// Maximum number of queued items.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Behavior of the queue when full.
+    pub fn policy(&self) -> DropPolicy {
+        self.policy
+    }
+
+    /// Queue `item`, applying the [`DropPolicy`] if the queue is full.
+    ///
+    /// Returns `AVERROR_EOF` if the queue has been closed.
+    pub fn push(&self, item: T) -> Result<()> {
+        let mut state = self.lock();
+        if self.policy == DropPolicy::DropNonKey && state.skipping {
+            if !item.is_key() {
+                return Self::accept_dropped(&mut state);
+            }
+            state.skipping = false;
+        }
+        while !state.closed && state.items.len() >= self.capacity {
+            match self.policy {
+                DropPolicy::Block => {
+                    state = self.shared.not_full.wait(state).unwrap();
+                }
+                DropPolicy::DropNonKey if !item.is_key() => {
+                    state.skipping = true;
+                    return Self::accept_dropped(&mut state);
+                }
+                DropPolicy::DropOldest => {
+                    state.items.pop_front();
+                    state.stats.dropped += 1;
+                }
+                DropPolicy::DropNonKey => {
+                    // The non-key items following the dropped one depend on
+                    // it, drop them up to the next key item.
+                    state.items.pop_front();
+                    state.stats.dropped += 1;
+                    while state.items.front().is_some_and(|x| !x.is_key()) {
+                        state.items.pop_front();
+                        state.stats.dropped += 1;
+                    }
+                }
+            }
+        }
+        if state.closed {
+            return Err(RsmpegError::AVError(ffi::AVERROR_EOF));
+        }
+        state.items.push_back(item);
+        state.stats.pushed += 1;
+        drop(state);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Wait for the next item.
+    ///
+    /// Returns `None` once the queue is closed and all the items popped.
+    pub fn pop(&self) -> Option<T> {
+        let mut state = self.lock();
+        loop {
+            if let Some(item) = self.take(&mut state) {
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.shared.not_empty.wait(state).unwrap();
+        }
+    }
+
+    /// Get the next item without waiting, `None` if the queue is empty.
+    pub fn try_pop(&self) -> Option<T> {
+        self.take(&mut self.lock())
+    }
+
+    /// Close the queue: the pushes fail from now on, the consumer gets the
+    /// remaining items then `None`.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.shared.not_empty.notify_all();
+        self.shared.not_full.notify_all();
+    }
+
+    /// Whether [`Self::close()`] has been called.
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Number of queued items.
+    pub fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    /// Returns true if no item is queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the counters of the queue.
+    pub fn stats(&self) -> QueueStats {
+        let state = self.lock();
+        QueueStats {
+            len: state.items.len(),
+            ..state.stats
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.shared.state.lock().unwrap()
+    }
+
+    fn take(&self, state: &mut State<T>) -> Option<T> {
+        let item = state.items.pop_front()?;
+        state.stats.popped += 1;
+        self.shared.not_full.notify_one();
+        Some(item)
+    }
+
+    /// Count an incoming item dropped by the policy.
+    fn accept_dropped(state: &mut State<T>) -> Result<()> {
+        if state.closed {
+            return Err(RsmpegError::AVError(ffi::AVERROR_EOF));
+        }
+        state.stats.pushed += 1;
+        state.stats.dropped += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avcodec::PacketFlags;
+    use std::thread;
+
+    fn packet(pts: i64, key: bool) -> AVPacket {
+        let mut packet = AVPacket::new();
+        packet.set_pts(pts);
+        if key {
+            packet.set_packet_flags(PacketFlags::KEY);
+        }
+        packet
+    }
+
+    fn drain(queue: &PacketQueue) -> Vec<i64> {
+        std::iter::from_fn(|| queue.try_pop())
+            .map(|x| x.pts)
+            .collect()
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let queue = PacketQueue::new(2, DropPolicy::DropOldest);
+        for pts in 0..5 {
+            queue.push(packet(pts, false)).unwrap();
+        }
+        assert_eq!(queue.len(), 2);
+        assert_eq!(drain(&queue), [3, 4]);
+        let stats = queue.stats();
+        assert_eq!((stats.pushed, stats.popped, stats.dropped), (5, 2, 3));
+        assert_eq!(stats.len, 0);
+    }
+
+    #[test]
+    fn test_drop_non_key() {
+        let queue = PacketQueue::new(2, DropPolicy::DropNonKey);
+        queue.push(packet(0, true)).unwrap();
+        queue.push(packet(1, false)).unwrap();
+        // Full: the non-key packets are dropped until the next key packet.
+        queue.push(packet(2, false)).unwrap();
+        assert_eq!(queue.try_pop().unwrap().pts, 0);
+        queue.push(packet(3, false)).unwrap();
+        queue.push(packet(4, true)).unwrap();
+        // Full: the key packet replaces the oldest one.
+        queue.push(packet(5, true)).unwrap();
+        assert_eq!(drain(&queue), [4, 5]);
+        assert_eq!(queue.stats().dropped, 3);
+
+        let queue = PacketQueue::new(3, DropPolicy::DropNonKey);
+        for (pts, key) in [(0, true), (1, false), (2, true)] {
+            queue.push(packet(pts, key)).unwrap();
+        }
+        // Full: the key packet replaces the oldest GOP.
+        queue.push(packet(3, true)).unwrap();
+        assert_eq!(drain(&queue), [2, 3]);
+        assert_eq!(queue.stats().dropped, 2);
+    }
+
+    #[test]
+    fn test_block() {
+        let queue = PacketQueue::new(1, DropPolicy::Block);
+        let producer = queue.clone();
+        let handle = thread::spawn(move || {
+            for pts in 0..10 {
+                producer.push(packet(pts, false)).unwrap();
+            }
+            producer.close();
+        });
+        let pts: Vec<_> = std::iter::from_fn(|| queue.pop()).map(|x| x.pts).collect();
+        handle.join().unwrap();
+        assert_eq!(pts, (0..10).collect::<Vec<_>>());
+        assert_eq!(queue.stats().dropped, 0);
+        assert!(queue.is_closed());
+        assert!(queue.push(packet(10, true)).is_err());
+    }
+}
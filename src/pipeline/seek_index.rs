@@ -0,0 +1,198 @@
+use crate::{
+    avformat::{AVFormatContextInput, SeekFlags},
+    avutil::AVRational,
+    error::{Result, RsmpegError},
+    ffi,
+    shared::RetUpgrade,
+};
+
+/// A keyframe of a [`SeekIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekIndexEntry {
+    /// Presentation timestamp of the keyframe, its decoding timestamp if the
+    /// packet has no pts, in the time base of the stream.
+    pub timestamp: i64,
+    /// Byte position of the packet in the input.
+    pub pos: i64,
+    /// Size of the packet in bytes.
+    pub size: i32,
+}
+
+/// Keyframe positions of a stream, built by scanning the packets of an input
+/// with [`build_seek_index()`].
+///
+/// It makes seeking fast and accurate on formats whose native index is poor
+/// or missing, e.g. some FLV and MPEG-TS files, and can be reused for every
+/// input opened on the same file.
+#[derive(Debug, Clone)]
+pub struct SeekIndex {
+    stream_index: usize,
+    time_base: AVRational,
+    /// Sorted by timestamp.
+    entries: Vec<SeekIndexEntry>,
+}
+
+/// Read all the remaining packets of `input`, recording the position and the
+/// timestamp of the keyframes of stream `stream_index`. Keyframes without
+/// byte position or timestamp are skipped.
+///
+/// The input is left at its end, seek it before reading again, e.g. with
+/// [`SeekIndex::seek()`].
+///
+/// Returns `AVERROR(EINVAL)` if there is no stream `stream_index`.
+///
+/// ```ignore
+/// let index = build_seek_index(&mut input, video_index)?;
+/// // Later, jump right before the 42nd second.
+/// let ts = av_rescale_q(42, ra(1, 1), index.time_base());
+/// if let Some(entry) = index.seek(&mut input, ts)? {
+///     // Decode from `entry`, dropping the frames before `ts`.
+/// }
+/// ```
+pub fn build_seek_index(
+    input: &mut AVFormatContextInput,
+    stream_index: usize,
+) -> Result<SeekIndex> {
+    let time_base = input
+        .streams()
+        .get(stream_index)
+        .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?
+        .time_base;
+    let mut entries = vec![];
+    while let Some(packet) = input.read_packet()? {
+        if packet.stream_index as usize != stream_index || !packet.is_keyframe() {
+            continue;
+        }
+        let timestamp = match (packet.pts, packet.dts) {
+            (ffi::AV_NOPTS_VALUE, ffi::AV_NOPTS_VALUE) => continue,
+            (ffi::AV_NOPTS_VALUE, x) | (x, _) => x,
+        };
+        let Some(pos) = packet.pos() else {
+            continue;
+        };
+        entries.push(SeekIndexEntry {
+            timestamp,
+            pos,
+            size: packet.size,
+        });
+    }
+    entries.sort_by_key(|x| (x.timestamp, x.pos));
+    entries.dedup_by_key(|x| x.timestamp);
+    Ok(SeekIndex {
+        stream_index,
+        time_base,
+        entries,
+    })
+}
+
+impl SeekIndex {
+    /// Index of the stream the keyframes belong to.
+    pub fn stream_index(&self) -> usize {
+        self.stream_index
+    }
+
+    /// Time base of the timestamps of the entries.
+    pub fn time_base(&self) -> AVRational {
+        self.time_base
+    }
+
+    /// Get the keyframes, sorted by timestamp.
+    pub fn entries(&self) -> &[SeekIndexEntry] {
+        &self.entries
+    }
+
+    /// Return the last keyframe at or before `timestamp`, `None` if
+    /// `timestamp` is before the first keyframe.
+    pub fn lookup(&self, timestamp: i64) -> Option<&SeekIndexEntry> {
+        let i = self.entries.partition_point(|x| x.timestamp <= timestamp);
+        i.checked_sub(1).map(|i| &self.entries[i])
+    }
+
+    /// Move `input` to the keyframe returned by [`Self::lookup()`] with a
+    /// byte seek, so the next packet read is this keyframe. Returns
+    /// `Ok(None)` without seeking if there is no such keyframe.
+    ///
+    /// Fails on formats not supporting byte seeking, use
+    /// [`Self::add_to_input()`] and [`AVFormatContextInput::seek()`] for
+    /// them.
+    pub fn seek(
+        &self,
+        input: &mut AVFormatContextInput,
+        timestamp: i64,
+    ) -> Result<Option<SeekIndexEntry>> {
+        let Some(entry) = self.lookup(timestamp).copied() else {
+            return Ok(None);
+        };
+        input.seek(
+            Some(self.stream_index),
+            entry.pos,
+            entry.pos,
+            entry.pos,
+            SeekFlags::BYTE,
+        )?;
+        Ok(Some(entry))
+    }
+
+    /// Add the keyframes to the native index of the stream of `input`, which
+    /// is used by the generic seeking of libavformat when the demuxer has no
+    /// better way.
+    pub fn add_to_input(&self, input: &mut AVFormatContextInput) -> Result<()> {
+        let stream = input
+            .streams_mut()
+            .get_mut(self.stream_index)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        for entry in &self.entries {
+            unsafe {
+                ffi::av_add_index_entry(
+                    stream.as_mut_ptr(),
+                    entry.pos,
+                    entry.timestamp,
+                    entry.size,
+                    0,
+                    ffi::AVINDEX_KEYFRAME as i32,
+                )
+            }
+            .upgrade()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_seek_index() {
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bunny.flv"), None, &mut None)
+                .unwrap();
+        let (video_index, _) = input
+            .find_best_stream(ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .unwrap();
+        assert!(build_seek_index(&mut input, 100).is_err());
+        let index = build_seek_index(&mut input, video_index).unwrap();
+        assert_eq!(index.stream_index(), video_index);
+        let entries = index.entries();
+        assert!(entries.len() > 1);
+        assert!(entries.windows(2).all(|x| x[0].timestamp < x[1].timestamp));
+
+        let first = entries[0];
+        assert!(index.lookup(first.timestamp - 1).is_none());
+        assert_eq!(index.lookup(first.timestamp), Some(&first));
+        let second = entries[1];
+        assert_eq!(index.lookup(second.timestamp - 1), Some(&first));
+        assert_eq!(index.lookup(i64::MAX), entries.last());
+
+        let entry = index.seek(&mut input, second.timestamp).unwrap().unwrap();
+        assert_eq!(entry, second);
+        let packet = input.read_packet().unwrap().unwrap();
+        assert_eq!(packet.stream_index as usize, video_index);
+        assert_eq!(packet.pos(), Some(second.pos));
+        assert!(packet.is_keyframe());
+
+        index.add_to_input(&mut input).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use std::ffi::CStr;
+
+use crate::{
+    avcodec::{AVCodecContext, DecodePolicy},
+    avformat::AVFormatContextInput,
+    avutil::AVFrame,
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// Demuxer and decoder of the best stream of a given media type in an input.
+pub struct StreamDecoder {
+    input: AVFormatContextInput,
+    decoder: AVCodecContext,
+    stream_index: usize,
+    input_eof: bool,
+}
+
+impl StreamDecoder {
+    /// Open `url` and the decoder of its best stream of `media_type`.
+    ///
+    /// Returns `Ok(None)` if there is no such stream.
+    pub fn open(url: &CStr, media_type: ffi::AVMediaType) -> Result<Option<Self>> {
+        let input = AVFormatContextInput::open(url, None, &mut None)?;
+        Self::from_input(input, media_type)
+    }
+
+    /// Open the decoder of the best stream of `media_type` in `input`.
+    ///
+    /// Returns `Ok(None)` if there is no such stream.
+    pub fn from_input(
+        input: AVFormatContextInput,
+        media_type: ffi::AVMediaType,
+    ) -> Result<Option<Self>> {
+        let Some((stream_index, codec)) = input.find_best_stream(media_type)? else {
+            return Ok(None);
+        };
+        let mut decoder = AVCodecContext::from_stream(&input.streams()[stream_index], &codec)?;
+        decoder.open(None)?;
+        Ok(Some(Self {
+            input,
+            decoder,
+            stream_index,
+            input_eof: false,
+        }))
+    }
+
+    /// Index of the decoded stream.
+    pub fn stream_index(&self) -> usize {
+        self.stream_index
+    }
+
+    /// Get the demuxer.
+    pub fn input(&self) -> &AVFormatContextInput {
+        &self.input
+    }
+
+    /// Get the demuxer mutably.
+    pub fn input_mut(&mut self) -> &mut AVFormatContextInput {
+        &mut self.input
+    }
+
+    /// Get the decoder.
+    pub fn decoder(&self) -> &AVCodecContext {
+        &self.decoder
+    }
+
+    /// Get the decoder mutably.
+    pub fn decoder_mut(&mut self) -> &mut AVCodecContext {
+        &mut self.decoder
+    }
+
+    /// Decode the next frame of the stream.
+    ///
+    /// Returns `Ok(None)` when the decoder is fully drained at the end of the
+    /// input.
+    pub fn decode_frame(&mut self) -> Result<Option<AVFrame>> {
+        loop {
+            match self.decoder.receive_frame() {
+                Ok(frame) => return Ok(Some(frame)),
+                Err(RsmpegError::DecoderDrainError) => {}
+                Err(RsmpegError::DecoderFlushedError) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            if self.input_eof {
+                // Receiving `DecoderDrainError` after the flush packet
+                // shouldn't happen, bail out instead of looping forever.
+                return Ok(None);
+            }
+            match self.input.read_packet()? {
+                Some(packet) if packet.stream_index as usize == self.stream_index => {
+                    self.decoder.send_packet(Some(&packet))?;
+                }
+                Some(_) => {}
+                None => {
+                    self.input_eof = true;
+                    self.decoder.send_packet(None)?;
+                }
+            }
+        }
+    }
+
+    /// Seek to the closest keyframe before `ts`, in the time base of the
+    /// decoded stream, and flush the decoder.
+    pub fn seek(&mut self, ts: i64) -> Result<()> {
+        self.input
+            .seek(Some(self.stream_index), i64::MIN, ts, ts, 0)?;
+        self.decoder.flush_buffers();
+        self.input_eof = false;
+        Ok(())
+    }
+
+    /// Seek to `ts`, in the time base of the decoded stream, and return the
+    /// first frame whose timestamp is not before `ts`.
+    ///
+    /// The input is seeked to the closest keyframe before `ts`, then the
+    /// frames between the keyframe and `ts` are decoded with `policy` (e.g.
+    /// [`DecodePolicy::fast_seek()`]) to converge faster. The previous decode
+    /// policy is restored once the target is reached. Frames discarded by
+    /// `policy` are never returned, so the returned frame may be later than
+    /// the first frame after `ts`.
+    ///
+    /// Returns `Ok(None)` if no frame at or after `ts` exists.
+    pub fn fast_seek_to(&mut self, ts: i64, policy: &DecodePolicy) -> Result<Option<AVFrame>> {
+        self.seek(ts)?;
+
+        let original = self.decoder.decode_policy();
+        self.decoder.set_decode_policy(policy);
+        let result = self.decode_until(ts);
+        self.decoder.set_decode_policy(&original);
+        result
+    }
+
+    fn decode_until(&mut self, ts: i64) -> Result<Option<AVFrame>> {
+        while let Some(frame) = self.decode_frame()? {
+            let frame_ts = match frame.best_effort_timestamp {
+                ffi::AV_NOPTS_VALUE => frame.pts,
+                x => x,
+            };
+            if frame_ts == ffi::AV_NOPTS_VALUE || frame_ts >= ts {
+                return Ok(Some(frame));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_stream_decoder() {
+        let mut decoder =
+            StreamDecoder::open(cstr!("tests/assets/vids/bear.mp4"), ffi::AVMEDIA_TYPE_VIDEO)
+                .unwrap()
+                .unwrap();
+        let mut count = 0;
+        while let Some(frame) = decoder.decode_frame().unwrap() {
+            assert_eq!(frame.width, decoder.decoder().width);
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_fast_seek_to() {
+        let mut decoder =
+            StreamDecoder::open(cstr!("tests/assets/vids/bear.mp4"), ffi::AVMEDIA_TYPE_VIDEO)
+                .unwrap()
+                .unwrap();
+        let time_base = decoder.input().streams()[decoder.stream_index()].time_base;
+        // Half a second
+        let ts = time_base.den as i64 / time_base.num as i64 / 2;
+
+        let original = decoder.decoder().decode_policy();
+        let frame = decoder
+            .fast_seek_to(ts, &DecodePolicy::fast_seek())
+            .unwrap()
+            .unwrap();
+        assert!(frame.best_effort_timestamp >= ts);
+        assert_eq!(decoder.decoder().decode_policy(), original);
+
+        // Decoding goes on after the target.
+        let next = decoder.decode_frame().unwrap().unwrap();
+        assert!(next.best_effort_timestamp > frame.best_effort_timestamp);
+    }
+}
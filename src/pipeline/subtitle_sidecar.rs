@@ -0,0 +1,297 @@
+use std::{ffi::CStr, ptr};
+
+use crate::{
+    avcodec::{AVCodec, AVCodecContext, AVCodecParameters, AVPacket},
+    avformat::{AVFormatContextInput, AVFormatContextOutput},
+    avutil::{av_rescale_q, AVRational},
+    error::{Result, RsmpegError},
+    ffi,
+    shared::*,
+};
+
+/// Codecs tried in order when the muxer can't store the subtitle codec of the
+/// sidecar file as-is.
+const FALLBACK_CODECS: [ffi::AVCodecID; 4] = [
+    ffi::AV_CODEC_ID_MOV_TEXT,
+    ffi::AV_CODEC_ID_SUBRIP,
+    ffi::AV_CODEC_ID_ASS,
+    ffi::AV_CODEC_ID_WEBVTT,
+];
+
+struct SubtitleTranscoder {
+    decoder: AVCodecContext,
+    encoder: AVCodecContext,
+}
+
+/// Subtitle file (e.g. SRT or ASS) muxed as an additional stream of an output.
+///
+/// ```ignore
+/// let mut sidecar = SubtitleSidecar::open(cstr!("movie.srt"))?;
+/// sidecar.add_stream(&mut output)?;
+/// output.write_header(&mut None)?;
+/// while let Some(mut packet) = input.read_packet()? {
+///     // Write subtitles that come before the A/V packet first.
+///     sidecar.write_until(&mut output, packet.dts, time_base)?;
+///     output.interleaved_write_frame(&mut packet)?;
+/// }
+/// sidecar.write_remaining(&mut output)?;
+/// output.write_trailer()?;
+/// ```
+pub struct SubtitleSidecar {
+    input: AVFormatContextInput,
+    stream_index: usize,
+    transcoder: Option<SubtitleTranscoder>,
+    output_stream_index: Option<usize>,
+    /// Packet already read but not yet written by [`Self::write_until()`].
+    pending: Option<AVPacket>,
+}
+
+impl SubtitleSidecar {
+    /// Open a subtitle file as a secondary input.
+    pub fn open(url: &CStr) -> Result<Self> {
+        let input = AVFormatContextInput::open(url, None, &mut None)?;
+        let (stream_index, _) = input
+            .subtitle_streams()
+            .next()
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_STREAM_NOT_FOUND))?;
+        Ok(Self {
+            input,
+            stream_index,
+            transcoder: None,
+            output_stream_index: None,
+            pending: None,
+        })
+    }
+
+    /// Time base of the packets returned by [`Self::next_packet()`].
+    pub fn time_base(&self) -> AVRational {
+        self.input.streams()[self.stream_index].time_base
+    }
+
+    /// Index of the subtitle stream in the output, `None` before
+    /// [`Self::add_stream()`].
+    pub fn output_stream_index(&self) -> Option<usize> {
+        self.output_stream_index
+    }
+
+    /// Add the subtitle stream to `output`, should be called before
+    /// [`AVFormatContextOutput::write_header()`].
+    ///
+    /// The subtitles are stream copied if the muxer supports their codec,
+    /// otherwise they are converted to the first supported one of
+    /// `mov_text`, `subrip`, `ass` and `webvtt` (e.g. SRT to `mov_text` for
+    /// MP4). Returns the index of the new stream.
+    pub fn add_stream(&mut self, output: &mut AVFormatContextOutput) -> Result<usize> {
+        let oformat = output.oformat();
+        let stream = &self.input.streams()[self.stream_index];
+        let codecpar = stream.codecpar();
+        let supported = |codec_id| {
+            oformat
+                .query_codec(codec_id, ffi::FF_COMPLIANCE_NORMAL as i32)
+                .unwrap_or(false)
+        };
+
+        let (codecpar, transcoder) = if oformat
+            .query_codec(codecpar.codec_id, ffi::FF_COMPLIANCE_NORMAL as i32)
+            .unwrap_or(true)
+        {
+            (codecpar.clone(), None)
+        } else {
+            let codec_id = FALLBACK_CODECS
+                .into_iter()
+                .find(|&x| supported(x))
+                .ok_or(RsmpegError::AVError(ffi::AVERROR_MUXER_NOT_FOUND))?;
+            let transcoder = SubtitleTranscoder::new(&codecpar, stream.time_base, codec_id)?;
+            (transcoder.encoder.extract_codecpar(), Some(transcoder))
+        };
+
+        let mut out_stream = output.new_stream();
+        out_stream.set_codecpar(codecpar);
+        out_stream.set_time_base(self.time_base());
+        let index = out_stream.index as usize;
+
+        self.transcoder = transcoder;
+        self.output_stream_index = Some(index);
+        Ok(index)
+    }
+
+    /// Read the next subtitle packet, converted if needed, in
+    /// [`Self::time_base()`]. Returns `Ok(None)` at the end of the file.
+    pub fn next_packet(&mut self) -> Result<Option<AVPacket>> {
+        if let Some(packet) = self.pending.take() {
+            return Ok(Some(packet));
+        }
+        while let Some(mut packet) = self.input.read_packet()? {
+            if packet.stream_index as usize != self.stream_index {
+                continue;
+            }
+            let Some(transcoder) = &mut self.transcoder else {
+                return Ok(Some(packet));
+            };
+            if let Some(packet) = transcoder.transcode(&mut packet)? {
+                return Ok(Some(packet));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write the subtitle packets that start before `ts` (in `time_base`) to
+    /// `output`, call it before writing each audio/video packet to keep the
+    /// output interleaved.
+    pub fn write_until(
+        &mut self,
+        output: &mut AVFormatContextOutput,
+        ts: i64,
+        time_base: AVRational,
+    ) -> Result<()> {
+        if ts == ffi::AV_NOPTS_VALUE {
+            return Ok(());
+        }
+        while let Some(packet) = self.next_packet()? {
+            if av_rescale_q(packet.pts, self.time_base(), time_base) > ts {
+                self.pending = Some(packet);
+                break;
+            }
+            self.write_packet(output, packet)?;
+        }
+        Ok(())
+    }
+
+    /// Write all the remaining subtitle packets to `output`.
+    pub fn write_remaining(&mut self, output: &mut AVFormatContextOutput) -> Result<()> {
+        while let Some(packet) = self.next_packet()? {
+            self.write_packet(output, packet)?;
+        }
+        Ok(())
+    }
+
+    fn write_packet(&self, output: &mut AVFormatContextOutput, mut packet: AVPacket) -> Result<()> {
+        let index = self
+            .output_stream_index
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        let out_time_base = output.streams()[index].time_base;
+        packet.rescale_ts(self.time_base(), out_time_base);
+        packet.set_stream_index(index as i32);
+        packet.set_pos(-1);
+        output.interleaved_write_frame(&mut packet)
+    }
+}
+
+impl SubtitleTranscoder {
+    fn new(
+        codecpar: &AVCodecParameters,
+        time_base: AVRational,
+        codec_id: ffi::AVCodecID,
+    ) -> Result<Self> {
+        let codec = AVCodec::find_decoder(codecpar.codec_id)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_DECODER_NOT_FOUND))?;
+        let mut decoder = AVCodecContext::new(&codec);
+        decoder.apply_codecpar(codecpar)?;
+        decoder.set_pkt_timebase(time_base);
+        decoder.open(None)?;
+
+        let codec = AVCodec::find_encoder(codec_id)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_ENCODER_NOT_FOUND))?;
+        let mut encoder = AVCodecContext::new(&codec);
+        encoder.set_time_base(time_base);
+        // Styles of the decoded subtitles are needed by the encoder.
+        if !decoder.subtitle_header.is_null() {
+            let size = decoder.subtitle_header_size as usize;
+            let header = unsafe { ffi::av_mallocz(size + 1) }
+                .upgrade()
+                .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::ENOMEM)))?;
+            unsafe {
+                ptr::copy_nonoverlapping(decoder.subtitle_header, header.as_ptr() as *mut u8, size);
+                encoder.deref_mut().subtitle_header = header.as_ptr() as *mut u8;
+                encoder.deref_mut().subtitle_header_size = decoder.subtitle_header_size;
+            }
+        }
+        encoder.open(None)?;
+        Ok(Self { decoder, encoder })
+    }
+
+    /// Decode `packet` and encode it again, the output packet is in the time
+    /// base of the input packet.
+    fn transcode(&mut self, packet: &mut AVPacket) -> Result<Option<AVPacket>> {
+        let Some(mut subtitle) = self.decoder.decode_subtitle(Some(packet))? else {
+            return Ok(None);
+        };
+        if subtitle.pts == ffi::AV_NOPTS_VALUE {
+            return Ok(None);
+        }
+        self.encoder.encode_subtitle_packet(&mut subtitle).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+    use std::ffi::CString;
+
+    const SRT: &str =
+        "1\n00:00:00,100 --> 00:00:00,500\nHello\n\n2\n00:00:00,600 --> 00:00:00,900\nWorld\n";
+
+    fn remux_with_sidecar(output_name: &str) -> (tempdir::TempDir, AVFormatContextInput, usize) {
+        let tempdir = tempdir::TempDir::new("sidecar").unwrap();
+        let srt_path = tempdir.path().join("sub.srt");
+        std::fs::write(&srt_path, SRT).unwrap();
+        let srt_path = CString::new(srt_path.to_str().unwrap()).unwrap();
+        let out_path = tempdir.path().join(output_name);
+        let out_path = CString::new(out_path.to_str().unwrap()).unwrap();
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let mut output = AVFormatContextOutput::create(&out_path, None).unwrap();
+        for stream in input.streams() {
+            output.new_stream_copy(stream);
+        }
+        let nb_streams = input.streams().len();
+
+        let mut sidecar = SubtitleSidecar::open(&srt_path).unwrap();
+        let sub_index = sidecar.add_stream(&mut output).unwrap();
+        assert_eq!(sub_index, nb_streams);
+
+        output.write_header(&mut None).unwrap();
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            let index = packet.stream_index as usize;
+            let in_time_base = input.streams()[index].time_base;
+            sidecar
+                .write_until(&mut output, packet.dts, in_time_base)
+                .unwrap();
+            packet.rescale_ts(in_time_base, output.streams()[index].time_base);
+            packet.set_pos(-1);
+            output.interleaved_write_frame(&mut packet).unwrap();
+        }
+        sidecar.write_remaining(&mut output).unwrap();
+        output.write_trailer().unwrap();
+        drop(output);
+
+        let result = AVFormatContextInput::open(&out_path, None, &mut None).unwrap();
+        (tempdir, result, sub_index)
+    }
+
+    #[test]
+    fn test_sidecar_mp4() {
+        let (_tempdir, result, index) = remux_with_sidecar("out.mp4");
+        let codecpar = result.streams()[index].codecpar();
+        assert_eq!(codecpar.codec_id, ffi::AV_CODEC_ID_MOV_TEXT);
+    }
+
+    #[test]
+    fn test_sidecar_mkv() {
+        let (_tempdir, mut result, index) = remux_with_sidecar("out.mkv");
+        assert_eq!(
+            result.streams()[index].codecpar().codec_id,
+            ffi::AV_CODEC_ID_SUBRIP
+        );
+        let mut count = 0;
+        while let Some(packet) = result.read_packet().unwrap() {
+            if packet.stream_index as usize == index {
+                count += 1;
+            }
+        }
+        assert_eq!(count, 2);
+    }
+}
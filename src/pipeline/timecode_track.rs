@@ -0,0 +1,188 @@
+use crate::{
+    avformat::{AVFormatContextInput, AVStreamRef},
+    avutil::{av_rescale_q_rnd, AVRational, Timecode},
+    error::Result,
+    ffi,
+};
+
+/// Codec tag of the timecode tracks of MOV files.
+const TMCD: u32 = u32::from_le_bytes(*b"tmcd");
+
+/// Start timecode of an input, found by [`read_timecode()`].
+#[derive(Debug, Clone, Copy)]
+pub struct TimecodeTrack {
+    /// Index of the stream the timecode was read from, the `tmcd` track of a
+    /// MOV file, `None` if it comes from the metadata of the container, e.g.
+    /// for MXF.
+    pub stream_index: Option<usize>,
+    /// Timecode of the first video frame.
+    pub timecode: Timecode,
+}
+
+impl TimecodeTrack {
+    /// Frame number, relative to the first frame, of the video frame at `pts`
+    /// of a stream whose first frame is at `start_time`, both in `time_base`.
+    pub fn frame_at(&self, pts: i64, start_time: i64, time_base: AVRational) -> i64 {
+        let rate = self.timecode.rate();
+        av_rescale_q_rnd(
+            pts - start_time,
+            time_base,
+            AVRational {
+                num: rate.den,
+                den: rate.num,
+            },
+            ffi::AV_ROUND_NEAR_INF | ffi::AV_ROUND_PASS_MINMAX,
+        )
+    }
+
+    /// Timecode of the video frame at `pts`, see [`Self::frame_at()`].
+    pub fn timecode_at(&self, pts: i64, start_time: i64, time_base: AVRational) -> String {
+        let frame = self.frame_at(pts, start_time, time_base);
+        self.timecode
+            .to_string_at(frame.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+}
+
+/// Read the start timecode of `input`, `None` if it has none.
+///
+/// The timecode is looked up, in order, in the `tmcd` tracks of MOV files,
+/// then in the `timecode` metadata of the streams, then in the one of the
+/// container, e.g. for MXF or MPEG-TS. Its frame rate is the one of the
+/// `tmcd` track if any, or else the one of the first video stream.
+///
+/// ```ignore
+/// let input = AVFormatContextInput::open(cstr!("camera.mov"), None, &mut None)?;
+/// if let Some(track) = read_timecode(&input)? {
+///     let stream = &input.streams()[0];
+///     println!("{}", track.timecode_at(pts, stream.start_time, stream.time_base));
+/// }
+/// ```
+pub fn read_timecode(input: &AVFormatContextInput) -> Result<Option<TimecodeTrack>> {
+    let streams = input.streams();
+    let video_rate = streams
+        .iter()
+        .find(|x| x.codecpar().codec_type == ffi::AVMEDIA_TYPE_VIDEO)
+        .and_then(frame_rate);
+
+    let tmcd = streams
+        .iter()
+        .position(|x| x.codecpar().codec_tag == TMCD && timecode_tag(x).is_some());
+    let tagged = tmcd.or_else(|| streams.iter().position(|x| timecode_tag(x).is_some()));
+    let (stream_index, value, rate) = match tagged {
+        Some(index) => {
+            let stream = &streams[index];
+            let rate = match tmcd {
+                Some(_) => frame_rate(stream).or(video_rate),
+                None => video_rate.or_else(|| frame_rate(stream)),
+            };
+            (Some(index), timecode_tag(stream).unwrap(), rate)
+        }
+        None => {
+            let Some(value) = input
+                .metadata()
+                .and_then(|x| x.get_str("timecode").map(str::to_string))
+            else {
+                return Ok(None);
+            };
+            (None, value, video_rate)
+        }
+    };
+    let Some(rate) = rate else {
+        return Ok(None);
+    };
+    Ok(Some(TimecodeTrack {
+        stream_index,
+        timecode: Timecode::from_string(rate, &value)?,
+    }))
+}
+
+fn timecode_tag(stream: &AVStreamRef) -> Option<String> {
+    Some(stream.metadata()?.get_str("timecode")?.to_string())
+}
+
+fn frame_rate(stream: &AVStreamRef) -> Option<AVRational> {
+    [stream.avg_frame_rate, stream.r_frame_rate]
+        .into_iter()
+        .find(|x| x.num > 0 && x.den > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avformat::AVFormatContextOutput;
+    use crate::avutil::AVDictionary;
+    use cstr::cstr;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_read_timecode() {
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        assert!(read_timecode(&input).unwrap().is_none());
+
+        // The MOV muxer writes a tmcd track for the timecode of the video.
+        let tempdir = tempdir::TempDir::new("timecode").unwrap();
+        let path = CString::new(tempdir.path().join("out.mov").to_str().unwrap()).unwrap();
+        let video = input
+            .streams()
+            .iter()
+            .position(|x| x.codecpar().codec_type == ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap();
+        let time_base = input.streams()[video].time_base;
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        {
+            let mut stream = output.new_stream_copy(&input.streams()[video]);
+            stream.set_metadata(Some(AVDictionary::new(
+                cstr!("timecode"),
+                cstr!("01:00:00:00"),
+                0,
+            )));
+        }
+        output.write_header(&mut None).unwrap();
+        while let Some(mut packet) = input.read_packet().unwrap() {
+            if packet.stream_index as usize != video {
+                continue;
+            }
+            packet.set_stream_index(0);
+            packet.rescale_ts(time_base, output.streams()[0].time_base);
+            output.interleaved_write_frame(&mut packet).unwrap();
+        }
+        output.write_trailer().unwrap();
+        drop(output);
+
+        let input = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        let track = read_timecode(&input).unwrap().unwrap();
+        let index = track.stream_index.unwrap();
+        assert_eq!(input.streams()[index].codecpar().codec_tag, TMCD);
+        let rate = track.timecode.rate();
+        let avg_frame_rate = input.streams()[0].avg_frame_rate;
+        assert_eq!(
+            (rate.num, rate.den),
+            (avg_frame_rate.num, avg_frame_rate.den)
+        );
+        let fps = (rate.num + rate.den / 2) / rate.den;
+        assert_eq!(track.timecode.start_frame(), fps * 3600);
+        assert_eq!(track.timecode.to_string(), "01:00:00:00");
+
+        let stream = &input.streams()[0];
+        let frame = av_rescale_q_rnd(
+            1,
+            AVRational {
+                num: rate.den,
+                den: rate.num,
+            },
+            stream.time_base,
+            ffi::AV_ROUND_NEAR_INF,
+        );
+        let pts = stream.start_time.max(0) + 2 * frame;
+        assert_eq!(
+            track.frame_at(pts, stream.start_time.max(0), stream.time_base),
+            2
+        );
+        assert_eq!(
+            track.timecode_at(pts, stream.start_time.max(0), stream.time_base),
+            "01:00:00:02"
+        );
+    }
+}
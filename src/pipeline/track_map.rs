@@ -0,0 +1,630 @@
+use std::{
+    ffi::{CStr, CString},
+    time::Duration,
+};
+
+use crate::{
+    avcodec::{AVCodec, AVCodecContext, AVPacket, CodecFlags},
+    avfilter::{AVFilter, AVFilterGraph, AVFilterInOut},
+    avformat::{AVFormatContextInput, AVFormatContextOutput, AVStream, IOFormatFlags},
+    avutil::{
+        av_inv_q, av_rescale_q, AVChannelLayout, AVDictionary, AVFrame, AVRational, PixelFormat,
+        SampleFormat,
+    },
+    error::{Result, RsmpegError},
+    ffi,
+    pipeline::{
+        finalize_all, output_bytes, pump, pump_flush, FinalizeStream, Progress, ProgressTracker,
+        TrackTags,
+    },
+};
+
+/// Settings of a track encoded by [`transcode_tracks()`], the unset ones
+/// are taken from the input track.
+pub struct TrackEncoding {
+    /// Codec of the track, encoded with the default encoder of the codec.
+    pub codec_id: ffi::AVCodecID,
+    /// Bitrate in bit/s, the default of the encoder if `None`.
+    pub bit_rate: Option<i64>,
+    /// Sample rate of an audio track.
+    pub sample_rate: Option<i32>,
+    /// Channel layout of an audio track, e.g. to downmix it to stereo.
+    pub ch_layout: Option<AVChannelLayout>,
+    /// Options of the encoder.
+    pub options: Option<AVDictionary>,
+}
+
+impl TrackEncoding {
+    /// Encode with the encoder of `codec_id` and its defaults.
+    pub fn new(codec_id: ffi::AVCodecID) -> Self {
+        Self {
+            codec_id,
+            bit_rate: None,
+            sample_rate: None,
+            ch_layout: None,
+            options: None,
+        }
+    }
+
+    /// Set the bitrate in bit/s.
+    pub fn with_bit_rate(mut self, bit_rate: i64) -> Self {
+        self.bit_rate = Some(bit_rate);
+        self
+    }
+
+    /// Set the sample rate of an audio track.
+    pub fn with_sample_rate(mut self, sample_rate: i32) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    /// Set the channel layout of an audio track.
+    pub fn with_ch_layout(mut self, ch_layout: AVChannelLayout) -> Self {
+        self.ch_layout = Some(ch_layout);
+        self
+    }
+
+    /// Set the options of the encoder.
+    pub fn with_options(mut self, options: AVDictionary) -> Self {
+        self.options = Some(options);
+        self
+    }
+}
+
+/// A track of the output of [`transcode_tracks()`].
+pub struct OutputTrack {
+    /// Index of the input stream the track is made from.
+    pub input_index: usize,
+    /// How the track is encoded, `None` to copy the packets.
+    pub encoding: Option<TrackEncoding>,
+    /// Tags of the track, the ones of the input stream if `None`.
+    pub tags: Option<TrackTags>,
+}
+
+/// The tracks of the output of [`transcode_tracks()`] and the input streams
+/// they are made from, in the order of the output streams.
+///
+/// Any number of tracks can be made from the same input stream, e.g. to
+/// keep the original audio next to a compatible encoding of it, and every
+/// track has its own encoding and tags. Input streams without track are
+/// dropped.
+///
+/// ```ignore
+/// // All the audio tracks of a movie, e.g. the languages and the
+/// // commentary, with the video copied.
+/// let map = TrackMap::all(&input)
+///     .remove(commentary_index)
+///     .encode(english_index, TrackEncoding::new(ffi::AV_CODEC_ID_AAC).with_bit_rate(128_000))
+///     .with_tags(TrackTags {
+///         language: Some("en".into()),
+///         title: Some("Stereo".into()),
+///         ..Default::default()
+///     });
+/// ```
+#[derive(Default)]
+pub struct TrackMap {
+    tracks: Vec<OutputTrack>,
+}
+
+impl TrackMap {
+    /// A map without tracks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy all the video, audio and subtitle streams of `input`, the other
+    /// streams, e.g. data streams, are dropped.
+    pub fn all(input: &AVFormatContextInput) -> Self {
+        let mut map = Self::new();
+        for (index, stream) in input.streams().iter().enumerate() {
+            let codec_type = stream.codecpar().codec_type();
+            if codec_type.is_video() || codec_type.is_audio() || codec_type.is_subtitle() {
+                map = map.copy(index);
+            }
+        }
+        map
+    }
+
+    /// Add a track copying the packets of the input stream `input_index`.
+    pub fn copy(self, input_index: usize) -> Self {
+        self.push(input_index, None)
+    }
+
+    /// Add a track encoding the input stream `input_index`.
+    pub fn encode(self, input_index: usize, encoding: TrackEncoding) -> Self {
+        self.push(input_index, Some(encoding))
+    }
+
+    /// Set the tags of the last added track.
+    pub fn with_tags(mut self, tags: TrackTags) -> Self {
+        if let Some(track) = self.tracks.last_mut() {
+            track.tags = Some(tags);
+        }
+        self
+    }
+
+    /// Remove the tracks made from the input stream `input_index`.
+    pub fn remove(mut self, input_index: usize) -> Self {
+        self.tracks.retain(|x| x.input_index != input_index);
+        self
+    }
+
+    /// The output tracks.
+    pub fn tracks(&self) -> &[OutputTrack] {
+        &self.tracks
+    }
+
+    /// The output tracks, e.g. to reorder them.
+    pub fn tracks_mut(&mut self) -> &mut Vec<OutputTrack> {
+        &mut self.tracks
+    }
+
+    fn push(mut self, input_index: usize, encoding: Option<TrackEncoding>) -> Self {
+        self.tracks.push(OutputTrack {
+            input_index,
+            encoding,
+            tags: None,
+        });
+        self
+    }
+}
+
+/// An output track being encoded.
+struct EncodedTrack {
+    encoder: AVCodecContext,
+    graph: AVFilterGraph,
+    stream_index: usize,
+}
+
+/// Where the packets of an input stream go.
+#[derive(Default)]
+struct Routes {
+    /// Output streams the packets are copied to.
+    copies: Vec<usize>,
+    /// Indexes of the encoded tracks the decoded frames are sent to.
+    encodes: Vec<usize>,
+    decoder: Option<AVCodecContext>,
+}
+
+/// Transcode `input` into `output` following `map`: create the output
+/// streams, write the header with `options`, copy or decode, convert and
+/// encode the packets of every track, then write the trailer.
+///
+/// The frames of an encoded track are converted to the sample format, rate,
+/// channel layout or pixel format of its encoder and, for audio encoders
+/// with a fixed frame size like AAC, regrouped into frames of that size.
+/// Video tracks keep their size and frame rate.
+///
+/// Returns `AVERROR_STREAM_NOT_FOUND` if a track refers to a missing input
+/// stream, `AVERROR(EINVAL)` if a track encodes a stream which is neither
+/// audio nor video.
+pub fn transcode_tracks(
+    input: &mut AVFormatContextInput,
+    output: &mut AVFormatContextOutput,
+    map: TrackMap,
+    options: &mut Option<AVDictionary>,
+) -> Result<()> {
+    transcode_tracks_with_progress(input, output, map, options, |_| {})
+}
+
+/// Same as [`transcode_tracks()`], calling `on_progress` with the progress of
+/// the transcode every 500 milliseconds, and once more when it's done.
+///
+/// The progress is computed from the timestamps of the input packets, the
+/// video frames are the video packets read.
+///
+/// ```ignore
+/// transcode_tracks_with_progress(&mut input, &mut output, map, &mut None, |progress| {
+///     println!("{:.0}% at {:.1}x", progress.ratio().unwrap_or(0.0) * 100.0, progress.speed);
+/// })?;
+/// ```
+pub fn transcode_tracks_with_progress(
+    input: &mut AVFormatContextInput,
+    output: &mut AVFormatContextOutput,
+    map: TrackMap,
+    options: &mut Option<AVDictionary>,
+    mut on_progress: impl FnMut(&Progress),
+) -> Result<()> {
+    let total = match input.duration {
+        ffi::AV_NOPTS_VALUE => None,
+        x => Some(Duration::from_micros(x.max(0) as u64)),
+    };
+    let mut tracker = ProgressTracker::new(total);
+    let global_header = output
+        .oformat()
+        .format_flags()
+        .contains(IOFormatFlags::GLOBALHEADER);
+    let mut routes: Vec<Routes> = input.streams().iter().map(|_| Routes::default()).collect();
+    let mut encoded = vec![];
+    for (stream_index, track) in map.tracks.into_iter().enumerate() {
+        let stream = input
+            .streams()
+            .get(track.input_index)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR_STREAM_NOT_FOUND))?;
+        let route = &mut routes[track.input_index];
+        let tags = track.tags.unwrap_or_else(|| stream.track_tags());
+        let Some(encoding) = track.encoding else {
+            output.new_stream_copy(stream).set_track_tags(&tags)?;
+            route.copies.push(stream_index);
+            continue;
+        };
+
+        if route.decoder.is_none() {
+            let codec = AVCodec::find_decoder(stream.codecpar().codec_id)
+                .ok_or(RsmpegError::AVError(ffi::AVERROR_DECODER_NOT_FOUND))?;
+            let mut decoder = AVCodecContext::from_stream(stream, &codec)?;
+            decoder.open(None)?;
+            route.decoder = Some(decoder);
+        }
+        let decoder = route.decoder.as_ref().unwrap();
+        let (encoder, graph) = open_encoder(stream, decoder, encoding, global_header)?;
+        let mut out_stream = output.new_stream();
+        out_stream.set_codecpar(encoder.extract_codecpar());
+        out_stream.set_time_base(encoder.time_base);
+        out_stream.set_track_tags(&tags)?;
+        route.encodes.push(encoded.len());
+        encoded.push(EncodedTrack {
+            encoder,
+            graph,
+            stream_index,
+        });
+    }
+    output.write_header(options)?;
+
+    while let Some(packet) = input.read_packet()? {
+        let input_index = packet.stream_index as usize;
+        let Some(route) = routes.get_mut(input_index) else {
+            continue;
+        };
+        let stream = &input.streams()[input_index];
+        let time_base = stream.time_base;
+        let video = stream.codecpar().codec_type == ffi::AVMEDIA_TYPE_VIDEO;
+        tracker.push_packet(&packet, time_base, video);
+        for &stream_index in &route.copies {
+            let mut packet = packet.clone();
+            packet.rescale_ts(time_base, output.streams()[stream_index].time_base);
+            packet.set_stream_index(stream_index as i32);
+            packet.set_pos(-1);
+            output.interleaved_write_frame(&mut packet)?;
+        }
+        if let Some(decoder) = route.decoder.as_mut() {
+            pump(decoder, Some(packet), |frame| {
+                encode_frame(frame, &route.encodes, &mut encoded, output)
+            })?;
+        }
+        tracker.set_bytes(output_bytes(output));
+        if let Some(progress) = tracker.poll() {
+            on_progress(&progress);
+        }
+    }
+    for route in &mut routes {
+        if let Some(decoder) = route.decoder.as_mut() {
+            pump_flush::<_, AVPacket>(decoder, |frame| {
+                encode_frame(frame, &route.encodes, &mut encoded, output)
+            })?;
+        }
+    }
+
+    let in_name = CStr::from_bytes_with_nul(b"in\0").unwrap();
+    let out_name = CStr::from_bytes_with_nul(b"out\0").unwrap();
+    let streams = encoded
+        .iter_mut()
+        .map(|x| {
+            FinalizeStream::new(&mut x.encoder, x.stream_index).with_filter(
+                &mut x.graph,
+                in_name,
+                out_name,
+            )
+        })
+        .collect();
+    finalize_all(output, streams)?;
+    tracker.set_bytes(output_bytes(output));
+    on_progress(&tracker.progress());
+    Ok(())
+}
+
+/// Create and open the encoder of a track made from `stream`, along with
+/// the filter graph converting the frames of `decoder` for it.
+fn open_encoder(
+    stream: &AVStream,
+    decoder: &AVCodecContext,
+    encoding: TrackEncoding,
+    global_header: bool,
+) -> Result<(AVCodecContext, AVFilterGraph)> {
+    let codec = AVCodec::find_encoder(encoding.codec_id)
+        .ok_or(RsmpegError::AVError(ffi::AVERROR_ENCODER_NOT_FOUND))?;
+    let mut encoder = AVCodecContext::new(&codec);
+    if let Some(bit_rate) = encoding.bit_rate {
+        encoder.set_bit_rate(bit_rate);
+    }
+    if global_header {
+        encoder.set_codec_flags(encoder.codec_flags() | CodecFlags::GLOBAL_HEADER);
+    }
+
+    let (src_args, filter_spec) = match decoder.codec_type {
+        ffi::AVMEDIA_TYPE_AUDIO => {
+            let sample_rate = encoding.sample_rate.unwrap_or(decoder.sample_rate);
+            let ch_layout = match encoding.ch_layout {
+                Some(x) => x,
+                None => ch_layout(decoder),
+            };
+            let sample_fmt = match codec.sample_fmts() {
+                Some(x) if !x.contains(&decoder.sample_fmt) => x[0],
+                _ => decoder.sample_fmt,
+            };
+            encoder.set_sample_rate(sample_rate);
+            encoder.set_sample_fmt(sample_fmt);
+            encoder.set_ch_layout(ch_layout.clone().into_inner());
+            encoder.set_time_base(AVRational {
+                num: 1,
+                den: sample_rate,
+            });
+            encoder.open(encoding.options)?;
+
+            let src_args = format!(
+                "time_base={}/{}:sample_rate={}:sample_fmt={}:channel_layout={}",
+                decoder.pkt_timebase.num,
+                decoder.pkt_timebase.den,
+                decoder.sample_rate,
+                name(SampleFormat(decoder.sample_fmt).name()),
+                ch_layout_name(&self::ch_layout(decoder))?,
+            );
+            let mut filter_spec = format!(
+                "aformat=sample_fmts={}:sample_rates={}:channel_layouts={}",
+                name(SampleFormat(sample_fmt).name()),
+                sample_rate,
+                ch_layout_name(&ch_layout)?,
+            );
+            if encoder.frame_size > 0 {
+                filter_spec += &format!(",asetnsamples=n={}:p=0", encoder.frame_size);
+            }
+            (src_args, filter_spec)
+        }
+        ffi::AVMEDIA_TYPE_VIDEO => {
+            let pix_fmt = match codec.pix_fmts() {
+                Some(x) if !x.contains(&decoder.pix_fmt) => x[0],
+                _ => decoder.pix_fmt,
+            };
+            let frame_rate = match stream.avg_frame_rate {
+                AVRational { num: 0, .. } | AVRational { den: 0, .. } => av_inv_q(stream.time_base),
+                x => x,
+            };
+            encoder.set_width(decoder.width);
+            encoder.set_height(decoder.height);
+            encoder.set_sample_aspect_ratio(decoder.sample_aspect_ratio);
+            encoder.set_pix_fmt(pix_fmt);
+            encoder.set_framerate(frame_rate);
+            encoder.set_time_base(av_inv_q(frame_rate));
+            encoder.open(encoding.options)?;
+
+            let sample_aspect_ratio = match decoder.sample_aspect_ratio {
+                AVRational { den: 0, .. } => AVRational { num: 0, den: 1 },
+                x => x,
+            };
+            let src_args = format!(
+                "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+                decoder.width,
+                decoder.height,
+                decoder.pix_fmt,
+                decoder.pkt_timebase.num,
+                decoder.pkt_timebase.den,
+                sample_aspect_ratio.num,
+                sample_aspect_ratio.den,
+            );
+            let filter_spec = format!("format=pix_fmts={}", name(PixelFormat(pix_fmt).name()));
+            (src_args, filter_spec)
+        }
+        _ => return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL))),
+    };
+
+    let (buffer, buffersink) = if decoder.codec_type == ffi::AVMEDIA_TYPE_AUDIO {
+        (&b"abuffer\0"[..], &b"abuffersink\0"[..])
+    } else {
+        (&b"buffer\0"[..], &b"buffersink\0"[..])
+    };
+    let src_args = CString::new(src_args).unwrap();
+    let filter_spec = CString::new(filter_spec).unwrap();
+    let graph = AVFilterGraph::new();
+    {
+        let buffer = AVFilter::get_by_name(CStr::from_bytes_with_nul(buffer).unwrap()).unwrap();
+        let buffersink =
+            AVFilter::get_by_name(CStr::from_bytes_with_nul(buffersink).unwrap()).unwrap();
+        let in_name = CStr::from_bytes_with_nul(b"in\0").unwrap();
+        let out_name = CStr::from_bytes_with_nul(b"out\0").unwrap();
+        let mut src = graph.create_filter_context(&buffer, in_name, Some(&src_args))?;
+        let mut sink = graph.create_filter_context(&buffersink, out_name, None)?;
+
+        let outputs = AVFilterInOut::new(in_name, &mut src, 0);
+        let inputs = AVFilterInOut::new(out_name, &mut sink, 0);
+        graph.parse_ptr(&filter_spec, Some(inputs), Some(outputs))?;
+    }
+    graph.config()?;
+    Ok((encoder, graph))
+}
+
+/// Send a decoded `frame` to the encoded tracks `indexes`, writing the
+/// packets they output.
+fn encode_frame(
+    frame: AVFrame,
+    indexes: &[usize],
+    encoded: &mut [EncodedTrack],
+    output: &mut AVFormatContextOutput,
+) -> Result<()> {
+    let in_name = CStr::from_bytes_with_nul(b"in\0").unwrap();
+    let out_name = CStr::from_bytes_with_nul(b"out\0").unwrap();
+    for &index in indexes {
+        let track = &mut encoded[index];
+        track
+            .graph
+            .get_filter(in_name)
+            .unwrap()
+            .buffersrc_add_frame(Some(frame.clone()), None)?;
+        let mut sink = track.graph.get_filter(out_name).unwrap();
+        loop {
+            let mut frame = match sink.buffersink_get_frame(None) {
+                Ok(frame) => frame,
+                Err(RsmpegError::BufferSinkDrainError | RsmpegError::BufferSinkEofError) => break,
+                Err(e) => return Err(e),
+            };
+            let encoder = &mut track.encoder;
+            if frame.pts != ffi::AV_NOPTS_VALUE {
+                frame.set_pts(av_rescale_q(
+                    frame.pts,
+                    sink.get_time_base(),
+                    encoder.time_base,
+                ));
+            }
+            frame.set_time_base(encoder.time_base);
+            frame.set_pict_type(ffi::AV_PICTURE_TYPE_NONE);
+            let time_base = encoder.time_base;
+            let stream_index = track.stream_index;
+            pump(encoder, Some(frame), |mut packet| {
+                packet.rescale_ts(time_base, output.streams()[stream_index].time_base);
+                packet.set_stream_index(stream_index as i32);
+                output.interleaved_write_frame(&mut packet)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Channel layout of the frames of `decoder`, with a default order if it
+/// only knows the number of channels.
+fn ch_layout(decoder: &AVCodecContext) -> AVChannelLayout {
+    if decoder.ch_layout.order == ffi::AV_CHANNEL_ORDER_UNSPEC {
+        AVChannelLayout::from_nb_channels(decoder.ch_layout.nb_channels)
+    } else {
+        decoder.ch_layout().clone()
+    }
+}
+
+fn ch_layout_name(ch_layout: &AVChannelLayout) -> Result<String> {
+    Ok(ch_layout.describe()?.to_string_lossy().into_owned())
+}
+
+fn name(name: Option<&CStr>) -> String {
+    name.map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+
+    #[test]
+    fn test_transcode_tracks() {
+        let tempdir = tempdir::TempDir::new("track_map").unwrap();
+        let path = tempdir.path().join("output.mkv");
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let (video_index, _) = input.video_streams().next().unwrap();
+        let (audio_index, _) = input.audio_streams().next().unwrap();
+        let map = TrackMap::all(&input)
+            // Replaced by two tracks.
+            .remove(audio_index)
+            .encode(
+                audio_index,
+                TrackEncoding::new(ffi::AV_CODEC_ID_AAC)
+                    .with_bit_rate(64_000)
+                    .with_ch_layout(AVChannelLayout::from_nb_channels(1)),
+            )
+            .with_tags(TrackTags {
+                language: Some("en".into()),
+                title: Some("Mono".into()),
+                default: true,
+                forced: false,
+            })
+            .copy(audio_index)
+            .with_tags(TrackTags {
+                language: Some("fr".into()),
+                ..Default::default()
+            })
+            .encode(video_index, TrackEncoding::new(ffi::AV_CODEC_ID_MPEG4));
+        assert_eq!(map.tracks().len(), 4);
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        transcode_tracks(&mut input, &mut output, map, &mut None).unwrap();
+        drop(output);
+
+        let mut result = AVFormatContextInput::open(&path, None, &mut None).unwrap();
+        let streams = result.streams();
+        assert_eq!(streams.len(), 4);
+        // The video copied, both audio tracks and the encoded video.
+        assert_eq!(streams[0].codecpar().codec_id, ffi::AV_CODEC_ID_H264);
+        let mono = &streams[1];
+        assert_eq!(mono.codecpar().codec_id, ffi::AV_CODEC_ID_AAC);
+        assert_eq!(mono.codecpar().ch_layout.nb_channels, 1);
+        assert_eq!(mono.track_tags().language.as_deref(), Some("eng"));
+        assert_eq!(mono.track_tags().title.as_deref(), Some("Mono"));
+        let copy = &streams[2];
+        assert_eq!(
+            copy.codecpar().ch_layout.nb_channels,
+            input.streams()[audio_index]
+                .codecpar()
+                .ch_layout
+                .nb_channels
+        );
+        assert_eq!(copy.track_tags().language.as_deref(), Some("fre"));
+        assert_eq!(streams[3].codecpar().codec_id, ffi::AV_CODEC_ID_MPEG4);
+
+        let mut counts = [0; 4];
+        while let Some(packet) = result.read_packet().unwrap() {
+            counts[packet.stream_index as usize] += 1;
+        }
+        assert!(counts.iter().all(|&x| x > 0));
+        assert_eq!(counts[0], counts[3]);
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        let missing = TrackMap::new().copy(input.streams().len());
+        assert_eq!(
+            transcode_tracks(&mut input, &mut output, missing, &mut None).err(),
+            Some(RsmpegError::AVError(ffi::AVERROR_STREAM_NOT_FOUND))
+        );
+    }
+
+    #[test]
+    fn test_transcode_tracks_with_progress() {
+        let tempdir = tempdir::TempDir::new("track_map").unwrap();
+        let path = tempdir.path().join("output.mkv");
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let mut input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let video_frames = input
+            .streams()
+            .iter()
+            .find(|x| x.codecpar().codec_type == ffi::AVMEDIA_TYPE_VIDEO)
+            .unwrap()
+            .nb_frames as u64;
+        let map = TrackMap::all(&input);
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        let mut reports = vec![];
+        transcode_tracks_with_progress(&mut input, &mut output, map, &mut None, |x| {
+            reports.push(*x)
+        })
+        .unwrap();
+        drop(output);
+
+        let last = reports.last().unwrap();
+        assert_eq!(last.frames, video_frames);
+        assert_eq!(
+            last.bytes,
+            std::fs::metadata(tempdir.path().join("output.mkv"))
+                .unwrap()
+                .len()
+        );
+        let ratio = last.ratio().unwrap();
+        assert!(ratio > 0.9, "{ratio}");
+        assert!(last.bit_rate > 0.0);
+        assert!(reports
+            .windows(2)
+            .all(|x| x[0].processed <= x[1].processed && x[0].bytes <= x[1].bytes));
+    }
+}
@@ -0,0 +1,389 @@
+use std::ffi::{CStr, CString};
+
+use crate::{
+    avformat::AVStream,
+    avutil::AVDictionary,
+    error::{Result, RsmpegError},
+    ffi,
+};
+
+/// ISO 639-1 codes with their ISO 639-2/B and ISO 639-2/T codes.
+const ISO639: [(&str, &str, &str); 184] = [
+    ("aa", "aar", "aar"),
+    ("ab", "abk", "abk"),
+    ("ae", "ave", "ave"),
+    ("af", "afr", "afr"),
+    ("ak", "aka", "aka"),
+    ("am", "amh", "amh"),
+    ("an", "arg", "arg"),
+    ("ar", "ara", "ara"),
+    ("as", "asm", "asm"),
+    ("av", "ava", "ava"),
+    ("ay", "aym", "aym"),
+    ("az", "aze", "aze"),
+    ("ba", "bak", "bak"),
+    ("be", "bel", "bel"),
+    ("bg", "bul", "bul"),
+    ("bh", "bih", "bih"),
+    ("bi", "bis", "bis"),
+    ("bm", "bam", "bam"),
+    ("bn", "ben", "ben"),
+    ("bo", "tib", "bod"),
+    ("br", "bre", "bre"),
+    ("bs", "bos", "bos"),
+    ("ca", "cat", "cat"),
+    ("ce", "che", "che"),
+    ("ch", "cha", "cha"),
+    ("co", "cos", "cos"),
+    ("cr", "cre", "cre"),
+    ("cs", "cze", "ces"),
+    ("cu", "chu", "chu"),
+    ("cv", "chv", "chv"),
+    ("cy", "wel", "cym"),
+    ("da", "dan", "dan"),
+    ("de", "ger", "deu"),
+    ("dv", "div", "div"),
+    ("dz", "dzo", "dzo"),
+    ("ee", "ewe", "ewe"),
+    ("el", "gre", "ell"),
+    ("en", "eng", "eng"),
+    ("eo", "epo", "epo"),
+    ("es", "spa", "spa"),
+    ("et", "est", "est"),
+    ("eu", "baq", "eus"),
+    ("fa", "per", "fas"),
+    ("ff", "ful", "ful"),
+    ("fi", "fin", "fin"),
+    ("fj", "fij", "fij"),
+    ("fo", "fao", "fao"),
+    ("fr", "fre", "fra"),
+    ("fy", "fry", "fry"),
+    ("ga", "gle", "gle"),
+    ("gd", "gla", "gla"),
+    ("gl", "glg", "glg"),
+    ("gn", "grn", "grn"),
+    ("gu", "guj", "guj"),
+    ("gv", "glv", "glv"),
+    ("ha", "hau", "hau"),
+    ("he", "heb", "heb"),
+    ("hi", "hin", "hin"),
+    ("ho", "hmo", "hmo"),
+    ("hr", "hrv", "hrv"),
+    ("ht", "hat", "hat"),
+    ("hu", "hun", "hun"),
+    ("hy", "arm", "hye"),
+    ("hz", "her", "her"),
+    ("ia", "ina", "ina"),
+    ("id", "ind", "ind"),
+    ("ie", "ile", "ile"),
+    ("ig", "ibo", "ibo"),
+    ("ii", "iii", "iii"),
+    ("ik", "ipk", "ipk"),
+    ("io", "ido", "ido"),
+    ("is", "ice", "isl"),
+    ("it", "ita", "ita"),
+    ("iu", "iku", "iku"),
+    ("ja", "jpn", "jpn"),
+    ("jv", "jav", "jav"),
+    ("ka", "geo", "kat"),
+    ("kg", "kon", "kon"),
+    ("ki", "kik", "kik"),
+    ("kj", "kua", "kua"),
+    ("kk", "kaz", "kaz"),
+    ("kl", "kal", "kal"),
+    ("km", "khm", "khm"),
+    ("kn", "kan", "kan"),
+    ("ko", "kor", "kor"),
+    ("kr", "kau", "kau"),
+    ("ks", "kas", "kas"),
+    ("ku", "kur", "kur"),
+    ("kv", "kom", "kom"),
+    ("kw", "cor", "cor"),
+    ("ky", "kir", "kir"),
+    ("la", "lat", "lat"),
+    ("lb", "ltz", "ltz"),
+    ("lg", "lug", "lug"),
+    ("li", "lim", "lim"),
+    ("ln", "lin", "lin"),
+    ("lo", "lao", "lao"),
+    ("lt", "lit", "lit"),
+    ("lu", "lub", "lub"),
+    ("lv", "lav", "lav"),
+    ("mg", "mlg", "mlg"),
+    ("mh", "mah", "mah"),
+    ("mi", "mao", "mri"),
+    ("mk", "mac", "mkd"),
+    ("ml", "mal", "mal"),
+    ("mn", "mon", "mon"),
+    ("mr", "mar", "mar"),
+    ("ms", "may", "msa"),
+    ("mt", "mlt", "mlt"),
+    ("my", "bur", "mya"),
+    ("na", "nau", "nau"),
+    ("nb", "nob", "nob"),
+    ("nd", "nde", "nde"),
+    ("ne", "nep", "nep"),
+    ("ng", "ndo", "ndo"),
+    ("nl", "dut", "nld"),
+    ("nn", "nno", "nno"),
+    ("no", "nor", "nor"),
+    ("nr", "nbl", "nbl"),
+    ("nv", "nav", "nav"),
+    ("ny", "nya", "nya"),
+    ("oc", "oci", "oci"),
+    ("oj", "oji", "oji"),
+    ("om", "orm", "orm"),
+    ("or", "ori", "ori"),
+    ("os", "oss", "oss"),
+    ("pa", "pan", "pan"),
+    ("pi", "pli", "pli"),
+    ("pl", "pol", "pol"),
+    ("ps", "pus", "pus"),
+    ("pt", "por", "por"),
+    ("qu", "que", "que"),
+    ("rm", "roh", "roh"),
+    ("rn", "run", "run"),
+    ("ro", "rum", "ron"),
+    ("ru", "rus", "rus"),
+    ("rw", "kin", "kin"),
+    ("sa", "san", "san"),
+    ("sc", "srd", "srd"),
+    ("sd", "snd", "snd"),
+    ("se", "sme", "sme"),
+    ("sg", "sag", "sag"),
+    ("si", "sin", "sin"),
+    ("sk", "slo", "slk"),
+    ("sl", "slv", "slv"),
+    ("sm", "smo", "smo"),
+    ("sn", "sna", "sna"),
+    ("so", "som", "som"),
+    ("sq", "alb", "sqi"),
+    ("sr", "srp", "srp"),
+    ("ss", "ssw", "ssw"),
+    ("st", "sot", "sot"),
+    ("su", "sun", "sun"),
+    ("sv", "swe", "swe"),
+    ("sw", "swa", "swa"),
+    ("ta", "tam", "tam"),
+    ("te", "tel", "tel"),
+    ("tg", "tgk", "tgk"),
+    ("th", "tha", "tha"),
+    ("ti", "tir", "tir"),
+    ("tk", "tuk", "tuk"),
+    ("tl", "tgl", "tgl"),
+    ("tn", "tsn", "tsn"),
+    ("to", "ton", "ton"),
+    ("tr", "tur", "tur"),
+    ("ts", "tso", "tso"),
+    ("tt", "tat", "tat"),
+    ("tw", "twi", "twi"),
+    ("ty", "tah", "tah"),
+    ("ug", "uig", "uig"),
+    ("uk", "ukr", "ukr"),
+    ("ur", "urd", "urd"),
+    ("uz", "uzb", "uzb"),
+    ("ve", "ven", "ven"),
+    ("vi", "vie", "vie"),
+    ("vo", "vol", "vol"),
+    ("wa", "wln", "wln"),
+    ("wo", "wol", "wol"),
+    ("xh", "xho", "xho"),
+    ("yi", "yid", "yid"),
+    ("yo", "yor", "yor"),
+    ("za", "zha", "zha"),
+    ("zh", "chi", "zho"),
+    ("zu", "zul", "zul"),
+];
+
+/// Convert a language tag, either an ISO 639-1 / ISO 639-2 code or a BCP 47
+/// tag such as `pt-BR`, into the ISO 639-2/B code stored in the `language`
+/// metadata by FFmpeg (e.g. `ger` rather than `deu`).
+///
+/// Unknown three letter codes are returned as is, e.g. `und`.
+pub fn language_to_iso639_2(tag: &str) -> Option<String> {
+    // The primary language subtag.
+    let code = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+    if !code.bytes().all(|x| x.is_ascii_lowercase()) {
+        return None;
+    }
+    match code.len() {
+        2 => ISO639
+            .iter()
+            .find(|(x, _, _)| *x == code)
+            .map(|(_, x, _)| x.to_string()),
+        3 => Some(
+            ISO639
+                .iter()
+                .find(|(_, _, x)| *x == code)
+                .map_or(code, |(_, x, _)| x.to_string()),
+        ),
+        _ => None,
+    }
+}
+
+/// Convert an ISO 639-2 (either /B or /T) code into the ISO 639-1 code, e.g.
+/// for BCP 47 tags. Returns `None` if the language has no ISO 639-1 code.
+pub fn language_to_iso639_1(code: &str) -> Option<&'static str> {
+    let code = code.to_ascii_lowercase();
+    ISO639
+        .iter()
+        .find(|(_, b, t)| *b == code || *t == code)
+        .map(|(x, _, _)| *x)
+}
+
+/// Tags of an audio, video or subtitle track which players use to select it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackTags {
+    /// Language, either an ISO 639 code or a BCP 47 tag when setting it.
+    /// Always the ISO 639-2/B code when read from a stream.
+    pub language: Option<String>,
+    /// Title of the track, e.g. `Director's commentary`.
+    pub title: Option<String>,
+    /// Whether the track is selected by default.
+    pub default: bool,
+    /// Whether the track should be displayed even if the user didn't select
+    /// it, e.g. subtitles of foreign language dialogs.
+    pub forced: bool,
+}
+
+impl AVStream {
+    /// Read the language, title and default/forced dispositions of the
+    /// stream.
+    pub fn track_tags(&self) -> TrackTags {
+        let get = |key: &[u8]| {
+            let key = CStr::from_bytes_with_nul(key).unwrap();
+            let metadata = self.metadata()?;
+            let entry = metadata.get(key, None, 0)?;
+            Some(entry.value().to_string_lossy().into_owned())
+        };
+        TrackTags {
+            language: get(b"language\0").and_then(|x| language_to_iso639_2(&x)),
+            title: get(b"title\0"),
+            default: self.has_disposition(ffi::AV_DISPOSITION_DEFAULT),
+            forced: self.has_disposition(ffi::AV_DISPOSITION_FORCED),
+        }
+    }
+
+    /// Set the language, title and default/forced dispositions of an output
+    /// stream, should be called before writing the header. `None` fields
+    /// leave the existing metadata untouched.
+    ///
+    /// Returns `AVERROR(EINVAL)` if the language isn't a valid tag.
+    pub fn set_track_tags(&mut self, tags: &TrackTags) -> Result<()> {
+        let language = match &tags.language {
+            Some(x) => Some(
+                language_to_iso639_2(x).ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?,
+            ),
+            None => None,
+        };
+        let entries: [(&[u8], _); 2] = [
+            (b"language\0", language.as_ref()),
+            (b"title\0", tags.title.as_ref()),
+        ];
+        let mut metadata = self.metadata().map(|x| AVDictionary::clone(&x));
+        for (key, value) in entries {
+            let Some(value) = value else {
+                continue;
+            };
+            let key = CStr::from_bytes_with_nul(key).unwrap();
+            let value = CString::new(value.as_str())
+                .map_err(|_| RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+            metadata = Some(match metadata {
+                Some(x) => x.set(key, &value, 0),
+                None => AVDictionary::new(key, &value, 0),
+            });
+        }
+        if metadata.is_some() {
+            self.set_metadata(metadata);
+        }
+
+        let mut disposition =
+            self.disposition & !(ffi::AV_DISPOSITION_DEFAULT | ffi::AV_DISPOSITION_FORCED) as i32;
+        if tags.default {
+            disposition |= ffi::AV_DISPOSITION_DEFAULT as i32;
+        }
+        if tags.forced {
+            disposition |= ffi::AV_DISPOSITION_FORCED as i32;
+        }
+        self.set_disposition(disposition);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avformat::{AVFormatContextInput, AVFormatContextOutput};
+    use cstr::cstr;
+
+    #[test]
+    fn test_language_conversion() {
+        assert_eq!(language_to_iso639_2("en").as_deref(), Some("eng"));
+        assert_eq!(language_to_iso639_2("pt-BR").as_deref(), Some("por"));
+        assert_eq!(language_to_iso639_2("zh_Hant").as_deref(), Some("chi"));
+        assert_eq!(language_to_iso639_2("DEU").as_deref(), Some("ger"));
+        assert_eq!(language_to_iso639_2("und").as_deref(), Some("und"));
+        assert_eq!(language_to_iso639_2("xx"), None);
+        assert_eq!(language_to_iso639_2("english"), None);
+        assert_eq!(language_to_iso639_2(""), None);
+
+        assert_eq!(language_to_iso639_1("fre"), Some("fr"));
+        assert_eq!(language_to_iso639_1("fra"), Some("fr"));
+        assert_eq!(language_to_iso639_1("und"), None);
+        assert!(ISO639.windows(2).all(|x| x[0].0 < x[1].0));
+    }
+
+    #[test]
+    fn test_track_tags() {
+        let tempdir = tempdir::TempDir::new("track_tags").unwrap();
+        let path = tempdir.path().join("output.mkv");
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let input =
+            AVFormatContextInput::open(cstr!("tests/assets/vids/bear.mp4"), None, &mut None)
+                .unwrap();
+        let mut output = AVFormatContextOutput::create(&path, None).unwrap();
+        let tags = [
+            TrackTags {
+                language: Some("de-AT".into()),
+                title: Some("Deutsch".into()),
+                default: true,
+                forced: false,
+            },
+            TrackTags {
+                language: Some("fr".into()),
+                title: None,
+                default: false,
+                forced: true,
+            },
+        ];
+        for (stream, tags) in input.streams().iter().zip(&tags) {
+            let mut out_stream = output.new_stream();
+            out_stream.set_codecpar(stream.codecpar().clone());
+            out_stream.set_time_base(stream.time_base);
+            out_stream.set_track_tags(tags).unwrap();
+        }
+        assert!(output
+            .new_stream()
+            .set_track_tags(&TrackTags {
+                language: Some("klingon".into()),
+                ..Default::default()
+            })
+            .is_err());
+
+        let streams = output.streams();
+        assert_eq!(
+            streams[0].track_tags(),
+            TrackTags {
+                language: Some("ger".into()),
+                title: Some("Deutsch".into()),
+                default: true,
+                forced: false,
+            }
+        );
+        let second = streams[1].track_tags();
+        assert_eq!(second.language.as_deref(), Some("fre"));
+        assert!(!second.default);
+        assert!(second.forced);
+    }
+}
@@ -0,0 +1,26 @@
+//! Commonly used types and traits, glob import them to save the long import
+//! blocks:
+//!
+//! ```
+//! use rsmpeg::prelude::*;
+//! ```
+pub use crate::{
+    avcodec::{
+        AVCodec, AVCodecContext, AVCodecID, AVCodecParameters, AVPacket, CodecFlags, CodecId,
+        Level, PacketFlags, Profile,
+    },
+    avfilter::{AVFilter, AVFilterContext, AVFilterGraph, AVFilterInOut},
+    avformat::{
+        AVFormatContextInput, AVFormatContextOutput, AVIOContextContainer, AVStream, FormatFlags,
+        IOFormatFlags, SeekFlags,
+    },
+    avutil::{
+        ra, AVChannelLayout, AVDictionary, AVFrame, AVMediaType, AVPixelFormat, AVRational,
+        AVSampleFormat, PixelFormat, SampleFormat,
+    },
+    error::RsmpegError,
+    ffi,
+    swresample::SwrContext,
+    swscale::SwsContext,
+    UnsafeDerefMut,
+};
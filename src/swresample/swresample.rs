@@ -1,7 +1,20 @@
-use crate::{avutil::AVFrame, error::*, ffi, shared::*};
-use std::ptr::{self, NonNull};
+use crate::{
+    avutil::{get_packed_sample_fmt, get_planar_sample_fmt, AVChannelLayout, AVFrame},
+    error::*,
+    ffi,
+    shared::*,
+};
+use std::{
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
+};
 
-wrap!(SwrContext: ffi::SwrContext);
+wrap! {
+    SwrContext: ffi::SwrContext,
+    // Referenced by libswresample until the context is freed, see
+    // `set_channel_mapping()`.
+    channel_map: Vec<i32> = Vec::new(),
+}
 
 impl SwrContext {
     /// Check whether an swr context has been initialized or not.
@@ -176,6 +189,146 @@ impl SwrContext {
         .upgrade()?;
         Ok(())
     }
+
+    /// Convert the samples of `input` into a new frame allocated with the
+    /// output parameters of the context, i.e. its channel layout, sample
+    /// format and sample rate.
+    ///
+    /// The output frame holds all the samples available, its `nb_samples`
+    /// may differ from the input's when resampling, the remaining ones are
+    /// buffered until the next call or [`Self::flush()`]. The timestamps of
+    /// the output frame are left unset.
+    pub fn convert_to_new_frame(&self, input: &AVFrame) -> Result<AVFrame> {
+        let mut output = self.output_frame(self.get_out_samples(input.nb_samples))?;
+        self.convert_frame(Some(input), &mut output)?;
+        Ok(output)
+    }
+
+    /// Drain the samples buffered in the context and the resampling delay
+    /// into a final frame, `None` if there are none left.
+    pub fn flush(&self) -> Result<Option<AVFrame>> {
+        let nb_samples = self.get_out_samples(0);
+        if nb_samples <= 0 {
+            return Ok(None);
+        }
+        let mut output = self.output_frame(nb_samples)?;
+        self.convert_frame(None, &mut output)?;
+        Ok((output.nb_samples > 0).then_some(output))
+    }
+
+    /// Allocate a frame of `nb_samples` samples with the output parameters of
+    /// the context.
+    fn output_frame(&self, nb_samples: i32) -> Result<AVFrame> {
+        let ptr = self.as_ptr() as *mut _;
+        let mut sample_fmt = ffi::AV_SAMPLE_FMT_NONE;
+        let mut sample_rate = 0;
+        unsafe {
+            ffi::av_opt_get_sample_fmt(ptr, b"out_sample_fmt\0".as_ptr() as _, 0, &mut sample_fmt)
+                .upgrade()?;
+            ffi::av_opt_get_int(ptr, b"out_sample_rate\0".as_ptr() as _, 0, &mut sample_rate)
+                .upgrade()?;
+        }
+        let ch_layout = self.opt_ch_layout(b"out_chlayout\0")?;
+
+        let mut output = AVFrame::new();
+        output.set_ch_layout(ch_layout.into_inner());
+        output.set_format(sample_fmt);
+        output.set_sample_rate(sample_rate as i32);
+        output.set_nb_samples(nb_samples.max(1));
+        output.alloc_buffer()?;
+        Ok(output)
+    }
+
+    /// Set a custom remix matrix, one row of gains per output channel with
+    /// one column per input channel, e.g. to downmix 5.1 with custom center
+    /// and LFE gains. Must be called before [`Self::init()`].
+    ///
+    /// Returns `AVERROR(EINVAL)` if the size of the matrix doesn't match the
+    /// channel layouts or the context is initialized.
+    pub fn set_matrix(&mut self, matrix: &[&[f64]]) -> Result<()> {
+        let nb_in = self.used_nb_channels()?;
+        let nb_out = self.opt_ch_layout(b"out_chlayout\0")?.nb_channels as usize;
+        if matrix.len() != nb_out || matrix.iter().any(|x| x.len() != nb_in) {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        let flat: Vec<f64> = matrix.concat();
+        unsafe { ffi::swr_set_matrix(self.as_mut_ptr(), flat.as_ptr(), nb_in as i32) }.upgrade()?;
+        Ok(())
+    }
+
+    /// Set a custom input channel mapping: the index of the input channel
+    /// used as each channel, `-1` for a muted channel. Must be called before
+    /// [`Self::init()`].
+    ///
+    /// Returns `AVERROR(EINVAL)` if the mapping doesn't have one entry per
+    /// input channel, an index is out of range, or the context is
+    /// initialized.
+    pub fn set_channel_mapping(&mut self, channel_map: &[i32]) -> Result<()> {
+        let nb_in = self.opt_ch_layout(b"in_chlayout\0")?.nb_channels;
+        if channel_map.len() != self.used_nb_channels()?
+            || channel_map.iter().any(|&x| x < -1 || x >= nb_in)
+        {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        if self.is_initialized() {
+            return Err(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)));
+        }
+        // libswresample keeps the pointer instead of copying the mapping.
+        self.channel_map = channel_map.to_vec();
+        unsafe { ffi::swr_set_channel_mapping(self.as_mut_ptr(), self.channel_map.as_ptr()) }
+            .upgrade()?;
+        Ok(())
+    }
+
+    /// Activate resampling compensation ("soft" compensation), used to
+    /// follow an external clock: `sample_delta` output samples are added
+    /// (or removed if negative) over the next `compensation_distance` output
+    /// samples. Resampling is enabled if needed, so it can be called on an
+    /// initialized context.
+    ///
+    /// Returns `AVERROR(EINVAL)` if `compensation_distance` is negative, or
+    /// zero while `sample_delta` isn't.
+    pub fn set_compensation(
+        &mut self,
+        sample_delta: i32,
+        compensation_distance: i32,
+    ) -> Result<()> {
+        unsafe {
+            ffi::swr_set_compensation(self.as_mut_ptr(), sample_delta, compensation_distance)
+        }
+        .upgrade()?;
+        Ok(())
+    }
+
+    /// Get the channel layout option `name` of the context.
+    fn opt_ch_layout(&self, name: &[u8]) -> Result<AVChannelLayout> {
+        let mut layout = MaybeUninit::<ffi::AVChannelLayout>::uninit();
+        unsafe {
+            ffi::av_opt_get_chlayout(
+                self.as_ptr() as *mut _,
+                name.as_ptr() as _,
+                0,
+                layout.as_mut_ptr(),
+            )
+        }
+        .upgrade()?;
+        let layout = unsafe { layout.assume_init() };
+        Ok(unsafe {
+            AVChannelLayout::from_raw(NonNull::new(Box::into_raw(Box::new(layout))).unwrap())
+        })
+    }
+
+    /// Number of input channels fed to the remixing, the ones of the used
+    /// channel layout if set, of the input channel layout otherwise.
+    fn used_nb_channels(&self) -> Result<usize> {
+        let used = self.opt_ch_layout(b"used_chlayout\0")?.nb_channels;
+        let nb_channels = if used > 0 {
+            used
+        } else {
+            self.opt_ch_layout(b"in_chlayout\0")?.nb_channels
+        };
+        Ok(nb_channels as usize)
+    }
 }
 
 impl Drop for SwrContext {
@@ -184,3 +337,224 @@ impl Drop for SwrContext {
         unsafe { ffi::swr_free(&mut ptr) }
     }
 }
+
+impl AVFrame {
+    /// Convert the audio frame into `sample_fmt`, keeping its sample rate and
+    /// channel layout. Returns a new reference to the frame if it's already
+    /// in `sample_fmt`.
+    pub fn to_sample_fmt(&self, sample_fmt: ffi::AVSampleFormat) -> Result<AVFrame> {
+        if self.format == sample_fmt {
+            return Ok(self.clone());
+        }
+        let ch_layout = self.ch_layout();
+        let mut context = SwrContext::new(
+            &ch_layout,
+            sample_fmt,
+            self.sample_rate,
+            &ch_layout,
+            self.format,
+            self.sample_rate,
+        )?;
+        context.init()?;
+
+        let mut output = AVFrame::new();
+        output.set_nb_samples(self.nb_samples);
+        output.set_ch_layout(ch_layout.clone().into_inner());
+        output.set_format(sample_fmt);
+        output.set_sample_rate(self.sample_rate);
+        output.alloc_buffer()?;
+        context.convert_frame(Some(self), &mut output)?;
+        output.set_pts(self.pts);
+        output.set_time_base(self.time_base);
+        Ok(output)
+    }
+
+    /// Convert the audio frame into the interleaved (packed) variant of its
+    /// sample format, e.g. `AV_SAMPLE_FMT_FLTP` into `AV_SAMPLE_FMT_FLT`.
+    pub fn to_interleaved(&self) -> Result<AVFrame> {
+        let sample_fmt = get_packed_sample_fmt(self.format)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        self.to_sample_fmt(sample_fmt)
+    }
+
+    /// Convert the audio frame into the planar variant of its sample format,
+    /// e.g. `AV_SAMPLE_FMT_S16` into `AV_SAMPLE_FMT_S16P`.
+    pub fn to_planar(&self) -> Result<AVFrame> {
+        let sample_fmt = get_planar_sample_fmt(self.format)
+            .ok_or(RsmpegError::AVError(ffi::AVERROR(ffi::EINVAL)))?;
+        self.to_sample_fmt(sample_fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstr::cstr;
+    use std::slice;
+
+    /// The first `len` samples of `plane`.
+    fn samples(frame: &AVFrame, plane: usize, len: usize) -> Vec<i16> {
+        unsafe { slice::from_raw_parts(frame.data[plane] as *const i16, len) }.to_vec()
+    }
+
+    #[test]
+    fn test_to_interleaved_and_planar() {
+        let mut planar = AVFrame::new();
+        planar.set_nb_samples(4);
+        planar.set_ch_layout(AVChannelLayout::from_nb_channels(2).into_inner());
+        planar.set_format(ffi::AV_SAMPLE_FMT_S16P);
+        planar.set_sample_rate(48000);
+        planar.set_pts(1234);
+        planar.alloc_buffer().unwrap();
+        for (plane, samples) in [(0, [1i16, 2, 3, 4]), (1, [-1, -2, -3, -4])] {
+            unsafe { slice::from_raw_parts_mut(planar.data[plane] as *mut i16, 4) }
+                .copy_from_slice(&samples);
+        }
+
+        let interleaved = planar.to_interleaved().unwrap();
+        assert_eq!(interleaved.format, ffi::AV_SAMPLE_FMT_S16);
+        assert_eq!(interleaved.nb_samples, 4);
+        assert_eq!(interleaved.sample_rate, 48000);
+        assert_eq!(interleaved.pts, 1234);
+        assert_eq!(interleaved.ch_layout().nb_channels, 2);
+        assert_eq!(samples(&interleaved, 0, 8), [1, -1, 2, -2, 3, -3, 4, -4]);
+
+        // Already interleaved.
+        let again = interleaved.to_interleaved().unwrap();
+        assert_eq!(again.data[0], interleaved.data[0]);
+
+        let back = interleaved.to_planar().unwrap();
+        assert_eq!(back.format, ffi::AV_SAMPLE_FMT_S16P);
+        assert_eq!(samples(&back, 0, 4), [1, 2, 3, 4]);
+        assert_eq!(samples(&back, 1, 4), [-1, -2, -3, -4]);
+
+        let float = planar.to_sample_fmt(ffi::AV_SAMPLE_FMT_FLT).unwrap();
+        assert_eq!(float.format, ffi::AV_SAMPLE_FMT_FLT);
+    }
+
+    #[test]
+    fn test_convert_to_new_frame() {
+        let stereo = AVChannelLayout::from_nb_channels(2);
+        let mono = AVChannelLayout::from_nb_channels(1);
+        let mut context = SwrContext::new(
+            &mono,
+            ffi::AV_SAMPLE_FMT_FLTP,
+            24000,
+            &stereo,
+            ffi::AV_SAMPLE_FMT_S16,
+            48000,
+        )
+        .unwrap();
+        context.init().unwrap();
+
+        let mut input = AVFrame::new();
+        input.set_nb_samples(1024);
+        input.set_ch_layout(stereo.clone().into_inner());
+        input.set_format(ffi::AV_SAMPLE_FMT_S16);
+        input.set_sample_rate(48000);
+        input.alloc_buffer().unwrap();
+        input.samples_mut::<i16>(0).unwrap().fill(1000);
+
+        let mut total = 0;
+        for _ in 0..4 {
+            let output = context.convert_to_new_frame(&input).unwrap();
+            assert_eq!(output.format, ffi::AV_SAMPLE_FMT_FLTP);
+            assert_eq!(output.sample_rate, 24000);
+            assert_eq!(output.ch_layout().nb_channels, 1);
+            total += output.nb_samples;
+        }
+        let last = context.flush().unwrap().unwrap();
+        total += last.nb_samples;
+        assert!(total.abs_diff(2048) <= 32);
+        assert!(context.flush().unwrap().is_none());
+    }
+
+    /// A packed float frame repeating `samples` for each of its `len` samples.
+    fn flt_frame(ch_layout: &AVChannelLayout, samples: &[f32], len: usize) -> AVFrame {
+        let mut frame = AVFrame::new();
+        frame.set_nb_samples(len as i32);
+        frame.set_ch_layout(ch_layout.clone().into_inner());
+        frame.set_format(ffi::AV_SAMPLE_FMT_FLT);
+        frame.set_sample_rate(48000);
+        frame.alloc_buffer().unwrap();
+        for group in frame
+            .samples_mut::<f32>(0)
+            .unwrap()
+            .chunks_exact_mut(samples.len())
+        {
+            group.copy_from_slice(samples);
+        }
+        frame
+    }
+
+    fn flt_context(out_ch_layout: &AVChannelLayout, in_ch_layout: &AVChannelLayout) -> SwrContext {
+        SwrContext::new(
+            out_ch_layout,
+            ffi::AV_SAMPLE_FMT_FLT,
+            48000,
+            in_ch_layout,
+            ffi::AV_SAMPLE_FMT_FLT,
+            48000,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_set_matrix() {
+        let surround = AVChannelLayout::from_string(cstr!("5.1")).unwrap();
+        let stereo = AVChannelLayout::from_nb_channels(2);
+        let mut context = flt_context(&stereo, &surround);
+        assert!(context.set_matrix(&[&[1.0; 6]]).is_err());
+        assert!(context.set_matrix(&[&[1.0; 5], &[1.0; 5]]).is_err());
+        // FL FR FC LFE BL BR
+        context
+            .set_matrix(&[
+                &[1.0, 0.0, 0.5, 0.0, 0.0, 0.0],
+                &[0.0, 1.0, 0.5, 0.0, 0.0, 0.0],
+            ])
+            .unwrap();
+        context.init().unwrap();
+        assert!(context.set_matrix(&[&[0.0; 6], &[0.0; 6]]).is_err());
+
+        let input = flt_frame(&surround, &[0.1, 0.2, 0.4, 0.8, 0.0, 0.0], 64);
+        let output = context.convert_to_new_frame(&input).unwrap();
+        let samples = output.samples::<f32>(0).unwrap();
+        assert!(!samples.is_empty());
+        for group in samples.chunks_exact(2) {
+            assert!((group[0] - 0.3).abs() < 1e-4);
+            assert!((group[1] - 0.4).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_set_channel_mapping() {
+        let stereo = AVChannelLayout::from_nb_channels(2);
+        let mut context = flt_context(&stereo, &stereo);
+        assert!(context.set_channel_mapping(&[1]).is_err());
+        assert!(context.set_channel_mapping(&[2, 0]).is_err());
+        context.set_channel_mapping(&[1, 0]).unwrap();
+        context.init().unwrap();
+        assert!(context.set_channel_mapping(&[0, 1]).is_err());
+
+        let input = flt_frame(&stereo, &[0.1, 0.2], 64);
+        let output = context.convert_to_new_frame(&input).unwrap();
+        let samples = output.samples::<f32>(0).unwrap();
+        assert!(!samples.is_empty());
+        assert!(samples.chunks_exact(2).all(|x| x == [0.2, 0.1]));
+    }
+
+    #[test]
+    fn test_set_compensation() {
+        let stereo = AVChannelLayout::from_nb_channels(2);
+        let mut context = flt_context(&stereo, &stereo);
+        context.init().unwrap();
+        assert!(context.set_compensation(10, -1).is_err());
+        assert!(context.set_compensation(10, 0).is_err());
+        context.set_compensation(48, 4800).unwrap();
+
+        let input = flt_frame(&stereo, &[0.1, 0.2], 4800);
+        let mut total = context.convert_to_new_frame(&input).unwrap().nb_samples;
+        total += context.flush().unwrap().map_or(0, |x| x.nb_samples);
+        assert!(total.abs_diff(4848) <= 32);
+    }
+}
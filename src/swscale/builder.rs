@@ -0,0 +1,391 @@
+use crate::{
+    avutil::{opt_set_double, opt_set_int, AVPixelFormat},
+    error::*,
+    ffi,
+    shared::*,
+    swscale::{SwsContext, SwsFilter},
+};
+use std::{ffi::CStr, ptr};
+
+/// Scaling algorithm of a [`SwsContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SwsAlgorithm {
+    FastBilinear,
+    Bilinear,
+    #[default]
+    Bicubic,
+    Experimental,
+    Point,
+    Area,
+    Bicublin,
+    Gauss,
+    Sinc,
+    Lanczos,
+    Spline,
+}
+
+impl SwsAlgorithm {
+    /// The `SWS_*` flag of the algorithm.
+    pub fn flag(self) -> u32 {
+        match self {
+            Self::FastBilinear => ffi::SWS_FAST_BILINEAR,
+            Self::Bilinear => ffi::SWS_BILINEAR,
+            Self::Bicubic => ffi::SWS_BICUBIC,
+            Self::Experimental => ffi::SWS_X,
+            Self::Point => ffi::SWS_POINT,
+            Self::Area => ffi::SWS_AREA,
+            Self::Bicublin => ffi::SWS_BICUBLIN,
+            Self::Gauss => ffi::SWS_GAUSS,
+            Self::Sinc => ffi::SWS_SINC,
+            Self::Lanczos => ffi::SWS_LANCZOS,
+            Self::Spline => ffi::SWS_SPLINE,
+        }
+    }
+}
+
+/// Color settings of the source or destination of a [`SwsContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SwsColor {
+    /// One of `SWS_CS_*`.
+    colorspace: i32,
+    full_range: bool,
+    /// Chroma sample position, in 1/256 of a luma sample.
+    chroma_pos: Option<(i32, i32)>,
+}
+
+impl Default for SwsColor {
+    fn default() -> Self {
+        Self {
+            colorspace: ffi::SWS_CS_DEFAULT as i32,
+            full_range: false,
+            chroma_pos: None,
+        }
+    }
+}
+
+/// Builder of a [`SwsContext`], for the settings not covered by
+/// [`SwsContext::get_context()`].
+///
+/// ```ignore
+/// // BT.709 limited range YUV to BT.601 full range YUV.
+/// let context = SwsContextBuilder::new(
+///     (1920, 1080, ffi::AV_PIX_FMT_YUV420P),
+///     (720, 576, ffi::AV_PIX_FMT_YUVJ420P),
+/// )
+/// .algorithm(SwsAlgorithm::Lanczos)
+/// .src_colorspace(ffi::SWS_CS_ITU709 as i32)
+/// .dst_colorspace(ffi::SWS_CS_ITU601 as i32)
+/// .dst_full_range(true)
+/// .build()?;
+/// ```
+pub struct SwsContextBuilder {
+    src: (i32, i32, AVPixelFormat),
+    dst: (i32, i32, AVPixelFormat),
+    algorithm: SwsAlgorithm,
+    flags: u32,
+    param: Option<[f64; 2]>,
+    src_color: SwsColor,
+    dst_color: SwsColor,
+    src_filter: Option<SwsFilter>,
+    dst_filter: Option<SwsFilter>,
+    threads: Option<i32>,
+}
+
+impl SwsContextBuilder {
+    /// Convert images of `src` `(width, height, pix_fmt)` into images of
+    /// `dst` `(width, height, pix_fmt)`.
+    pub fn new(src: (i32, i32, AVPixelFormat), dst: (i32, i32, AVPixelFormat)) -> Self {
+        Self {
+            src,
+            dst,
+            algorithm: SwsAlgorithm::default(),
+            flags: 0,
+            param: None,
+            src_color: SwsColor::default(),
+            dst_color: SwsColor::default(),
+            src_filter: None,
+            dst_filter: None,
+            threads: None,
+        }
+    }
+
+    /// Returns true if the linked swscale supports slice threading, i.e.
+    /// has the `threads` option. Otherwise [`Self::threads()`] is ignored.
+    pub fn threads_supported() -> bool {
+        let class = unsafe { ffi::sws_get_class() };
+        let name = CStr::from_bytes_with_nul(b"threads\0").unwrap();
+        let option = unsafe {
+            ffi::av_opt_find(
+                &class as *const _ as *mut _,
+                name.as_ptr(),
+                ptr::null(),
+                0,
+                ffi::AV_OPT_SEARCH_FAKE_OBJ as i32,
+            )
+        };
+        !option.is_null()
+    }
+
+    /// Scaling algorithm, [`SwsAlgorithm::Bicubic`] by default.
+    pub fn algorithm(mut self, algorithm: SwsAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Additional `SWS_*` flags, e.g. `SWS_ACCURATE_RND` or
+    /// `SWS_FULL_CHR_H_INT`.
+    pub fn flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Tuning parameters of the scaling algorithm, e.g. the B and C values of
+    /// bicubic or the number of taps of lanczos.
+    pub fn param(mut self, param: [f64; 2]) -> Self {
+        self.param = Some(param);
+        self
+    }
+
+    /// Colorspace of the source, one of `SWS_CS_*`. `SWS_CS_DEFAULT`
+    /// (BT.601) by default.
+    pub fn src_colorspace(mut self, colorspace: i32) -> Self {
+        self.src_color.colorspace = colorspace;
+        self
+    }
+
+    /// Colorspace of the destination, one of `SWS_CS_*`. `SWS_CS_DEFAULT`
+    /// (BT.601) by default.
+    pub fn dst_colorspace(mut self, colorspace: i32) -> Self {
+        self.dst_color.colorspace = colorspace;
+        self
+    }
+
+    /// Whether the source is full range (JPEG) instead of limited range
+    /// (MPEG), limited by default.
+    pub fn src_full_range(mut self, full_range: bool) -> Self {
+        self.src_color.full_range = full_range;
+        self
+    }
+
+    /// Whether the destination is full range (JPEG) instead of limited range
+    /// (MPEG), limited by default.
+    pub fn dst_full_range(mut self, full_range: bool) -> Self {
+        self.dst_color.full_range = full_range;
+        self
+    }
+
+    /// Chroma sample location of the source.
+    pub fn src_chroma_location(mut self, location: ffi::AVChromaLocation) -> Result<Self> {
+        self.src_color.chroma_pos = Some(chroma_pos(location)?);
+        Ok(self)
+    }
+
+    /// Chroma sample location of the destination.
+    pub fn dst_chroma_location(mut self, location: ffi::AVChromaLocation) -> Result<Self> {
+        self.dst_color.chroma_pos = Some(chroma_pos(location)?);
+        Ok(self)
+    }
+
+    /// Filter applied to the source, e.g. blurring or sharpening.
+    pub fn src_filter(mut self, filter: SwsFilter) -> Self {
+        self.src_filter = Some(filter);
+        self
+    }
+
+    /// Filter applied to the destination.
+    pub fn dst_filter(mut self, filter: SwsFilter) -> Self {
+        self.dst_filter = Some(filter);
+        self
+    }
+
+    /// Number of threads scaling slices of the image in parallel, `0` to use
+    /// one per CPU core. Scaling is single threaded by default.
+    ///
+    /// Ignored if slice threading isn't supported, see
+    /// [`Self::threads_supported()`].
+    pub fn threads(mut self, threads: i32) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Allocate and initialize the [`SwsContext`].
+    pub fn build(mut self) -> Result<SwsContext> {
+        let context = unsafe { ffi::sws_alloc_context() }
+            .upgrade()
+            .ok_or(RsmpegError::AVError(AVERROR_ENOMEM))?;
+        let mut context = unsafe { SwsContext::from_raw(context) };
+
+        let (src_w, src_h, src_format) = self.src;
+        let (dst_w, dst_h, dst_format) = self.dst;
+        let mut options = vec![
+            (
+                &b"sws_flags\0"[..],
+                (self.algorithm.flag() | self.flags) as i64,
+            ),
+            (b"srcw\0", src_w as i64),
+            (b"srch\0", src_h as i64),
+            (b"src_format\0", src_format as i64),
+            (b"dstw\0", dst_w as i64),
+            (b"dsth\0", dst_h as i64),
+            (b"dst_format\0", dst_format as i64),
+            (b"src_range\0", self.src_color.full_range as i64),
+            (b"dst_range\0", self.dst_color.full_range as i64),
+        ];
+        if let Some((x, y)) = self.src_color.chroma_pos {
+            options.push((b"src_h_chr_pos\0", x as i64));
+            options.push((b"src_v_chr_pos\0", y as i64));
+        }
+        if let Some((x, y)) = self.dst_color.chroma_pos {
+            options.push((b"dst_h_chr_pos\0", x as i64));
+            options.push((b"dst_v_chr_pos\0", y as i64));
+        }
+        if let Some(threads) = self.threads.filter(|_| Self::threads_supported()) {
+            options.push((b"threads\0", threads as i64));
+        }
+
+        let obj = context.as_mut_ptr() as *mut _;
+        for (name, value) in options {
+            let name = CStr::from_bytes_with_nul(name).unwrap();
+            unsafe { opt_set_int(obj, name, value, 0) }?;
+        }
+        if let Some([param0, param1]) = self.param {
+            let param0_name = CStr::from_bytes_with_nul(b"param0\0").unwrap();
+            let param1_name = CStr::from_bytes_with_nul(b"param1\0").unwrap();
+            unsafe { opt_set_double(obj, param0_name, param0, 0) }?;
+            unsafe { opt_set_double(obj, param1_name, param1, 0) }?;
+        }
+
+        let src_filter = self
+            .src_filter
+            .as_mut()
+            .map(|x| x.as_mut_ptr())
+            .unwrap_or_else(ptr::null_mut);
+        let dst_filter = self
+            .dst_filter
+            .as_mut()
+            .map(|x| x.as_mut_ptr())
+            .unwrap_or_else(ptr::null_mut);
+        unsafe { ffi::sws_init_context(context.as_mut_ptr(), src_filter, dst_filter) }.upgrade()?;
+
+        // The YUV <-> RGB coefficients can only be set after initialization.
+        // Like `vf_scale`, the result is ignored since it's negative for the
+        // conversions where the coefficients are unused (e.g. RGB to RGB).
+        unsafe {
+            ffi::sws_setColorspaceDetails(
+                context.as_mut_ptr(),
+                ffi::sws_getCoefficients(self.src_color.colorspace),
+                self.src_color.full_range as i32,
+                ffi::sws_getCoefficients(self.dst_color.colorspace),
+                self.dst_color.full_range as i32,
+                0,
+                1 << 16,
+                1 << 16,
+            )
+        };
+        Ok(context)
+    }
+}
+
+/// Convert `location` into the swscale chroma position.
+fn chroma_pos(location: ffi::AVChromaLocation) -> Result<(i32, i32)> {
+    let (mut x, mut y) = (0, 0);
+    unsafe { ffi::av_chroma_location_enum_to_pos(&mut x, &mut y, location) }.upgrade()?;
+    Ok((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avutil::AVFrame;
+
+    fn convert(src: &AVFrame, colorspace: u32) -> AVFrame {
+        let mut context = SwsContextBuilder::new(
+            (src.width, src.height, src.format),
+            (src.width, src.height, ffi::AV_PIX_FMT_RGB24),
+        )
+        .algorithm(SwsAlgorithm::Point)
+        .flags(ffi::SWS_ACCURATE_RND)
+        .src_colorspace(colorspace as i32)
+        .src_chroma_location(ffi::AVCHROMA_LOC_LEFT)
+        .unwrap()
+        .build()
+        .unwrap();
+        let mut dst = AVFrame::new();
+        dst.set_width(src.width);
+        dst.set_height(src.height);
+        dst.set_format(ffi::AV_PIX_FMT_RGB24);
+        dst.alloc_buffer().unwrap();
+        context.scale_frame(src, 0, src.height, &mut dst).unwrap();
+        dst
+    }
+
+    #[test]
+    fn test_sws_context_builder() {
+        let mut src = AVFrame::new();
+        src.set_width(16);
+        src.set_height(16);
+        src.set_format(ffi::AV_PIX_FMT_YUV420P);
+        src.alloc_buffer().unwrap();
+        for (plane, value) in [(0, 128), (1, 64), (2, 192)] {
+            for row in src.rows_mut(plane).unwrap() {
+                row.fill(value);
+            }
+        }
+
+        let bt601 = convert(&src, ffi::SWS_CS_ITU601);
+        let bt709 = convert(&src, ffi::SWS_CS_ITU709);
+        let bt601_pixel = &bt601.plane_data(0).unwrap()[..3];
+        let bt709_pixel = &bt709.plane_data(0).unwrap()[..3];
+        assert_ne!(bt601_pixel, bt709_pixel);
+    }
+
+    #[test]
+    fn test_sws_context_builder_threads() {
+        // Slice threading is available since FFmpeg 5.
+        assert!(SwsContextBuilder::threads_supported());
+
+        let mut src = AVFrame::new();
+        src.set_width(1920);
+        src.set_height(1080);
+        src.set_format(ffi::AV_PIX_FMT_YUV420P);
+        src.alloc_buffer().unwrap();
+        for plane in 0..3 {
+            for (y, row) in src.rows_mut(plane).unwrap().enumerate() {
+                for (x, value) in row.iter_mut().enumerate() {
+                    *value = (x ^ y) as u8;
+                }
+            }
+        }
+
+        let scale = |threads: Option<i32>| {
+            let mut builder = SwsContextBuilder::new(
+                (1920, 1080, ffi::AV_PIX_FMT_YUV420P),
+                (1280, 720, ffi::AV_PIX_FMT_RGB24),
+            );
+            if let Some(threads) = threads {
+                builder = builder.threads(threads);
+            }
+            let mut context = builder.build().unwrap();
+            let mut dst = AVFrame::new();
+            dst.set_width(1280);
+            dst.set_height(720);
+            dst.set_format(ffi::AV_PIX_FMT_RGB24);
+            dst.alloc_buffer().unwrap();
+            context.scale_frame(&src, 0, src.height, &mut dst).unwrap();
+            dst.rows(0).unwrap().flatten().copied().collect::<Vec<_>>()
+        };
+        let single = scale(None);
+        assert_eq!(scale(Some(4)), single);
+        assert_eq!(scale(Some(0)), single);
+    }
+
+    #[test]
+    fn test_sws_context_builder_invalid() {
+        assert!(SwsContextBuilder::new(
+            (0, 0, ffi::AV_PIX_FMT_YUV420P),
+            (16, 16, ffi::AV_PIX_FMT_RGB24)
+        )
+        .build()
+        .is_err());
+    }
+}
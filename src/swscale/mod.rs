@@ -1,6 +1,8 @@
 //! Everything related to `libswscale`.
+mod builder;
 mod swscale;
 mod utils;
 
+pub use builder::*;
 pub use swscale::*;
 pub use utils::*;
@@ -0,0 +1,121 @@
+//! FFmpeg version and feature detection, for crates building on top of rsmpeg.
+use std::ffi::CStr;
+
+use crate::ffi;
+
+/// Whether rsmpeg is compiled with the FFmpeg 6 API (the `ffmpeg6` feature).
+pub const FFMPEG6_API: bool = cfg!(feature = "ffmpeg6");
+/// Whether rsmpeg is compiled with the FFmpeg 7 API (the `ffmpeg7` feature).
+pub const FFMPEG7_API: bool = cfg!(feature = "ffmpeg7");
+
+/// Version of one of the linked FFmpeg libraries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LibraryVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub micro: u32,
+}
+
+impl LibraryVersion {
+    /// Decode a version from `AV_VERSION_INT` format.
+    pub const fn from_int(version: u32) -> Self {
+        Self {
+            major: version >> 16,
+            minor: (version >> 8) & 0xff,
+            micro: version & 0xff,
+        }
+    }
+}
+
+/// FFmpeg releases, detected at runtime from the linked libraries.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    Ffmpeg6,
+    Ffmpeg6_1,
+    Ffmpeg7,
+    Ffmpeg7_1,
+    Ffmpeg8,
+}
+
+impl Feature {
+    /// Minimal libavcodec version of the release.
+    const fn avcodec_version(self) -> LibraryVersion {
+        let (major, minor) = match self {
+            Self::Ffmpeg6 => (60, 3),
+            Self::Ffmpeg6_1 => (60, 31),
+            Self::Ffmpeg7 => (61, 3),
+            Self::Ffmpeg7_1 => (61, 19),
+            Self::Ffmpeg8 => (62, 11),
+        };
+        LibraryVersion {
+            major,
+            minor,
+            micro: 0,
+        }
+    }
+}
+
+/// Returns true if the linked FFmpeg is at least the given release.
+pub fn has_feature(feature: Feature) -> bool {
+    avcodec_version() >= feature.avcodec_version()
+}
+
+/// Get the FFmpeg version string, e.g. `7.0.2` or `n7.1-10-g1234567`.
+pub fn ffmpeg_version() -> &'static CStr {
+    unsafe { CStr::from_ptr(ffi::av_version_info()) }
+}
+
+/// Get the version of the linked libavutil.
+pub fn avutil_version() -> LibraryVersion {
+    LibraryVersion::from_int(unsafe { ffi::avutil_version() })
+}
+
+/// Get the version of the linked libavcodec.
+pub fn avcodec_version() -> LibraryVersion {
+    LibraryVersion::from_int(unsafe { ffi::avcodec_version() })
+}
+
+/// Get the version of the linked libavformat.
+pub fn avformat_version() -> LibraryVersion {
+    LibraryVersion::from_int(unsafe { ffi::avformat_version() })
+}
+
+/// Get the version of the linked libavfilter.
+pub fn avfilter_version() -> LibraryVersion {
+    LibraryVersion::from_int(unsafe { ffi::avfilter_version() })
+}
+
+/// Get the version of the linked libswscale.
+pub fn swscale_version() -> LibraryVersion {
+    LibraryVersion::from_int(unsafe { ffi::swscale_version() })
+}
+
+/// Get the version of the linked libswresample.
+pub fn swresample_version() -> LibraryVersion {
+    LibraryVersion::from_int(unsafe { ffi::swresample_version() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version() {
+        assert!(!ffmpeg_version().to_bytes().is_empty());
+        assert_eq!(
+            LibraryVersion::from_int((61 << 16) | (3 << 8) | 100),
+            LibraryVersion {
+                major: 61,
+                minor: 3,
+                micro: 100,
+            }
+        );
+        assert!(avutil_version().major > 0);
+        // FFmpeg 6 is the oldest supported version.
+        assert!(has_feature(Feature::Ffmpeg6));
+        if FFMPEG7_API {
+            assert!(has_feature(Feature::Ffmpeg7));
+        }
+    }
+}
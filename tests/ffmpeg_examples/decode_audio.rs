@@ -77,7 +77,7 @@ fn decode_audio(audio_path: &str, out_file_path: &str) -> Result<()> {
     let (decoder, mut decode_context) = {
         // safety, &str ensures no internal null bytes.
         let audio_path = CString::new(audio_path).unwrap();
-        let mut input_format_context = AVFormatContextInput::open(&audio_path, None, &mut None)
+        let input_format_context = AVFormatContextInput::open(&audio_path, None, &mut None)
             .context("Open audio file failed.")?;
         let (stream_index, decoder) = input_format_context
             .find_best_stream(ffi::AVMEDIA_TYPE_AUDIO)
@@ -41,9 +41,7 @@ fn remux(input_path: &CStr, output_path: &CStr) -> Result<()> {
                 if !codec_type.is_video() && !codec_type.is_audio() && !codec_type.is_subtitle() {
                     None
                 } else {
-                    output_format_context
-                        .new_stream()
-                        .set_codecpar(stream.codecpar().clone());
+                    output_format_context.new_stream_copy(stream);
                     stream_index += 1;
                     Some(stream_index - 1)
                 }
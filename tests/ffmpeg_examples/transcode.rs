@@ -2,9 +2,9 @@
 use anyhow::{anyhow, bail, Context, Result};
 use cstr::cstr;
 use rsmpeg::{
-    avcodec::{AVCodec, AVCodecContext},
+    avcodec::{AVCodec, AVCodecContext, CodecFlags},
     avfilter::{AVFilter, AVFilterContextMut, AVFilterGraph, AVFilterInOut},
-    avformat::{AVFormatContextInput, AVFormatContextOutput},
+    avformat::{AVFormatContextInput, AVFormatContextOutput, IOFormatFlags},
     avutil::{
         av_inv_q, av_rescale_q, get_sample_fmt_name, ra, AVChannelLayout, AVDictionary, AVFrame,
     },
@@ -37,7 +37,7 @@ struct FilterContext<'graph> {
 /// context corresponds to each stream, if the stream is neither audio nor
 /// audio, decode context at this index is set to `None`.
 fn open_input_file(filename: &CStr) -> Result<(Vec<Option<AVCodecContext>>, AVFormatContextInput)> {
-    let mut ifmt_ctx = AVFormatContextInput::open(filename, None, &mut None)?;
+    let ifmt_ctx = AVFormatContextInput::open(filename, None, &mut None)?;
     let mut stream_ctx = Vec::with_capacity(ifmt_ctx.nb_streams as usize);
 
     for (i, input_stream) in ifmt_ctx.streams().into_iter().enumerate() {
@@ -116,8 +116,12 @@ fn open_output_file(
         }
 
         // Some formats want stream headers to be separate.
-        if ofmt_ctx.oformat().flags & ffi::AVFMT_GLOBALHEADER as i32 != 0 {
-            enc_ctx.set_flags(enc_ctx.flags | ffi::AV_CODEC_FLAG_GLOBAL_HEADER as i32);
+        if ofmt_ctx
+            .oformat()
+            .format_flags()
+            .contains(IOFormatFlags::GLOBALHEADER)
+        {
+            enc_ctx.set_codec_flags(enc_ctx.codec_flags() | CodecFlags::GLOBAL_HEADER);
         }
 
         enc_ctx.open(None).with_context(|| {
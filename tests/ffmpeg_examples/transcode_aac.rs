@@ -1,13 +1,9 @@
 //! RIIR: https://github.com/FFmpeg/FFmpeg/blob/master/doc/examples/transcode_aac.c
-use anyhow::{bail, Context as AnyhowContext, Result};
+use anyhow::{Context as AnyhowContext, Result};
 use cstr::cstr;
 use rsmpeg::{
-    avcodec::{AVCodec, AVCodecContext},
-    avformat::{AVFormatContextInput, AVFormatContextOutput},
-    avutil::{ra, AVAudioFifo, AVChannelLayout, AVFrame, AVSamples},
-    error::RsmpegError,
-    ffi,
-    swresample::SwrContext,
+    avutil::{AVAudioFifo, AVSamples},
+    prelude::*,
 };
 use std::{
     ffi::CStr,
@@ -101,25 +97,6 @@ fn add_samples_to_fifo(
     Ok(())
 }
 
-fn init_output_frame(
-    nb_samples: i32,
-    ch_layout: ffi::AVChannelLayout,
-    sample_fmt: i32,
-    sample_rate: i32,
-) -> Result<AVFrame> {
-    let mut frame = AVFrame::new();
-    frame.set_nb_samples(nb_samples);
-    frame.set_ch_layout(ch_layout);
-    frame.set_format(sample_fmt);
-    frame.set_sample_rate(sample_rate);
-
-    frame
-        .get_buffer(0)
-        .context("Could not allocate output frame samples")?;
-
-    Ok(frame)
-}
-
 /// Return boolean: if data is written.
 fn encode_audio_frame(
     mut frame: Option<AVFrame>,
@@ -154,16 +131,14 @@ fn load_encode_and_write(
     output_format_context: &mut AVFormatContextOutput,
     encode_context: &mut AVCodecContext,
 ) -> Result<()> {
-    let frame_size = fifo.size().min(encode_context.frame_size);
-    let mut frame = init_output_frame(
-        frame_size,
-        encode_context.ch_layout().clone().into_inner(),
-        encode_context.sample_fmt,
-        encode_context.sample_rate,
-    )?;
-    if unsafe { fifo.read(frame.data_mut().as_mut_ptr(), frame_size)? } < frame_size {
-        bail!("Could not read data from FIFO");
-    }
+    let frame = fifo
+        .read_frame(
+            encode_context.frame_size,
+            &encode_context.ch_layout(),
+            encode_context.sample_rate,
+        )
+        .context("Could not read data from FIFO")?
+        .context("FIFO is empty")?;
     encode_audio_frame(Some(frame), output_format_context, encode_context)?;
     Ok(())
 }
@@ -19,15 +19,13 @@ fn av_spliter(file_path: &CStr, out_video: &str, out_audio: &CStr) -> Result<()>
     let mut input_format_context = AVFormatContextInput::open(file_path, None, &mut None)?;
     input_format_context.dump(0, file_path)?;
 
-    let video_index = input_format_context
-        .streams()
-        .into_iter()
-        .position(|x| x.codecpar().codec_type().is_video())
+    let (video_index, _) = input_format_context
+        .video_streams()
+        .next()
         .context("Cannot find video stream!")?;
-    let audio_index = input_format_context
-        .streams()
-        .into_iter()
-        .position(|x| x.codecpar().codec_type().is_audio())
+    let (audio_index, _) = input_format_context
+        .audio_streams()
+        .next()
         .context("Cannot find audio stream!")?;
 
     let bsf = AVBitStreamFilter::find_by_name(cstr!("h264_mp4toannexb"))
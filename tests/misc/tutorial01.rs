@@ -49,10 +49,9 @@ fn _main(file: &CStr, out_dir: &str) -> Result<()> {
     fs::create_dir_all(out_dir)?;
     let mut input_format_context = AVFormatContextInput::open(file, None, &mut None)?;
     input_format_context.dump(0, file)?;
-    let video_stream_index = input_format_context
-        .streams()
-        .into_iter()
-        .position(|stream| stream.codecpar().codec_type().is_video())
+    let (video_stream_index, _) = input_format_context
+        .video_streams()
+        .next()
         .context("No video stream")?;
     let mut decode_context = {
         let video_stream = &input_format_context.streams()[video_stream_index];